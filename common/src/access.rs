@@ -0,0 +1,107 @@
+use std::error::Error;
+use uuid::Uuid;
+use zorgit_db::{Database, ProjectSummary};
+use crate::{Project, entities::User};
+
+/// Errors are sendable, because the checks also run in spawned tasks.
+pub type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// What a user may do in a project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Access {
+    None,
+    /// See the project, its repository, issues and pull requests.
+    Read,
+    /// Push to the repository and change the settings of the project.
+    Write,
+}
+
+impl Access {
+    pub fn can_read(self) -> bool {
+        self >= Access::Read
+    }
+
+    pub fn can_write(self) -> bool {
+        self == Access::Write
+    }
+}
+
+/// The parts of a project that decide who may access it.
+#[derive(Debug, Clone, Copy)]
+pub struct Scope {
+    pub project_id: Uuid,
+    pub owner_id: Uuid,
+    pub owner_is_organisation: bool,
+    pub is_private: bool,
+}
+
+impl Scope {
+    pub fn of(project: &Project) -> Result<Scope> {
+        Ok(Scope {
+            project_id: project.id.to_uuid().map_err(|e| e.to_string())?,
+            owner_id: project.owner.id().to_uuid().map_err(|e| e.to_string())?,
+            owner_is_organisation: project.owner.is_organisation(),
+            is_private: project.is_private,
+        })
+    }
+}
+
+impl From<&ProjectSummary> for Scope {
+    fn from(project: &ProjectSummary) -> Scope {
+        Scope {
+            project_id: project.id,
+            owner_id: project.owner_id,
+            owner_is_organisation: project.owner_is_organisation,
+            is_private: project.is_private,
+        }
+    }
+}
+
+/// What the user may do in the project. Admins and the owner may do everything, members
+/// of the owning organisation may read, and collaborators may read or write. Everybody
+/// else, including anonymous visitors, may only read public projects.
+pub async fn access(db: &Database, scope: &Scope, user_id: Option<&Uuid>, is_admin: bool) -> Result<Access> {
+    let public = if scope.is_private { Access::None } else { Access::Read };
+    let user_id = match user_id {
+        Some(user_id) => user_id,
+        None => return Ok(public),
+    };
+    if is_admin || &scope.owner_id == user_id {
+        return Ok(Access::Write);
+    }
+    if let Some(can_write) = db.collaborators.can_write(&scope.project_id, user_id).await? {
+        return Ok(if can_write { Access::Write } else { Access::Read });
+    }
+    if scope.owner_is_organisation && db.teams.is_organisation_member(&scope.owner_id, user_id).await? {
+        return Ok(Access::Read);
+    }
+    Ok(public)
+}
+
+/// What the logged in user, or an anonymous visitor, may do in the project.
+pub async fn access_of(db: &Database, project: &Project, user: Option<&User>) -> Result<Access> {
+    let scope = Scope::of(project)?;
+    match user {
+        Some(user) => {
+            let user_id = user.id.to_uuid().map_err(|e| e.to_string())?;
+            access(db, &scope, Some(&user_id), user.is_admin).await
+        }
+        None => access(db, &scope, None, false).await,
+    }
+}
+
+/// What the user with the given id may do in the project, for places without a logged in user.
+pub async fn access_by_id(db: &Database, scope: &Scope, user_id: &Uuid) -> Result<Access> {
+    let is_admin = db.users.is_admin(user_id).await?;
+    access(db, scope, Some(user_id), is_admin).await
+}
+
+/// Whether the user may see the project and its repository.
+pub async fn can_read(db: &Database, project: &Project, user: Option<&User>) -> Result<bool> {
+    Ok(access_of(db, project, user).await?.can_read())
+}
+
+/// Whether the user may change the repository or the settings of the project.
+pub async fn can_write(db: &Database, project: &Project, user: &User) -> Result<bool> {
+    Ok(access_of(db, project, Some(user)).await?.can_write())
+}
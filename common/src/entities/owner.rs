@@ -1,6 +1,7 @@
 use rocket::{Request, request::{FromParam, FromRequest, Outcome}};
 use rocket_airlock::Airlock;
 use zorgit_security::AuthHatch;
+use crate::Id;
 use crate::entities::{Organisation, User};
 
 
@@ -10,6 +11,27 @@ pub enum Owner {
     Organisation(Organisation),
 }
 
+impl Owner {
+    pub fn id(&self) -> &Id {
+        match self {
+            Owner::User(user) => &user.id,
+            Owner::Organisation(org) => &org.id,
+        }
+    }
+
+    /// The username of a user or the name of an organisation.
+    pub fn name(&self) -> &str {
+        match self {
+            Owner::User(user) => &user.username,
+            Owner::Organisation(org) => &org.name,
+        }
+    }
+
+    pub fn is_organisation(&self) -> bool {
+        matches!(self, Owner::Organisation(_))
+    }
+}
+
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for Owner {
     type Error = Box<dyn std::error::Error>;
@@ -1,8 +1,25 @@
+use std::str::FromStr;
+use uuid::Uuid;
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Id(String);
 
 impl Id {
+    /// All ids in the database are UUIDs, this parses the id into one.
+    pub fn to_uuid(&self) -> crate::Result<Uuid> {
+        Ok(Uuid::from_str(&self.0)?)
+    }
+}
+
+impl From<Uuid> for Id {
+    fn from(uuid: Uuid) -> Self {
+        Id(uuid.to_string())
+    }
+}
 
+impl std::fmt::Display for Id {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.0, f)
+    }
 }
@@ -1,3 +1,4 @@
+pub mod access;
 mod avatar;
 mod dotfile;
 mod email;
@@ -5,6 +6,7 @@ pub mod entities;
 mod id;
 mod notification;
 mod project;
+pub mod reference;
 mod sha1;
 mod url;
 
@@ -0,0 +1,210 @@
+/// Words that close the referenced issue, once the text lands on the default branch.
+const CLOSING_KEYWORDS: [&str; 9] = ["close", "closes", "closed", "fix", "fixes", "fixed", "resolve", "resolves", "resolved"];
+const MIN_COMMIT_ID_LEN: usize = 7;
+const MAX_COMMIT_ID_LEN: usize = 40;
+
+
+/// Something a text points at.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Reference {
+    /// `#12` in the same project or `owner/project#12` in another one. Issues and pull
+    /// requests share their numbers, so it can be either of them.
+    Number { project: Option<(String, String)>, number: i64 },
+    /// A short or full commit id. Only commits of the same project are referenced.
+    Commit(String),
+    /// `@username`
+    Mention(String),
+}
+
+/// A reference together with where it was found in the text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FoundReference {
+    pub reference: Reference,
+    /// Byte offset of the first character, i.e. the `#` or `@`.
+    pub start: usize,
+    /// Byte offset after the last character.
+    pub end: usize,
+    /// Whether a closing keyword like `Fixes` stands right in front of it.
+    pub closes: bool,
+}
+
+impl FoundReference {
+    pub fn is_closing(&self) -> bool {
+        self.closes && matches!(self.reference, Reference::Number { .. })
+    }
+}
+
+/// Finds all references in a markdown text. Code blocks and code spans are skipped, so
+/// pasted logs and snippets don't reference random issues.
+pub fn find_references(text: &str) -> Vec<FoundReference> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+    let mut fence: Option<&str> = None;
+
+    for line in text.split('\n') {
+        let trimmed = line.trim_start();
+        let line_fence = ["```", "~~~"].iter().find(|f| trimmed.starts_with(*f)).copied();
+        match (fence, line_fence) {
+            (None, Some(f)) => fence = Some(f),
+            (Some(open), Some(f)) if open == f => fence = None,
+            (None, None) => find_in_line(line, offset, &mut found),
+            _ => {}
+        }
+        // The `\n` that ended the line
+        offset += line.len() + 1;
+    }
+
+    found
+}
+
+/// Splits the line into the parts outside of code spans.
+fn find_in_line(line: &str, offset: usize, found: &mut Vec<FoundReference>) {
+    let mut start = 0;
+    let mut in_code = false;
+    for (i, c) in line.char_indices() {
+        if c == '`' {
+            if !in_code {
+                find_in_segment(line, start, i, offset, found);
+            }
+            in_code = !in_code;
+            start = i + 1;
+        }
+    }
+    // Text after an unclosed backtick is no code
+    find_in_segment(line, start, line.len(), offset, found);
+}
+
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-' | '/' | '#' | '@')
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-')
+}
+
+fn find_in_segment(line: &str, from: usize, to: usize, offset: usize, found: &mut Vec<FoundReference>) {
+    if from >= to {
+        return;
+    }
+    let segment = &line[from..to];
+    let mut token_start = None;
+    for (i, c) in segment.char_indices().chain(std::iter::once((segment.len(), ' '))) {
+        match (token_start, is_token_char(c)) {
+            (None, true) => token_start = Some(i),
+            (Some(start), false) => {
+                token_start = None;
+                // `&#39;` is an HTML entity, not an issue
+                if segment[..start].ends_with('&') {
+                    continue;
+                }
+                // Sentences end with a dot, references don't
+                let token = segment[start..i].trim_end_matches(|c| c == '.' || c == '-');
+                if let Some(reference) = parse_token(token) {
+                    found.push(FoundReference {
+                        reference,
+                        start: offset + from + start,
+                        end: offset + from + start + token.len(),
+                        closes: follows_closing_keyword(&segment[..start]),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn parse_token(token: &str) -> Option<Reference> {
+    if let Some(username) = token.strip_prefix('@') {
+        let is_username = username.starts_with(|c: char| c.is_ascii_alphanumeric())
+            && username.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        return match is_username {
+            true => Some(Reference::Mention(username.to_string())),
+            false => None,
+        };
+    }
+
+    if let Some(hash) = token.find('#') {
+        let number = parse_number(&token[hash + 1..])?;
+        let project = &token[..hash];
+        if project.is_empty() {
+            return Some(Reference::Number { project: None, number });
+        }
+        let mut names = project.splitn(2, '/');
+        return match (names.next(), names.next()) {
+            (Some(owner), Some(name)) if is_name(owner) && is_name(name) => Some(Reference::Number {
+                project: Some((owner.to_string(), name.to_string())),
+                number,
+            }),
+            _ => None,
+        };
+    }
+
+    // Words like `decade` are valid hex, but no one writes a commit id without any digit
+    let is_commit_id = (MIN_COMMIT_ID_LEN..=MAX_COMMIT_ID_LEN).contains(&token.len())
+        && token.chars().all(|c| c.is_ascii_hexdigit())
+        && token.chars().any(|c| c.is_ascii_digit());
+    match is_commit_id {
+        true => Some(Reference::Commit(token.to_ascii_lowercase())),
+        false => None,
+    }
+}
+
+fn parse_number(digits: &str) -> Option<i64> {
+    match !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        true => digits.parse::<i64>().ok().filter(|n| *n > 0),
+        false => None,
+    }
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(is_name_char)
+}
+
+/// Whether the text in front of a reference ends with a closing keyword, i.e. `Fixes ` or `closes: `.
+fn follows_closing_keyword(before: &str) -> bool {
+    let before = before.trim_end();
+    let before = before.strip_suffix(':').unwrap_or(before);
+    let word = before.rsplit(|c: char| !c.is_ascii_alphabetic()).next().unwrap_or_default();
+    CLOSING_KEYWORDS.iter().any(|keyword| word.eq_ignore_ascii_case(keyword))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn references(text: &str) -> Vec<Reference> {
+        find_references(text).into_iter().map(|found| found.reference).collect()
+    }
+
+    #[test]
+    fn finds_numbers_commits_and_mentions() {
+        assert_eq!(references("See #12, acme/web#3 and 1a2b3c4d. Thanks @alice."), vec![
+            Reference::Number { project: None, number: 12 },
+            Reference::Number { project: Some(("acme".to_string(), "web".to_string())), number: 3 },
+            Reference::Commit("1a2b3c4d".to_string()),
+            Reference::Mention("alice".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn reports_offsets() {
+        let text = "Hi @bob,\r\nsee #7\n\nand #8";
+        let found = find_references(text);
+        assert_eq!(&text[found[0].start..found[0].end], "@bob");
+        assert_eq!(&text[found[1].start..found[1].end], "#7");
+        assert_eq!(&text[found[2].start..found[2].end], "#8");
+    }
+
+    #[test]
+    fn skips_code_and_lookalikes() {
+        let text = "`#1` and\n```\n#2 @carol\n```\nnot #0, &#39;, decade, #12a or user@example.com";
+        assert!(references(text).is_empty());
+    }
+
+    #[test]
+    fn detects_closing_keywords() {
+        let found = find_references("Fixes #1, closes: #2 and mentions #3");
+        assert_eq!(found.iter().map(FoundReference::is_closing).collect::<Vec<_>>(), vec![true, true, false]);
+        assert!(!find_references("Fixes @dave")[0].is_closing());
+    }
+}
@@ -0,0 +1,8 @@
+-- Which users belong to which team of an organisation. Teams can be allowed to push to
+-- protected branches, own code and make their members see the private projects of the
+-- organisation. Older installations may already have the table.
+CREATE TABLE IF NOT EXISTS team_members (
+    team_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    PRIMARY KEY (team_id, user_id)
+);
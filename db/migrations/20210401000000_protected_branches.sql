@@ -0,0 +1,11 @@
+CREATE TABLE protected_branches (
+    id UUID PRIMARY KEY,
+    project_id UUID NOT NULL,
+    pattern TEXT NOT NULL,
+    allow_force_push BOOLEAN NOT NULL DEFAULT FALSE,
+    allow_deletion BOOLEAN NOT NULL DEFAULT FALSE,
+    push_user_ids UUID[] NOT NULL DEFAULT '{}',
+    push_team_ids UUID[] NOT NULL DEFAULT '{}',
+    require_linear_history BOOLEAN NOT NULL DEFAULT FALSE,
+    UNIQUE (project_id, pattern)
+);
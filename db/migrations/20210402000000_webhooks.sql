@@ -0,0 +1,29 @@
+CREATE TABLE webhooks (
+    id UUID PRIMARY KEY,
+    project_id UUID,
+    organisation_id UUID,
+    url TEXT NOT NULL,
+    secret TEXT,
+    events TEXT[] NOT NULL DEFAULT '{}',
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL,
+    CHECK ((project_id IS NULL) <> (organisation_id IS NULL))
+);
+CREATE INDEX webhooks_project_id_idx ON webhooks (project_id);
+CREATE INDEX webhooks_organisation_id_idx ON webhooks (organisation_id);
+
+CREATE TABLE webhook_deliveries (
+    id UUID PRIMARY KEY,
+    webhook_id UUID NOT NULL REFERENCES webhooks (id) ON DELETE CASCADE,
+    event TEXT NOT NULL,
+    request_headers TEXT NOT NULL,
+    request_body TEXT NOT NULL,
+    response_status INTEGER,
+    response_headers TEXT,
+    response_body TEXT,
+    is_success BOOLEAN NOT NULL DEFAULT FALSE,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    duration_ms BIGINT NOT NULL DEFAULT 0,
+    delivered_at TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX webhook_deliveries_webhook_id_idx ON webhook_deliveries (webhook_id, delivered_at DESC);
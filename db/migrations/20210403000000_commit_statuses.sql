@@ -0,0 +1,13 @@
+CREATE TABLE commit_statuses (
+    id UUID PRIMARY KEY,
+    project_id UUID NOT NULL,
+    commit_id TEXT NOT NULL,
+    context TEXT NOT NULL,
+    state TEXT NOT NULL CHECK (state IN ('pending', 'success', 'failure', 'error')),
+    target_url TEXT,
+    description TEXT,
+    creator_id UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    UNIQUE (project_id, commit_id, context)
+);
@@ -0,0 +1,25 @@
+CREATE TABLE pull_requests (
+    id UUID PRIMARY KEY,
+    project_id UUID NOT NULL,
+    number BIGINT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT,
+    author_id UUID NOT NULL,
+    head_project_id UUID NOT NULL,
+    head_branch TEXT NOT NULL,
+    head_commit_id TEXT NOT NULL,
+    base_branch TEXT NOT NULL,
+    base_commit_id TEXT NOT NULL,
+    merge_base_id TEXT,
+    is_draft BOOLEAN NOT NULL DEFAULT FALSE,
+    state TEXT NOT NULL DEFAULT 'open' CHECK (state IN ('open', 'closed', 'merged')),
+    merge_commit_id TEXT,
+    merged_by UUID,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    closed_at TIMESTAMPTZ,
+    merged_at TIMESTAMPTZ,
+    UNIQUE (project_id, number)
+);
+CREATE INDEX pull_requests_head_idx ON pull_requests (head_project_id, head_branch) WHERE state = 'open';
+CREATE INDEX pull_requests_base_idx ON pull_requests (project_id, base_branch) WHERE state = 'open';
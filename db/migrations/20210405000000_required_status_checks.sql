@@ -0,0 +1 @@
+ALTER TABLE protected_branches ADD COLUMN required_status_checks TEXT[] NOT NULL DEFAULT '{}';
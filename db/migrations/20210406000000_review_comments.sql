@@ -0,0 +1,30 @@
+CREATE TABLE review_threads (
+    id UUID PRIMARY KEY,
+    pull_request_id UUID NOT NULL,
+    path TEXT NOT NULL,
+    side TEXT NOT NULL CHECK (side IN ('old', 'new')),
+    original_commit_id TEXT NOT NULL,
+    original_start_line INTEGER NOT NULL,
+    original_end_line INTEGER NOT NULL,
+    commit_id TEXT NOT NULL,
+    start_line INTEGER NOT NULL,
+    end_line INTEGER NOT NULL,
+    is_outdated BOOLEAN NOT NULL DEFAULT FALSE,
+    is_resolved BOOLEAN NOT NULL DEFAULT FALSE,
+    resolved_by UUID,
+    resolved_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    CHECK (start_line <= end_line)
+);
+CREATE INDEX review_threads_pull_request_idx ON review_threads (pull_request_id);
+
+CREATE TABLE review_comments (
+    id UUID PRIMARY KEY,
+    thread_id UUID NOT NULL REFERENCES review_threads (id) ON DELETE CASCADE,
+    author_id UUID NOT NULL,
+    body TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX review_comments_thread_idx ON review_comments (thread_id, created_at);
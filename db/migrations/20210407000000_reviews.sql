@@ -0,0 +1,15 @@
+CREATE TABLE reviews (
+    id UUID PRIMARY KEY,
+    pull_request_id UUID NOT NULL,
+    author_id UUID NOT NULL,
+    state TEXT NOT NULL CHECK (state IN ('approved', 'changes_requested', 'commented', 'dismissed')),
+    body TEXT,
+    commit_id TEXT NOT NULL,
+    is_official BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL,
+    dismissed_at TIMESTAMPTZ
+);
+CREATE INDEX reviews_pull_request_idx ON reviews (pull_request_id, created_at);
+
+ALTER TABLE protected_branches ADD COLUMN required_approvals INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE protected_branches ADD COLUMN dismiss_stale_approvals BOOLEAN NOT NULL DEFAULT FALSE;
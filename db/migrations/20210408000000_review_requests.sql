@@ -0,0 +1,14 @@
+CREATE TABLE review_requests (
+    id UUID PRIMARY KEY,
+    pull_request_id UUID NOT NULL,
+    user_id UUID,
+    team_id UUID,
+    requested_by UUID,
+    is_code_owner BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL,
+    CHECK ((user_id IS NULL) <> (team_id IS NULL))
+);
+CREATE UNIQUE INDEX review_requests_user_idx ON review_requests (pull_request_id, user_id) WHERE user_id IS NOT NULL;
+CREATE UNIQUE INDEX review_requests_team_idx ON review_requests (pull_request_id, team_id) WHERE team_id IS NOT NULL;
+
+ALTER TABLE protected_branches ADD COLUMN require_code_owner_review BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,69 @@
+-- Issues and pull requests share their numbers, so `#12` is unambiguous inside a project.
+CREATE TABLE project_numbers (
+    project_id UUID PRIMARY KEY,
+    last_number BIGINT NOT NULL
+);
+INSERT INTO project_numbers (project_id, last_number)
+    SELECT project_id, MAX(number) FROM pull_requests GROUP BY project_id;
+
+CREATE TABLE labels (
+    id UUID PRIMARY KEY,
+    project_id UUID NOT NULL,
+    name TEXT NOT NULL,
+    color TEXT NOT NULL CHECK (color ~ '^#[0-9a-f]{6}$'),
+    description TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    UNIQUE (project_id, name)
+);
+
+CREATE TABLE milestones (
+    id UUID PRIMARY KEY,
+    project_id UUID NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT,
+    due_on TIMESTAMPTZ,
+    state TEXT NOT NULL DEFAULT 'open' CHECK (state IN ('open', 'closed')),
+    closed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    UNIQUE (project_id, title)
+);
+
+CREATE TABLE issues (
+    id UUID PRIMARY KEY,
+    project_id UUID NOT NULL,
+    number BIGINT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT,
+    author_id UUID NOT NULL,
+    state TEXT NOT NULL DEFAULT 'open' CHECK (state IN ('open', 'closed')),
+    milestone_id UUID REFERENCES milestones (id) ON DELETE SET NULL,
+    closed_by UUID,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    closed_at TIMESTAMPTZ,
+    UNIQUE (project_id, number)
+);
+CREATE INDEX issues_project_state_idx ON issues (project_id, state);
+
+CREATE TABLE issue_labels (
+    issue_id UUID NOT NULL REFERENCES issues (id) ON DELETE CASCADE,
+    label_id UUID NOT NULL REFERENCES labels (id) ON DELETE CASCADE,
+    PRIMARY KEY (issue_id, label_id)
+);
+
+CREATE TABLE issue_assignees (
+    issue_id UUID NOT NULL REFERENCES issues (id) ON DELETE CASCADE,
+    user_id UUID NOT NULL,
+    PRIMARY KEY (issue_id, user_id)
+);
+
+CREATE TABLE issue_comments (
+    id UUID PRIMARY KEY,
+    issue_id UUID NOT NULL REFERENCES issues (id) ON DELETE CASCADE,
+    author_id UUID NOT NULL,
+    body TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX issue_comments_issue_idx ON issue_comments (issue_id, created_at);
@@ -0,0 +1,18 @@
+-- Back-references shown on issues and pull requests, i.e. "mentioned in #12" or "closed by 1a2b3c4".
+CREATE TABLE cross_references (
+    id UUID PRIMARY KEY,
+    -- The issue or pull request that got referenced. They share their numbers.
+    target_project_id UUID NOT NULL,
+    target_number BIGINT NOT NULL,
+    source_project_id UUID NOT NULL,
+    source_kind TEXT NOT NULL CHECK (source_kind IN ('issue', 'pull_request', 'issue_comment', 'commit')),
+    -- The issue or pull request the source belongs to, commits have none.
+    source_number BIGINT,
+    -- Id of the issue, pull request or comment, or the commit id.
+    source_id TEXT NOT NULL,
+    actor_id UUID,
+    closes BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL,
+    UNIQUE (target_project_id, target_number, source_kind, source_id)
+);
+CREATE INDEX cross_references_source_idx ON cross_references (source_kind, source_id);
@@ -0,0 +1,37 @@
+CREATE TABLE watches (
+    user_id UUID NOT NULL,
+    project_id UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (user_id, project_id)
+);
+CREATE INDEX watches_project_idx ON watches (project_id);
+
+-- A thread is an issue or pull request, identified by its number inside the project.
+CREATE TABLE subscriptions (
+    user_id UUID NOT NULL,
+    project_id UUID NOT NULL,
+    thread_number BIGINT NOT NULL,
+    -- Unsubscribing keeps the row, so watching the project doesn't bring the thread back.
+    is_subscribed BOOLEAN NOT NULL,
+    reason TEXT NOT NULL CHECK (reason IN ('author', 'comment', 'mention', 'assign', 'manual')),
+    created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (user_id, project_id, thread_number)
+);
+CREATE INDEX subscriptions_thread_idx ON subscriptions (project_id, thread_number);
+
+-- One notification per user and thread. New activity marks it unread again.
+CREATE TABLE notifications (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL,
+    project_id UUID NOT NULL,
+    thread_number BIGINT NOT NULL,
+    thread_kind TEXT NOT NULL CHECK (thread_kind IN ('issue', 'pull_request')),
+    subject TEXT NOT NULL,
+    reason TEXT NOT NULL CHECK (reason IN ('author', 'comment', 'mention', 'assign', 'manual', 'watching')),
+    last_actor TEXT NOT NULL,
+    is_read BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    UNIQUE (user_id, project_id, thread_number)
+);
+CREATE INDEX notifications_inbox_idx ON notifications (user_id, is_read, updated_at DESC);
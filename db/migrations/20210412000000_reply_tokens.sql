@@ -0,0 +1,11 @@
+-- Notification emails are sent with a `reply+<token>@...` address. The token tells whose
+-- reply it is and which issue or pull request it belongs to.
+CREATE TABLE reply_tokens (
+    token TEXT PRIMARY KEY,
+    user_id UUID NOT NULL,
+    project_id UUID NOT NULL,
+    thread_number BIGINT NOT NULL,
+    thread_kind TEXT NOT NULL CHECK (thread_kind IN ('issue', 'pull_request')),
+    created_at TIMESTAMPTZ NOT NULL,
+    UNIQUE (user_id, project_id, thread_number)
+);
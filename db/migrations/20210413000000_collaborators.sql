@@ -0,0 +1,9 @@
+-- Users who may see a project besides its owner and the members of its organisation.
+-- Those with `can_write` may also push and change its settings.
+CREATE TABLE collaborators (
+    project_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    can_write BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (project_id, user_id)
+);
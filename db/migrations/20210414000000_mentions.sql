@@ -0,0 +1,6 @@
+-- Mentioned users are referenced like issues, so they can see where they got mentioned.
+ALTER TABLE cross_references ADD COLUMN target_user_id UUID;
+ALTER TABLE cross_references ALTER COLUMN target_project_id DROP NOT NULL;
+ALTER TABLE cross_references ALTER COLUMN target_number DROP NOT NULL;
+ALTER TABLE cross_references ADD CHECK ((target_user_id IS NULL) = (target_project_id IS NOT NULL AND target_number IS NOT NULL));
+CREATE UNIQUE INDEX cross_references_user_idx ON cross_references (target_user_id, source_kind, source_id) WHERE target_user_id IS NOT NULL;
@@ -0,0 +1,25 @@
+use sqlx::{PgPool, Pool, Postgres};
+use uuid::Uuid;
+
+
+pub struct Collaborators {
+    pool: PgPool
+}
+
+impl Collaborators {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> Collaborators {
+        Collaborators {
+            pool,
+        }
+    }
+
+    /// Whether the user may write to the project, if they are a collaborator of it at all.
+    pub async fn can_write(&self, project_id: &Uuid, user_id: &Uuid) -> sqlx::Result<Option<bool>> {
+        sqlx::query_as("SELECT can_write FROM collaborators WHERE project_id = $1 AND user_id = $2")
+            .bind(project_id)
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map(|row: Option<(bool,)>| row.map(|(can_write,)| can_write))
+    }
+}
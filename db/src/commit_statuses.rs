@@ -0,0 +1,75 @@
+use sqlx::{FromRow, PgPool, Pool, Postgres};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+
+/// The latest status a context, like `ci/build`, reported for a commit.
+#[derive(Debug, Clone, FromRow)]
+pub struct CommitStatus {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub commit_id: String,
+    pub context: String,
+    /// One of `pending`, `success`, `failure` or `error`.
+    pub state: String,
+    pub target_url: Option<String>,
+    pub description: Option<String>,
+    pub creator_id: Uuid,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+pub struct CommitStatuses {
+    pool: PgPool
+}
+
+impl CommitStatuses {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> CommitStatuses {
+        CommitStatuses {
+            pool,
+        }
+    }
+
+    /// Stores the status. A context has only one status per commit, so an
+    /// existing status of the same context is replaced.
+    pub async fn save(&self, status: &CommitStatus) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO commit_statuses (id, project_id, commit_id, context, state, target_url, description, creator_id, created_at, updated_at)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                     ON CONFLICT (project_id, commit_id, context) DO UPDATE SET
+                        state = EXCLUDED.state,
+                        target_url = EXCLUDED.target_url,
+                        description = EXCLUDED.description,
+                        creator_id = EXCLUDED.creator_id,
+                        updated_at = EXCLUDED.updated_at")
+            .bind(&status.id)
+            .bind(&status.project_id)
+            .bind(&status.commit_id)
+            .bind(&status.context)
+            .bind(&status.state)
+            .bind(&status.target_url)
+            .bind(&status.description)
+            .bind(&status.creator_id)
+            .bind(status.created_at)
+            .bind(status.updated_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn for_commit(&self, project_id: &Uuid, commit_id: &str) -> sqlx::Result<Vec<CommitStatus>> {
+        sqlx::query_as::<_, CommitStatus>("SELECT * FROM commit_statuses WHERE project_id = $1 AND commit_id = $2 ORDER BY context")
+            .bind(project_id)
+            .bind(commit_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// All statuses of the given commits at once, for lists of commits.
+    pub async fn for_commits(&self, project_id: &Uuid, commit_ids: &[String]) -> sqlx::Result<Vec<CommitStatus>> {
+        sqlx::query_as::<_, CommitStatus>("SELECT * FROM commit_statuses WHERE project_id = $1 AND commit_id = ANY($2) ORDER BY commit_id, context")
+            .bind(project_id)
+            .bind(commit_ids)
+            .fetch_all(&self.pool)
+            .await
+    }
+}
@@ -0,0 +1,109 @@
+use sqlx::{FromRow, PgPool, Pool, Postgres};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+
+#[derive(Debug, Clone, FromRow)]
+pub struct CrossReference {
+    pub id: Uuid,
+    /// The issue or pull request that got referenced. `None` for mentions.
+    pub target_project_id: Option<Uuid>,
+    pub target_number: Option<i64>,
+    /// The user that got mentioned.
+    pub target_user_id: Option<Uuid>,
+    pub source_project_id: Uuid,
+    /// One of `issue`, `pull_request`, `issue_comment` or `commit`.
+    pub source_kind: String,
+    /// Number of the issue or pull request the source belongs to. `None` for commits.
+    pub source_number: Option<i64>,
+    /// Id of the issue, pull request or comment, or the commit id.
+    pub source_id: String,
+    pub actor_id: Option<Uuid>,
+    /// Whether the source closed the target, i.e. with `Fixes #12`.
+    pub closes: bool,
+    pub created_at: OffsetDateTime,
+}
+
+pub struct CrossReferences {
+    pool: PgPool
+}
+
+impl CrossReferences {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> CrossReferences {
+        CrossReferences {
+            pool,
+        }
+    }
+
+    /// Stores the reference, unless the source already referenced the target. A source that
+    /// later closes its target upgrades the existing reference.
+    pub async fn create(&self, reference: &CrossReference) -> sqlx::Result<()> {
+        let on_conflict = match reference.target_user_id {
+            Some(_) => "ON CONFLICT (target_user_id, source_kind, source_id) WHERE target_user_id IS NOT NULL DO NOTHING",
+            None => "ON CONFLICT (target_project_id, target_number, source_kind, source_id) DO UPDATE SET closes = cross_references.closes OR EXCLUDED.closes",
+        };
+        sqlx::query(&format!("INSERT INTO cross_references (id, target_project_id, target_number, target_user_id, source_project_id, source_kind, source_number, source_id, actor_id, closes, created_at)
+                              VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                              {}", on_conflict))
+            .bind(&reference.id)
+            .bind(&reference.target_project_id)
+            .bind(reference.target_number)
+            .bind(&reference.target_user_id)
+            .bind(&reference.source_project_id)
+            .bind(&reference.source_kind)
+            .bind(reference.source_number)
+            .bind(&reference.source_id)
+            .bind(&reference.actor_id)
+            .bind(reference.closes)
+            .bind(reference.created_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    /// Everything that references the issue or pull request, oldest first.
+    pub async fn for_target(&self, project_id: &Uuid, number: i64) -> sqlx::Result<Vec<CrossReference>> {
+        sqlx::query_as::<_, CrossReference>("SELECT * FROM cross_references WHERE target_project_id = $1 AND target_number = $2 ORDER BY created_at")
+            .bind(project_id)
+            .bind(number)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Everywhere the user got mentioned, newest first.
+    pub async fn for_user(&self, user_id: &Uuid) -> sqlx::Result<Vec<CrossReference>> {
+        sqlx::query_as::<_, CrossReference>("SELECT * FROM cross_references WHERE target_user_id = $1 ORDER BY created_at DESC")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Forgets the references an edited source doesn't contain anymore. `numbers` are the
+    /// issues and pull requests it still references, by project. References that closed
+    /// their target are kept, because the target stays closed.
+    pub async fn delete_removed(&self, source_kind: &str, source_id: &str, numbers: &[(Uuid, i64)], user_ids: &[Uuid]) -> sqlx::Result<()> {
+        let (project_ids, numbers): (Vec<Uuid>, Vec<i64>) = numbers.iter().copied().unzip();
+        sqlx::query("DELETE FROM cross_references WHERE source_kind = $1 AND source_id = $2 AND NOT closes
+                     AND NOT EXISTS (SELECT 1 FROM UNNEST($3::UUID[], $4::BIGINT[]) AS kept (project_id, number)
+                                     WHERE kept.project_id = target_project_id AND kept.number = target_number)
+                     AND (target_user_id IS NULL OR NOT target_user_id = ANY($5))")
+            .bind(source_kind)
+            .bind(source_id)
+            .bind(&project_ids)
+            .bind(&numbers)
+            .bind(user_ids)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    /// Forgets the references of a deleted source.
+    pub async fn delete_for_source(&self, source_kind: &str, source_id: &str) -> sqlx::Result<()> {
+        sqlx::query("DELETE FROM cross_references WHERE source_kind = $1 AND source_id = $2")
+            .bind(source_kind)
+            .bind(source_id)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+}
@@ -0,0 +1,33 @@
+use sqlx::{PgPool, Pool, Postgres};
+use uuid::Uuid;
+
+
+pub struct Emails {
+    pool: PgPool
+}
+
+impl Emails {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> Emails {
+        Emails {
+            pool,
+        }
+    }
+
+    /// The activated primary address of each of the users, together with its notification
+    /// setting as stored by `Notification::to_i16`.
+    pub async fn notification_addresses(&self, user_ids: &[Uuid]) -> sqlx::Result<Vec<(Uuid, String, i16)>> {
+        sqlx::query_as("SELECT user_id, address, notification FROM emails WHERE user_id = ANY($1) AND is_primary AND activated_at IS NOT NULL")
+            .bind(user_ids)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// The user an activated address belongs to. Addresses are compared case-insensitively.
+    pub async fn owner_of(&self, address: &str) -> sqlx::Result<Option<Uuid>> {
+        sqlx::query_as("SELECT user_id FROM emails WHERE LOWER(address) = LOWER($1) AND activated_at IS NOT NULL")
+            .bind(address)
+            .fetch_optional(&self.pool)
+            .await
+            .map(|row: Option<(Uuid,)>| row.map(|(id,)| id))
+    }
+}
@@ -0,0 +1,71 @@
+use sqlx::{FromRow, PgPool, Pool, Postgres};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+
+#[derive(Debug, Clone, FromRow)]
+pub struct IssueComment {
+    pub id: Uuid,
+    pub issue_id: Uuid,
+    pub author_id: Uuid,
+    pub body: String,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+pub struct IssueComments {
+    pool: PgPool
+}
+
+impl IssueComments {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> IssueComments {
+        IssueComments {
+            pool,
+        }
+    }
+
+    pub async fn create(&self, comment: &IssueComment) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO issue_comments (id, issue_id, author_id, body, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $5)")
+            .bind(&comment.id)
+            .bind(&comment.issue_id)
+            .bind(&comment.author_id)
+            .bind(&comment.body)
+            .bind(comment.created_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn by_id(&self, id: &Uuid) -> sqlx::Result<Option<IssueComment>> {
+        sqlx::query_as::<_, IssueComment>("SELECT * FROM issue_comments WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// All comments of an issue, oldest first.
+    pub async fn for_issue(&self, issue_id: &Uuid) -> sqlx::Result<Vec<IssueComment>> {
+        sqlx::query_as::<_, IssueComment>("SELECT * FROM issue_comments WHERE issue_id = $1 ORDER BY created_at")
+            .bind(issue_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn update_body(&self, id: &Uuid, body: &str, updated_at: OffsetDateTime) -> sqlx::Result<()> {
+        sqlx::query("UPDATE issue_comments SET body = $2, updated_at = $3 WHERE id = $1")
+            .bind(id)
+            .bind(body)
+            .bind(updated_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn delete(&self, id: &Uuid) -> sqlx::Result<()> {
+        sqlx::query("DELETE FROM issue_comments WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+}
@@ -0,0 +1,199 @@
+use sqlx::{FromRow, PgPool, Pool, Postgres, Transaction};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use crate::numbers::next_number;
+
+
+#[derive(Debug, Clone, FromRow)]
+pub struct Issue {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub number: i64,
+    pub title: String,
+    pub description: Option<String>,
+    pub author_id: Uuid,
+    /// Either `open` or `closed`.
+    pub state: String,
+    pub milestone_id: Option<Uuid>,
+    pub closed_by: Option<Uuid>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+    pub closed_at: Option<OffsetDateTime>,
+}
+
+impl Issue {
+    pub fn is_open(&self) -> bool {
+        self.state == "open"
+    }
+}
+
+/// Narrows down the issues of a project. Unset fields match every issue.
+#[derive(Debug, Clone, Default)]
+pub struct IssueFilter {
+    pub state: Option<String>,
+    pub label_id: Option<Uuid>,
+    pub milestone_id: Option<Uuid>,
+    pub assignee_id: Option<Uuid>,
+}
+
+pub struct Issues {
+    pool: PgPool
+}
+
+impl Issues {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> Issues {
+        Issues {
+            pool,
+        }
+    }
+
+    /// Stores a new issue with the next free number of its project and returns that number.
+    pub async fn create(&self, issue: &Issue) -> sqlx::Result<i64> {
+        let mut tx = self.pool.begin().await?;
+        let number = next_number(&mut tx, &issue.project_id).await?;
+        sqlx::query("INSERT INTO issues (id, project_id, number, title, description, author_id, state, milestone_id, created_at, updated_at)
+                     VALUES ($1, $2, $3, $4, $5, $6, 'open', $7, $8, $8)")
+            .bind(&issue.id)
+            .bind(&issue.project_id)
+            .bind(number)
+            .bind(&issue.title)
+            .bind(&issue.description)
+            .bind(&issue.author_id)
+            .bind(&issue.milestone_id)
+            .bind(issue.created_at)
+            .execute(&mut tx)
+            .await?;
+        refresh_counters(&mut tx, &issue.project_id).await?;
+        tx.commit().await?;
+        Ok(number)
+    }
+
+    pub async fn by_id(&self, id: &Uuid) -> sqlx::Result<Option<Issue>> {
+        sqlx::query_as::<_, Issue>("SELECT * FROM issues WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    pub async fn by_number(&self, project_id: &Uuid, number: i64) -> sqlx::Result<Option<Issue>> {
+        sqlx::query_as::<_, Issue>("SELECT * FROM issues WHERE project_id = $1 AND number = $2")
+            .bind(project_id)
+            .bind(number)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// Issues of the project matching the filter, newest first.
+    pub async fn for_project(&self, project_id: &Uuid, filter: &IssueFilter, limit: i64, offset: i64) -> sqlx::Result<Vec<Issue>> {
+        sqlx::query_as::<_, Issue>("SELECT * FROM issues WHERE project_id = $1
+                                        AND ($2::TEXT IS NULL OR state = $2)
+                                        AND ($3::UUID IS NULL OR EXISTS (SELECT 1 FROM issue_labels WHERE issue_id = issues.id AND label_id = $3))
+                                        AND ($4::UUID IS NULL OR milestone_id = $4)
+                                        AND ($5::UUID IS NULL OR EXISTS (SELECT 1 FROM issue_assignees WHERE issue_id = issues.id AND user_id = $5))
+                                    ORDER BY number DESC LIMIT $6 OFFSET $7")
+            .bind(project_id)
+            .bind(&filter.state)
+            .bind(&filter.label_id)
+            .bind(&filter.milestone_id)
+            .bind(&filter.assignee_id)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn update_details(&self, issue: &Issue) -> sqlx::Result<()> {
+        sqlx::query("UPDATE issues SET title = $2, description = $3, milestone_id = $4, updated_at = $5 WHERE id = $1")
+            .bind(&issue.id)
+            .bind(&issue.title)
+            .bind(&issue.description)
+            .bind(&issue.milestone_id)
+            .bind(issue.updated_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn close(&self, issue: &Issue, closed_by: &Uuid, closed_at: OffsetDateTime) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE issues SET state = 'closed', closed_by = $2, closed_at = $3, updated_at = $3 WHERE id = $1 AND state = 'open'")
+            .bind(&issue.id)
+            .bind(closed_by)
+            .bind(closed_at)
+            .execute(&mut tx)
+            .await?;
+        refresh_counters(&mut tx, &issue.project_id).await?;
+        tx.commit().await
+    }
+
+    pub async fn reopen(&self, issue: &Issue, reopened_at: OffsetDateTime) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE issues SET state = 'open', closed_by = NULL, closed_at = NULL, updated_at = $2 WHERE id = $1 AND state = 'closed'")
+            .bind(&issue.id)
+            .bind(reopened_at)
+            .execute(&mut tx)
+            .await?;
+        refresh_counters(&mut tx, &issue.project_id).await?;
+        tx.commit().await
+    }
+
+    /// Pairs of issue id and label id for the given issues.
+    pub async fn label_ids(&self, issue_ids: &[Uuid]) -> sqlx::Result<Vec<(Uuid, Uuid)>> {
+        sqlx::query_as("SELECT issue_id, label_id FROM issue_labels WHERE issue_id = ANY($1)")
+            .bind(issue_ids)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Replaces the labels of the issue.
+    pub async fn set_labels(&self, issue_id: &Uuid, label_ids: &[Uuid]) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM issue_labels WHERE issue_id = $1")
+            .bind(issue_id)
+            .execute(&mut tx)
+            .await?;
+        sqlx::query("INSERT INTO issue_labels (issue_id, label_id) SELECT $1, UNNEST($2::UUID[]) ON CONFLICT DO NOTHING")
+            .bind(issue_id)
+            .bind(label_ids)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await
+    }
+
+    /// Pairs of issue id and user id for the given issues.
+    pub async fn assignee_ids(&self, issue_ids: &[Uuid]) -> sqlx::Result<Vec<(Uuid, Uuid)>> {
+        sqlx::query_as("SELECT issue_id, user_id FROM issue_assignees WHERE issue_id = ANY($1)")
+            .bind(issue_ids)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Replaces the assignees of the issue.
+    pub async fn set_assignees(&self, issue_id: &Uuid, user_ids: &[Uuid]) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM issue_assignees WHERE issue_id = $1")
+            .bind(issue_id)
+            .execute(&mut tx)
+            .await?;
+        sqlx::query("INSERT INTO issue_assignees (issue_id, user_id) SELECT $1, UNNEST($2::UUID[]) ON CONFLICT DO NOTHING")
+            .bind(issue_id)
+            .bind(user_ids)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await
+    }
+}
+
+/// Counts the issues of the project again. Counting instead of incrementing keeps
+/// the counters right, even if a change got lost somewhere.
+async fn refresh_counters(tx: &mut Transaction<'_, Postgres>, project_id: &Uuid) -> sqlx::Result<()> {
+    sqlx::query("UPDATE projects SET
+                    num_issues = (SELECT COUNT(*) FROM issues WHERE project_id = $1),
+                    num_issues_open = (SELECT COUNT(*) FROM issues WHERE project_id = $1 AND state = 'open'),
+                    num_issues_closed = (SELECT COUNT(*) FROM issues WHERE project_id = $1 AND state = 'closed')
+                 WHERE id = $1")
+        .bind(project_id)
+        .execute(tx)
+        .await
+        .map(|_| ())
+}
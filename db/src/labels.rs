@@ -0,0 +1,95 @@
+use sqlx::{FromRow, PgPool, Pool, Postgres, Transaction};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+
+#[derive(Debug, Clone, FromRow)]
+pub struct Label {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub name: String,
+    /// Lowercase hex color like `#d73a4a`.
+    pub color: String,
+    pub description: Option<String>,
+    pub created_at: OffsetDateTime,
+}
+
+pub struct Labels {
+    pool: PgPool
+}
+
+impl Labels {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> Labels {
+        Labels {
+            pool,
+        }
+    }
+
+    pub async fn create(&self, label: &Label) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("INSERT INTO labels (id, project_id, name, color, description, created_at) VALUES ($1, $2, $3, $4, $5, $6)")
+            .bind(&label.id)
+            .bind(&label.project_id)
+            .bind(&label.name)
+            .bind(&label.color)
+            .bind(&label.description)
+            .bind(label.created_at)
+            .execute(&mut tx)
+            .await?;
+        refresh_counters(&mut tx, &label.project_id).await?;
+        tx.commit().await
+    }
+
+    pub async fn by_id(&self, id: &Uuid) -> sqlx::Result<Option<Label>> {
+        sqlx::query_as::<_, Label>("SELECT * FROM labels WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    pub async fn by_name(&self, project_id: &Uuid, name: &str) -> sqlx::Result<Option<Label>> {
+        sqlx::query_as::<_, Label>("SELECT * FROM labels WHERE project_id = $1 AND name = $2")
+            .bind(project_id)
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// All labels of the project, sorted by name.
+    pub async fn for_project(&self, project_id: &Uuid) -> sqlx::Result<Vec<Label>> {
+        sqlx::query_as::<_, Label>("SELECT * FROM labels WHERE project_id = $1 ORDER BY name")
+            .bind(project_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn update(&self, label: &Label) -> sqlx::Result<()> {
+        sqlx::query("UPDATE labels SET name = $2, color = $3, description = $4 WHERE id = $1")
+            .bind(&label.id)
+            .bind(&label.name)
+            .bind(&label.color)
+            .bind(&label.description)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    /// Deletes the label and takes it off all issues.
+    pub async fn delete(&self, label: &Label) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM labels WHERE id = $1")
+            .bind(&label.id)
+            .execute(&mut tx)
+            .await?;
+        refresh_counters(&mut tx, &label.project_id).await?;
+        tx.commit().await
+    }
+}
+
+async fn refresh_counters(tx: &mut Transaction<'_, Postgres>, project_id: &Uuid) -> sqlx::Result<()> {
+    sqlx::query("UPDATE projects SET num_labels = (SELECT COUNT(*) FROM labels WHERE project_id = $1) WHERE id = $1")
+        .bind(project_id)
+        .execute(tx)
+        .await
+        .map(|_| ())
+}
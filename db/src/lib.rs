@@ -1,12 +1,71 @@
+use collaborators::Collaborators;
+use commit_statuses::CommitStatuses;
+use cross_references::CrossReferences;
+use emails::Emails;
+use issue_comments::IssueComments;
+use issues::Issues;
+use labels::Labels;
+use milestones::Milestones;
+use notifications::Notifications;
 use owners::Owners;
 use projects::Projects;
+use protected_branches::ProtectedBranches;
+use pull_requests::PullRequests;
+use reply_tokens::ReplyTokens;
+use review_comments::ReviewComments;
+use review_requests::ReviewRequests;
+use review_threads::ReviewThreads;
+use reviews::Reviews;
 use rocket::{Request, try_outcome, State, request::{self, FromRequest}};
 use sqlx::{Pool, Postgres, postgres::PgPool};
+use subscriptions::Subscriptions;
+use teams::Teams;
 use users::Users;
+use watches::Watches;
+use webhooks::Webhooks;
 
+mod collaborators;
+mod commit_statuses;
+mod cross_references;
+mod emails;
+mod issue_comments;
+mod issues;
+mod labels;
+mod milestones;
+mod notifications;
+mod numbers;
 mod owners;
 mod projects;
+mod protected_branches;
+mod pull_requests;
+mod reply_tokens;
+mod review_comments;
+mod review_requests;
+mod review_threads;
+mod reviews;
+mod subscriptions;
+mod teams;
 mod users;
+mod watches;
+mod webhooks;
+
+pub use commit_statuses::CommitStatus;
+pub use cross_references::CrossReference;
+pub use issue_comments::IssueComment;
+pub use issues::{Issue, IssueFilter};
+pub use labels::Label;
+pub use milestones::Milestone;
+pub use notifications::Notification;
+pub use projects::ProjectSummary;
+pub use protected_branches::ProtectedBranch;
+pub use pull_requests::PullRequest;
+pub use reply_tokens::ReplyToken;
+pub use review_comments::ReviewComment;
+pub use review_requests::ReviewRequest;
+pub use review_threads::ReviewThread;
+pub use reviews::Review;
+pub use subscriptions::Subscription;
+pub use webhooks::{Webhook, WebhookDelivery};
 
 
 pub struct Database {
@@ -14,6 +73,26 @@ pub struct Database {
     pub users: Users,
     pub owners: Owners,
     pub projects: Projects,
+    pub protected_branches: ProtectedBranches,
+    pub teams: Teams,
+    pub collaborators: Collaborators,
+    pub webhooks: Webhooks,
+    pub commit_statuses: CommitStatuses,
+    pub pull_requests: PullRequests,
+    pub review_threads: ReviewThreads,
+    pub review_comments: ReviewComments,
+    pub reviews: Reviews,
+    pub review_requests: ReviewRequests,
+    pub issues: Issues,
+    pub issue_comments: IssueComments,
+    pub labels: Labels,
+    pub milestones: Milestones,
+    pub cross_references: CrossReferences,
+    pub watches: Watches,
+    pub subscriptions: Subscriptions,
+    pub notifications: Notifications,
+    pub emails: Emails,
+    pub reply_tokens: ReplyTokens,
 }
 
 impl Database {
@@ -23,11 +102,37 @@ impl Database {
             pool: pool.clone(),
             users: Users::with_pool(pool.clone()),
             owners: Owners::with_pool(pool.clone()),
-            projects: Projects::with_pool(pool),
+            projects: Projects::with_pool(pool.clone()),
+            protected_branches: ProtectedBranches::with_pool(pool.clone()),
+            teams: Teams::with_pool(pool.clone()),
+            collaborators: Collaborators::with_pool(pool.clone()),
+            webhooks: Webhooks::with_pool(pool.clone()),
+            commit_statuses: CommitStatuses::with_pool(pool.clone()),
+            pull_requests: PullRequests::with_pool(pool.clone()),
+            review_threads: ReviewThreads::with_pool(pool.clone()),
+            review_comments: ReviewComments::with_pool(pool.clone()),
+            reviews: Reviews::with_pool(pool.clone()),
+            review_requests: ReviewRequests::with_pool(pool.clone()),
+            issues: Issues::with_pool(pool.clone()),
+            issue_comments: IssueComments::with_pool(pool.clone()),
+            labels: Labels::with_pool(pool.clone()),
+            milestones: Milestones::with_pool(pool.clone()),
+            cross_references: CrossReferences::with_pool(pool.clone()),
+            watches: Watches::with_pool(pool.clone()),
+            subscriptions: Subscriptions::with_pool(pool.clone()),
+            notifications: Notifications::with_pool(pool.clone()),
+            emails: Emails::with_pool(pool.clone()),
+            reply_tokens: ReplyTokens::with_pool(pool),
         }
     }
 }
 
+impl Clone for Database {
+    fn clone(&self) -> Self {
+        Database::with_pool(self.pool.clone())
+    }
+}
+
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for Database {
     type Error = ();
@@ -0,0 +1,115 @@
+use sqlx::{FromRow, PgPool, Pool, Postgres, Transaction};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+
+#[derive(Debug, Clone, FromRow)]
+pub struct Milestone {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub title: String,
+    pub description: Option<String>,
+    pub due_on: Option<OffsetDateTime>,
+    /// Either `open` or `closed`.
+    pub state: String,
+    pub closed_at: Option<OffsetDateTime>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+    /// Counted on every read, writes ignore it.
+    pub num_open_issues: i64,
+    pub num_closed_issues: i64,
+}
+
+impl Milestone {
+    pub fn is_open(&self) -> bool {
+        self.state == "open"
+    }
+}
+
+const SELECT_MILESTONES: &str = "SELECT milestones.*,
+                                    (SELECT COUNT(*) FROM issues WHERE milestone_id = milestones.id AND state = 'open') AS num_open_issues,
+                                    (SELECT COUNT(*) FROM issues WHERE milestone_id = milestones.id AND state = 'closed') AS num_closed_issues
+                                 FROM milestones";
+
+pub struct Milestones {
+    pool: PgPool
+}
+
+impl Milestones {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> Milestones {
+        Milestones {
+            pool,
+        }
+    }
+
+    pub async fn create(&self, milestone: &Milestone) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("INSERT INTO milestones (id, project_id, title, description, due_on, state, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, 'open', $6, $6)")
+            .bind(&milestone.id)
+            .bind(&milestone.project_id)
+            .bind(&milestone.title)
+            .bind(&milestone.description)
+            .bind(milestone.due_on)
+            .bind(milestone.created_at)
+            .execute(&mut tx)
+            .await?;
+        refresh_counters(&mut tx, &milestone.project_id).await?;
+        tx.commit().await
+    }
+
+    pub async fn by_id(&self, id: &Uuid) -> sqlx::Result<Option<Milestone>> {
+        sqlx::query_as::<_, Milestone>(&format!("{} WHERE id = $1", SELECT_MILESTONES))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// Milestones of the project, the ones due first on top and those without a due date last.
+    pub async fn for_project(&self, project_id: &Uuid, state: Option<&str>) -> sqlx::Result<Vec<Milestone>> {
+        sqlx::query_as::<_, Milestone>(&format!("{} WHERE project_id = $1 AND ($2::TEXT IS NULL OR state = $2) ORDER BY due_on NULLS LAST, title", SELECT_MILESTONES))
+            .bind(project_id)
+            .bind(state)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Stores title, description, due date and state of the milestone.
+    pub async fn update(&self, milestone: &Milestone) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE milestones SET title = $2, description = $3, due_on = $4, state = $5, closed_at = $6, updated_at = $7 WHERE id = $1")
+            .bind(&milestone.id)
+            .bind(&milestone.title)
+            .bind(&milestone.description)
+            .bind(milestone.due_on)
+            .bind(&milestone.state)
+            .bind(milestone.closed_at)
+            .bind(milestone.updated_at)
+            .execute(&mut tx)
+            .await?;
+        refresh_counters(&mut tx, &milestone.project_id).await?;
+        tx.commit().await
+    }
+
+    /// Deletes the milestone. Its issues stay, just without a milestone.
+    pub async fn delete(&self, milestone: &Milestone) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM milestones WHERE id = $1")
+            .bind(&milestone.id)
+            .execute(&mut tx)
+            .await?;
+        refresh_counters(&mut tx, &milestone.project_id).await?;
+        tx.commit().await
+    }
+}
+
+async fn refresh_counters(tx: &mut Transaction<'_, Postgres>, project_id: &Uuid) -> sqlx::Result<()> {
+    sqlx::query("UPDATE projects SET
+                    num_milestones = (SELECT COUNT(*) FROM milestones WHERE project_id = $1),
+                    num_milestones_open = (SELECT COUNT(*) FROM milestones WHERE project_id = $1 AND state = 'open'),
+                    num_milestones_closed = (SELECT COUNT(*) FROM milestones WHERE project_id = $1 AND state = 'closed')
+                 WHERE id = $1")
+        .bind(project_id)
+        .execute(tx)
+        .await
+        .map(|_| ())
+}
@@ -0,0 +1,100 @@
+use sqlx::{FromRow, PgPool, Pool, Postgres};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+
+#[derive(Debug, Clone, FromRow)]
+pub struct Notification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub project_id: Uuid,
+    pub thread_number: i64,
+    /// Either `issue` or `pull_request`.
+    pub thread_kind: String,
+    /// Title of the issue or pull request.
+    pub subject: String,
+    /// Why the user got notified: `author`, `comment`, `mention`, `assign`, `manual` or `watching`.
+    pub reason: String,
+    /// Username of the user who caused the newest activity.
+    pub last_actor: String,
+    pub is_read: bool,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+pub struct Notifications {
+    pool: PgPool
+}
+
+impl Notifications {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> Notifications {
+        Notifications {
+            pool,
+        }
+    }
+
+    /// Adds the notification to the inbox of the user. If the user already has one for the
+    /// thread, it is updated and marked as unread again.
+    pub async fn notify(&self, notification: &Notification) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO notifications (id, user_id, project_id, thread_number, thread_kind, subject, reason, last_actor, is_read, created_at, updated_at)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, FALSE, $9, $9)
+                     ON CONFLICT (user_id, project_id, thread_number) DO UPDATE SET
+                        subject = EXCLUDED.subject, reason = EXCLUDED.reason, last_actor = EXCLUDED.last_actor, is_read = FALSE, updated_at = EXCLUDED.updated_at")
+            .bind(&notification.id)
+            .bind(&notification.user_id)
+            .bind(&notification.project_id)
+            .bind(notification.thread_number)
+            .bind(&notification.thread_kind)
+            .bind(&notification.subject)
+            .bind(&notification.reason)
+            .bind(&notification.last_actor)
+            .bind(notification.created_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn by_id(&self, id: &Uuid) -> sqlx::Result<Option<Notification>> {
+        sqlx::query_as::<_, Notification>("SELECT * FROM notifications WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// The inbox of the user, newest activity first. Read notifications are left out, unless `with_read` is set.
+    pub async fn for_user(&self, user_id: &Uuid, with_read: bool, limit: i64, offset: i64) -> sqlx::Result<Vec<Notification>> {
+        sqlx::query_as::<_, Notification>("SELECT * FROM notifications WHERE user_id = $1 AND ($2 OR NOT is_read) ORDER BY updated_at DESC LIMIT $3 OFFSET $4")
+            .bind(user_id)
+            .bind(with_read)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn count_unread(&self, user_id: &Uuid) -> sqlx::Result<i64> {
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND NOT is_read")
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count)
+    }
+
+    pub async fn set_read(&self, id: &Uuid, is_read: bool) -> sqlx::Result<()> {
+        sqlx::query("UPDATE notifications SET is_read = $2 WHERE id = $1")
+            .bind(id)
+            .bind(is_read)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    /// Marks the whole inbox of the user as read.
+    pub async fn read_all(&self, user_id: &Uuid) -> sqlx::Result<()> {
+        sqlx::query("UPDATE notifications SET is_read = TRUE WHERE user_id = $1 AND NOT is_read")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+}
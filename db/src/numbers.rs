@@ -0,0 +1,14 @@
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+
+/// Hands out the next number for an issue or pull request of the project. Issues and pull
+/// requests share their numbers, just like on GitHub.
+pub(crate) async fn next_number(tx: &mut Transaction<'_, Postgres>, project_id: &Uuid) -> sqlx::Result<i64> {
+    let (number,): (i64,) = sqlx::query_as("INSERT INTO project_numbers (project_id, last_number) VALUES ($1, 1)
+                                            ON CONFLICT (project_id) DO UPDATE SET last_number = project_numbers.last_number + 1
+                                            RETURNING last_number")
+        .bind(project_id)
+        .fetch_one(tx)
+        .await?;
+    Ok(number)
+}
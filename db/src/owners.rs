@@ -1,4 +1,5 @@
 use sqlx::{PgPool, Pool, Postgres};
+use uuid::Uuid;
 
 
 pub struct Owners {
@@ -11,4 +12,22 @@ impl Owners {
             pool,
         }
     }
+
+    /// The id of the user or organisation with the given name.
+    pub async fn find(&self, name: &str, is_organisation: bool) -> sqlx::Result<Option<Uuid>> {
+        sqlx::query_as("SELECT id FROM owners WHERE name = $1 AND is_organisation = $2")
+            .bind(name)
+            .bind(is_organisation)
+            .fetch_optional(&self.pool)
+            .await
+            .map(|row: Option<(Uuid,)>| row.map(|(id,)| id))
+    }
+
+    /// Pairs of id and name for the given users or organisations.
+    pub async fn names(&self, ids: &[Uuid]) -> sqlx::Result<Vec<(Uuid, String)>> {
+        sqlx::query_as("SELECT id, name FROM owners WHERE id = ANY($1)")
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await
+    }
 }
@@ -1,6 +1,21 @@
-use sqlx::{PgPool, Pool, Postgres};
+use sqlx::{FromRow, PgPool, Pool, Postgres};
+use time::OffsetDateTime;
+use uuid::Uuid;
 
 
+/// A project together with the name of its owner, for places that only know the id of a project.
+#[derive(Debug, Clone, FromRow)]
+pub struct ProjectSummary {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub owner: String,
+    pub owner_is_organisation: bool,
+    pub name: String,
+    pub description: Option<String>,
+    pub is_private: bool,
+    pub default_branch: Option<String>,
+}
+
 pub struct Projects {
     pool: PgPool
 }
@@ -11,4 +26,54 @@ impl Projects {
             pool,
         }
     }
+
+    /// Refreshes everything about a project that can change with a push.
+    pub async fn update_after_push(&self, id: &Uuid, is_empty: bool, default_branch: Option<&str>, disk_size: i64, updated_at: OffsetDateTime) -> sqlx::Result<()> {
+        sqlx::query("UPDATE projects SET is_empty = $2, default_branch = $3, disk_size = $4, updated_at = $5 WHERE id = $1")
+            .bind(id)
+            .bind(is_empty)
+            .bind(default_branch)
+            .bind(disk_size)
+            .bind(updated_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn update_default_branch(&self, id: &Uuid, default_branch: Option<&str>, updated_at: OffsetDateTime) -> sqlx::Result<()> {
+        sqlx::query("UPDATE projects SET default_branch = $2, updated_at = $3 WHERE id = $1")
+            .bind(id)
+            .bind(default_branch)
+            .bind(updated_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn summary(&self, id: &Uuid) -> sqlx::Result<Option<ProjectSummary>> {
+        sqlx::query_as::<_, ProjectSummary>("SELECT p.id, p.owner_id, o.name AS owner, o.is_organisation AS owner_is_organisation, p.name, p.description, p.is_private, p.default_branch
+                                            FROM projects p JOIN owners o ON o.id = p.owner_id WHERE p.id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// The id of the project the given project is a fork of, `None` if it isn't a fork.
+    pub async fn forked_from(&self, id: &Uuid) -> sqlx::Result<Option<Uuid>> {
+        sqlx::query_as("SELECT forked_project FROM projects WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map(|row: Option<(Option<Uuid>,)>| row.and_then(|(forked,)| forked))
+    }
+
+    /// The id of the project with the given owner and name.
+    pub async fn find(&self, owner: &str, name: &str) -> sqlx::Result<Option<Uuid>> {
+        sqlx::query_as("SELECT p.id FROM projects p JOIN owners o ON o.id = p.owner_id WHERE o.name = $1 AND p.name = $2")
+            .bind(owner)
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await
+            .map(|row: Option<(Uuid,)>| row.map(|(id,)| id))
+    }
 }
@@ -0,0 +1,84 @@
+use sqlx::{FromRow, PgPool, Pool, Postgres};
+use uuid::Uuid;
+
+
+/// A rule that protects all branches of a project, whose name matches `pattern`.
+#[derive(Debug, Clone, FromRow)]
+pub struct ProtectedBranch {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    /// Branch name or glob, like `main` or `release/*`.
+    pub pattern: String,
+    pub allow_force_push: bool,
+    pub allow_deletion: bool,
+    /// If both lists are empty, everyone with write access may push.
+    pub push_user_ids: Vec<Uuid>,
+    pub push_team_ids: Vec<Uuid>,
+    pub require_linear_history: bool,
+    /// Contexts of commit statuses that must be successful, before a pull request can be merged.
+    pub required_status_checks: Vec<String>,
+    /// Approving reviews that are needed, before a pull request can be merged.
+    pub required_approvals: i32,
+    /// Whether approvals are dismissed, when new commits are pushed to the pull request.
+    pub dismiss_stale_approvals: bool,
+    /// Whether every changed file needs the approval of one of its code owners.
+    pub require_code_owner_review: bool,
+}
+
+pub struct ProtectedBranches {
+    pool: PgPool
+}
+
+impl ProtectedBranches {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> ProtectedBranches {
+        ProtectedBranches {
+            pool,
+        }
+    }
+
+    pub async fn for_project(&self, project_id: &Uuid) -> sqlx::Result<Vec<ProtectedBranch>> {
+        sqlx::query_as::<_, ProtectedBranch>("SELECT * FROM protected_branches WHERE project_id = $1 ORDER BY pattern")
+            .bind(project_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn save(&self, rule: &ProtectedBranch) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO protected_branches (id, project_id, pattern, allow_force_push, allow_deletion, push_user_ids, push_team_ids, require_linear_history, required_status_checks, required_approvals, dismiss_stale_approvals, require_code_owner_review)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                     ON CONFLICT (id) DO UPDATE SET
+                        pattern = EXCLUDED.pattern,
+                        allow_force_push = EXCLUDED.allow_force_push,
+                        allow_deletion = EXCLUDED.allow_deletion,
+                        push_user_ids = EXCLUDED.push_user_ids,
+                        push_team_ids = EXCLUDED.push_team_ids,
+                        require_linear_history = EXCLUDED.require_linear_history,
+                        required_status_checks = EXCLUDED.required_status_checks,
+                        required_approvals = EXCLUDED.required_approvals,
+                        dismiss_stale_approvals = EXCLUDED.dismiss_stale_approvals,
+                        require_code_owner_review = EXCLUDED.require_code_owner_review")
+            .bind(&rule.id)
+            .bind(&rule.project_id)
+            .bind(&rule.pattern)
+            .bind(rule.allow_force_push)
+            .bind(rule.allow_deletion)
+            .bind(&rule.push_user_ids)
+            .bind(&rule.push_team_ids)
+            .bind(rule.require_linear_history)
+            .bind(&rule.required_status_checks)
+            .bind(rule.required_approvals)
+            .bind(rule.dismiss_stale_approvals)
+            .bind(rule.require_code_owner_review)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn delete(&self, id: &Uuid) -> sqlx::Result<()> {
+        sqlx::query("DELETE FROM protected_branches WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+}
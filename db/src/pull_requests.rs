@@ -0,0 +1,221 @@
+use sqlx::{FromRow, PgPool, Pool, Postgres, Transaction};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use crate::numbers::next_number;
+
+
+#[derive(Debug, Clone, FromRow)]
+pub struct PullRequest {
+    pub id: Uuid,
+    /// The project the pull request wants to be merged into.
+    pub project_id: Uuid,
+    /// Number of the pull request inside its project, starting at 1.
+    pub number: i64,
+    pub title: String,
+    pub description: Option<String>,
+    pub author_id: Uuid,
+    /// The project of the head branch. It differs from `project_id` for pull requests from a fork.
+    pub head_project_id: Uuid,
+    pub head_branch: String,
+    pub head_commit_id: String,
+    pub base_branch: String,
+    pub base_commit_id: String,
+    pub merge_base_id: Option<String>,
+    pub is_draft: bool,
+    /// One of `open`, `closed` or `merged`.
+    pub state: String,
+    pub merge_commit_id: Option<String>,
+    pub merged_by: Option<Uuid>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+    pub closed_at: Option<OffsetDateTime>,
+    pub merged_at: Option<OffsetDateTime>,
+}
+
+impl PullRequest {
+    pub fn is_open(&self) -> bool {
+        self.state == "open"
+    }
+
+    pub fn is_from_fork(&self) -> bool {
+        self.project_id != self.head_project_id
+    }
+}
+
+pub struct PullRequests {
+    pool: PgPool
+}
+
+impl PullRequests {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> PullRequests {
+        PullRequests {
+            pool,
+        }
+    }
+
+    /// Stores a new pull request with the next free number of its project and returns that number.
+    pub async fn create(&self, pull: &PullRequest) -> sqlx::Result<i64> {
+        let mut tx = self.pool.begin().await?;
+        let number = next_number(&mut tx, &pull.project_id).await?;
+        sqlx::query("INSERT INTO pull_requests (id, project_id, number, title, description, author_id, head_project_id, head_branch, head_commit_id, base_branch, base_commit_id, merge_base_id, is_draft, state, created_at, updated_at)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, 'open', $14, $14)")
+            .bind(&pull.id)
+            .bind(&pull.project_id)
+            .bind(number)
+            .bind(&pull.title)
+            .bind(&pull.description)
+            .bind(&pull.author_id)
+            .bind(&pull.head_project_id)
+            .bind(&pull.head_branch)
+            .bind(&pull.head_commit_id)
+            .bind(&pull.base_branch)
+            .bind(&pull.base_commit_id)
+            .bind(&pull.merge_base_id)
+            .bind(pull.is_draft)
+            .bind(pull.created_at)
+            .execute(&mut tx)
+            .await?;
+        refresh_counters(&mut tx, &pull.project_id).await?;
+        tx.commit().await?;
+        Ok(number)
+    }
+
+    pub async fn by_id(&self, id: &Uuid) -> sqlx::Result<Option<PullRequest>> {
+        sqlx::query_as::<_, PullRequest>("SELECT * FROM pull_requests WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    pub async fn by_number(&self, project_id: &Uuid, number: i64) -> sqlx::Result<Option<PullRequest>> {
+        sqlx::query_as::<_, PullRequest>("SELECT * FROM pull_requests WHERE project_id = $1 AND number = $2")
+            .bind(project_id)
+            .bind(number)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// Pull requests of a project, newest first. Without a state, all pull requests are returned.
+    pub async fn for_project(&self, project_id: &Uuid, state: Option<&str>, limit: i64, offset: i64) -> sqlx::Result<Vec<PullRequest>> {
+        sqlx::query_as::<_, PullRequest>("SELECT * FROM pull_requests WHERE project_id = $1 AND ($2::TEXT IS NULL OR state = $2) ORDER BY number DESC LIMIT $3 OFFSET $4")
+            .bind(project_id)
+            .bind(state)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Open pull requests, whose head is the given branch.
+    pub async fn open_for_head(&self, head_project_id: &Uuid, head_branch: &str) -> sqlx::Result<Vec<PullRequest>> {
+        sqlx::query_as::<_, PullRequest>("SELECT * FROM pull_requests WHERE head_project_id = $1 AND head_branch = $2 AND state = 'open'")
+            .bind(head_project_id)
+            .bind(head_branch)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Open pull requests, that want to be merged into the given branch.
+    pub async fn open_for_base(&self, project_id: &Uuid, base_branch: &str) -> sqlx::Result<Vec<PullRequest>> {
+        sqlx::query_as::<_, PullRequest>("SELECT * FROM pull_requests WHERE project_id = $1 AND base_branch = $2 AND state = 'open'")
+            .bind(project_id)
+            .bind(base_branch)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// The open pull request between exactly these branches, if there is one.
+    pub async fn find_open(&self, project_id: &Uuid, base_branch: &str, head_project_id: &Uuid, head_branch: &str) -> sqlx::Result<Option<PullRequest>> {
+        sqlx::query_as::<_, PullRequest>("SELECT * FROM pull_requests WHERE project_id = $1 AND base_branch = $2 AND head_project_id = $3 AND head_branch = $4 AND state = 'open'")
+            .bind(project_id)
+            .bind(base_branch)
+            .bind(head_project_id)
+            .bind(head_branch)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    pub async fn update_details(&self, id: &Uuid, title: &str, description: Option<&str>, is_draft: bool, updated_at: OffsetDateTime) -> sqlx::Result<()> {
+        sqlx::query("UPDATE pull_requests SET title = $2, description = $3, is_draft = $4, updated_at = $5 WHERE id = $1")
+            .bind(id)
+            .bind(title)
+            .bind(description)
+            .bind(is_draft)
+            .bind(updated_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn update_head(&self, id: &Uuid, head_commit_id: &str, merge_base_id: Option<&str>, updated_at: OffsetDateTime) -> sqlx::Result<()> {
+        sqlx::query("UPDATE pull_requests SET head_commit_id = $2, merge_base_id = $3, updated_at = $4 WHERE id = $1")
+            .bind(id)
+            .bind(head_commit_id)
+            .bind(merge_base_id)
+            .bind(updated_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn update_base(&self, id: &Uuid, base_commit_id: &str, merge_base_id: Option<&str>, updated_at: OffsetDateTime) -> sqlx::Result<()> {
+        sqlx::query("UPDATE pull_requests SET base_commit_id = $2, merge_base_id = $3, updated_at = $4 WHERE id = $1")
+            .bind(id)
+            .bind(base_commit_id)
+            .bind(merge_base_id)
+            .bind(updated_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn close(&self, pull: &PullRequest, closed_at: OffsetDateTime) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE pull_requests SET state = 'closed', closed_at = $2, updated_at = $2 WHERE id = $1 AND state = 'open'")
+            .bind(&pull.id)
+            .bind(closed_at)
+            .execute(&mut tx)
+            .await?;
+        refresh_counters(&mut tx, &pull.project_id).await?;
+        tx.commit().await
+    }
+
+    /// Marks the pull request as merged. `merged_by` is `None`, if the head got merged by a push.
+    pub async fn mark_merged(&self, pull: &PullRequest, merge_commit_id: &str, merged_by: Option<&Uuid>, merged_at: OffsetDateTime) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE pull_requests SET state = 'merged', merge_commit_id = $2, merged_by = $3, merged_at = $4, closed_at = $4, updated_at = $4 WHERE id = $1 AND state = 'open'")
+            .bind(&pull.id)
+            .bind(merge_commit_id)
+            .bind(merged_by)
+            .bind(merged_at)
+            .execute(&mut tx)
+            .await?;
+        refresh_counters(&mut tx, &pull.project_id).await?;
+        tx.commit().await
+    }
+
+    pub async fn reopen(&self, pull: &PullRequest, reopened_at: OffsetDateTime) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE pull_requests SET state = 'open', closed_at = NULL, updated_at = $2 WHERE id = $1 AND state = 'closed'")
+            .bind(&pull.id)
+            .bind(reopened_at)
+            .execute(&mut tx)
+            .await?;
+        refresh_counters(&mut tx, &pull.project_id).await?;
+        tx.commit().await
+    }
+}
+
+/// Counts the pull requests of the project again. Counting instead of incrementing keeps
+/// the counters right, even if a change got lost somewhere.
+async fn refresh_counters(tx: &mut Transaction<'_, Postgres>, project_id: &Uuid) -> sqlx::Result<()> {
+    sqlx::query("UPDATE projects SET
+                    num_pull_reqs = (SELECT COUNT(*) FROM pull_requests WHERE project_id = $1),
+                    num_pull_reqs_open = (SELECT COUNT(*) FROM pull_requests WHERE project_id = $1 AND state = 'open'),
+                    num_pull_reqs_closed = (SELECT COUNT(*) FROM pull_requests WHERE project_id = $1 AND state <> 'open')
+                 WHERE id = $1")
+        .bind(project_id)
+        .execute(tx)
+        .await
+        .map(|_| ())
+}
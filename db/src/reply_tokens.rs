@@ -0,0 +1,51 @@
+use sqlx::{FromRow, PgPool, Pool, Postgres};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ReplyToken {
+    pub token: String,
+    pub user_id: Uuid,
+    pub project_id: Uuid,
+    pub thread_number: i64,
+    /// Either `issue` or `pull_request`.
+    pub thread_kind: String,
+    pub created_at: OffsetDateTime,
+}
+
+pub struct ReplyTokens {
+    pool: PgPool
+}
+
+impl ReplyTokens {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> ReplyTokens {
+        ReplyTokens {
+            pool,
+        }
+    }
+
+    /// Stores the token, unless the user already has one for the thread. Returns the token
+    /// that is in use, so every notification of a thread carries the same reply address.
+    pub async fn get_or_create(&self, token: &ReplyToken) -> sqlx::Result<String> {
+        let (token,): (String,) = sqlx::query_as("INSERT INTO reply_tokens (token, user_id, project_id, thread_number, thread_kind, created_at) VALUES ($1, $2, $3, $4, $5, $6)
+                                                  ON CONFLICT (user_id, project_id, thread_number) DO UPDATE SET token = reply_tokens.token
+                                                  RETURNING token")
+            .bind(&token.token)
+            .bind(&token.user_id)
+            .bind(&token.project_id)
+            .bind(token.thread_number)
+            .bind(&token.thread_kind)
+            .bind(token.created_at)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(token)
+    }
+
+    pub async fn by_token(&self, token: &str) -> sqlx::Result<Option<ReplyToken>> {
+        sqlx::query_as::<_, ReplyToken>("SELECT * FROM reply_tokens WHERE token = $1")
+            .bind(token)
+            .fetch_optional(&self.pool)
+            .await
+    }
+}
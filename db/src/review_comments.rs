@@ -0,0 +1,77 @@
+use sqlx::{FromRow, PgPool, Pool, Postgres};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ReviewComment {
+    pub id: Uuid,
+    pub thread_id: Uuid,
+    pub author_id: Uuid,
+    pub body: String,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+pub struct ReviewComments {
+    pool: PgPool
+}
+
+impl ReviewComments {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> ReviewComments {
+        ReviewComments {
+            pool,
+        }
+    }
+
+    pub async fn create(&self, comment: &ReviewComment) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO review_comments (id, thread_id, author_id, body, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $5)")
+            .bind(&comment.id)
+            .bind(&comment.thread_id)
+            .bind(&comment.author_id)
+            .bind(&comment.body)
+            .bind(comment.created_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn by_id(&self, id: &Uuid) -> sqlx::Result<Option<ReviewComment>> {
+        sqlx::query_as::<_, ReviewComment>("SELECT * FROM review_comments WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// Comments of all given threads, oldest first.
+    pub async fn for_threads(&self, thread_ids: &[Uuid]) -> sqlx::Result<Vec<ReviewComment>> {
+        sqlx::query_as::<_, ReviewComment>("SELECT * FROM review_comments WHERE thread_id = ANY($1) ORDER BY created_at")
+            .bind(thread_ids)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn update_body(&self, id: &Uuid, body: &str, updated_at: OffsetDateTime) -> sqlx::Result<()> {
+        sqlx::query("UPDATE review_comments SET body = $2, updated_at = $3 WHERE id = $1")
+            .bind(id)
+            .bind(body)
+            .bind(updated_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    /// Deletes the comment. A thread without comments is deleted as well.
+    pub async fn delete(&self, comment: &ReviewComment) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM review_comments WHERE id = $1")
+            .bind(&comment.id)
+            .execute(&mut tx)
+            .await?;
+        sqlx::query("DELETE FROM review_threads WHERE id = $1 AND NOT EXISTS (SELECT 1 FROM review_comments WHERE thread_id = $1)")
+            .bind(&comment.thread_id)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await
+    }
+}
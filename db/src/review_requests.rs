@@ -0,0 +1,64 @@
+use sqlx::{FromRow, PgPool, Pool, Postgres};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+
+/// A user or a team that is asked to review a pull request.
+#[derive(Debug, Clone, FromRow)]
+pub struct ReviewRequest {
+    pub id: Uuid,
+    pub pull_request_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub team_id: Option<Uuid>,
+    /// `None` for requests that were made automatically.
+    pub requested_by: Option<Uuid>,
+    /// Whether the reviewer was requested, because they own some of the changed files.
+    pub is_code_owner: bool,
+    pub created_at: OffsetDateTime,
+}
+
+pub struct ReviewRequests {
+    pool: PgPool
+}
+
+impl ReviewRequests {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> ReviewRequests {
+        ReviewRequests {
+            pool,
+        }
+    }
+
+    /// Stores the request, unless the user or team was already requested.
+    pub async fn create(&self, request: &ReviewRequest) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO review_requests (id, pull_request_id, user_id, team_id, requested_by, is_code_owner, created_at)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7)
+                     ON CONFLICT DO NOTHING")
+            .bind(&request.id)
+            .bind(&request.pull_request_id)
+            .bind(&request.user_id)
+            .bind(&request.team_id)
+            .bind(&request.requested_by)
+            .bind(request.is_code_owner)
+            .bind(request.created_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn for_pull_request(&self, pull_request_id: &Uuid) -> sqlx::Result<Vec<ReviewRequest>> {
+        sqlx::query_as::<_, ReviewRequest>("SELECT * FROM review_requests WHERE pull_request_id = $1 ORDER BY created_at")
+            .bind(pull_request_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Removes the request for the user, i.e. because they submitted their review.
+    pub async fn remove_user(&self, pull_request_id: &Uuid, user_id: &Uuid) -> sqlx::Result<()> {
+        sqlx::query("DELETE FROM review_requests WHERE pull_request_id = $1 AND user_id = $2")
+            .bind(pull_request_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+}
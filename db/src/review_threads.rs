@@ -0,0 +1,115 @@
+use sqlx::{FromRow, PgPool, Pool, Postgres};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use crate::review_comments::ReviewComment;
+
+
+/// Comments on a range of lines in the diff of a pull request.
+#[derive(Debug, Clone, FromRow)]
+pub struct ReviewThread {
+    pub id: Uuid,
+    pub pull_request_id: Uuid,
+    pub path: String,
+    /// `old` for lines of the merge base, `new` for lines of the head.
+    pub side: String,
+    /// Commit and lines the thread was started on.
+    pub original_commit_id: String,
+    pub original_start_line: i32,
+    pub original_end_line: i32,
+    /// Commit and lines the thread belongs to now. They follow new pushes, until the lines get changed.
+    pub commit_id: String,
+    pub start_line: i32,
+    pub end_line: i32,
+    pub is_outdated: bool,
+    pub is_resolved: bool,
+    pub resolved_by: Option<Uuid>,
+    pub resolved_at: Option<OffsetDateTime>,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime,
+}
+
+pub struct ReviewThreads {
+    pool: PgPool
+}
+
+impl ReviewThreads {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> ReviewThreads {
+        ReviewThreads {
+            pool,
+        }
+    }
+
+    /// Creates the thread together with its first comment, a thread is never without comments.
+    pub async fn create(&self, thread: &ReviewThread, first_comment: &ReviewComment) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("INSERT INTO review_threads (id, pull_request_id, path, side, original_commit_id, original_start_line, original_end_line, commit_id, start_line, end_line, created_at, updated_at)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $5, $6, $7, $8, $8)")
+            .bind(&thread.id)
+            .bind(&thread.pull_request_id)
+            .bind(&thread.path)
+            .bind(&thread.side)
+            .bind(&thread.original_commit_id)
+            .bind(thread.original_start_line)
+            .bind(thread.original_end_line)
+            .bind(thread.created_at)
+            .execute(&mut tx)
+            .await?;
+        sqlx::query("INSERT INTO review_comments (id, thread_id, author_id, body, created_at, updated_at) VALUES ($1, $2, $3, $4, $5, $5)")
+            .bind(&first_comment.id)
+            .bind(&first_comment.thread_id)
+            .bind(&first_comment.author_id)
+            .bind(&first_comment.body)
+            .bind(first_comment.created_at)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await
+    }
+
+    pub async fn by_id(&self, id: &Uuid) -> sqlx::Result<Option<ReviewThread>> {
+        sqlx::query_as::<_, ReviewThread>("SELECT * FROM review_threads WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// All threads of a pull request, ordered by file and line.
+    pub async fn for_pull_request(&self, pull_request_id: &Uuid) -> sqlx::Result<Vec<ReviewThread>> {
+        sqlx::query_as::<_, ReviewThread>("SELECT * FROM review_threads WHERE pull_request_id = $1 ORDER BY path, start_line, created_at")
+            .bind(pull_request_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Moves the thread to the same lines in a newer commit.
+    pub async fn update_anchor(&self, id: &Uuid, commit_id: &str, start_line: i32, end_line: i32, updated_at: OffsetDateTime) -> sqlx::Result<()> {
+        sqlx::query("UPDATE review_threads SET commit_id = $2, start_line = $3, end_line = $4, updated_at = $5 WHERE id = $1")
+            .bind(id)
+            .bind(commit_id)
+            .bind(start_line)
+            .bind(end_line)
+            .bind(updated_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn mark_outdated(&self, id: &Uuid, updated_at: OffsetDateTime) -> sqlx::Result<()> {
+        sqlx::query("UPDATE review_threads SET is_outdated = TRUE, updated_at = $2 WHERE id = $1")
+            .bind(id)
+            .bind(updated_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    /// Resolves the thread, or unresolves it without a user.
+    pub async fn set_resolved(&self, id: &Uuid, resolved_by: Option<&Uuid>, updated_at: OffsetDateTime) -> sqlx::Result<()> {
+        sqlx::query("UPDATE review_threads SET is_resolved = $2::UUID IS NOT NULL, resolved_by = $2, resolved_at = CASE WHEN $2::UUID IS NULL THEN NULL ELSE $3 END, updated_at = $3 WHERE id = $1")
+            .bind(id)
+            .bind(resolved_by)
+            .bind(updated_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+}
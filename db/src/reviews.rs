@@ -0,0 +1,82 @@
+use sqlx::{FromRow, PgPool, Pool, Postgres};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+
+#[derive(Debug, Clone, FromRow)]
+pub struct Review {
+    pub id: Uuid,
+    pub pull_request_id: Uuid,
+    pub author_id: Uuid,
+    /// One of `approved`, `changes_requested`, `commented` or `dismissed`.
+    pub state: String,
+    pub body: Option<String>,
+    /// Head of the pull request at the time of the review.
+    pub commit_id: String,
+    /// Whether the author could write to the project. Only official reviews count towards required approvals.
+    pub is_official: bool,
+    pub created_at: OffsetDateTime,
+    pub dismissed_at: Option<OffsetDateTime>,
+}
+
+pub struct Reviews {
+    pool: PgPool
+}
+
+impl Reviews {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> Reviews {
+        Reviews {
+            pool,
+        }
+    }
+
+    pub async fn create(&self, review: &Review) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO reviews (id, pull_request_id, author_id, state, body, commit_id, is_official, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)")
+            .bind(&review.id)
+            .bind(&review.pull_request_id)
+            .bind(&review.author_id)
+            .bind(&review.state)
+            .bind(&review.body)
+            .bind(&review.commit_id)
+            .bind(review.is_official)
+            .bind(review.created_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn by_id(&self, id: &Uuid) -> sqlx::Result<Option<Review>> {
+        sqlx::query_as::<_, Review>("SELECT * FROM reviews WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// All reviews of a pull request, oldest first.
+    pub async fn for_pull_request(&self, pull_request_id: &Uuid) -> sqlx::Result<Vec<Review>> {
+        sqlx::query_as::<_, Review>("SELECT * FROM reviews WHERE pull_request_id = $1 ORDER BY created_at")
+            .bind(pull_request_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn dismiss(&self, id: &Uuid, dismissed_at: OffsetDateTime) -> sqlx::Result<()> {
+        sqlx::query("UPDATE reviews SET state = 'dismissed', dismissed_at = $2 WHERE id = $1 AND state IN ('approved', 'changes_requested')")
+            .bind(id)
+            .bind(dismissed_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    /// Dismisses all approvals that were given for another head than `head_commit_id`.
+    pub async fn dismiss_stale_approvals(&self, pull_request_id: &Uuid, head_commit_id: &str, dismissed_at: OffsetDateTime) -> sqlx::Result<u64> {
+        sqlx::query("UPDATE reviews SET state = 'dismissed', dismissed_at = $3 WHERE pull_request_id = $1 AND commit_id <> $2 AND state = 'approved'")
+            .bind(pull_request_id)
+            .bind(head_commit_id)
+            .bind(dismissed_at)
+            .execute(&self.pool)
+            .await
+            .map(|done| done.rows_affected())
+    }
+}
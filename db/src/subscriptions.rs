@@ -0,0 +1,75 @@
+use sqlx::{FromRow, PgPool, Pool, Postgres};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+
+/// Whether a user gets notified about an issue or pull request.
+#[derive(Debug, Clone, FromRow)]
+pub struct Subscription {
+    pub user_id: Uuid,
+    pub project_id: Uuid,
+    pub thread_number: i64,
+    pub is_subscribed: bool,
+    /// Why the user got subscribed: `author`, `comment`, `mention`, `assign` or `manual`.
+    pub reason: String,
+    pub created_at: OffsetDateTime,
+}
+
+pub struct Subscriptions {
+    pool: PgPool
+}
+
+impl Subscriptions {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> Subscriptions {
+        Subscriptions {
+            pool,
+        }
+    }
+
+    pub async fn get(&self, user_id: &Uuid, project_id: &Uuid, thread_number: i64) -> sqlx::Result<Option<Subscription>> {
+        sqlx::query_as::<_, Subscription>("SELECT * FROM subscriptions WHERE user_id = $1 AND project_id = $2 AND thread_number = $3")
+            .bind(user_id)
+            .bind(project_id)
+            .bind(thread_number)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// Subscriptions and unsubscriptions of everybody to the thread.
+    pub async fn for_thread(&self, project_id: &Uuid, thread_number: i64) -> sqlx::Result<Vec<Subscription>> {
+        sqlx::query_as::<_, Subscription>("SELECT * FROM subscriptions WHERE project_id = $1 AND thread_number = $2")
+            .bind(project_id)
+            .bind(thread_number)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Subscribes the user because of taking part in the thread. Users who unsubscribed
+    /// stay unsubscribed.
+    pub async fn subscribe_implicitly(&self, subscription: &Subscription) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO subscriptions (user_id, project_id, thread_number, is_subscribed, reason, created_at) VALUES ($1, $2, $3, TRUE, $4, $5) ON CONFLICT DO NOTHING")
+            .bind(&subscription.user_id)
+            .bind(&subscription.project_id)
+            .bind(subscription.thread_number)
+            .bind(&subscription.reason)
+            .bind(subscription.created_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    /// Stores the explicit choice of the user, which wins over everything else.
+    pub async fn set(&self, subscription: &Subscription) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO subscriptions (user_id, project_id, thread_number, is_subscribed, reason, created_at) VALUES ($1, $2, $3, $4, $5, $6)
+                     ON CONFLICT (user_id, project_id, thread_number) DO UPDATE SET is_subscribed = EXCLUDED.is_subscribed, reason = EXCLUDED.reason")
+            .bind(&subscription.user_id)
+            .bind(&subscription.project_id)
+            .bind(subscription.thread_number)
+            .bind(subscription.is_subscribed)
+            .bind(&subscription.reason)
+            .bind(subscription.created_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+}
@@ -0,0 +1,57 @@
+use sqlx::{PgPool, Pool, Postgres};
+use uuid::Uuid;
+
+
+pub struct Teams {
+    pool: PgPool
+}
+
+impl Teams {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> Teams {
+        Teams {
+            pool,
+        }
+    }
+
+    /// Checks if the user is a member of at least one of the given teams.
+    pub async fn is_member_of_any(&self, user_id: &Uuid, team_ids: &[Uuid]) -> sqlx::Result<bool> {
+        if team_ids.is_empty() {
+            return Ok(false);
+        }
+
+        let (is_member,): (bool,) = sqlx::query_as("SELECT EXISTS (SELECT 1 FROM team_members WHERE user_id = $1 AND team_id = ANY($2))")
+            .bind(user_id)
+            .bind(team_ids)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(is_member)
+    }
+
+    /// Checks if the user is a member of any team of the organisation.
+    pub async fn is_organisation_member(&self, organisation_id: &Uuid, user_id: &Uuid) -> sqlx::Result<bool> {
+        let (is_member,): (bool,) = sqlx::query_as("SELECT EXISTS (SELECT 1 FROM team_members m JOIN teams t ON t.id = m.team_id WHERE t.organisation_id = $1 AND m.user_id = $2)")
+            .bind(organisation_id)
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(is_member)
+    }
+
+    /// The id of the team of the organisation with the given name.
+    pub async fn find(&self, organisation_id: &Uuid, name: &str) -> sqlx::Result<Option<Uuid>> {
+        sqlx::query_as("SELECT id FROM teams WHERE organisation_id = $1 AND name = $2")
+            .bind(organisation_id)
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await
+            .map(|row: Option<(Uuid,)>| row.map(|(id,)| id))
+    }
+
+    /// Pairs of id and name for the given teams.
+    pub async fn names(&self, ids: &[Uuid]) -> sqlx::Result<Vec<(Uuid, String)>> {
+        sqlx::query_as("SELECT id, name FROM teams WHERE id = ANY($1)")
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await
+    }
+}
@@ -1,4 +1,5 @@
 use sqlx::{PgPool, Pool, Postgres};
+use uuid::Uuid;
 
 
 pub struct Users {
@@ -11,4 +12,13 @@ impl Users {
             pool,
         }
     }
+
+    /// Whether the user is an admin of this instance. Unknown users are no admins.
+    pub async fn is_admin(&self, id: &Uuid) -> sqlx::Result<bool> {
+        sqlx::query_as("SELECT is_admin FROM users WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map(|row: Option<(bool,)>| row.map_or(false, |(is_admin,)| is_admin))
+    }
 }
@@ -0,0 +1,65 @@
+use sqlx::{PgPool, Pool, Postgres, Transaction};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+
+pub struct Watches {
+    pool: PgPool
+}
+
+impl Watches {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> Watches {
+        Watches {
+            pool,
+        }
+    }
+
+    pub async fn is_watching(&self, user_id: &Uuid, project_id: &Uuid) -> sqlx::Result<bool> {
+        let (is_watching,): (bool,) = sqlx::query_as("SELECT EXISTS (SELECT 1 FROM watches WHERE user_id = $1 AND project_id = $2)")
+            .bind(user_id)
+            .bind(project_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(is_watching)
+    }
+
+    /// Ids of all users who watch the project.
+    pub async fn watchers(&self, project_id: &Uuid) -> sqlx::Result<Vec<Uuid>> {
+        sqlx::query_as("SELECT user_id FROM watches WHERE project_id = $1")
+            .bind(project_id)
+            .fetch_all(&self.pool)
+            .await
+            .map(|rows: Vec<(Uuid,)>| rows.into_iter().map(|(id,)| id).collect())
+    }
+
+    pub async fn watch(&self, user_id: &Uuid, project_id: &Uuid, created_at: OffsetDateTime) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("INSERT INTO watches (user_id, project_id, created_at) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")
+            .bind(user_id)
+            .bind(project_id)
+            .bind(created_at)
+            .execute(&mut tx)
+            .await?;
+        refresh_counter(&mut tx, project_id).await?;
+        tx.commit().await
+    }
+
+    pub async fn unwatch(&self, user_id: &Uuid, project_id: &Uuid) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM watches WHERE user_id = $1 AND project_id = $2")
+            .bind(user_id)
+            .bind(project_id)
+            .execute(&mut tx)
+            .await?;
+        refresh_counter(&mut tx, project_id).await?;
+        tx.commit().await
+    }
+}
+
+async fn refresh_counter(tx: &mut Transaction<'_, Postgres>, project_id: &Uuid) -> sqlx::Result<()> {
+    sqlx::query("UPDATE projects SET num_watches = (SELECT COUNT(*) FROM watches WHERE project_id = $1) WHERE id = $1")
+        .bind(project_id)
+        .execute(tx)
+        .await
+        .map(|_| ())
+}
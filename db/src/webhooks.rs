@@ -0,0 +1,155 @@
+use sqlx::{FromRow, PgPool, Pool, Postgres};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+
+/// A webhook belongs either to a project or to an organisation. Organisation webhooks
+/// get the events of all projects of the organisation.
+#[derive(Debug, Clone, FromRow)]
+pub struct Webhook {
+    pub id: Uuid,
+    pub project_id: Option<Uuid>,
+    pub organisation_id: Option<Uuid>,
+    pub url: String,
+    /// Used to sign the payload with HMAC-SHA256.
+    pub secret: Option<String>,
+    /// Names of the events this webhook is interested in. Empty means all events.
+    pub events: Vec<String>,
+    pub is_active: bool,
+    pub created_at: OffsetDateTime,
+}
+
+impl Webhook {
+    pub fn wants(&self, event: &str) -> bool {
+        self.is_active && (self.events.is_empty() || self.events.iter().any(|e| e == event))
+    }
+}
+
+/// A single delivery of an event to a webhook, including every detail of the last attempt.
+#[derive(Debug, Clone, FromRow)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event: String,
+    /// Request headers as JSON object.
+    pub request_headers: String,
+    pub request_body: String,
+    pub response_status: Option<i32>,
+    /// Response headers as JSON object.
+    pub response_headers: Option<String>,
+    pub response_body: Option<String>,
+    pub is_success: bool,
+    pub attempts: i32,
+    pub duration_ms: i64,
+    pub delivered_at: OffsetDateTime,
+}
+
+pub struct Webhooks {
+    pool: PgPool
+}
+
+impl Webhooks {
+    pub(crate) fn with_pool(pool: Pool<Postgres>) -> Webhooks {
+        Webhooks {
+            pool,
+        }
+    }
+
+    pub async fn by_id(&self, id: &Uuid) -> sqlx::Result<Option<Webhook>> {
+        sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    pub async fn for_project(&self, project_id: &Uuid) -> sqlx::Result<Vec<Webhook>> {
+        sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks WHERE project_id = $1 ORDER BY created_at")
+            .bind(project_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn for_organisation(&self, organisation_id: &Uuid) -> sqlx::Result<Vec<Webhook>> {
+        sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks WHERE organisation_id = $1 ORDER BY created_at")
+            .bind(organisation_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// All active webhooks that get the events of the project, including the ones of its organisation.
+    pub async fn active_for(&self, project_id: &Uuid, organisation_id: Option<&Uuid>) -> sqlx::Result<Vec<Webhook>> {
+        sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks WHERE is_active AND (project_id = $1 OR organisation_id = $2)")
+            .bind(project_id)
+            .bind(organisation_id)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn create(&self, webhook: &Webhook) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO webhooks (id, project_id, organisation_id, url, secret, events, is_active, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)")
+            .bind(&webhook.id)
+            .bind(&webhook.project_id)
+            .bind(&webhook.organisation_id)
+            .bind(&webhook.url)
+            .bind(&webhook.secret)
+            .bind(&webhook.events)
+            .bind(webhook.is_active)
+            .bind(webhook.created_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn delete(&self, id: &Uuid) -> sqlx::Result<()> {
+        sqlx::query("DELETE FROM webhooks WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+
+    /// The newest deliveries of a webhook first.
+    pub async fn deliveries(&self, webhook_id: &Uuid, limit: i64) -> sqlx::Result<Vec<WebhookDelivery>> {
+        sqlx::query_as::<_, WebhookDelivery>("SELECT * FROM webhook_deliveries WHERE webhook_id = $1 ORDER BY delivered_at DESC LIMIT $2")
+            .bind(webhook_id)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn delivery_by_id(&self, id: &Uuid) -> sqlx::Result<Option<WebhookDelivery>> {
+        sqlx::query_as::<_, WebhookDelivery>("SELECT * FROM webhook_deliveries WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// Inserts the delivery or updates it with the result of the latest attempt.
+    pub async fn save_delivery(&self, delivery: &WebhookDelivery) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO webhook_deliveries (id, webhook_id, event, request_headers, request_body, response_status, response_headers, response_body, is_success, attempts, duration_ms, delivered_at)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                     ON CONFLICT (id) DO UPDATE SET
+                        response_status = EXCLUDED.response_status,
+                        response_headers = EXCLUDED.response_headers,
+                        response_body = EXCLUDED.response_body,
+                        is_success = EXCLUDED.is_success,
+                        attempts = EXCLUDED.attempts,
+                        duration_ms = EXCLUDED.duration_ms,
+                        delivered_at = EXCLUDED.delivered_at")
+            .bind(&delivery.id)
+            .bind(&delivery.webhook_id)
+            .bind(&delivery.event)
+            .bind(&delivery.request_headers)
+            .bind(&delivery.request_body)
+            .bind(&delivery.response_status)
+            .bind(&delivery.response_headers)
+            .bind(&delivery.response_body)
+            .bind(delivery.is_success)
+            .bind(delivery.attempts)
+            .bind(delivery.duration_ms)
+            .bind(delivery.delivered_at)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+    }
+}
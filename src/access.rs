@@ -0,0 +1,43 @@
+use rocket::http::Status;
+use uuid::Uuid;
+use zorgit_common::{Project, access::{self, Scope}, entities::User};
+use zorgit_db::{Database, ProjectSummary};
+use zorgit_vcs::events::ProjectInfo;
+
+
+/// Whether the user may see the project and its repository.
+pub async fn can_read(db: &Database, project: &Project, logged_user: Option<&User>) -> Result<bool, Status> {
+    access::can_read(db, project, logged_user).await.map_err(|_| Status::InternalServerError)
+}
+
+/// Whether the user may change the repository or the settings of the project.
+pub async fn can_write(db: &Database, project: &Project, user: &User) -> Result<bool, Status> {
+    access::can_write(db, project, user).await.map_err(|_| Status::InternalServerError)
+}
+
+/// Whether the user with the given id may see the project, i.e. before they are assigned to something in it.
+pub async fn can_read_by_id(db: &Database, project: &Project, user_id: &Uuid) -> Result<bool, Status> {
+    let scope = Scope::of(project).map_err(|_| Status::InternalServerError)?;
+    access::access_by_id(db, &scope, user_id).await
+        .map(|access| access.can_read())
+        .map_err(|_| Status::InternalServerError)
+}
+
+/// Whether the user may see a project of which only the summary is at hand, i.e. the fork a
+/// pull request comes from.
+pub async fn can_read_summary(db: &Database, project: &ProjectSummary, user: &User) -> Result<bool, Status> {
+    let user_id = user.id.to_uuid().map_err(|_| Status::InternalServerError)?;
+    access::access(db, &Scope::from(project), Some(&user_id), user.is_admin).await
+        .map(|access| access.can_read())
+        .map_err(|_| Status::InternalServerError)
+}
+
+/// The scope of a project that is only at hand as `ProjectInfo`, i.e. in event listeners.
+pub fn scope_of(project: &ProjectInfo) -> Result<Scope, uuid::Error> {
+    Ok(Scope {
+        project_id: project.id.parse()?,
+        owner_id: project.owner_id.parse()?,
+        owner_is_organisation: project.owner_is_organisation,
+        is_private: project.is_private,
+    })
+}
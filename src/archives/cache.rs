@@ -0,0 +1,79 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::fs::File;
+use uuid::Uuid;
+use zorgit_vcs::{ArchiveFormat, git};
+use crate::Result;
+use crate::config::Archives;
+
+
+/// Returns the cached archive, or creates it first. Archives are cached per commit, so they
+/// never get outdated. Archives bigger than the configured maximum are created, but removed
+/// again as soon as they are opened.
+pub async fn cached_archive(config: &Archives, repo_path: &Path, project_id: &str, commit_id: &str, format: ArchiveFormat, prefix: &str) -> Result<File> {
+    let dir = config.path.join(project_id).join(commit_id);
+    let path = dir.join(format.file_name(prefix));
+    if let Ok(file) = File::open(&path).await {
+        return Ok(file);
+    }
+
+    tokio::fs::create_dir_all(&dir).await?;
+    // Concurrent requests for the same archive each write their own file, the last rename wins
+    let tmp_path = dir.join(format!(".{}.tmp", Uuid::new_v4()));
+    if let Err(e) = git::write_archive(repo_path, commit_id, format, prefix, &tmp_path).await {
+        // The error isn't Send, so it can't be kept across the await
+        let message = e.to_string();
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+        return Err(message.into());
+    }
+
+    let size = tokio::fs::metadata(&tmp_path).await?.len();
+    if size > config.max_archive_size.as_u64() {
+        // The open file stays readable after it got removed
+        let file = File::open(&tmp_path).await?;
+        tokio::fs::remove_file(&tmp_path).await?;
+        return Ok(file);
+    }
+
+    tokio::fs::rename(&tmp_path, &path).await?;
+    let file = File::open(&path).await?;
+
+    let cache_dir = config.path.clone();
+    let cache_size = config.cache_size.as_u64();
+    tokio::task::spawn_blocking(move || evict(&cache_dir, cache_size));
+
+    Ok(file)
+}
+
+/// Removes the oldest archives, until all archives together fit into the cache size.
+fn evict(cache_dir: &Path, cache_size: u64) {
+    let mut archives = walkdir::WalkDir::new(cache_dir)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file() && !entry.file_name().to_string_lossy().starts_with('.'))
+        .flat_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            Some((entry.into_path(), metadata.len(), modified))
+        })
+        .collect::<Vec<(PathBuf, u64, SystemTime)>>();
+
+    let mut total = archives.iter().map(|(_, size, _)| size).sum::<u64>();
+    if total <= cache_size {
+        return;
+    }
+
+    archives.sort_by_key(|(_, _, modified)| *modified);
+    for (path, size, _) in archives {
+        if total <= cache_size {
+            break;
+        }
+        if std::fs::remove_file(&path).is_ok() {
+            total -= size;
+            if let Some(dir) = path.parent() {
+                // Only succeeds for empty folders
+                let _ = std::fs::remove_dir(dir);
+            }
+        }
+    }
+}
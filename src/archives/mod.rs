@@ -0,0 +1,4 @@
+mod cache;
+mod routes;
+
+pub use routes::routes;
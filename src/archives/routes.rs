@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+use rocket::{get, routes, Route, Response, State, http::{ContentType, Header, Status}};
+use zorgit_common::{Project, entities::{Owner, User}};
+use zorgit_db::Database;
+use zorgit_vcs::{ArchiveFormat, RefKind, VersionControl, git::{self, Repository}};
+use crate::access::can_read;
+use crate::config::ZorgitConfig;
+use super::cache;
+
+//##### Routes #####//
+// [get]     /{user|org}/{project}/archive/<rev>.{tar.gz|zip}
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        archive_get,
+    ]
+}
+
+fn content_type(format: ArchiveFormat) -> ContentType {
+    match format {
+        ArchiveFormat::TarGz => ContentType::new("application", "gzip"),
+        ArchiveFormat::Zip => ContentType::ZIP,
+    }
+}
+
+/// Escapes a file name for a quoted header value. Branch names may contain `"`.
+fn quote(file_name: &str) -> String {
+    file_name.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Downloads the repository at any revision. Archives of tags and commits can't change,
+/// so they are cached. Archives of branches are streamed straight from git.
+#[get("/<_owner>/<_project_name>/archive/<file..>")]
+pub async fn archive_get(_owner: Owner, _project_name: &str, project: Project, file: PathBuf, logged_user: Option<User>, db: Database, config: State<'_, ZorgitConfig>) -> Result<Response<'static>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    // Branch names can contain slashes, so the file name spans multiple segments
+    let file = file.to_str().ok_or(Status::NotFound)?.replace('\\', "/");
+    let (rev, format) = ArchiveFormat::split_file_name(&file).ok_or(Status::NotFound)?;
+    let resolved = Repository::open(&project.dir)
+        .and_then(|repo| repo.resolve(rev))
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::NotFound)?;
+
+    let prefix = ArchiveFormat::prefix(&project.name, rev);
+    let disposition = Header::new("Content-Disposition", format!("attachment; filename=\"{}\"", quote(&format.file_name(&prefix))));
+    let mut response = Response::build();
+    response.header(content_type(format))
+        .header(disposition);
+
+    if resolved.kind == RefKind::Branch {
+        let stream = git::archive(&project.dir, &resolved.commit_id, format, &prefix)
+            .map_err(|_| Status::InternalServerError)?;
+        return Ok(response.streamed_body(stream).finalize());
+    }
+
+    let project_id = project.id.to_string();
+    let archive = cache::cached_archive(&config.archives, &project.dir, &project_id, &resolved.commit_id, format, &prefix).await;
+    match archive {
+        Ok(archive) => Ok(response.streamed_body(archive).finalize()),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_file_names() {
+        assert_eq!(quote("zorgit-v1.0.zip"), "zorgit-v1.0.zip");
+        assert_eq!(quote("zorgit-say-\"hi\"\\.zip"), "zorgit-say-\\\"hi\\\"\\\\.zip");
+    }
+}
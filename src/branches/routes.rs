@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use rocket::{delete, get, post, routes, Route, State, http::Status};
+use rocket_contrib::{json::Json, uuid::Uuid as UuidParam};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use zorgit_common::{Project, entities::{Owner, User}};
+use zorgit_db::{Database, ProtectedBranch};
+use zorgit_vcs::{CombinedStatus, PushPolicy, RefUpdate, VersionControl, git::Repository};
+use zorgit_vcs::events::{Event, EventBus, ProjectAction, ProjectEvent, ProjectInfo, PushEvent};
+use crate::access::{can_read, can_write};
+
+//##### Routes #####//
+// [get]     /{user|org}/{project}/branches
+// [post]    /{user|org}/{project}/branches
+// [post]    /{user|org}/{project}/branches/rename
+// [delete]  /{user|org}/{project}/branches/<name..>
+// [post]    /{user|org}/{project}/settings/default_branch
+// [get]     /{user|org}/{project}/settings/branches
+// [post]    /{user|org}/{project}/settings/branches
+// [delete]  /{user|org}/{project}/settings/branches/<id>
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        branches_get,
+        branches_post,
+        branch_rename_post,
+        branch_delete,
+        default_branch_post,
+        protections_get,
+        protections_post,
+        protection_delete,
+    ]
+}
+
+#[derive(Debug, Serialize)]
+pub struct BranchView {
+    pub name: String,
+    pub commit_id: String,
+    pub is_default: bool,
+    pub is_protected: bool,
+    /// Combined status of the commit the branch points to.
+    pub status: Option<CombinedStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewBranch {
+    pub name: String,
+    /// Branch, tag or commit the new branch starts at.
+    pub from: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BranchRename {
+    pub name: String,
+    pub new_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DefaultBranch {
+    pub name: String,
+}
+
+/// A protection rule, as it is created. A rule with the pattern of an existing rule replaces it.
+#[derive(Debug, Deserialize)]
+pub struct NewProtection {
+    pub pattern: String,
+    #[serde(default)]
+    pub allow_force_push: bool,
+    #[serde(default)]
+    pub allow_deletion: bool,
+    /// Names of the users that may push. If there are neither users nor teams, everyone
+    /// with write access may push.
+    #[serde(default)]
+    pub push_users: Vec<String>,
+    /// Names of the teams of the organisation, whose members may push.
+    #[serde(default)]
+    pub push_teams: Vec<String>,
+    #[serde(default)]
+    pub require_linear_history: bool,
+    #[serde(default)]
+    pub required_status_checks: Vec<String>,
+    #[serde(default)]
+    pub required_approvals: u32,
+    #[serde(default)]
+    pub dismiss_stale_approvals: bool,
+    #[serde(default)]
+    pub require_code_owner_review: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProtectionView {
+    pub id: String,
+    pub pattern: String,
+    pub allow_force_push: bool,
+    pub allow_deletion: bool,
+    pub push_users: Vec<String>,
+    pub push_teams: Vec<String>,
+    pub require_linear_history: bool,
+    pub required_status_checks: Vec<String>,
+    pub required_approvals: u32,
+    pub dismiss_stale_approvals: bool,
+    pub require_code_owner_review: bool,
+}
+
+impl ProtectionView {
+    /// Shows the rules with the names of the users and teams that may push.
+    async fn list(db: &Database, rules: Vec<ProtectedBranch>) -> Result<Vec<ProtectionView>, Status> {
+        let user_ids = rules.iter().flat_map(|rule| rule.push_user_ids.iter().cloned()).collect::<Vec<_>>();
+        let team_ids = rules.iter().flat_map(|rule| rule.push_team_ids.iter().cloned()).collect::<Vec<_>>();
+        let users = db.owners.names(&user_ids).await.map_err(|_| Status::InternalServerError)?
+            .into_iter().collect::<HashMap<_, _>>();
+        let teams = db.teams.names(&team_ids).await.map_err(|_| Status::InternalServerError)?
+            .into_iter().collect::<HashMap<_, _>>();
+        let names = |ids: &[Uuid], names: &HashMap<Uuid, String>| ids.iter().flat_map(|id| names.get(id).cloned()).collect();
+
+        Ok(rules.into_iter()
+            .map(|rule| ProtectionView {
+                id: rule.id.to_string(),
+                push_users: names(&rule.push_user_ids, &users),
+                push_teams: names(&rule.push_team_ids, &teams),
+                pattern: rule.pattern,
+                allow_force_push: rule.allow_force_push,
+                allow_deletion: rule.allow_deletion,
+                require_linear_history: rule.require_linear_history,
+                required_status_checks: rule.required_status_checks,
+                required_approvals: rule.required_approvals.max(0) as u32,
+                dismiss_stale_approvals: rule.dismiss_stale_approvals,
+                require_code_owner_review: rule.require_code_owner_review,
+            })
+            .collect())
+    }
+}
+
+fn open(project: &Project) -> Result<Repository, Status> {
+    Repository::open(&project.dir).map_err(|_| Status::InternalServerError)
+}
+
+fn commit_id(repo: &Repository, name: &str) -> Result<Option<String>, Status> {
+    repo.resolve(name)
+        .map(|resolved| resolved.map(|r| r.commit_id))
+        .map_err(|_| Status::InternalServerError)
+}
+
+fn branch_ref(name: &str) -> String {
+    format!("refs/heads/{}", name)
+}
+
+/// Loads the protection rules for the user. Changes that need write access are refused right away.
+async fn load_policy(db: &Database, project: &Project, user: &User) -> Result<PushPolicy, Status> {
+    if !can_write(db, project, user).await? {
+        return Err(Status::Forbidden);
+    }
+    PushPolicy::load(db, project, user).await.map_err(|_| Status::InternalServerError)
+}
+
+/// Applies the same protection rules to changes from the web, that apply to a push.
+fn check(repo: &Repository, policy: &PushPolicy, updates: &[RefUpdate]) -> Result<(), Status> {
+    let rejections = repo.check_updates(policy, updates).map_err(|_| Status::InternalServerError)?;
+    if rejections.is_empty() {
+        Ok(())
+    }
+    else {
+        Err(Status::Forbidden)
+    }
+}
+
+fn emit(events: &EventBus, project: &Project, user: &User, updates: Vec<RefUpdate>) {
+    events.emit(Event::Push(PushEvent {
+        project: ProjectInfo::from(project),
+        pusher: user.username.clone(),
+        pusher_email: user.email.address.clone(),
+        updates,
+    }));
+}
+
+/// The protection rules of the project, which only users with write access may see and change.
+async fn protections(db: &Database, project: &Project, user: &User) -> Result<(Uuid, Vec<ProtectedBranch>), Status> {
+    if !can_write(db, project, user).await? {
+        return Err(Status::Forbidden);
+    }
+    let project_id = project.id.to_uuid().map_err(|_| Status::InternalServerError)?;
+    let rules = db.protected_branches.for_project(&project_id).await.map_err(|_| Status::InternalServerError)?;
+    Ok((project_id, rules))
+}
+
+/// Looks up the users and teams that may push. Unknown names are refused, so a typo doesn't
+/// silently leave the branch open to everyone with write access.
+async fn push_allow_list(db: &Database, project: &Project, protection: &NewProtection) -> Result<(Vec<Uuid>, Vec<Uuid>), Status> {
+    let mut user_ids = Vec::with_capacity(protection.push_users.len());
+    for name in &protection.push_users {
+        let id = db.owners.find(name, false).await.map_err(|_| Status::InternalServerError)?;
+        user_ids.push(id.ok_or(Status::UnprocessableEntity)?);
+    }
+
+    let mut team_ids = Vec::with_capacity(protection.push_teams.len());
+    if !protection.push_teams.is_empty() {
+        // Only organisations have teams
+        if !project.owner.is_organisation() {
+            return Err(Status::UnprocessableEntity);
+        }
+        let organisation_id = project.owner.id().to_uuid().map_err(|_| Status::InternalServerError)?;
+        for name in &protection.push_teams {
+            let id = db.teams.find(&organisation_id, name).await.map_err(|_| Status::InternalServerError)?;
+            team_ids.push(id.ok_or(Status::UnprocessableEntity)?);
+        }
+    }
+
+    Ok((user_ids, team_ids))
+}
+
+async fn save_default_branch(db: &Database, project: &Project, default_branch: &str) -> Result<(), Status> {
+    let project_id = project.id.to_uuid().map_err(|_| Status::InternalServerError)?;
+    db.projects.update_default_branch(&project_id, Some(default_branch), OffsetDateTime::now_utc()).await
+        .map_err(|_| Status::InternalServerError)
+}
+
+#[get("/<_owner>/<_project_name>/branches")]
+pub async fn branches_get(_owner: Owner, _project_name: &str, project: Project, logged_user: Option<User>, db: Database) -> Result<Json<Vec<BranchView>>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    let project_id = project.id.to_uuid().map_err(|_| Status::InternalServerError)?;
+    let rules = db.protected_branches.for_project(&project_id).await.map_err(|_| Status::InternalServerError)?;
+
+    let mut branches = {
+        let repo = open(&project)?;
+        let default_branch = repo.default_branch().ok().flatten();
+        let names = repo.branches().map_err(|_| Status::InternalServerError)?.unwrap_or_default();
+        let mut branches = Vec::with_capacity(names.len());
+        for name in names {
+            let commit_id = commit_id(&repo, &name)?.unwrap_or_default();
+            branches.push(BranchView {
+                is_default: default_branch.as_ref() == Some(&name),
+                is_protected: rules.iter().any(|rule| zorgit_vcs::glob_match(&rule.pattern, &name)),
+                name,
+                commit_id,
+                status: None,
+            });
+        }
+        branches
+    };
+
+    let commit_ids = branches.iter().map(|b| b.commit_id.clone()).collect::<Vec<_>>();
+    let statuses = CombinedStatus::load_many(&db, &project, &commit_ids).await
+        .map_err(|_| Status::InternalServerError)?;
+    for branch in &mut branches {
+        branch.status = statuses.get(&branch.commit_id).cloned();
+    }
+
+    Ok(Json(branches))
+}
+
+#[post("/<_owner>/<_project_name>/branches", data = "<branch>")]
+pub async fn branches_post(_owner: Owner, _project_name: &str, project: Project, logged_user: User, db: Database, events: State<'_, EventBus>, branch: Json<NewBranch>) -> Result<Status, Status> {
+    let policy = load_policy(&db, &project, &logged_user).await?;
+    let repo = open(&project)?;
+    if commit_id(&repo, &branch_ref(&branch.name))?.is_some() {
+        return Err(Status::Conflict);
+    }
+    let from = commit_id(&repo, &branch.from)?.ok_or(Status::UnprocessableEntity)?;
+
+    let update = RefUpdate { name: branch_ref(&branch.name), old_id: None, new_id: Some(from.clone()) };
+    check(&repo, &policy, &[update.clone()])?;
+    repo.create_branch(&branch.name, &from).map_err(|_| Status::UnprocessableEntity)?;
+
+    emit(&events, &project, &logged_user, vec![update]);
+    // The first branch of an empty repository becomes its default branch
+    if project.default_branch.is_none() {
+        repo.set_default_branch(&branch.name).map_err(|_| Status::InternalServerError)?;
+        save_default_branch(&db, &project, &branch.name).await?;
+    }
+    Ok(Status::Created)
+}
+
+#[post("/<_owner>/<_project_name>/branches/rename", data = "<rename>")]
+pub async fn branch_rename_post(_owner: Owner, _project_name: &str, project: Project, logged_user: User, db: Database, events: State<'_, EventBus>, rename: Json<BranchRename>) -> Result<Status, Status> {
+    let policy = load_policy(&db, &project, &logged_user).await?;
+    let repo = open(&project)?;
+    let old_id = commit_id(&repo, &branch_ref(&rename.name))?.ok_or(Status::NotFound)?;
+    if commit_id(&repo, &branch_ref(&rename.new_name))?.is_some() {
+        return Err(Status::Conflict);
+    }
+
+    // For the protection rules a rename deletes the old and creates the new branch
+    let updates = vec![
+        RefUpdate { name: branch_ref(&rename.name), old_id: Some(old_id.clone()), new_id: None },
+        RefUpdate { name: branch_ref(&rename.new_name), old_id: None, new_id: Some(old_id) },
+    ];
+    check(&repo, &policy, &updates)?;
+    let is_default = repo.default_branch().ok().flatten().as_deref() == Some(rename.name.as_str());
+    repo.rename_branch(&rename.name, &rename.new_name).map_err(|_| Status::UnprocessableEntity)?;
+
+    emit(&events, &project, &logged_user, updates);
+    if is_default {
+        save_default_branch(&db, &project, &rename.new_name).await?;
+    }
+    Ok(Status::NoContent)
+}
+
+#[delete("/<_owner>/<_project_name>/branches/<name..>")]
+pub async fn branch_delete(_owner: Owner, _project_name: &str, project: Project, name: PathBuf, logged_user: User, db: Database, events: State<'_, EventBus>) -> Result<Status, Status> {
+    let policy = load_policy(&db, &project, &logged_user).await?;
+    let name = name.to_str().ok_or(Status::NotFound)?.replace('\\', "/");
+    let repo = open(&project)?;
+    let old_id = commit_id(&repo, &branch_ref(&name))?.ok_or(Status::NotFound)?;
+    if repo.default_branch().ok().flatten().as_deref() == Some(name.as_str()) {
+        return Err(Status::Conflict);
+    }
+
+    let update = RefUpdate { name: branch_ref(&name), old_id: Some(old_id), new_id: None };
+    check(&repo, &policy, &[update.clone()])?;
+    repo.delete_branch(&name).map_err(|_| Status::InternalServerError)?;
+
+    emit(&events, &project, &logged_user, vec![update]);
+    Ok(Status::NoContent)
+}
+
+#[post("/<_owner>/<_project_name>/settings/default_branch", data = "<branch>")]
+pub async fn default_branch_post(_owner: Owner, _project_name: &str, project: Project, logged_user: User, db: Database, events: State<'_, EventBus>, branch: Json<DefaultBranch>) -> Result<Status, Status> {
+    if !can_write(&db, &project, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    {
+        let repo = open(&project)?;
+        repo.set_default_branch(&branch.name).map_err(|_| Status::NotFound)?;
+    }
+
+    save_default_branch(&db, &project, &branch.name).await?;
+    let mut info = ProjectInfo::from(&project);
+    info.default_branch = Some(branch.name.clone());
+    events.emit(Event::Project(ProjectEvent {
+        project: info,
+        action: ProjectAction::Edited,
+        sender: logged_user.username.clone(),
+    }));
+    Ok(Status::NoContent)
+}
+
+#[get("/<_owner>/<_project_name>/settings/branches")]
+pub async fn protections_get(_owner: Owner, _project_name: &str, project: Project, logged_user: User, db: Database) -> Result<Json<Vec<ProtectionView>>, Status> {
+    let (_, rules) = protections(&db, &project, &logged_user).await?;
+    Ok(Json(ProtectionView::list(&db, rules).await?))
+}
+
+#[post("/<_owner>/<_project_name>/settings/branches", data = "<protection>")]
+pub async fn protections_post(_owner: Owner, _project_name: &str, project: Project, logged_user: User, db: Database, protection: Json<NewProtection>) -> Result<Json<ProtectionView>, Status> {
+    let (project_id, rules) = protections(&db, &project, &logged_user).await?;
+    let pattern = protection.pattern.trim();
+    if pattern.is_empty() {
+        return Err(Status::UnprocessableEntity);
+    }
+    let (push_user_ids, push_team_ids) = push_allow_list(&db, &project, &protection).await?;
+
+    let rule = ProtectedBranch {
+        id: rules.iter().find(|rule| rule.pattern == pattern).map_or_else(Uuid::new_v4, |rule| rule.id),
+        project_id,
+        pattern: pattern.to_string(),
+        allow_force_push: protection.allow_force_push,
+        allow_deletion: protection.allow_deletion,
+        push_user_ids,
+        push_team_ids,
+        require_linear_history: protection.require_linear_history,
+        required_status_checks: protection.required_status_checks.clone(),
+        required_approvals: protection.required_approvals.min(i32::MAX as u32) as i32,
+        dismiss_stale_approvals: protection.dismiss_stale_approvals,
+        require_code_owner_review: protection.require_code_owner_review,
+    };
+    db.protected_branches.save(&rule).await.map_err(|_| Status::InternalServerError)?;
+
+    let mut views = ProtectionView::list(&db, vec![rule]).await?;
+    views.pop().map(Json).ok_or(Status::InternalServerError)
+}
+
+#[delete("/<_owner>/<_project_name>/settings/branches/<id>")]
+pub async fn protection_delete(_owner: Owner, _project_name: &str, project: Project, id: UuidParam, logged_user: User, db: Database) -> Result<Status, Status> {
+    let (_, rules) = protections(&db, &project, &logged_user).await?;
+    let rule = rules.iter().find(|rule| rule.id == *id).ok_or(Status::NotFound)?;
+    db.protected_branches.delete(&rule.id).await.map_err(|_| Status::InternalServerError)?;
+    Ok(Status::NoContent)
+}
@@ -0,0 +1,181 @@
+use std::path::PathBuf;
+use rocket::{get, routes, Route, http::Status};
+use rocket_contrib::json::Json;
+use serde::Serialize;
+use zorgit_common::{Project, entities::{Owner, User}};
+use zorgit_db::Database;
+use zorgit_vcs::{CombinedStatus, CompareMode, LogFilter, MergeFilter, PathCommit, VersionControl, events::ProjectInfo, git::Repository};
+use crate::access::can_read;
+use crate::references;
+use crate::views::{self, CommitView, DiffView};
+
+//##### Routes #####//
+// [get]     /{user|org}/{project}/commits/<rev..>?<after>&<author>&<committer>&<since>&<until>&<message>&<merges>
+// [get]     /{user|org}/{project}/history/<path..>?<rev>&<page>
+// [get]     /{user|org}/{project}/compare/<base>{...|..}<head>
+
+/// How many commits are shown on one page of a history.
+const PAGE_SIZE: usize = 50;
+/// How many commits a comparison lists at most.
+const COMPARE_COMMITS_LIMIT: usize = 250;
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        log_get,
+        history_get,
+        compare_get,
+    ]
+}
+
+#[derive(Debug, Serialize)]
+pub struct PathCommitView {
+    #[serde(flatten)]
+    pub commit: CommitView,
+    /// Path of the file in this commit, it differs from the requested path before a rename.
+    pub path: String,
+}
+
+impl From<PathCommit> for PathCommitView {
+    fn from(path_commit: PathCommit) -> Self {
+        PathCommitView {
+            commit: path_commit.commit.into(),
+            path: path_commit.path.to_string_lossy().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistoryView {
+    pub page: usize,
+    pub has_more: bool,
+    pub commits: Vec<PathCommitView>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogView {
+    pub commits: Vec<CommitView>,
+    pub next_cursor: Option<String>,
+}
+
+fn open_at(project: &Project, rev: &str) -> Result<Repository, Status> {
+    let repo = Repository::open(&project.dir).map_err(|_| Status::InternalServerError)?;
+    match repo.resolve(rev) {
+        Ok(Some(_)) => Ok(repo),
+        Ok(None) => Err(Status::NotFound),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+/// Dates are unix timestamps. Revisions with slashes, like `feature/login`, are split over segments.
+/// Ranked below `commits/<rev..>/statuses` and `commits/<rev..>/status`, which it would match as well,
+/// so the log of a branch that ends in `/status` or `/statuses` can't be shown.
+#[get("/<_owner>/<_project_name>/commits/<rev..>?<after>&<author>&<committer>&<since>&<until>&<message>&<merges>", rank = 2)]
+pub async fn log_get(_owner: Owner, _project_name: &str, project: Project, rev: PathBuf, after: Option<&str>, author: Option<String>, committer: Option<String>, since: Option<i64>, until: Option<i64>, message: Option<String>, merges: Option<&str>, logged_user: Option<User>, db: Database) -> Result<Json<LogView>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    let rev = rev.to_str().ok_or(Status::NotFound)?.replace('\\', "/");
+    // The cursor is the full id of a commit, anything else can't come from a previous page
+    if after.map_or(false, |after| after.len() != 40 || !after.bytes().all(|b| b.is_ascii_hexdigit())) {
+        return Err(Status::BadRequest);
+    }
+    let filter = LogFilter {
+        author,
+        committer,
+        since: since.map(|since| views::timestamp(since).ok_or(Status::BadRequest)).transpose()?,
+        until: until.map(|until| views::timestamp(until).ok_or(Status::BadRequest)).transpose()?,
+        message,
+        merges: match merges {
+            Some(merges) => merges.parse::<MergeFilter>().map_err(|_| Status::BadRequest)?,
+            None => MergeFilter::default(),
+        },
+    };
+
+    let log = open_at(&project, &rev)?
+        .log(&rev, &filter, after, PAGE_SIZE)
+        .map_err(|_| Status::InternalServerError)?;
+    let commit_ids = log.commits.iter().map(|c| c.id.clone()).collect::<Vec<_>>();
+    let statuses = CombinedStatus::load_many(&db, &project, &commit_ids).await
+        .map_err(|_| Status::InternalServerError)?;
+    let mut messages = references::render_messages(&db, &ProjectInfo::from(&project), &log.commits).await
+        .map_err(|_| Status::InternalServerError)?;
+
+    Ok(Json(LogView {
+        commits: CommitView::list(log.commits, &statuses, &mut messages),
+        next_cursor: log.next_cursor,
+    }))
+}
+
+/// The revision is a query parameter, because it can contain slashes just like the path.
+/// Without it, the history of the default branch is shown.
+#[get("/<_owner>/<_project_name>/history/<path..>?<rev>&<page>")]
+pub async fn history_get(_owner: Owner, _project_name: &str, project: Project, path: PathBuf, rev: Option<&str>, page: Option<usize>, logged_user: Option<User>, db: Database) -> Result<Json<HistoryView>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    let rev = rev.or_else(|| project.default_branch.as_deref()).unwrap_or("HEAD");
+    let page = page.unwrap_or(1).max(1);
+    let history = open_at(&project, rev)?
+        .path_history(rev, &path, (page - 1) * PAGE_SIZE, PAGE_SIZE)
+        .map_err(|_| Status::InternalServerError)?;
+    let mut messages = references::render_messages(&db, &ProjectInfo::from(&project), history.commits.iter().map(|c| &c.commit)).await
+        .map_err(|_| Status::InternalServerError)?;
+    Ok(Json(HistoryView {
+        page,
+        has_more: history.has_more,
+        commits: history.commits.into_iter()
+            .map(|path_commit| {
+                let mut view = PathCommitView::from(path_commit);
+                view.commit.message_html = messages.remove(&view.commit.id);
+                view
+            })
+            .collect(),
+    }))
+}
+
+#[derive(Serialize)]
+pub struct CompareView {
+    pub base_id: String,
+    pub head_id: String,
+    pub merge_base_id: Option<String>,
+    pub ahead_by: usize,
+    pub behind_by: usize,
+    pub commits: Vec<CommitView>,
+    /// Combined commit status of the head.
+    pub status: Option<CombinedStatus>,
+    pub diff: DiffView,
+}
+
+/// Compares `base...head` with the changes since the merge base, or `base..head` with the
+/// direct difference. Revisions with slashes, like `feature/login`, are split over segments.
+#[get("/<_owner>/<_project_name>/compare/<spec..>")]
+pub async fn compare_get(_owner: Owner, _project_name: &str, project: Project, spec: PathBuf, logged_user: Option<User>, db: Database) -> Result<Json<CompareView>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    let spec = spec.to_str().ok_or(Status::NotFound)?.replace('\\', "/");
+    let (base, head, mode) = CompareMode::split_spec(&spec).ok_or(Status::NotFound)?;
+    let comparison = {
+        let repo = open_at(&project, base)?;
+        open_at(&project, head)?;
+        repo.compare(base, head, mode, COMPARE_COMMITS_LIMIT).map_err(|_| Status::UnprocessableEntity)?
+    };
+
+    let mut commit_ids = comparison.commits.iter().map(|c| c.id.clone()).collect::<Vec<_>>();
+    commit_ids.push(comparison.head_id.clone());
+    let statuses = CombinedStatus::load_many(&db, &project, &commit_ids).await
+        .map_err(|_| Status::InternalServerError)?;
+    let mut messages = references::render_messages(&db, &ProjectInfo::from(&project), &comparison.commits).await
+        .map_err(|_| Status::InternalServerError)?;
+
+    Ok(Json(CompareView {
+        status: statuses.get(&comparison.head_id).cloned(),
+        commits: CommitView::list(comparison.commits, &statuses, &mut messages),
+        base_id: comparison.base_id,
+        head_id: comparison.head_id,
+        merge_base_id: comparison.merge_base_id,
+        ahead_by: comparison.ahead_by,
+        behind_by: comparison.behind_by,
+        diff: comparison.diff.into(),
+    }))
+}
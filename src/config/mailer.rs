@@ -25,6 +25,13 @@ pub struct Config {
     pub subject_prefix: Option<String>,
     /// Uses the given path to look for sendmail.
     pub sendmail_path: Option<PathBuf>,
+    /// Address that replies to notification emails go to, like `reply@example.com`. Each email
+    /// gets its own `reply+<token>@example.com`, so replies can be posted as comments.
+    pub reply_to: Option<String>,
+    /// Maildir that the replies are delivered to, i.e. by fetchmail or the mail server.
+    pub incoming_maildir: Option<PathBuf>,
+    /// Seconds between two looks into the incoming maildir.
+    pub incoming_interval: u64,
 }
 
 impl Default for Config {
@@ -38,7 +45,10 @@ impl Default for Config {
             smtp_password: None,
             from: None,
             subject_prefix: Some("[Zorgit]".to_string()),
-            sendmail_path: None
+            sendmail_path: None,
+            reply_to: None,
+            incoming_maildir: None,
+            incoming_interval: 30,
         }
     }
 }
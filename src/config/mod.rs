@@ -5,6 +5,7 @@ use mailer::Config as MailerConfig;
 use rocket::{
     error,
     config::{Config, SecretKey},
+    data::{ByteUnit, ToByteUnit},
     fairing::AdHoc,
     figment::{Figment, providers::{Format, Toml, Serialized, Env}},
 };
@@ -32,6 +33,8 @@ pub struct ZorgitConfig {
     pub projects: Projects,
     /// All attachment related configs
     pub attachments: Attachments,
+    /// Cache for downloadable archives of the repositories
+    pub archives: Archives,
     /// Holds the configuration for the Mailer service.
     pub mailer: MailerConfig,
 }
@@ -46,6 +49,7 @@ impl Default for ZorgitConfig {
             data_path: project_dirs().data_dir().to_path_buf(),
             projects: Projects::default(),
             attachments: Attachments::default(),
+            archives: Archives::default(),
             mailer: MailerConfig::default(),
         }
     }
@@ -83,6 +87,7 @@ impl ZorgitConfig {
         zorgit_config.projects.path = absolutify(&zorgit_config.projects.path);
         zorgit_config.attachments.path = absolutify(&zorgit_config.attachments.path);
         zorgit_config.attachments.avatars = absolutify(&zorgit_config.attachments.avatars);
+        zorgit_config.archives.path = absolutify(&zorgit_config.archives.path);
 
         Self::create_all_dirs(&zorgit_config).unwrap_or_else(|e| {
             error!("{}", e);
@@ -97,6 +102,7 @@ impl ZorgitConfig {
         std::fs::create_dir_all(&zorgit_config.data_path)?;
         std::fs::create_dir_all(&zorgit_config.projects.path)?;
         std::fs::create_dir_all(&zorgit_config.attachments.path)?;
+        std::fs::create_dir_all(&zorgit_config.archives.path)?;
         std::fs::create_dir_all(&zorgit_config.attachments.avatars)
     }
 
@@ -140,6 +146,26 @@ pub struct Attachments {
 }
 
 #[derive(Debug, Deserialize, Serialize)]
+pub struct Archives {
+    /// Folder in which generated archives are cached. A relative path is placed inside of the data folder
+    pub path: PathBuf,
+    /// Once all cached archives together are bigger than this, the oldest ones are removed
+    pub cache_size: ByteUnit,
+    /// Bigger archives are still sent, but not cached
+    pub max_archive_size: ByteUnit,
+}
+
+impl Default for Archives {
+    fn default() -> Self {
+        Archives {
+            path: PathBuf::from("archives"),
+            cache_size: 1.gibibytes(),
+            max_archive_size: 100.mebibytes(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Projects {
     /// Path to the folder in which all projects are stored
     pub path: PathBuf,
@@ -147,6 +173,13 @@ pub struct Projects {
     pub pull_requests: PullRequests,
 }
 
+impl Projects {
+    /// Folder of the repository of a project. Repositories are created as `<path>/<owner>/<project>`.
+    pub fn dir(&self, owner: &str, project: &str) -> PathBuf {
+        self.path.join(owner).join(project)
+    }
+}
+
 impl Default for Projects {
     fn default() -> Self {
         Projects {
@@ -165,12 +198,20 @@ impl Default for Attachments {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PullRequests {
     /// The start of a pull request title will be matched for this values to determine if its a work in progress
     pub work_in_progress_prefixes: Vec<String>,
 }
 
+impl PullRequests {
+    /// Whether a pull request with this title is a draft. Prefixes are matched case-insensitively.
+    pub fn is_work_in_progress(&self, title: &str) -> bool {
+        let title = title.trim_start().to_lowercase();
+        self.work_in_progress_prefixes.iter().any(|prefix| title.starts_with(&prefix.to_lowercase()))
+    }
+}
+
 impl Default for PullRequests {
     fn default() -> Self {
         PullRequests {
@@ -178,3 +219,17 @@ impl Default for PullRequests {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_work_in_progress() {
+        let pulls = PullRequests::default();
+        assert!(pulls.is_work_in_progress("WIP: Add webhooks"));
+        assert!(pulls.is_work_in_progress("  [wip]: Add webhooks"));
+        assert!(!pulls.is_work_in_progress("Add webhooks (WIP:)"));
+        assert!(!pulls.is_work_in_progress("Wipe caches"));
+    }
+}
@@ -0,0 +1,20 @@
+//! Binary that is called by the git hooks zorgit installs into every repository.
+//! It runs inside of git, so everything printed to stderr ends up at the client.
+
+fn main() {
+    let hook = std::env::args().nth(1);
+    let code = match hook.as_deref() {
+        Some("pre-receive") => zorgit_vcs::git::hooks::pre_receive_main(),
+        Some("post-receive") => zorgit_vcs::git::hooks::post_receive_main(),
+        Some(hook) => {
+            eprintln!("Zorgit: unknown hook '{}'", hook);
+            1
+        }
+        None => {
+            eprintln!("Zorgit: no hook given");
+            1
+        }
+    };
+
+    std::process::exit(code);
+}
@@ -0,0 +1,574 @@
+use std::collections::HashMap;
+use rocket::{delete, get, post, routes, Route, State, http::Status};
+use rocket_contrib::{json::Json, uuid::Uuid as UuidParam};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use zorgit_common::{Project, entities::{Owner, User}};
+use zorgit_db::{Database, Issue, IssueComment, IssueFilter, Label};
+use zorgit_vcs::events::{Event, EventBus, IssueAction, IssueCommentAction, IssueCommentEvent, IssueEvent, ProjectInfo};
+use crate::access::{can_read, can_read_by_id, can_write};
+use crate::labels::LabelView;
+use crate::references;
+
+//##### Routes #####//
+// [get]     /{user|org}/{project}/issues?<state>&<label>&<milestone>&<assignee>&<page>
+// [post]    /{user|org}/{project}/issues
+// [get]     /{user|org}/{project}/issues/<number>
+// [post]    /{user|org}/{project}/issues/<number>
+// [post]    /{user|org}/{project}/issues/<number>/close
+// [post]    /{user|org}/{project}/issues/<number>/reopen
+// [post]    /{user|org}/{project}/issues/<number>/labels
+// [post]    /{user|org}/{project}/issues/<number>/assignees
+// [get]     /{user|org}/{project}/issues/<number>/comments
+// [post]    /{user|org}/{project}/issues/<number>/comments
+// [post]    /{user|org}/{project}/issues/<number>/comments/<id>
+// [delete]  /{user|org}/{project}/issues/<number>/comments/<id>
+
+const PAGE_SIZE: i64 = 25;
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        issues_get,
+        issues_post,
+        issue_get,
+        issue_post,
+        issue_close_post,
+        issue_reopen_post,
+        issue_labels_post,
+        issue_assignees_post,
+        comments_get,
+        comments_post,
+        comment_post,
+        comment_delete,
+    ]
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewIssue {
+    pub title: String,
+    pub description: Option<String>,
+    /// Names of the labels. Like milestone and assignees, they are ignored unless the user can write to the project.
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// Usernames of the assignees.
+    #[serde(default)]
+    pub assignees: Vec<String>,
+    pub milestone_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IssueEdit {
+    pub title: String,
+    pub description: Option<String>,
+    /// Only changed if the user can write to the project.
+    pub milestone_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LabelsEdit {
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssigneesEdit {
+    pub assignees: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewComment {
+    pub body: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IssueView {
+    pub number: i64,
+    pub title: String,
+    pub description: Option<String>,
+    pub state: String,
+    pub author: Option<String>,
+    pub labels: Vec<LabelView>,
+    /// Usernames of the assignees.
+    pub assignees: Vec<String>,
+    pub milestone_id: Option<String>,
+    pub closed_by: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub closed_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IssueCommentView {
+    pub id: String,
+    pub author: Option<String>,
+    pub body: String,
+    /// The body as HTML, with references to issues, pull requests, commits and users linked.
+    pub body_html: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IssueDetailView {
+    #[serde(flatten)]
+    pub issue: IssueView,
+    pub description_html: Option<String>,
+    pub comments: Vec<IssueCommentView>,
+}
+
+fn project_uuid(project: &Project) -> Result<Uuid, Status> {
+    project.id.to_uuid().map_err(|_| Status::InternalServerError)
+}
+
+fn user_uuid(user: &User) -> Result<Uuid, Status> {
+    user.id.to_uuid().map_err(|_| Status::InternalServerError)
+}
+
+async fn load(db: &Database, project: &Project, number: i64) -> Result<Issue, Status> {
+    let project_id = project_uuid(project)?;
+    db.issues.by_number(&project_id, number).await
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::NotFound)
+}
+
+async fn load_comment(db: &Database, issue: &Issue, id: &Uuid) -> Result<IssueComment, Status> {
+    db.issue_comments.by_id(id).await
+        .map_err(|_| Status::InternalServerError)?
+        .filter(|comment| comment.issue_id == issue.id)
+        .ok_or(Status::NotFound)
+}
+
+/// The author of an issue may change it, just like everybody who can write to the project.
+async fn can_change(db: &Database, project: &Project, issue: &Issue, user: &User) -> Result<bool, Status> {
+    Ok(user.id.to_uuid().ok() == Some(issue.author_id) || can_write(db, project, user).await?)
+}
+
+async fn names(db: &Database, ids: &[Uuid]) -> Result<HashMap<Uuid, String>, Status> {
+    db.owners.names(ids).await
+        .map(|names| names.into_iter().collect())
+        .map_err(|_| Status::InternalServerError)
+}
+
+/// Looks up the labels of the project by name. Unknown names are refused instead of
+/// silently dropped, so typos don't go unnoticed.
+async fn find_labels(db: &Database, project_id: &Uuid, names: &[String]) -> Result<Vec<Label>, Status> {
+    let labels = db.labels.for_project(project_id).await.map_err(|_| Status::InternalServerError)?;
+    names.iter()
+        .map(|name| labels.iter().find(|label| &label.name == name).cloned().ok_or(Status::UnprocessableEntity))
+        .collect()
+}
+
+/// Looks up the users to assign by name. Like unknown users, users who can't see the
+/// project are refused, so they aren't notified about an issue they can't read.
+async fn find_users(db: &Database, project: &Project, usernames: &[String]) -> Result<Vec<Uuid>, Status> {
+    let mut ids = Vec::new();
+    for username in usernames {
+        let id = db.owners.find(username, false).await
+            .map_err(|_| Status::InternalServerError)?
+            .ok_or(Status::UnprocessableEntity)?;
+        if !can_read_by_id(db, project, &id).await? {
+            return Err(Status::UnprocessableEntity);
+        }
+        ids.push(id);
+    }
+    Ok(ids)
+}
+
+/// Milestones of other projects can't be used.
+async fn check_milestone(db: &Database, project_id: &Uuid, milestone_id: Option<&Uuid>) -> Result<(), Status> {
+    if let Some(milestone_id) = milestone_id {
+        db.milestones.by_id(milestone_id).await
+            .map_err(|_| Status::InternalServerError)?
+            .filter(|milestone| &milestone.project_id == project_id)
+            .ok_or(Status::UnprocessableEntity)?;
+    }
+    Ok(())
+}
+
+/// Builds the views of the issues, together with their labels and assignees.
+async fn issue_views(db: &Database, project_id: &Uuid, issues: Vec<Issue>) -> Result<Vec<IssueView>, Status> {
+    let issue_ids = issues.iter().map(|i| i.id).collect::<Vec<_>>();
+    let labels = db.labels.for_project(project_id).await.map_err(|_| Status::InternalServerError)?;
+    let label_ids = db.issues.label_ids(&issue_ids).await.map_err(|_| Status::InternalServerError)?;
+    let assignee_ids = db.issues.assignee_ids(&issue_ids).await.map_err(|_| Status::InternalServerError)?;
+
+    let mut user_ids = assignee_ids.iter().map(|(_, user_id)| *user_id).collect::<Vec<_>>();
+    user_ids.extend(issues.iter().map(|i| i.author_id));
+    user_ids.extend(issues.iter().filter_map(|i| i.closed_by));
+    let names = names(db, &user_ids).await?;
+
+    Ok(issues.into_iter().map(|issue| IssueView {
+        labels: labels.iter()
+            .filter(|label| label_ids.contains(&(issue.id, label.id)))
+            .cloned()
+            .map(Into::into)
+            .collect(),
+        assignees: assignee_ids.iter()
+            .filter(|(issue_id, _)| issue_id == &issue.id)
+            .filter_map(|(_, user_id)| names.get(user_id).cloned())
+            .collect(),
+        author: names.get(&issue.author_id).cloned(),
+        closed_by: issue.closed_by.and_then(|id| names.get(&id).cloned()),
+        milestone_id: issue.milestone_id.map(|id| id.to_string()),
+        number: issue.number,
+        title: issue.title,
+        description: issue.description,
+        state: issue.state,
+        created_at: issue.created_at.unix_timestamp(),
+        updated_at: issue.updated_at.unix_timestamp(),
+        closed_at: issue.closed_at.map(|t| t.unix_timestamp()),
+    }).collect())
+}
+
+async fn issue_view(db: &Database, issue: Issue) -> Result<IssueView, Status> {
+    let project_id = issue.project_id;
+    issue_views(db, &project_id, vec![issue]).await?
+        .pop()
+        .ok_or(Status::InternalServerError)
+}
+
+async fn comment_views(db: &Database, project: &Project, comments: Vec<IssueComment>) -> Result<Vec<IssueCommentView>, Status> {
+    let author_ids = comments.iter().map(|c| c.author_id).collect::<Vec<_>>();
+    let names = names(db, &author_ids).await?;
+    let info = ProjectInfo::from(project);
+    let mut views = Vec::with_capacity(comments.len());
+    for comment in comments {
+        views.push(IssueCommentView {
+            body_html: references::render(db, &info, &comment.body).await.map_err(|_| Status::InternalServerError)?,
+            id: comment.id.to_string(),
+            author: names.get(&comment.author_id).cloned(),
+            body: comment.body,
+            created_at: comment.created_at.unix_timestamp(),
+            updated_at: comment.updated_at.unix_timestamp(),
+        });
+    }
+    Ok(views)
+}
+
+fn emit(events: &EventBus, project: &Project, action: IssueAction, issue: &Issue, user: &User) {
+    emit_change(events, project, action, issue, None, None, user);
+}
+
+fn emit_change(events: &EventBus, project: &Project, action: IssueAction, issue: &Issue, label: Option<String>, assignee: Option<String>, user: &User) {
+    events.emit(Event::Issue(IssueEvent {
+        project: ProjectInfo::from(project),
+        action,
+        issue: issue.into(),
+        label,
+        assignee,
+        sender: user.username.clone(),
+    }));
+}
+
+fn emit_comment(events: &EventBus, project: &Project, action: IssueCommentAction, issue: &Issue, comment: &IssueComment, user: &User) {
+    events.emit(Event::IssueComment(IssueCommentEvent {
+        project: ProjectInfo::from(project),
+        action,
+        issue: issue.into(),
+        comment: comment.into(),
+        sender: user.username.clone(),
+    }));
+}
+
+#[get("/<_owner>/<_project_name>/issues?<state>&<label>&<milestone>&<assignee>&<page>")]
+pub async fn issues_get(_owner: Owner, _project_name: &str, project: Project, state: Option<&str>, label: Option<&str>, milestone: Option<UuidParam>, assignee: Option<&str>, page: Option<i64>, logged_user: Option<User>, db: Database) -> Result<Json<Vec<IssueView>>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    let state = match state.unwrap_or("open") {
+        "all" => None,
+        state @ "open" | state @ "closed" => Some(state.to_string()),
+        _ => return Err(Status::BadRequest),
+    };
+    let page = page.unwrap_or(1).max(1);
+
+    let project_id = project_uuid(&project)?;
+    // An unknown label or assignee can't match anything
+    let label_id = match label {
+        Some(name) => match db.labels.by_name(&project_id, name).await.map_err(|_| Status::InternalServerError)? {
+            Some(label) => Some(label.id),
+            None => return Ok(Json(Vec::new())),
+        },
+        None => None,
+    };
+    let assignee_id = match assignee {
+        Some(username) => match db.owners.find(username, false).await.map_err(|_| Status::InternalServerError)? {
+            Some(id) => Some(id),
+            None => return Ok(Json(Vec::new())),
+        },
+        None => None,
+    };
+    let filter = IssueFilter {
+        state,
+        label_id,
+        milestone_id: milestone.map(|id| id.into_inner()),
+        assignee_id,
+    };
+
+    let issues = db.issues.for_project(&project_id, &filter, PAGE_SIZE, (page - 1) * PAGE_SIZE).await
+        .map_err(|_| Status::InternalServerError)?;
+    Ok(Json(issue_views(&db, &project_id, issues).await?))
+}
+
+#[post("/<_owner>/<_project_name>/issues", data = "<new>")]
+pub async fn issues_post(_owner: Owner, _project_name: &str, project: Project, logged_user: User, db: Database, events: State<'_, EventBus>, new: Json<NewIssue>) -> Result<Json<IssueView>, Status> {
+    if !can_read(&db, &project, Some(&logged_user)).await? {
+        return Err(Status::NotFound);
+    }
+    let title = new.title.trim();
+    if title.is_empty() {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    let project_id = project_uuid(&project)?;
+    // Like on GitHub, only those who can write to the project triage the issues
+    let is_writer = can_write(&db, &project, &logged_user).await?;
+    let (labels, assignee_ids, milestone_id) = match is_writer {
+        true => {
+            check_milestone(&db, &project_id, new.milestone_id.as_ref()).await?;
+            (find_labels(&db, &project_id, &new.labels).await?, find_users(&db, &project, &new.assignees).await?, new.milestone_id)
+        }
+        false => (Vec::new(), Vec::new(), None),
+    };
+
+    let now = OffsetDateTime::now_utc();
+    let mut issue = Issue {
+        id: Uuid::new_v4(),
+        project_id,
+        number: 0,
+        title: title.to_string(),
+        description: new.description.clone(),
+        author_id: user_uuid(&logged_user)?,
+        state: "open".to_string(),
+        milestone_id,
+        closed_by: None,
+        created_at: now,
+        updated_at: now,
+        closed_at: None,
+    };
+    issue.number = db.issues.create(&issue).await.map_err(|_| Status::InternalServerError)?;
+    if !labels.is_empty() {
+        let label_ids = labels.iter().map(|l| l.id).collect::<Vec<_>>();
+        db.issues.set_labels(&issue.id, &label_ids).await.map_err(|_| Status::InternalServerError)?;
+    }
+    if !assignee_ids.is_empty() {
+        db.issues.set_assignees(&issue.id, &assignee_ids).await.map_err(|_| Status::InternalServerError)?;
+    }
+
+    emit(&events, &project, IssueAction::Opened, &issue, &logged_user);
+    Ok(Json(issue_view(&db, issue).await?))
+}
+
+#[get("/<_owner>/<_project_name>/issues/<number>")]
+pub async fn issue_get(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: Option<User>, db: Database) -> Result<Json<IssueDetailView>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    let issue = load(&db, &project, number).await?;
+    let comments = db.issue_comments.for_issue(&issue.id).await.map_err(|_| Status::InternalServerError)?;
+
+    let description_html = match &issue.description {
+        Some(description) => Some(references::render(&db, &ProjectInfo::from(&project), description).await.map_err(|_| Status::InternalServerError)?),
+        None => None,
+    };
+
+    Ok(Json(IssueDetailView {
+        comments: comment_views(&db, &project, comments).await?,
+        description_html,
+        issue: issue_view(&db, issue).await?,
+    }))
+}
+
+#[post("/<_owner>/<_project_name>/issues/<number>", data = "<edit>")]
+pub async fn issue_post(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: User, db: Database, events: State<'_, EventBus>, edit: Json<IssueEdit>) -> Result<Json<IssueView>, Status> {
+    if !can_read(&db, &project, Some(&logged_user)).await? {
+        return Err(Status::NotFound);
+    }
+    let mut issue = load(&db, &project, number).await?;
+    if !can_change(&db, &project, &issue, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    let title = edit.title.trim();
+    if title.is_empty() {
+        return Err(Status::UnprocessableEntity);
+    }
+    if can_write(&db, &project, &logged_user).await? {
+        check_milestone(&db, &issue.project_id, edit.milestone_id.as_ref()).await?;
+        issue.milestone_id = edit.milestone_id;
+    }
+
+    issue.title = title.to_string();
+    issue.description = edit.description.clone();
+    issue.updated_at = OffsetDateTime::now_utc();
+    db.issues.update_details(&issue).await.map_err(|_| Status::InternalServerError)?;
+
+    emit(&events, &project, IssueAction::Edited, &issue, &logged_user);
+    Ok(Json(issue_view(&db, issue).await?))
+}
+
+#[post("/<_owner>/<_project_name>/issues/<number>/close")]
+pub async fn issue_close_post(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: User, db: Database, events: State<'_, EventBus>) -> Result<Json<IssueView>, Status> {
+    if !can_read(&db, &project, Some(&logged_user)).await? {
+        return Err(Status::NotFound);
+    }
+    let issue = load(&db, &project, number).await?;
+    if !can_change(&db, &project, &issue, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    if !issue.is_open() {
+        return Err(Status::Conflict);
+    }
+
+    let now = OffsetDateTime::now_utc();
+    db.issues.close(&issue, &user_uuid(&logged_user)?, now).await.map_err(|_| Status::InternalServerError)?;
+    let issue = load(&db, &project, number).await?;
+
+    emit(&events, &project, IssueAction::Closed, &issue, &logged_user);
+    Ok(Json(issue_view(&db, issue).await?))
+}
+
+#[post("/<_owner>/<_project_name>/issues/<number>/reopen")]
+pub async fn issue_reopen_post(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: User, db: Database, events: State<'_, EventBus>) -> Result<Json<IssueView>, Status> {
+    if !can_read(&db, &project, Some(&logged_user)).await? {
+        return Err(Status::NotFound);
+    }
+    let issue = load(&db, &project, number).await?;
+    if !can_change(&db, &project, &issue, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    if issue.is_open() {
+        return Err(Status::Conflict);
+    }
+
+    db.issues.reopen(&issue, OffsetDateTime::now_utc()).await.map_err(|_| Status::InternalServerError)?;
+    let issue = load(&db, &project, number).await?;
+
+    emit(&events, &project, IssueAction::Reopened, &issue, &logged_user);
+    Ok(Json(issue_view(&db, issue).await?))
+}
+
+#[post("/<_owner>/<_project_name>/issues/<number>/labels", data = "<edit>")]
+pub async fn issue_labels_post(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: User, db: Database, events: State<'_, EventBus>, edit: Json<LabelsEdit>) -> Result<Json<IssueView>, Status> {
+    if !can_write(&db, &project, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    let issue = load(&db, &project, number).await?;
+    let labels = find_labels(&db, &issue.project_id, &edit.labels).await?;
+    let all_labels = db.labels.for_project(&issue.project_id).await.map_err(|_| Status::InternalServerError)?;
+    let old_ids = db.issues.label_ids(&[issue.id]).await
+        .map_err(|_| Status::InternalServerError)?
+        .into_iter()
+        .map(|(_, label_id)| label_id)
+        .collect::<Vec<_>>();
+    let new_ids = labels.iter().map(|l| l.id).collect::<Vec<_>>();
+    db.issues.set_labels(&issue.id, &new_ids).await.map_err(|_| Status::InternalServerError)?;
+
+    for label in all_labels.iter().filter(|l| !old_ids.contains(&l.id) && new_ids.contains(&l.id)) {
+        emit_change(&events, &project, IssueAction::Labeled, &issue, Some(label.name.clone()), None, &logged_user);
+    }
+    for label in all_labels.iter().filter(|l| old_ids.contains(&l.id) && !new_ids.contains(&l.id)) {
+        emit_change(&events, &project, IssueAction::Unlabeled, &issue, Some(label.name.clone()), None, &logged_user);
+    }
+    Ok(Json(issue_view(&db, issue).await?))
+}
+
+#[post("/<_owner>/<_project_name>/issues/<number>/assignees", data = "<edit>")]
+pub async fn issue_assignees_post(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: User, db: Database, events: State<'_, EventBus>, edit: Json<AssigneesEdit>) -> Result<Json<IssueView>, Status> {
+    if !can_write(&db, &project, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    let issue = load(&db, &project, number).await?;
+    let new_ids = find_users(&db, &project, &edit.assignees).await?;
+    let old_ids = db.issues.assignee_ids(&[issue.id]).await
+        .map_err(|_| Status::InternalServerError)?
+        .into_iter()
+        .map(|(_, user_id)| user_id)
+        .collect::<Vec<_>>();
+    db.issues.set_assignees(&issue.id, &new_ids).await.map_err(|_| Status::InternalServerError)?;
+
+    let mut all_ids = old_ids.clone();
+    all_ids.extend(new_ids.iter().copied());
+    let names = names(&db, &all_ids).await?;
+    for id in new_ids.iter().filter(|id| !old_ids.contains(id)) {
+        emit_change(&events, &project, IssueAction::Assigned, &issue, None, names.get(id).cloned(), &logged_user);
+    }
+    for id in old_ids.iter().filter(|id| !new_ids.contains(id)) {
+        emit_change(&events, &project, IssueAction::Unassigned, &issue, None, names.get(id).cloned(), &logged_user);
+    }
+    Ok(Json(issue_view(&db, issue).await?))
+}
+
+#[get("/<_owner>/<_project_name>/issues/<number>/comments")]
+pub async fn comments_get(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: Option<User>, db: Database) -> Result<Json<Vec<IssueCommentView>>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    let issue = load(&db, &project, number).await?;
+    let comments = db.issue_comments.for_issue(&issue.id).await.map_err(|_| Status::InternalServerError)?;
+    Ok(Json(comment_views(&db, &project, comments).await?))
+}
+
+#[post("/<_owner>/<_project_name>/issues/<number>/comments", data = "<new>")]
+pub async fn comments_post(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: User, db: Database, events: State<'_, EventBus>, new: Json<NewComment>) -> Result<Json<IssueCommentView>, Status> {
+    if !can_read(&db, &project, Some(&logged_user)).await? {
+        return Err(Status::NotFound);
+    }
+    if new.body.trim().is_empty() {
+        return Err(Status::UnprocessableEntity);
+    }
+    let issue = load(&db, &project, number).await?;
+
+    let now = OffsetDateTime::now_utc();
+    let comment = IssueComment {
+        id: Uuid::new_v4(),
+        issue_id: issue.id,
+        author_id: user_uuid(&logged_user)?,
+        body: new.body.clone(),
+        created_at: now,
+        updated_at: now,
+    };
+    db.issue_comments.create(&comment).await.map_err(|_| Status::InternalServerError)?;
+
+    emit_comment(&events, &project, IssueCommentAction::Created, &issue, &comment, &logged_user);
+    Ok(Json(comment_views(&db, &project, vec![comment]).await?.remove(0)))
+}
+
+#[post("/<_owner>/<_project_name>/issues/<number>/comments/<id>", data = "<edit>")]
+pub async fn comment_post(_owner: Owner, _project_name: &str, project: Project, number: i64, id: UuidParam, logged_user: User, db: Database, events: State<'_, EventBus>, edit: Json<NewComment>) -> Result<Json<IssueCommentView>, Status> {
+    if !can_read(&db, &project, Some(&logged_user)).await? {
+        return Err(Status::NotFound);
+    }
+    let issue = load(&db, &project, number).await?;
+    let mut comment = load_comment(&db, &issue, &id).await?;
+    // Only the author can put words into their own mouth
+    if comment.author_id != user_uuid(&logged_user)? {
+        return Err(Status::Forbidden);
+    }
+    if edit.body.trim().is_empty() {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    comment.body = edit.body.clone();
+    comment.updated_at = OffsetDateTime::now_utc();
+    db.issue_comments.update_body(&comment.id, &comment.body, comment.updated_at).await
+        .map_err(|_| Status::InternalServerError)?;
+
+    emit_comment(&events, &project, IssueCommentAction::Edited, &issue, &comment, &logged_user);
+    Ok(Json(comment_views(&db, &project, vec![comment]).await?.remove(0)))
+}
+
+#[delete("/<_owner>/<_project_name>/issues/<number>/comments/<id>")]
+pub async fn comment_delete(_owner: Owner, _project_name: &str, project: Project, number: i64, id: UuidParam, logged_user: User, db: Database, events: State<'_, EventBus>) -> Result<Status, Status> {
+    if !can_read(&db, &project, Some(&logged_user)).await? {
+        return Err(Status::NotFound);
+    }
+    let issue = load(&db, &project, number).await?;
+    let comment = load_comment(&db, &issue, &id).await?;
+    if comment.author_id != user_uuid(&logged_user)? && !can_write(&db, &project, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+
+    db.issue_comments.delete(&comment.id).await.map_err(|_| Status::InternalServerError)?;
+    emit_comment(&events, &project, IssueCommentAction::Deleted, &issue, &comment, &logged_user);
+    Ok(Status::NoContent)
+}
@@ -0,0 +1,147 @@
+use rocket::{delete, get, post, routes, Route, http::Status};
+use rocket_contrib::{json::Json, uuid::Uuid as UuidParam};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use zorgit_common::{Project, entities::{Owner, User}};
+use zorgit_db::{Database, Label};
+use crate::access::{can_read, can_write};
+
+//##### Routes #####//
+// [get]     /{user|org}/{project}/labels
+// [post]    /{user|org}/{project}/labels
+// [post]    /{user|org}/{project}/labels/<id>
+// [delete]  /{user|org}/{project}/labels/<id>
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        labels_get,
+        labels_post,
+        label_post,
+        label_delete,
+    ]
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LabelForm {
+    pub name: String,
+    /// Hex color, with or without the leading `#`.
+    pub color: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LabelView {
+    pub id: String,
+    pub name: String,
+    pub color: String,
+    pub description: Option<String>,
+}
+
+impl From<Label> for LabelView {
+    fn from(label: Label) -> Self {
+        LabelView {
+            id: label.id.to_string(),
+            name: label.name,
+            color: label.color,
+            description: label.description,
+        }
+    }
+}
+
+/// Turns `D73A4A` or `#d73a4a` into `#d73a4a`. Anything else is not a color.
+fn normalize_color(color: &str) -> Option<String> {
+    let hex = color.trim().trim_start_matches('#');
+    match hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => Some(format!("#{}", hex.to_ascii_lowercase())),
+        false => None,
+    }
+}
+
+fn project_uuid(project: &Project) -> Result<Uuid, Status> {
+    project.id.to_uuid().map_err(|_| Status::InternalServerError)
+}
+
+async fn load(db: &Database, project: &Project, id: &Uuid) -> Result<Label, Status> {
+    let project_id = project_uuid(project)?;
+    db.labels.by_id(id).await
+        .map_err(|_| Status::InternalServerError)?
+        .filter(|label| label.project_id == project_id)
+        .ok_or(Status::NotFound)
+}
+
+/// Label names are unique inside a project.
+async fn is_name_taken(db: &Database, project_id: &Uuid, name: &str, except: Option<&Uuid>) -> Result<bool, Status> {
+    db.labels.by_name(project_id, name).await
+        .map(|label| label.map_or(false, |label| Some(&label.id) != except))
+        .map_err(|_| Status::InternalServerError)
+}
+
+#[get("/<_owner>/<_project_name>/labels")]
+pub async fn labels_get(_owner: Owner, _project_name: &str, project: Project, logged_user: Option<User>, db: Database) -> Result<Json<Vec<LabelView>>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    let project_id = project_uuid(&project)?;
+    let labels = db.labels.for_project(&project_id).await.map_err(|_| Status::InternalServerError)?;
+    Ok(Json(labels.into_iter().map(Into::into).collect()))
+}
+
+#[post("/<_owner>/<_project_name>/labels", data = "<form>")]
+pub async fn labels_post(_owner: Owner, _project_name: &str, project: Project, logged_user: User, db: Database, form: Json<LabelForm>) -> Result<Json<LabelView>, Status> {
+    if !can_write(&db, &project, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    let name = form.name.trim();
+    let color = normalize_color(&form.color).ok_or(Status::UnprocessableEntity)?;
+    if name.is_empty() {
+        return Err(Status::UnprocessableEntity);
+    }
+    let project_id = project_uuid(&project)?;
+    if is_name_taken(&db, &project_id, name, None).await? {
+        return Err(Status::Conflict);
+    }
+
+    let label = Label {
+        id: Uuid::new_v4(),
+        project_id,
+        name: name.to_string(),
+        color,
+        description: form.description.clone(),
+        created_at: OffsetDateTime::now_utc(),
+    };
+    db.labels.create(&label).await.map_err(|_| Status::InternalServerError)?;
+    Ok(Json(label.into()))
+}
+
+#[post("/<_owner>/<_project_name>/labels/<id>", data = "<form>")]
+pub async fn label_post(_owner: Owner, _project_name: &str, project: Project, id: UuidParam, logged_user: User, db: Database, form: Json<LabelForm>) -> Result<Json<LabelView>, Status> {
+    if !can_write(&db, &project, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    let mut label = load(&db, &project, &id).await?;
+    let name = form.name.trim();
+    let color = normalize_color(&form.color).ok_or(Status::UnprocessableEntity)?;
+    if name.is_empty() {
+        return Err(Status::UnprocessableEntity);
+    }
+    if is_name_taken(&db, &label.project_id, name, Some(&label.id)).await? {
+        return Err(Status::Conflict);
+    }
+
+    label.name = name.to_string();
+    label.color = color;
+    label.description = form.description.clone();
+    db.labels.update(&label).await.map_err(|_| Status::InternalServerError)?;
+    Ok(Json(label.into()))
+}
+
+#[delete("/<_owner>/<_project_name>/labels/<id>")]
+pub async fn label_delete(_owner: Owner, _project_name: &str, project: Project, id: UuidParam, logged_user: User, db: Database) -> Result<Status, Status> {
+    if !can_write(&db, &project, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    let label = load(&db, &project, &id).await?;
+    db.labels.delete(&label).await.map_err(|_| Status::InternalServerError)?;
+    Ok(Status::NoContent)
+}
@@ -4,9 +4,28 @@ use rocket_contrib::serve::StaticFiles;
 use zorgit_security::{AuthHatch, rocket_airlock::Airlock};
 use crate::config::ZorgitConfig;
 use zorgit_common::Url;
-use zorgit_vcs;
+use zorgit_vcs::{self, events::EventBus};
 
+mod access;
+mod archives;
+mod branches;
+mod commits;
 mod config;
+mod issues;
+mod labels;
+mod milestones;
+mod notifications;
+mod pulls;
+mod references;
+mod replies;
+mod reviews;
+mod statuses;
+mod tags;
+mod views;
+mod webhooks;
+
+/// Errors are sendable, because they are also returned from spawned tasks and event listeners.
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
 
 #[rocket::launch]
@@ -18,6 +37,25 @@ fn rocket() -> _ {
         .mount("/static", StaticFiles::from("static").rank(11))
         .mount("/static/img/", StaticFiles::from("assets/Logos"))
         .mount("/avatars", StaticFiles::from(avatars))
+        .mount("/", webhooks::routes())
+        .mount("/", archives::routes())
+        .mount("/", branches::routes())
+        .mount("/", commits::routes())
+        .mount("/", issues::routes())
+        .mount("/", labels::routes())
+        .mount("/", milestones::routes())
+        .mount("/", notifications::routes())
+        .mount("/", pulls::routes())
+        .mount("/", references::routes())
+        .mount("/", reviews::routes())
+        .mount("/", statuses::routes())
+        .mount("/", tags::routes())
         .attach(ZorgitConfig::attach())
         .attach(Airlock::<AuthHatch>::fairing())
+        .attach(EventBus::fairing())
+        .attach(webhooks::Dispatcher::fairing())
+        .attach(pulls::Tracker::fairing())
+        .attach(references::Linker::fairing())
+        .attach(notifications::Notifier::fairing())
+        .attach(replies::Inbox::fairing())
 }
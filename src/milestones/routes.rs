@@ -0,0 +1,203 @@
+use rocket::{delete, get, post, routes, Route, http::Status};
+use rocket_contrib::{json::Json, uuid::Uuid as UuidParam};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use zorgit_common::{Project, entities::{Owner, User}};
+use zorgit_db::{Database, Milestone};
+use crate::access::{can_read, can_write};
+use crate::views;
+
+//##### Routes #####//
+// [get]     /{user|org}/{project}/milestones?<state>
+// [post]    /{user|org}/{project}/milestones
+// [get]     /{user|org}/{project}/milestones/<id>
+// [post]    /{user|org}/{project}/milestones/<id>
+// [delete]  /{user|org}/{project}/milestones/<id>
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        milestones_get,
+        milestones_post,
+        milestone_get,
+        milestone_post,
+        milestone_delete,
+    ]
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MilestoneForm {
+    pub title: String,
+    pub description: Option<String>,
+    /// Unix timestamp of the due date.
+    pub due_on: Option<i64>,
+    /// `open` or `closed`, only used when editing. Defaults to the current state.
+    pub state: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MilestoneView {
+    pub id: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub state: String,
+    pub due_on: Option<i64>,
+    pub is_overdue: bool,
+    pub num_open_issues: i64,
+    pub num_closed_issues: i64,
+    /// Share of closed issues in percent.
+    pub progress: u8,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub closed_at: Option<i64>,
+}
+
+impl From<Milestone> for MilestoneView {
+    fn from(milestone: Milestone) -> Self {
+        let total = milestone.num_open_issues + milestone.num_closed_issues;
+        let progress = match total {
+            0 => 0,
+            total => (milestone.num_closed_issues * 100 / total) as u8,
+        };
+        MilestoneView {
+            is_overdue: milestone.is_open() && milestone.due_on.map_or(false, |due| due < OffsetDateTime::now_utc()),
+            id: milestone.id.to_string(),
+            title: milestone.title,
+            description: milestone.description,
+            state: milestone.state,
+            due_on: milestone.due_on.map(|t| t.unix_timestamp()),
+            num_open_issues: milestone.num_open_issues,
+            num_closed_issues: milestone.num_closed_issues,
+            progress,
+            created_at: milestone.created_at.unix_timestamp(),
+            updated_at: milestone.updated_at.unix_timestamp(),
+            closed_at: milestone.closed_at.map(|t| t.unix_timestamp()),
+        }
+    }
+}
+
+fn project_uuid(project: &Project) -> Result<Uuid, Status> {
+    project.id.to_uuid().map_err(|_| Status::InternalServerError)
+}
+
+async fn load(db: &Database, project: &Project, id: &Uuid) -> Result<Milestone, Status> {
+    let project_id = project_uuid(project)?;
+    db.milestones.by_id(id).await
+        .map_err(|_| Status::InternalServerError)?
+        .filter(|milestone| milestone.project_id == project_id)
+        .ok_or(Status::NotFound)
+}
+
+/// Milestone titles are unique inside a project.
+async fn is_title_taken(db: &Database, project_id: &Uuid, title: &str, except: Option<&Uuid>) -> Result<bool, Status> {
+    db.milestones.for_project(project_id, None).await
+        .map(|milestones| milestones.iter().any(|m| m.title == title && Some(&m.id) != except))
+        .map_err(|_| Status::InternalServerError)
+}
+
+fn due_on(form: &MilestoneForm) -> Result<Option<OffsetDateTime>, Status> {
+    form.due_on
+        .map(|due_on| views::timestamp(due_on).ok_or(Status::UnprocessableEntity))
+        .transpose()
+}
+
+#[get("/<_owner>/<_project_name>/milestones?<state>")]
+pub async fn milestones_get(_owner: Owner, _project_name: &str, project: Project, state: Option<&str>, logged_user: Option<User>, db: Database) -> Result<Json<Vec<MilestoneView>>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    let state = match state.unwrap_or("open") {
+        "all" => None,
+        state @ "open" | state @ "closed" => Some(state),
+        _ => return Err(Status::BadRequest),
+    };
+    let project_id = project_uuid(&project)?;
+    let milestones = db.milestones.for_project(&project_id, state).await.map_err(|_| Status::InternalServerError)?;
+    Ok(Json(milestones.into_iter().map(Into::into).collect()))
+}
+
+#[post("/<_owner>/<_project_name>/milestones", data = "<form>")]
+pub async fn milestones_post(_owner: Owner, _project_name: &str, project: Project, logged_user: User, db: Database, form: Json<MilestoneForm>) -> Result<Json<MilestoneView>, Status> {
+    if !can_write(&db, &project, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    let title = form.title.trim();
+    if title.is_empty() {
+        return Err(Status::UnprocessableEntity);
+    }
+    let project_id = project_uuid(&project)?;
+    if is_title_taken(&db, &project_id, title, None).await? {
+        return Err(Status::Conflict);
+    }
+
+    let now = OffsetDateTime::now_utc();
+    let milestone = Milestone {
+        id: Uuid::new_v4(),
+        project_id,
+        title: title.to_string(),
+        description: form.description.clone(),
+        due_on: due_on(&form)?,
+        state: "open".to_string(),
+        closed_at: None,
+        created_at: now,
+        updated_at: now,
+        num_open_issues: 0,
+        num_closed_issues: 0,
+    };
+    db.milestones.create(&milestone).await.map_err(|_| Status::InternalServerError)?;
+    Ok(Json(milestone.into()))
+}
+
+#[get("/<_owner>/<_project_name>/milestones/<id>")]
+pub async fn milestone_get(_owner: Owner, _project_name: &str, project: Project, id: UuidParam, logged_user: Option<User>, db: Database) -> Result<Json<MilestoneView>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    Ok(Json(load(&db, &project, &id).await?.into()))
+}
+
+#[post("/<_owner>/<_project_name>/milestones/<id>", data = "<form>")]
+pub async fn milestone_post(_owner: Owner, _project_name: &str, project: Project, id: UuidParam, logged_user: User, db: Database, form: Json<MilestoneForm>) -> Result<Json<MilestoneView>, Status> {
+    if !can_write(&db, &project, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    let mut milestone = load(&db, &project, &id).await?;
+    let title = form.title.trim();
+    if title.is_empty() {
+        return Err(Status::UnprocessableEntity);
+    }
+    if is_title_taken(&db, &milestone.project_id, title, Some(&milestone.id)).await? {
+        return Err(Status::Conflict);
+    }
+
+    let now = OffsetDateTime::now_utc();
+    match form.state.as_deref() {
+        Some("open") => {
+            milestone.state = "open".to_string();
+            milestone.closed_at = None;
+        }
+        Some("closed") if milestone.is_open() => {
+            milestone.state = "closed".to_string();
+            milestone.closed_at = Some(now);
+        }
+        Some("closed") | None => {}
+        Some(_) => return Err(Status::UnprocessableEntity),
+    }
+    milestone.title = title.to_string();
+    milestone.description = form.description.clone();
+    milestone.due_on = due_on(&form)?;
+    milestone.updated_at = now;
+    db.milestones.update(&milestone).await.map_err(|_| Status::InternalServerError)?;
+
+    Ok(Json(load(&db, &project, &id).await?.into()))
+}
+
+#[delete("/<_owner>/<_project_name>/milestones/<id>")]
+pub async fn milestone_delete(_owner: Owner, _project_name: &str, project: Project, id: UuidParam, logged_user: User, db: Database) -> Result<Status, Status> {
+    if !can_write(&db, &project, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    let milestone = load(&db, &project, &id).await?;
+    db.milestones.delete(&milestone).await.map_err(|_| Status::InternalServerError)?;
+    Ok(Status::NoContent)
+}
@@ -0,0 +1,77 @@
+use lettre::{SendmailTransport, SmtpClient, Transport, smtp::{authentication::Credentials, extension::ClientId}};
+use lettre_email::EmailBuilder;
+use crate::config::mailer::Config as MailerConfig;
+
+/// An email that is ready to be sent.
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    /// Where replies go, if they should not go to the sender.
+    pub reply_to: Option<String>,
+}
+
+/// Sends emails over sendmail or SMTP, whichever is configured. Sending blocks, so it
+/// should happen in `spawn_blocking`.
+#[derive(Clone)]
+pub struct Mailer {
+    config: MailerConfig,
+    from: String,
+}
+
+impl Mailer {
+    /// `None` if neither sendmail nor an SMTP server is configured, or if there is no sender address.
+    pub fn new(config: &MailerConfig) -> Option<Mailer> {
+        if config.sendmail_path.is_none() && config.smtp_server.is_none() {
+            return None;
+        }
+        Some(Mailer {
+            from: config.from.clone()?,
+            config: config.clone(),
+        })
+    }
+
+    /// The reply address of a token, like `reply+<token>@example.com`. `None` if replies
+    /// by email are not configured.
+    pub fn reply_address(&self, token: &str) -> Option<String> {
+        let reply_to = self.config.reply_to.as_ref()?;
+        let at = reply_to.rfind('@')?;
+        Some(format!("{}+{}{}", &reply_to[..at], token, &reply_to[at..]))
+    }
+
+    pub fn send(&self, mail: &Mail) -> Result<(), String> {
+        let subject = match &self.config.subject_prefix {
+            Some(prefix) => format!("{} {}", prefix, mail.subject),
+            None => mail.subject.clone(),
+        };
+        let mut builder = EmailBuilder::new()
+            .to(mail.to.as_str())
+            .from(self.from.as_str())
+            .subject(subject)
+            .text(mail.body.as_str());
+        if let Some(reply_to) = &mail.reply_to {
+            builder = builder.reply_to(reply_to.as_str());
+        }
+        let email = builder.build().map_err(|e| e.to_string())?;
+
+        if let Some(path) = &self.config.sendmail_path {
+            let mut transport = SendmailTransport::new_with_command(path.to_string_lossy());
+            return transport.send(email.into()).map(|_| ()).map_err(|e| e.to_string());
+        }
+        let server = match &self.config.smtp_server {
+            Some(server) => server,
+            None => return Ok(()),
+        };
+        // `smtp.example.com:587` is parsed with the host as scheme
+        let host = server.host_str().unwrap_or_else(|| server.scheme());
+        let mut client = SmtpClient::new_simple(host).map_err(|e| e.to_string())?;
+        if let (Some(user), Some(password)) = (&self.config.smtp_user, &self.config.smtp_password) {
+            client = client.credentials(Credentials::new(user.clone(), password.clone()));
+        }
+        if let (true, Some(hostname)) = (self.config.with_helo, &self.config.helo_hostname) {
+            client = client.hello_name(ClientId::Domain(hostname.clone()));
+        }
+        client.transport().send(email.into()).map(|_| ()).map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,6 @@
+mod mailer;
+mod notifier;
+mod routes;
+
+pub use notifier::Notifier;
+pub use routes::routes;
@@ -0,0 +1,340 @@
+use std::collections::{HashMap, HashSet};
+use rocket::{error, warn, fairing::AdHoc};
+use time::OffsetDateTime;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+use zorgit_common::{Notification as EmailSetting, Url, access::{access_by_id, Scope}, reference::{find_references, Reference}};
+use zorgit_db::{Database, Notification, ReplyToken, Subscription};
+use zorgit_vcs::events::{Event, EventBus, IssueAction, IssueCommentAction, ProjectInfo, PullRequestAction};
+use crate::Result;
+use crate::access::scope_of;
+use crate::config::ZorgitConfig;
+use super::mailer::{Mail, Mailer};
+
+/// Something that happened in an issue or pull request.
+struct Activity<'a> {
+    project: &'a ProjectInfo,
+    number: i64,
+    /// Either `issue` or `pull_request`.
+    kind: &'static str,
+    subject: &'a str,
+    /// Username of the user who did it.
+    actor: &'a str,
+    /// What the notification says. Users mentioned in it get notified too.
+    text: String,
+    /// Why the actor gets subscribed, if taking part subscribes them.
+    actor_reason: Option<&'static str>,
+    /// Username of the user who got assigned.
+    assignee: Option<&'a str>,
+}
+
+impl Activity<'_> {
+    fn url(&self, domain: &Url) -> String {
+        let kind = if self.kind == "pull_request" { "pulls" } else { "issues" };
+        format!("{}{}/{}/{}/{}", domain, self.project.owner, self.project.name, kind, self.number)
+    }
+}
+
+fn explain(reason: &str) -> &'static str {
+    match reason {
+        "author" => "you opened the thread",
+        "comment" => "you commented on the thread",
+        "mention" => "you were mentioned",
+        "assign" => "you were assigned",
+        "watching" => "you are watching the project",
+        _ => "you are subscribed to the thread",
+    }
+}
+
+/// Turns the events of issues and pull requests into notifications for everybody who is
+/// subscribed to them or watches their project, and emails them if the users want that.
+#[derive(Clone)]
+pub struct Notifier {
+    db: Database,
+    domain: Url,
+    mailer: Option<Mailer>,
+}
+
+impl Notifier {
+    /// Starts notifying. Needs the `Database`, `EventBus` and `ZorgitConfig` to be managed
+    /// already. Without a configured mailer, notifications only end up in the inboxes.
+    pub fn fairing() -> AdHoc {
+        AdHoc::on_attach("Notifications", |rocket| async {
+            let notifier = match (rocket.state::<Database>(), rocket.state::<ZorgitConfig>()) {
+                (Some(db), Some(config)) => Notifier {
+                    db: db.clone(),
+                    domain: config.domain.clone(),
+                    mailer: Mailer::new(&config.mailer),
+                },
+                _ => {
+                    warn!("Notifications are disabled, because the database or config is missing.");
+                    return Ok(rocket);
+                }
+            };
+            let events = match rocket.state::<EventBus>() {
+                Some(events) => events.subscribe(),
+                None => {
+                    warn!("Notifications are disabled, because the event bus is missing.");
+                    return Ok(rocket);
+                }
+            };
+
+            tokio::spawn(async move { notifier.listen(events).await });
+            Ok(rocket)
+        })
+    }
+
+    async fn listen(&self, mut events: tokio::sync::broadcast::Receiver<Event>) {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    if let Err(e) = self.handle(&event).await {
+                        error!("Sending notifications failed: {}", e);
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => error!("Notifications missed {} events.", missed),
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+
+    async fn handle(&self, event: &Event) -> Result<()> {
+        let activity = match event {
+            Event::Issue(event) => {
+                let (text, actor_reason) = match event.action {
+                    IssueAction::Opened => (event.issue.description.clone().unwrap_or_default(), Some("author")),
+                    IssueAction::Closed => (format!("Closed #{}.", event.issue.number), None),
+                    IssueAction::Reopened => (format!("Reopened #{}.", event.issue.number), None),
+                    IssueAction::Assigned => match &event.assignee {
+                        Some(assignee) => (format!("Assigned #{} to @{}.", event.issue.number, assignee), None),
+                        None => return Ok(()),
+                    },
+                    _ => return Ok(()),
+                };
+                Activity {
+                    project: &event.project,
+                    number: event.issue.number,
+                    kind: "issue",
+                    subject: &event.issue.title,
+                    actor: &event.sender,
+                    text,
+                    actor_reason,
+                    assignee: event.assignee.as_deref().filter(|_| event.action == IssueAction::Assigned),
+                }
+            }
+            Event::IssueComment(event) if event.action == IssueCommentAction::Created => Activity {
+                project: &event.project,
+                number: event.issue.number,
+                kind: "issue",
+                subject: &event.issue.title,
+                actor: &event.sender,
+                text: event.comment.body.clone(),
+                actor_reason: Some("comment"),
+                assignee: None,
+            },
+            Event::PullRequest(event) => {
+                let pull = &event.pull_request;
+                let (text, actor_reason) = match event.action {
+                    PullRequestAction::Opened => (pull.description.clone().unwrap_or_default(), Some("author")),
+                    PullRequestAction::Closed if pull.state == "merged" => (format!("Merged #{} into {}.", pull.number, pull.base_branch), None),
+                    PullRequestAction::Closed => (format!("Closed #{}.", pull.number), None),
+                    PullRequestAction::Reopened => (format!("Reopened #{}.", pull.number), None),
+                    _ => return Ok(()),
+                };
+                Activity {
+                    project: &event.project,
+                    number: pull.number,
+                    kind: "pull_request",
+                    subject: &pull.title,
+                    actor: &event.sender,
+                    text,
+                    actor_reason,
+                    assignee: None,
+                }
+            }
+            Event::PullRequestReview(event) => {
+                let number = event.pull_request.number;
+                let text = match (&event.review.body, event.review.state.as_str()) {
+                    (Some(body), _) => body.clone(),
+                    (None, "approved") => format!("Approved #{}.", number),
+                    (None, "changes_requested") => format!("Requested changes on #{}.", number),
+                    (None, _) => return Ok(()),
+                };
+                Activity {
+                    project: &event.project,
+                    number,
+                    kind: "pull_request",
+                    subject: &event.pull_request.title,
+                    actor: &event.sender,
+                    text,
+                    actor_reason: Some("comment"),
+                    assignee: None,
+                }
+            }
+            _ => return Ok(()),
+        };
+        self.notify(&activity).await
+    }
+
+    async fn subscribe(&self, user_id: &Uuid, project_id: &Uuid, number: i64, reason: &str) -> Result<()> {
+        self.db.subscriptions.subscribe_implicitly(&Subscription {
+            user_id: *user_id,
+            project_id: *project_id,
+            thread_number: number,
+            is_subscribed: true,
+            reason: reason.to_string(),
+            created_at: OffsetDateTime::now_utc(),
+        }).await?;
+        Ok(())
+    }
+
+    /// The address the user can reply to, so the reply ends up in the thread. Every user
+    /// gets their own token per thread, which is reused for all of its emails.
+    async fn reply_address(&self, mailer: &Mailer, user_id: &Uuid, project_id: &Uuid, activity: &Activity<'_>) -> Result<Option<String>> {
+        let new_token = Uuid::new_v4().to_simple().to_string();
+        if mailer.reply_address(&new_token).is_none() {
+            return Ok(None);
+        }
+        let token = self.db.reply_tokens.get_or_create(&ReplyToken {
+            token: new_token,
+            user_id: *user_id,
+            project_id: *project_id,
+            thread_number: activity.number,
+            thread_kind: activity.kind.to_string(),
+            created_at: OffsetDateTime::now_utc(),
+        }).await?;
+        Ok(mailer.reply_address(&token))
+    }
+
+    /// Watchers and subscribers can lose their access, i.e. once a project turns private or
+    /// a collaborator gets removed, so it is checked for every notification.
+    async fn can_read(&self, scope: &Scope, user_id: &Uuid) -> Result<bool> {
+        Ok(access_by_id(&self.db, scope, user_id).await?.can_read())
+    }
+
+    /// Who gets notified and why, and who got mentioned in this activity. A mention or an
+    /// assignment is more important than a subscription, which is more important than
+    /// watching the project.
+    async fn recipients(&self, activity: &Activity<'_>, scope: &Scope) -> Result<(HashMap<Uuid, String>, HashSet<Uuid>)> {
+        let project_id = &scope.project_id;
+        let mut recipients = HashMap::new();
+        let mut mentioned = HashSet::new();
+        let mentions = find_references(&activity.text).into_iter()
+            .filter_map(|found| match found.reference {
+                Reference::Mention(username) => Some(username),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+        for username in mentions {
+            if let Some(user_id) = self.db.owners.find(&username, false).await? {
+                // Mentioning someone doesn't let them into a project they can't see
+                if !self.can_read(scope, &user_id).await? {
+                    continue;
+                }
+                self.subscribe(&user_id, project_id, activity.number, "mention").await?;
+                recipients.insert(user_id, "mention".to_string());
+                mentioned.insert(user_id);
+            }
+        }
+        if let Some(assignee) = activity.assignee {
+            if let Some(user_id) = self.db.owners.find(assignee, false).await? {
+                if self.can_read(scope, &user_id).await? {
+                    self.subscribe(&user_id, project_id, activity.number, "assign").await?;
+                    recipients.entry(user_id).or_insert_with(|| "assign".to_string());
+                }
+            }
+        }
+
+        let subscriptions = self.db.subscriptions.for_thread(project_id, activity.number).await?;
+        for subscription in subscriptions.iter().filter(|s| s.is_subscribed) {
+            recipients.entry(subscription.user_id).or_insert_with(|| subscription.reason.clone());
+        }
+        // Unsubscribing from a thread mutes it, even for those who watch the project
+        let muted = subscriptions.iter().filter(|s| !s.is_subscribed).map(|s| s.user_id).collect::<HashSet<_>>();
+        for user_id in self.db.watches.watchers(project_id).await? {
+            if !muted.contains(&user_id) {
+                recipients.entry(user_id).or_insert_with(|| "watching".to_string());
+            }
+        }
+
+        let mut readers = HashMap::with_capacity(recipients.len());
+        for (user_id, reason) in recipients {
+            if mentioned.contains(&user_id) || self.can_read(scope, &user_id).await? {
+                readers.insert(user_id, reason);
+            }
+        }
+        Ok((readers, mentioned))
+    }
+
+    async fn notify(&self, activity: &Activity<'_>) -> Result<()> {
+        let scope = scope_of(activity.project)?;
+        let project_id = scope.project_id;
+        let actor_id = self.db.owners.find(activity.actor, false).await?;
+        if let (Some(actor_id), Some(reason)) = (actor_id, activity.actor_reason) {
+            self.subscribe(&actor_id, &project_id, activity.number, reason).await?;
+        }
+
+        let (mut recipients, mentioned) = self.recipients(activity, &scope).await?;
+        // Nobody needs to be told what they just did themselves
+        if let Some(actor_id) = actor_id {
+            recipients.remove(&actor_id);
+        }
+        if recipients.is_empty() {
+            return Ok(());
+        }
+
+        let now = OffsetDateTime::now_utc();
+        for (user_id, reason) in &recipients {
+            self.db.notifications.notify(&Notification {
+                id: Uuid::new_v4(),
+                user_id: *user_id,
+                project_id,
+                thread_number: activity.number,
+                thread_kind: activity.kind.to_string(),
+                subject: activity.subject.to_string(),
+                reason: reason.clone(),
+                last_actor: activity.actor.to_string(),
+                is_read: false,
+                created_at: now,
+                updated_at: now,
+            }).await?;
+        }
+
+        let mailer = match &self.mailer {
+            Some(mailer) => mailer,
+            None => return Ok(()),
+        };
+        let user_ids = recipients.keys().copied().collect::<Vec<_>>();
+        for (user_id, address, setting) in self.db.emails.notification_addresses(&user_ids).await? {
+            let reason = &recipients[&user_id];
+            // The reason may come from an earlier mention, so only this activity's text counts
+            let wants_mail = match EmailSetting::from(setting) {
+                EmailSetting::Disabled => false,
+                EmailSetting::Enabled => true,
+                EmailSetting::OnMentions => mentioned.contains(&user_id),
+            };
+            if !wants_mail {
+                continue;
+            }
+            let reply_to = self.reply_address(mailer, &user_id, &project_id, activity).await?;
+            let view = match reply_to {
+                Some(_) => "Reply to this email directly or view it on Zorgit",
+                None => "View it on Zorgit",
+            };
+            let mail = Mail {
+                to: address,
+                subject: format!("[{}/{}] {} (#{})", activity.project.owner, activity.project.name, activity.subject, activity.number),
+                body: format!("@{}: {}\n\n-- \n{}: {}\nYou are receiving this because {}.\n",
+                    activity.actor, activity.text, view, activity.url(&self.domain), explain(reason)),
+                reply_to,
+            };
+            let mailer = mailer.clone();
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = mailer.send(&mail) {
+                    error!("Sending a notification email to {} failed: {}", mail.to, e);
+                }
+            });
+        }
+        Ok(())
+    }
+}
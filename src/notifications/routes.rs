@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use rocket::{delete, get, post, routes, Route, http::Status};
+use rocket_contrib::{json::Json, uuid::Uuid as UuidParam};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use zorgit_common::{Project, entities::{Owner, User}};
+use zorgit_db::{Database, Notification, ProjectSummary, Subscription};
+use crate::access::can_read;
+
+//##### Routes #####//
+// [get]     /notifications?<all>&<page>
+// [get]     /notifications/count
+// [post]    /notifications/read
+// [post]    /notifications/<id>/read
+// [post]    /notifications/<id>/unread
+// [get]     /{user|org}/{project}/watch
+// [post]    /{user|org}/{project}/watch
+// [delete]  /{user|org}/{project}/watch
+// [get]     /{user|org}/{project}/issues/<number>/subscription
+// [post]    /{user|org}/{project}/issues/<number>/subscription
+// [get]     /{user|org}/{project}/pulls/<number>/subscription
+// [post]    /{user|org}/{project}/pulls/<number>/subscription
+
+const PAGE_SIZE: i64 = 50;
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        notifications_get,
+        notifications_count_get,
+        notifications_read_post,
+        notification_read_post,
+        notification_unread_post,
+        watch_get,
+        watch_post,
+        watch_delete,
+        issue_subscription_get,
+        issue_subscription_post,
+        pull_subscription_get,
+        pull_subscription_post,
+    ]
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SubscriptionEdit {
+    pub subscribed: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NotificationView {
+    pub id: String,
+    /// `owner/project` of the thread.
+    pub project: String,
+    pub thread_number: i64,
+    pub thread_kind: String,
+    pub subject: String,
+    pub reason: String,
+    pub last_actor: String,
+    pub is_read: bool,
+    pub url: String,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnreadView {
+    pub unread: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WatchView {
+    pub is_watching: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubscriptionView {
+    pub is_subscribed: bool,
+    /// Why the user is subscribed or not: `author`, `comment`, `mention`, `assign`, `manual` or `watching`.
+    /// `None` if the user never had anything to do with the thread.
+    pub reason: Option<String>,
+}
+
+fn user_uuid(user: &User) -> Result<Uuid, Status> {
+    user.id.to_uuid().map_err(|_| Status::InternalServerError)
+}
+
+fn project_uuid(project: &Project) -> Result<Uuid, Status> {
+    project.id.to_uuid().map_err(|_| Status::InternalServerError)
+}
+
+fn notification_view(project: &ProjectSummary, notification: Notification) -> NotificationView {
+    let kind = if notification.thread_kind == "pull_request" { "pulls" } else { "issues" };
+    NotificationView {
+        id: notification.id.to_string(),
+        project: format!("{}/{}", project.owner, project.name),
+        url: format!("/{}/{}/{}/{}", project.owner, project.name, kind, notification.thread_number),
+        thread_number: notification.thread_number,
+        thread_kind: notification.thread_kind,
+        subject: notification.subject,
+        reason: notification.reason,
+        last_actor: notification.last_actor,
+        is_read: notification.is_read,
+        updated_at: notification.updated_at.unix_timestamp(),
+    }
+}
+
+/// Notifications can only be read by the user they belong to.
+async fn load(db: &Database, user: &User, id: &Uuid) -> Result<Notification, Status> {
+    let user_id = user_uuid(user)?;
+    db.notifications.by_id(id).await
+        .map_err(|_| Status::InternalServerError)?
+        .filter(|notification| notification.user_id == user_id)
+        .ok_or(Status::NotFound)
+}
+
+/// Whether the issue or pull request exists.
+async fn thread_exists(db: &Database, project_id: &Uuid, number: i64, is_pull_request: bool) -> Result<bool, Status> {
+    match is_pull_request {
+        true => db.pull_requests.by_number(project_id, number).await.map(|pull| pull.is_some()),
+        false => db.issues.by_number(project_id, number).await.map(|issue| issue.is_some()),
+    }.map_err(|_| Status::InternalServerError)
+}
+
+async fn subscription(db: &Database, project: &Project, number: i64, is_pull_request: bool, user: &User) -> Result<SubscriptionView, Status> {
+    if !can_read(db, project, Some(user)).await? {
+        return Err(Status::NotFound);
+    }
+    let project_id = project_uuid(project)?;
+    if !thread_exists(db, &project_id, number, is_pull_request).await? {
+        return Err(Status::NotFound);
+    }
+    let user_id = user_uuid(user)?;
+    if let Some(subscription) = db.subscriptions.get(&user_id, &project_id, number).await.map_err(|_| Status::InternalServerError)? {
+        return Ok(SubscriptionView { is_subscribed: subscription.is_subscribed, reason: Some(subscription.reason) });
+    }
+    let is_watching = db.watches.is_watching(&user_id, &project_id).await.map_err(|_| Status::InternalServerError)?;
+    Ok(SubscriptionView {
+        is_subscribed: is_watching,
+        reason: if is_watching { Some("watching".to_string()) } else { None },
+    })
+}
+
+async fn subscribe(db: &Database, project: &Project, number: i64, is_pull_request: bool, user: &User, edit: &SubscriptionEdit) -> Result<SubscriptionView, Status> {
+    if !can_read(db, project, Some(user)).await? {
+        return Err(Status::NotFound);
+    }
+    let project_id = project_uuid(project)?;
+    if !thread_exists(db, &project_id, number, is_pull_request).await? {
+        return Err(Status::NotFound);
+    }
+    db.subscriptions.set(&Subscription {
+        user_id: user_uuid(user)?,
+        project_id,
+        thread_number: number,
+        is_subscribed: edit.subscribed,
+        reason: "manual".to_string(),
+        created_at: OffsetDateTime::now_utc(),
+    }).await.map_err(|_| Status::InternalServerError)?;
+    Ok(SubscriptionView { is_subscribed: edit.subscribed, reason: Some("manual".to_string()) })
+}
+
+#[get("/notifications?<all>&<page>")]
+pub async fn notifications_get(all: Option<bool>, page: Option<i64>, logged_user: User, db: Database) -> Result<Json<Vec<NotificationView>>, Status> {
+    let page = page.unwrap_or(1).max(1);
+    let user_id = user_uuid(&logged_user)?;
+    let notifications = db.notifications.for_user(&user_id, all.unwrap_or(false), PAGE_SIZE, (page - 1) * PAGE_SIZE).await
+        .map_err(|_| Status::InternalServerError)?;
+
+    let mut projects = HashMap::<Uuid, Option<ProjectSummary>>::new();
+    for notification in &notifications {
+        if !projects.contains_key(&notification.project_id) {
+            let summary = db.projects.summary(&notification.project_id).await.map_err(|_| Status::InternalServerError)?;
+            projects.insert(notification.project_id, summary);
+        }
+    }
+    // Notifications of deleted projects are gone with their project
+    Ok(Json(notifications.into_iter()
+        .filter_map(|notification| {
+            let project = projects.get(&notification.project_id)?.as_ref()?;
+            Some(notification_view(project, notification))
+        })
+        .collect()))
+}
+
+#[get("/notifications/count")]
+pub async fn notifications_count_get(logged_user: User, db: Database) -> Result<Json<UnreadView>, Status> {
+    let unread = db.notifications.count_unread(&user_uuid(&logged_user)?).await.map_err(|_| Status::InternalServerError)?;
+    Ok(Json(UnreadView { unread }))
+}
+
+#[post("/notifications/read")]
+pub async fn notifications_read_post(logged_user: User, db: Database) -> Result<Status, Status> {
+    db.notifications.read_all(&user_uuid(&logged_user)?).await.map_err(|_| Status::InternalServerError)?;
+    Ok(Status::NoContent)
+}
+
+#[post("/notifications/<id>/read")]
+pub async fn notification_read_post(id: UuidParam, logged_user: User, db: Database) -> Result<Status, Status> {
+    let notification = load(&db, &logged_user, &id).await?;
+    db.notifications.set_read(&notification.id, true).await.map_err(|_| Status::InternalServerError)?;
+    Ok(Status::NoContent)
+}
+
+#[post("/notifications/<id>/unread")]
+pub async fn notification_unread_post(id: UuidParam, logged_user: User, db: Database) -> Result<Status, Status> {
+    let notification = load(&db, &logged_user, &id).await?;
+    db.notifications.set_read(&notification.id, false).await.map_err(|_| Status::InternalServerError)?;
+    Ok(Status::NoContent)
+}
+
+#[get("/<_owner>/<_project_name>/watch")]
+pub async fn watch_get(_owner: Owner, _project_name: &str, project: Project, logged_user: User, db: Database) -> Result<Json<WatchView>, Status> {
+    if !can_read(&db, &project, Some(&logged_user)).await? {
+        return Err(Status::NotFound);
+    }
+    let is_watching = db.watches.is_watching(&user_uuid(&logged_user)?, &project_uuid(&project)?).await
+        .map_err(|_| Status::InternalServerError)?;
+    Ok(Json(WatchView { is_watching }))
+}
+
+#[post("/<_owner>/<_project_name>/watch")]
+pub async fn watch_post(_owner: Owner, _project_name: &str, project: Project, logged_user: User, db: Database) -> Result<Json<WatchView>, Status> {
+    if !can_read(&db, &project, Some(&logged_user)).await? {
+        return Err(Status::NotFound);
+    }
+    db.watches.watch(&user_uuid(&logged_user)?, &project_uuid(&project)?, OffsetDateTime::now_utc()).await
+        .map_err(|_| Status::InternalServerError)?;
+    Ok(Json(WatchView { is_watching: true }))
+}
+
+#[delete("/<_owner>/<_project_name>/watch")]
+pub async fn watch_delete(_owner: Owner, _project_name: &str, project: Project, logged_user: User, db: Database) -> Result<Json<WatchView>, Status> {
+    db.watches.unwatch(&user_uuid(&logged_user)?, &project_uuid(&project)?).await
+        .map_err(|_| Status::InternalServerError)?;
+    Ok(Json(WatchView { is_watching: false }))
+}
+
+#[get("/<_owner>/<_project_name>/issues/<number>/subscription")]
+pub async fn issue_subscription_get(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: User, db: Database) -> Result<Json<SubscriptionView>, Status> {
+    Ok(Json(subscription(&db, &project, number, false, &logged_user).await?))
+}
+
+#[post("/<_owner>/<_project_name>/issues/<number>/subscription", data = "<edit>")]
+pub async fn issue_subscription_post(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: User, db: Database, edit: Json<SubscriptionEdit>) -> Result<Json<SubscriptionView>, Status> {
+    Ok(Json(subscribe(&db, &project, number, false, &logged_user, &edit).await?))
+}
+
+#[get("/<_owner>/<_project_name>/pulls/<number>/subscription")]
+pub async fn pull_subscription_get(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: User, db: Database) -> Result<Json<SubscriptionView>, Status> {
+    Ok(Json(subscription(&db, &project, number, true, &logged_user).await?))
+}
+
+#[post("/<_owner>/<_project_name>/pulls/<number>/subscription", data = "<edit>")]
+pub async fn pull_subscription_post(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: User, db: Database, edit: Json<SubscriptionEdit>) -> Result<Json<SubscriptionView>, Status> {
+    Ok(Json(subscribe(&db, &project, number, true, &logged_user, &edit).await?))
+}
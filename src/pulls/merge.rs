@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use uuid::Uuid;
+use zorgit_common::Project;
+use zorgit_db::{Database, PullRequest, Review};
+use zorgit_vcs::{CombinedStatus, CommitState, MergeStyle, PushPolicy};
+use crate::{reviews, Result};
+
+/// Reasons why a pull request can't be merged right now. Conflicts are not part of them,
+/// because they need the repository and are reported on their own.
+pub async fn blockers(db: &Database, project: &Project, pull: &PullRequest, policy: &PushPolicy) -> Result<Vec<String>> {
+    let mut blockers = Vec::new();
+    if pull.is_draft {
+        blockers.push("Draft pull requests can't be merged".to_string());
+    }
+
+    let protection = match policy.protection_for(&pull.base_branch) {
+        Some(protection) => protection,
+        None => return Ok(blockers),
+    };
+    if !protection.pusher_allowed {
+        blockers.push(format!("{} is not allowed to push to {}", policy.pusher, pull.base_branch));
+    }
+    if !protection.required_status_checks.is_empty() {
+        let status = CombinedStatus::load(db, project, &pull.head_commit_id).await
+            .map_err(|e| e.to_string())?;
+        let statuses = status.map(|s| s.statuses).unwrap_or_default();
+        for context in &protection.required_status_checks {
+            match statuses.iter().find(|s| &s.context == context) {
+                Some(status) if status.state == CommitState::Success => (),
+                Some(status) => blockers.push(format!("Required status check {} is {}", context, status.state)),
+                None => blockers.push(format!("Required status check {} is expected", context)),
+            }
+        }
+    }
+
+    if protection.required_approvals > 0 {
+        let reviews = db.reviews.for_pull_request(&pull.id).await?;
+        let verdicts = verdicts(&reviews);
+        let approvals = verdicts.values().filter(|&&state| state == "approved").count();
+        if approvals < protection.required_approvals as usize {
+            blockers.push(format!("{} of {} required approvals", approvals, protection.required_approvals));
+        }
+        if verdicts.values().any(|&state| state == "changes_requested") {
+            blockers.push("Changes are requested".to_string());
+        }
+    }
+    if protection.require_code_owner_review {
+        let reviews = db.reviews.for_pull_request(&pull.id).await?;
+        let approver_ids = verdicts(&reviews).into_iter()
+            .filter(|(_, state)| *state == "approved")
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        for path in reviews::unapproved_files(db, &project.dir, pull, &approver_ids).await? {
+            blockers.push(format!("A code owner of {} has to approve", path));
+        }
+    }
+
+    Ok(blockers)
+}
+
+/// The current verdict of every official reviewer. A newer review replaces an older one,
+/// a dismissed review takes the verdict back and comments don't change it.
+pub fn verdicts(reviews: &[Review]) -> HashMap<Uuid, &str> {
+    let mut verdicts = HashMap::new();
+    for review in reviews.iter().filter(|r| r.is_official && r.state != "commented") {
+        verdicts.insert(review.author_id, review.state.as_str());
+    }
+    verdicts
+}
+
+/// Commit message, if none was given. Rebased commits keep their own messages.
+pub fn default_message(pull: &PullRequest, style: MergeStyle) -> String {
+    match style {
+        MergeStyle::Merge => format!("Merge pull request #{} from {}\n\n{}", pull.number, pull.head_branch, pull.title),
+        MergeStyle::Squash => match &pull.description {
+            Some(description) if !description.trim().is_empty() => format!("{} (#{})\n\n{}", pull.title, pull.number, description.trim()),
+            _ => format!("{} (#{})", pull.title, pull.number),
+        },
+        MergeStyle::Rebase => String::new(),
+    }
+}
@@ -0,0 +1,7 @@
+mod merge;
+mod routes;
+mod sync;
+mod tracker;
+
+pub use routes::routes;
+pub use tracker::Tracker;
@@ -0,0 +1,538 @@
+use rocket::{get, post, routes, Route, State, http::Status};
+use rocket_contrib::json::Json;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use zorgit_common::{Project, entities::{Owner, User}};
+use zorgit_db::{Database, PullRequest};
+use zorgit_vcs::{CombinedStatus, CompareMode, MergeStyle, PushPolicy, RefUpdate, Signature, VersionControl, git::{self, Repository}};
+use zorgit_vcs::events::{Event, EventBus, ProjectInfo, PullRequestAction, PullRequestEvent, PushEvent};
+use crate::access::{can_read, can_read_summary, can_write};
+use crate::config::ZorgitConfig;
+use crate::references;
+use crate::reviews;
+use crate::views::{CommitView, DiffView};
+use super::{merge, sync};
+
+//##### Routes #####//
+// [get]     /{user|org}/{project}/pulls?<state>&<page>
+// [post]    /{user|org}/{project}/pulls
+// [get]     /{user|org}/{project}/pulls/<number>
+// [post]    /{user|org}/{project}/pulls/<number>
+// [post]    /{user|org}/{project}/pulls/<number>/close
+// [post]    /{user|org}/{project}/pulls/<number>/reopen
+// [get]     /{user|org}/{project}/pulls/<number>/merge
+// [post]    /{user|org}/{project}/pulls/<number>/merge
+
+const PAGE_SIZE: i64 = 25;
+const PULL_COMMITS_LIMIT: usize = 250;
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        pulls_get,
+        pulls_post,
+        pull_get,
+        pull_post,
+        pull_close_post,
+        pull_reopen_post,
+        pull_merge_get,
+        pull_merge_post,
+    ]
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewPullRequest {
+    pub title: String,
+    pub description: Option<String>,
+    /// Branch with the changes. A branch of a fork is given as `<owner>/<project>:<branch>`.
+    pub head: String,
+    /// Branch the changes should be merged into.
+    pub base: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PullRequestEdit {
+    pub title: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MergeRequest {
+    #[serde(default)]
+    pub style: MergeStyle,
+    /// Replaces the default message of merge and squash commits.
+    pub message: Option<String>,
+    /// The head commit the user reviewed. If the head moved since, the merge is refused.
+    pub head_commit_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MergeabilityView {
+    pub is_mergeable: bool,
+    /// Paths that conflict between the base and the head branch.
+    pub conflicts: Vec<String>,
+    /// Everything else that stops the pull request from being merged.
+    pub blockers: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PullRequestView {
+    pub number: i64,
+    pub title: String,
+    pub description: Option<String>,
+    pub state: String,
+    pub is_draft: bool,
+    pub is_from_fork: bool,
+    pub head_branch: String,
+    pub head_commit_id: String,
+    pub base_branch: String,
+    pub base_commit_id: String,
+    pub merge_base_id: Option<String>,
+    pub merge_commit_id: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub closed_at: Option<i64>,
+    pub merged_at: Option<i64>,
+}
+
+impl From<PullRequest> for PullRequestView {
+    fn from(pull: PullRequest) -> Self {
+        PullRequestView {
+            is_from_fork: pull.is_from_fork(),
+            number: pull.number,
+            title: pull.title,
+            description: pull.description,
+            state: pull.state,
+            is_draft: pull.is_draft,
+            head_branch: pull.head_branch,
+            head_commit_id: pull.head_commit_id,
+            base_branch: pull.base_branch,
+            base_commit_id: pull.base_commit_id,
+            merge_base_id: pull.merge_base_id,
+            merge_commit_id: pull.merge_commit_id,
+            created_at: pull.created_at.unix_timestamp(),
+            updated_at: pull.updated_at.unix_timestamp(),
+            closed_at: pull.closed_at.map(|t| t.unix_timestamp()),
+            merged_at: pull.merged_at.map(|t| t.unix_timestamp()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PullRequestDetailView {
+    #[serde(flatten)]
+    pub pull_request: PullRequestView,
+    /// The description as HTML, with references to issues, pull requests, commits and users linked.
+    pub description_html: Option<String>,
+    pub ahead_by: usize,
+    pub behind_by: usize,
+    /// Combined status of the head commit.
+    pub status: Option<CombinedStatus>,
+    pub commits: Vec<CommitView>,
+    pub diff: DiffView,
+}
+
+/// Splits `<owner>/<project>:<branch>` into the project and the branch. Git doesn't allow `:`
+/// in branch names, so a plain branch name is always a branch of the base project.
+fn split_head(head: &str) -> (Option<(&str, &str)>, &str) {
+    let mut parts = head.splitn(2, ':');
+    match (parts.next(), parts.next()) {
+        (Some(project), Some(branch)) => {
+            let mut names = project.splitn(2, '/');
+            match (names.next(), names.next()) {
+                (Some(owner), Some(name)) => (Some((owner, name)), branch),
+                _ => (None, head),
+            }
+        }
+        _ => (None, head),
+    }
+}
+
+fn project_uuid(project: &Project) -> Result<Uuid, Status> {
+    project.id.to_uuid().map_err(|_| Status::InternalServerError)
+}
+
+async fn load(db: &Database, project: &Project, number: i64) -> Result<PullRequest, Status> {
+    let project_id = project_uuid(project)?;
+    db.pull_requests.by_number(&project_id, number).await
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::NotFound)
+}
+
+/// The author of a pull request may change it, just like everybody who can write to the project.
+async fn can_change(db: &Database, project: &Project, pull: &PullRequest, user: &User) -> Result<bool, Status> {
+    Ok(user.id.to_uuid().ok() == Some(pull.author_id) || can_write(db, project, user).await?)
+}
+
+/// The project of the head branch, which is the project itself unless the pull request comes from a fork.
+async fn head_project(db: &Database, config: &ZorgitConfig, project: &Project, pull: &PullRequest) -> Result<ProjectInfo, Status> {
+    if !pull.is_from_fork() {
+        return Ok(ProjectInfo::from(project));
+    }
+    sync::project_info(db, &config.projects, &pull.head_project_id).await
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::UnprocessableEntity)
+}
+
+fn emit(events: &EventBus, project: &Project, action: PullRequestAction, pull: &PullRequest, user: &User) {
+    events.emit(Event::PullRequest(PullRequestEvent {
+        project: ProjectInfo::from(project),
+        action,
+        pull_request: pull.into(),
+        sender: user.username.clone(),
+    }));
+}
+
+async fn load_policy(db: &Database, project: &Project, user: &User) -> Result<PushPolicy, Status> {
+    PushPolicy::load(db, project, user).await.map_err(|_| Status::InternalServerError)
+}
+
+/// Current commit of the base branch. Merging needs it, even if the pull request stores an older one.
+fn base_commit(project: &Project, pull: &PullRequest) -> Result<String, Status> {
+    sync::branch_commit(&project.dir, &pull.base_branch)
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::MethodNotAllowed)
+}
+
+#[get("/<_owner>/<_project_name>/pulls?<state>&<page>")]
+pub async fn pulls_get(_owner: Owner, _project_name: &str, project: Project, state: Option<&str>, page: Option<i64>, logged_user: Option<User>, db: Database) -> Result<Json<Vec<PullRequestView>>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    let state = match state.unwrap_or("open") {
+        "all" => None,
+        state @ "open" | state @ "closed" | state @ "merged" => Some(state),
+        _ => return Err(Status::BadRequest),
+    };
+    let page = page.unwrap_or(1).max(1);
+
+    let project_id = project_uuid(&project)?;
+    let pulls = db.pull_requests.for_project(&project_id, state, PAGE_SIZE, (page - 1) * PAGE_SIZE).await
+        .map_err(|_| Status::InternalServerError)?;
+    Ok(Json(pulls.into_iter().map(Into::into).collect()))
+}
+
+#[post("/<_owner>/<_project_name>/pulls", data = "<new>")]
+pub async fn pulls_post(_owner: Owner, _project_name: &str, project: Project, logged_user: User, db: Database, config: State<'_, ZorgitConfig>, events: State<'_, EventBus>, new: Json<NewPullRequest>) -> Result<Json<PullRequestView>, Status> {
+    if !can_read(&db, &project, Some(&logged_user)).await? {
+        return Err(Status::NotFound);
+    }
+    let title = new.title.trim();
+    if title.is_empty() {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    let project_id = project_uuid(&project)?;
+    let base = ProjectInfo::from(&project);
+    let (fork, head_branch) = split_head(&new.head);
+    let head = match fork {
+        Some((owner, name)) => {
+            let head_id = db.projects.find(owner, name).await
+                .map_err(|_| Status::InternalServerError)?
+                .ok_or(Status::UnprocessableEntity)?;
+            let summary = db.projects.summary(&head_id).await
+                .map_err(|_| Status::InternalServerError)?
+                .ok_or(Status::UnprocessableEntity)?;
+            // Projects the user can't see don't exist for them
+            if !can_read_summary(&db, &summary, &logged_user).await? {
+                return Err(Status::NotFound);
+            }
+            // Other projects don't share their history with this one
+            let forked_from = db.projects.forked_from(&head_id).await.map_err(|_| Status::InternalServerError)?;
+            if head_id != project_id && forked_from != Some(project_id) {
+                return Err(Status::UnprocessableEntity);
+            }
+            ProjectInfo::from_summary(&summary, config.projects.dir(&summary.owner, &summary.name))
+        }
+        None => base.clone(),
+    };
+    let head_project_id = head.id.parse::<Uuid>().map_err(|_| Status::InternalServerError)?;
+    if head_project_id == project_id && head_branch == new.base {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    let base_commit_id = sync::branch_commit(&base.dir, &new.base)
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::UnprocessableEntity)?;
+    sync::branch_commit(&head.dir, head_branch)
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::UnprocessableEntity)?;
+    let existing = db.pull_requests.find_open(&project_id, &new.base, &head_project_id, head_branch).await
+        .map_err(|_| Status::InternalServerError)?;
+    if existing.is_some() {
+        return Err(Status::Conflict);
+    }
+
+    // The head is fetched before the pull request exists, so it is never stored without its commits
+    let id = Uuid::new_v4();
+    let pending = sync::pending_ref(&id);
+    let (head_commit_id, merge_base_id) = sync::fetch_head(&base.dir, &head.dir, head_branch, &new.base, &pending).await
+        .map_err(|_| Status::InternalServerError)?;
+
+    let now = OffsetDateTime::now_utc();
+    let mut pull = PullRequest {
+        id,
+        project_id,
+        number: 0,
+        title: title.to_string(),
+        description: new.description.clone(),
+        author_id: logged_user.id.to_uuid().map_err(|_| Status::InternalServerError)?,
+        head_project_id,
+        head_branch: head_branch.to_string(),
+        head_commit_id,
+        base_branch: new.base.clone(),
+        base_commit_id,
+        merge_base_id,
+        is_draft: config.projects.pull_requests.is_work_in_progress(title),
+        state: "open".to_string(),
+        merge_commit_id: None,
+        merged_by: None,
+        created_at: now,
+        updated_at: now,
+        closed_at: None,
+        merged_at: None,
+    };
+    pull.number = match db.pull_requests.create(&pull).await {
+        Ok(number) => number,
+        Err(_) => {
+            let _ = git::delete_ref(&base.dir, &pending).await;
+            return Err(Status::InternalServerError);
+        }
+    };
+    git::move_ref(&base.dir, &pending, &sync::head_ref(pull.number)).await
+        .map_err(|_| Status::InternalServerError)?;
+    reviews::request_code_owners(&db, &base.dir, &pull).await.map_err(|_| Status::InternalServerError)?;
+
+    emit(&events, &project, PullRequestAction::Opened, &pull, &logged_user);
+    Ok(Json(pull.into()))
+}
+
+#[get("/<_owner>/<_project_name>/pulls/<number>")]
+pub async fn pull_get(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: Option<User>, db: Database) -> Result<Json<PullRequestDetailView>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    let pull = load(&db, &project, number).await?;
+    // The stored commits are used instead of the branches, so closed pull requests still
+    // show what they contained, even after their branches are gone
+    let comparison = {
+        let repo = Repository::open(&project.dir).map_err(|_| Status::InternalServerError)?;
+        repo.compare(&pull.base_commit_id, &pull.head_commit_id, CompareMode::ThreeDot, PULL_COMMITS_LIMIT)
+            .map_err(|_| Status::UnprocessableEntity)?
+    };
+
+    let mut commit_ids = comparison.commits.iter().map(|c| c.id.clone()).collect::<Vec<_>>();
+    commit_ids.push(comparison.head_id.clone());
+    let statuses = CombinedStatus::load_many(&db, &project, &commit_ids).await
+        .map_err(|_| Status::InternalServerError)?;
+    let info = ProjectInfo::from(&project);
+    let description_html = match &pull.description {
+        Some(description) => Some(references::render(&db, &info, description).await.map_err(|_| Status::InternalServerError)?),
+        None => None,
+    };
+    let mut messages = references::render_messages(&db, &info, &comparison.commits).await
+        .map_err(|_| Status::InternalServerError)?;
+
+    Ok(Json(PullRequestDetailView {
+        description_html,
+        status: statuses.get(&comparison.head_id).cloned(),
+        commits: CommitView::list(comparison.commits, &statuses, &mut messages),
+        ahead_by: comparison.ahead_by,
+        behind_by: comparison.behind_by,
+        diff: comparison.diff.into(),
+        pull_request: pull.into(),
+    }))
+}
+
+#[post("/<_owner>/<_project_name>/pulls/<number>", data = "<edit>")]
+pub async fn pull_post(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: User, db: Database, config: State<'_, ZorgitConfig>, events: State<'_, EventBus>, edit: Json<PullRequestEdit>) -> Result<Json<PullRequestView>, Status> {
+    let pull = load(&db, &project, number).await?;
+    if !can_change(&db, &project, &pull, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    let title = edit.title.trim();
+    if title.is_empty() {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    let is_draft = config.projects.pull_requests.is_work_in_progress(title);
+    let updated_at = OffsetDateTime::now_utc();
+    db.pull_requests.update_details(&pull.id, title, edit.description.as_deref(), is_draft, updated_at).await
+        .map_err(|_| Status::InternalServerError)?;
+
+    let pull = PullRequest { title: title.to_string(), description: edit.description.clone(), is_draft, updated_at, ..pull };
+    emit(&events, &project, PullRequestAction::Edited, &pull, &logged_user);
+    Ok(Json(pull.into()))
+}
+
+#[post("/<_owner>/<_project_name>/pulls/<number>/close")]
+pub async fn pull_close_post(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: User, db: Database, events: State<'_, EventBus>) -> Result<Json<PullRequestView>, Status> {
+    let pull = load(&db, &project, number).await?;
+    if !can_change(&db, &project, &pull, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    if !pull.is_open() {
+        return Err(Status::Conflict);
+    }
+
+    let closed_at = OffsetDateTime::now_utc();
+    db.pull_requests.close(&pull, closed_at).await.map_err(|_| Status::InternalServerError)?;
+
+    let pull = PullRequest { state: "closed".to_string(), closed_at: Some(closed_at), updated_at: closed_at, ..pull };
+    emit(&events, &project, PullRequestAction::Closed, &pull, &logged_user);
+    Ok(Json(pull.into()))
+}
+
+#[post("/<_owner>/<_project_name>/pulls/<number>/reopen")]
+pub async fn pull_reopen_post(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: User, db: Database, config: State<'_, ZorgitConfig>, events: State<'_, EventBus>) -> Result<Json<PullRequestView>, Status> {
+    let pull = load(&db, &project, number).await?;
+    if !can_change(&db, &project, &pull, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    // Merged pull requests stay merged
+    if pull.state != "closed" {
+        return Err(Status::Conflict);
+    }
+    let existing = db.pull_requests.find_open(&pull.project_id, &pull.base_branch, &pull.head_project_id, &pull.head_branch).await
+        .map_err(|_| Status::InternalServerError)?;
+    if existing.is_some() {
+        return Err(Status::Conflict);
+    }
+
+    // Both branches might have moved or be gone, while the pull request was closed
+    let head = head_project(&db, &config, &project, &pull).await?;
+    let base_commit_id = sync::branch_commit(&project.dir, &pull.base_branch)
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::UnprocessableEntity)?;
+    sync::branch_commit(&head.dir, &pull.head_branch)
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::UnprocessableEntity)?;
+
+    let now = OffsetDateTime::now_utc();
+    db.pull_requests.reopen(&pull, now).await.map_err(|_| Status::InternalServerError)?;
+    let (head_commit_id, merge_base_id) = sync::sync_head(&project.dir, &head.dir, &pull).await
+        .map_err(|_| Status::InternalServerError)?;
+    db.pull_requests.update_base(&pull.id, &base_commit_id, merge_base_id.as_deref(), now).await
+        .map_err(|_| Status::InternalServerError)?;
+    db.pull_requests.update_head(&pull.id, &head_commit_id, merge_base_id.as_deref(), now).await
+        .map_err(|_| Status::InternalServerError)?;
+
+    let pull = PullRequest {
+        state: "open".to_string(),
+        closed_at: None,
+        updated_at: now,
+        head_commit_id,
+        base_commit_id,
+        merge_base_id,
+        ..pull
+    };
+    reviews::follow_threads(&db, &project.dir, &pull).await.map_err(|_| Status::InternalServerError)?;
+    emit(&events, &project, PullRequestAction::Reopened, &pull, &logged_user);
+    Ok(Json(pull.into()))
+}
+
+#[get("/<_owner>/<_project_name>/pulls/<number>/merge")]
+pub async fn pull_merge_get(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: User, db: Database) -> Result<Json<MergeabilityView>, Status> {
+    if !can_read(&db, &project, Some(&logged_user)).await? {
+        return Err(Status::NotFound);
+    }
+    let pull = load(&db, &project, number).await?;
+    if !pull.is_open() {
+        return Ok(Json(MergeabilityView {
+            is_mergeable: false,
+            conflicts: Vec::new(),
+            blockers: vec![format!("The pull request is {}", pull.state)],
+        }));
+    }
+
+    let policy = load_policy(&db, &project, &logged_user).await?;
+    let mut blockers = merge::blockers(&db, &project, &pull, &policy).await
+        .map_err(|_| Status::InternalServerError)?;
+    if !can_write(&db, &project, &logged_user).await? {
+        blockers.push(format!("{} has no write access", logged_user.username));
+    }
+    let conflicts = {
+        let base_id = base_commit(&project, &pull)?;
+        let repo = Repository::open(&project.dir).map_err(|_| Status::InternalServerError)?;
+        repo.merge_conflicts(&base_id, &pull.head_commit_id).map_err(|_| Status::InternalServerError)?
+    };
+
+    Ok(Json(MergeabilityView {
+        is_mergeable: conflicts.is_empty() && blockers.is_empty(),
+        conflicts,
+        blockers,
+    }))
+}
+
+#[post("/<_owner>/<_project_name>/pulls/<number>/merge", data = "<request>")]
+pub async fn pull_merge_post(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: User, db: Database, events: State<'_, EventBus>, request: Json<MergeRequest>) -> Result<Json<PullRequestView>, Status> {
+    if !can_write(&db, &project, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    let pull = load(&db, &project, number).await?;
+    if !pull.is_open() {
+        return Err(Status::MethodNotAllowed);
+    }
+    if request.head_commit_id.as_ref().map_or(false, |id| id != &pull.head_commit_id) {
+        return Err(Status::Conflict);
+    }
+    let policy = load_policy(&db, &project, &logged_user).await?;
+    let blockers = merge::blockers(&db, &project, &pull, &policy).await
+        .map_err(|_| Status::InternalServerError)?;
+    if !blockers.is_empty() {
+        return Err(Status::MethodNotAllowed);
+    }
+
+    let base_id = base_commit(&project, &pull)?;
+    let message = request.message.clone()
+        .filter(|m| !m.trim().is_empty())
+        .unwrap_or_else(|| merge::default_message(&pull, request.style));
+    let merger = Signature {
+        name: logged_user.full_name.clone().unwrap_or_else(|| logged_user.username.clone()),
+        email: logged_user.email.address.clone(),
+    };
+    let update = {
+        let repo = Repository::open(&project.dir).map_err(|_| Status::InternalServerError)?;
+        let conflicts = repo.merge_conflicts(&base_id, &pull.head_commit_id).map_err(|_| Status::InternalServerError)?;
+        if !conflicts.is_empty() {
+            return Err(Status::MethodNotAllowed);
+        }
+        let merge_commit_id = repo.merge(&base_id, &pull.head_commit_id, request.style, &message, &merger)
+            .map_err(|_| Status::MethodNotAllowed)?;
+
+        // The new base commit has to pass the same protection rules as a push,
+        // i.e. merge commits are refused on branches that require a linear history
+        let update = RefUpdate { name: sync::branch_ref(&pull.base_branch), old_id: Some(base_id.clone()), new_id: Some(merge_commit_id.clone()) };
+        let rejections = repo.check_updates(&policy, &[update.clone()]).map_err(|_| Status::InternalServerError)?;
+        if !rejections.is_empty() {
+            return Err(Status::Forbidden);
+        }
+        // Fails if the base branch moved while merging
+        repo.update_branch(&pull.base_branch, &base_id, &merge_commit_id).map_err(|_| Status::Conflict)?;
+        update
+    };
+
+    let merge_commit_id = update.new_id.clone().unwrap_or_default();
+    let merged_by = logged_user.id.to_uuid().map_err(|_| Status::InternalServerError)?;
+    let merged_at = OffsetDateTime::now_utc();
+    db.pull_requests.mark_merged(&pull, &merge_commit_id, Some(&merged_by), merged_at).await
+        .map_err(|_| Status::InternalServerError)?;
+
+    let pull = PullRequest {
+        state: "merged".to_string(),
+        merge_commit_id: Some(merge_commit_id),
+        merged_by: Some(merged_by),
+        merged_at: Some(merged_at),
+        closed_at: Some(merged_at),
+        updated_at: merged_at,
+        ..pull
+    };
+    events.emit(Event::Push(PushEvent {
+        project: ProjectInfo::from(&project),
+        pusher: logged_user.username.clone(),
+        pusher_email: logged_user.email.address.clone(),
+        updates: vec![update],
+    }));
+    emit(&events, &project, PullRequestAction::Closed, &pull, &logged_user);
+    Ok(Json(pull.into()))
+}
@@ -0,0 +1,68 @@
+use std::path::Path;
+use uuid::Uuid;
+use zorgit_db::{Database, PullRequest};
+use zorgit_vcs::{VersionControl, events::ProjectInfo, git::{self, Repository}};
+use crate::Result;
+use crate::config::Projects;
+
+/// Reference in the base repository that holds the head of a pull request. The commits of a
+/// pull request from a fork only exist in the fork, until they are fetched into this reference.
+pub fn head_ref(number: i64) -> String {
+    format!("refs/pull/{}/head", number)
+}
+
+/// Reference that holds the head of a pull request, before it got its number.
+pub fn pending_ref(id: &Uuid) -> String {
+    format!("refs/pull/pending/{}", id)
+}
+
+pub fn branch_ref(name: &str) -> String {
+    format!("refs/heads/{}", name)
+}
+
+/// The commit a branch points to, `None` if the branch doesn't exist.
+pub fn branch_commit(dir: &Path, branch: &str) -> Result<Option<String>> {
+    let repo = Repository::open(dir).map_err(|e| e.to_string())?;
+    let resolved = repo.resolve(&branch_ref(branch)).map_err(|e| e.to_string())?;
+    Ok(resolved.map(|r| r.commit_id))
+}
+
+pub fn merge_base(dir: &Path, base: &str, head: &str) -> Result<Option<String>> {
+    let repo = Repository::open(dir).map_err(|e| e.to_string())?;
+    Ok(repo.merge_base(base, head).map_err(|e| e.to_string())?)
+}
+
+pub fn is_ancestor(dir: &Path, ancestor_id: &str, commit_id: &str) -> Result<bool> {
+    let repo = Repository::open(dir).map_err(|e| e.to_string())?;
+    Ok(repo.is_ancestor(ancestor_id, commit_id).map_err(|e| e.to_string())?)
+}
+
+/// Fetches the head branch into the head reference of the base repository. Returns the new
+/// head commit and its merge base with the base branch.
+pub async fn sync_head(base_dir: &Path, head_dir: &Path, pull: &PullRequest) -> Result<(String, Option<String>)> {
+    fetch_head(base_dir, head_dir, &pull.head_branch, &pull.base_branch, &head_ref(pull.number)).await
+}
+
+/// Fetches the head branch into `target` of the base repository. Returns the fetched commit
+/// and its merge base with the base branch.
+pub async fn fetch_head(base_dir: &Path, head_dir: &Path, head_branch: &str, base_branch: &str, target: &str) -> Result<(String, Option<String>)> {
+    git::fetch_ref(base_dir, head_dir, &branch_ref(head_branch), target).await
+        .map_err(|e| e.to_string())?;
+
+    let repo = Repository::open(base_dir).map_err(|e| e.to_string())?;
+    let head_id = repo.resolve(target).map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("{} is missing after the fetch", target))?
+        .commit_id;
+    let merge_base = repo.merge_base(&branch_ref(base_branch), &head_id).map_err(|e| e.to_string())?;
+    Ok((head_id, merge_base))
+}
+
+/// Loads a project of which only the id is known, i.e. the fork a pull request comes from.
+pub async fn project_info(db: &Database, projects: &Projects, id: &Uuid) -> Result<Option<ProjectInfo>> {
+    let summary = match db.projects.summary(id).await? {
+        Some(summary) => summary,
+        None => return Ok(None),
+    };
+    let dir = projects.dir(&summary.owner, &summary.name);
+    Ok(Some(ProjectInfo::from_summary(&summary, dir)))
+}
@@ -0,0 +1,164 @@
+use rocket::{error, warn, fairing::AdHoc};
+use time::OffsetDateTime;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+use zorgit_db::{Database, PullRequest};
+use zorgit_vcs::{BranchProtection, PushPolicy};
+use zorgit_vcs::events::{Event, EventBus, PullRequestAction, PullRequestEvent, PushEvent};
+use crate::config::{Projects, ZorgitConfig};
+use crate::reviews;
+use super::sync::{self, Result};
+
+/// Listens for pushes and moves the head and base commits of the open pull requests along.
+#[derive(Clone)]
+pub struct Tracker {
+    db: Database,
+    events: EventBus,
+    projects: Projects,
+}
+
+impl Tracker {
+    /// Starts tracking pushes. Needs the `Database`, `EventBus` and `ZorgitConfig` to be
+    /// managed already, otherwise pull requests don't follow their branches.
+    pub fn fairing() -> AdHoc {
+        AdHoc::on_attach("Pull Requests", |rocket| async {
+            let tracker = match (rocket.state::<Database>(), rocket.state::<EventBus>(), rocket.state::<ZorgitConfig>()) {
+                (Some(db), Some(events), Some(config)) => Tracker {
+                    db: db.clone(),
+                    events: events.clone(),
+                    projects: config.projects.clone(),
+                },
+                _ => {
+                    warn!("Pull requests are not tracked, because the database, event bus or config is missing.");
+                    return Ok(rocket);
+                }
+            };
+
+            let events = tracker.events.subscribe();
+            tokio::spawn(async move { tracker.listen(events).await });
+            Ok(rocket)
+        })
+    }
+
+    async fn listen(&self, mut events: tokio::sync::broadcast::Receiver<Event>) {
+        loop {
+            match events.recv().await {
+                Ok(Event::Push(push)) => {
+                    if let Err(e) = self.track(&push).await {
+                        error!("Updating pull requests after a push failed: {}", e);
+                    }
+                }
+                Ok(_) => (),
+                Err(RecvError::Lagged(missed)) => error!("Pull requests missed {} events.", missed),
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// Updates every pull request on its own, so one that fails doesn't hold up the others.
+    async fn track(&self, push: &PushEvent) -> Result<()> {
+        let project_id = push.project.id.parse::<Uuid>()?;
+        for update in &push.updates {
+            // Pull requests of a deleted branch stay open with their last known commits
+            let (branch, new_id) = match (update.branch(), &update.new_id) {
+                (Some(branch), Some(new_id)) => (branch, new_id),
+                _ => continue,
+            };
+
+            match self.db.pull_requests.open_for_head(&project_id, branch).await {
+                Ok(pulls) => for pull in pulls {
+                    let number = pull.number;
+                    if let Err(e) = self.update_head(push, pull, new_id).await {
+                        error!("Updating the head of pull request #{} failed: {}", number, e);
+                    }
+                },
+                Err(e) => error!("Loading the pull requests of head branch {} failed: {}", branch, e),
+            }
+            match self.db.pull_requests.open_for_base(&project_id, branch).await {
+                Ok(pulls) => for pull in pulls {
+                    let number = pull.number;
+                    if let Err(e) = self.update_base(push, pull, new_id).await {
+                        error!("Updating the base of pull request #{} failed: {}", number, e);
+                    }
+                },
+                Err(e) => error!("Loading the pull requests of base branch {} failed: {}", branch, e),
+            }
+        }
+        Ok(())
+    }
+
+    async fn update_head(&self, push: &PushEvent, pull: PullRequest, new_id: &str) -> Result<()> {
+        if pull.head_commit_id == new_id {
+            return Ok(());
+        }
+        let base = match pull.is_from_fork() {
+            true => match sync::project_info(&self.db, &self.projects, &pull.project_id).await? {
+                Some(base) => base,
+                None => return Ok(()),
+            },
+            false => push.project.clone(),
+        };
+
+        let (head_commit_id, merge_base_id) = sync::sync_head(&base.dir, &push.project.dir, &pull).await?;
+        let updated_at = OffsetDateTime::now_utc();
+        self.db.pull_requests.update_head(&pull.id, &head_commit_id, merge_base_id.as_deref(), updated_at).await?;
+
+        let pull = PullRequest { head_commit_id, merge_base_id, updated_at, ..pull };
+        reviews::follow_threads(&self.db, &base.dir, &pull).await?;
+        reviews::request_code_owners(&self.db, &base.dir, &pull).await?;
+        if self.dismisses_stale_approvals(&pull).await? {
+            self.db.reviews.dismiss_stale_approvals(&pull.id, &pull.head_commit_id, updated_at).await?;
+        }
+        self.events.emit(Event::PullRequest(PullRequestEvent {
+            project: base,
+            action: PullRequestAction::Synchronize,
+            pull_request: (&pull).into(),
+            sender: push.pusher.clone(),
+        }));
+        Ok(())
+    }
+
+    /// Whether the protection rule of the base branch dismisses approvals on new pushes.
+    async fn dismisses_stale_approvals(&self, pull: &PullRequest) -> Result<bool> {
+        let rules = self.db.protected_branches.for_project(&pull.project_id).await?;
+        let policy = PushPolicy {
+            protections: rules.iter().map(BranchProtection::from).collect(),
+            ..PushPolicy::default()
+        };
+        Ok(policy.protection_for(&pull.base_branch).map_or(false, |protection| protection.dismiss_stale_approvals))
+    }
+
+    async fn update_base(&self, push: &PushEvent, pull: PullRequest, new_id: &str) -> Result<()> {
+        if pull.base_commit_id == new_id {
+            return Ok(());
+        }
+        let now = OffsetDateTime::now_utc();
+        // The head got merged by a push, i.e. after merging locally
+        if sync::is_ancestor(&push.project.dir, &pull.head_commit_id, new_id)? {
+            self.db.pull_requests.mark_merged(&pull, new_id, None, now).await?;
+            let pull = PullRequest {
+                state: "merged".to_string(),
+                merge_commit_id: Some(new_id.to_string()),
+                merged_at: Some(now),
+                closed_at: Some(now),
+                updated_at: now,
+                ..pull
+            };
+            self.events.emit(Event::PullRequest(PullRequestEvent {
+                project: push.project.clone(),
+                action: PullRequestAction::Closed,
+                pull_request: (&pull).into(),
+                sender: push.pusher.clone(),
+            }));
+            return Ok(());
+        }
+
+        let merge_base_id = sync::merge_base(&push.project.dir, new_id, &sync::head_ref(pull.number))?;
+        self.db.pull_requests.update_base(&pull.id, new_id, merge_base_id.as_deref(), now).await?;
+        if merge_base_id != pull.merge_base_id {
+            let pull = PullRequest { base_commit_id: new_id.to_string(), merge_base_id, updated_at: now, ..pull };
+            reviews::follow_threads(&self.db, &push.project.dir, &pull).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+use rocket::{error, warn, fairing::AdHoc};
+use time::OffsetDateTime;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+use zorgit_common::access::access_by_id;
+use zorgit_db::{CrossReference, Database};
+use zorgit_vcs::{VersionControl, git::Repository};
+use zorgit_vcs::events::{Event, EventBus, IssueAction, IssueCommentAction, IssueEvent, ProjectInfo, PullRequestAction, PushEvent};
+use crate::access::scope_of;
+use super::resolve::{resolve, Result, Target};
+
+/// Commit messages of at most this many commits per pushed branch are read.
+const MAX_COMMITS: usize = 100;
+
+/// Where a text with references comes from.
+struct Source<'a> {
+    /// One of `issue`, `pull_request`, `issue_comment` or `commit`.
+    kind: &'static str,
+    /// Number of the issue or pull request, so they don't reference themselves.
+    number: Option<i64>,
+    id: String,
+    /// Username of the one who wrote or pushed the text.
+    actor: &'a str,
+}
+
+/// Listens for new texts and records their references on the referenced issues and pull
+/// requests. Issues referenced with a closing keyword like `Fixes #12` are closed, once the
+/// text lands on the default branch.
+#[derive(Clone)]
+pub struct Linker {
+    db: Database,
+    events: EventBus,
+}
+
+impl Linker {
+    /// Starts linking. Needs the `Database` and `EventBus` to be managed already.
+    pub fn fairing() -> AdHoc {
+        AdHoc::on_attach("Cross References", |rocket| async {
+            let linker = match (rocket.state::<Database>(), rocket.state::<EventBus>()) {
+                (Some(db), Some(events)) => Linker {
+                    db: db.clone(),
+                    events: events.clone(),
+                },
+                _ => {
+                    warn!("Cross references are not recorded, because the database or event bus is missing.");
+                    return Ok(rocket);
+                }
+            };
+
+            let events = linker.events.subscribe();
+            tokio::spawn(async move { linker.listen(events).await });
+            Ok(rocket)
+        })
+    }
+
+    async fn listen(&self, mut events: tokio::sync::broadcast::Receiver<Event>) {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    if let Err(e) = self.link(&event).await {
+                        error!("Recording cross references failed: {}", e);
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => error!("Cross references missed {} events.", missed),
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+
+    async fn link(&self, event: &Event) -> Result<()> {
+        match event {
+            Event::Push(push) => self.link_push(push).await,
+            Event::Issue(event) if matches!(event.action, IssueAction::Opened | IssueAction::Edited) => {
+                let source = Source { kind: "issue", number: Some(event.issue.number), id: event.issue.id.clone(), actor: &event.sender };
+                self.record(&event.project, &source, event.issue.description.as_deref().unwrap_or_default(), false).await
+            }
+            Event::IssueComment(event) => match event.action {
+                IssueCommentAction::Created | IssueCommentAction::Edited => {
+                    let source = Source { kind: "issue_comment", number: Some(event.issue.number), id: event.comment.id.clone(), actor: &event.sender };
+                    self.record(&event.project, &source, &event.comment.body, false).await
+                }
+                IssueCommentAction::Deleted => Ok(self.db.cross_references.delete_for_source("issue_comment", &event.comment.id).await?),
+            },
+            Event::PullRequest(event) => {
+                let pull = &event.pull_request;
+                // Like on GitHub, merging into the default branch closes the issues the description fixes
+                let is_merged = event.action == PullRequestAction::Closed && pull.state == "merged"
+                    && event.project.default_branch.as_deref() == Some(pull.base_branch.as_str());
+                if !matches!(event.action, PullRequestAction::Opened | PullRequestAction::Edited) && !is_merged {
+                    return Ok(());
+                }
+                let source = Source { kind: "pull_request", number: Some(pull.number), id: pull.id.clone(), actor: &event.sender };
+                self.record(&event.project, &source, pull.description.as_deref().unwrap_or_default(), is_merged).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn link_push(&self, push: &PushEvent) -> Result<()> {
+        for update in &push.updates {
+            let (branch, new_id) = match (update.branch(), &update.new_id) {
+                (Some(branch), Some(new_id)) => (branch, new_id),
+                _ => continue,
+            };
+            let commits = {
+                let repo = Repository::open(&push.project.dir).map_err(|e| e.to_string())?;
+                repo.commits_between(update.old_id.as_deref(), new_id, MAX_COMMITS).map_err(|e| e.to_string())?
+            };
+            let lands = push.project.default_branch.as_deref() == Some(branch);
+            // Oldest first, so the first commit that fixes an issue is the one that closes it
+            for commit in commits.iter().rev() {
+                let source = Source { kind: "commit", number: None, id: commit.id.clone(), actor: &push.pusher };
+                self.record(&push.project, &source, &commit.message(), lands).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the references of the text and forgets those an edited text doesn't contain
+    /// anymore. With `lands` set, issues of the same project that are referenced with a
+    /// closing keyword get closed.
+    async fn record(&self, project: &ProjectInfo, source: &Source<'_>, text: &str, lands: bool) -> Result<()> {
+        let references = resolve(&self.db, project, text).await?;
+        // Commit messages never change, so there is nothing to forget
+        if references.is_empty() && source.kind == "commit" {
+            return Ok(());
+        }
+        let project_id = project.id.parse::<Uuid>()?;
+        let scope = scope_of(project)?;
+        let actor_id = self.db.owners.find(source.actor, false).await?;
+
+        let mut numbers = Vec::new();
+        let mut user_ids = Vec::new();
+        for reference in references {
+            let mut cross_reference = CrossReference {
+                id: Uuid::new_v4(),
+                target_project_id: None,
+                target_number: None,
+                target_user_id: None,
+                source_project_id: project_id,
+                source_kind: source.kind.to_string(),
+                source_number: source.number,
+                source_id: source.id.clone(),
+                actor_id,
+                closes: false,
+                created_at: OffsetDateTime::now_utc(),
+            };
+            let (target_project_id, number, is_pull_request) = match &reference.target {
+                Target::Number { project_id, number, is_pull_request, .. } => (*project_id, *number, *is_pull_request),
+                Target::User { id, .. } => {
+                    // Users that can't see the project don't learn about it through a mention
+                    if !user_ids.contains(id) && access_by_id(&self.db, &scope, id).await?.can_read() {
+                        self.db.cross_references.create(&CrossReference { target_user_id: Some(*id), ..cross_reference }).await?;
+                        user_ids.push(*id);
+                    }
+                    continue;
+                }
+                Target::Commit { .. } => continue,
+            };
+            // Private projects don't show up in the timelines of other projects
+            if project.is_private && target_project_id != project_id {
+                continue;
+            }
+            if target_project_id == project_id && source.number == Some(number) {
+                continue;
+            }
+            let closes = lands && reference.found.is_closing() && !is_pull_request && target_project_id == project_id;
+            cross_reference.target_project_id = Some(target_project_id);
+            cross_reference.target_number = Some(number);
+            cross_reference.closes = closes;
+            self.db.cross_references.create(&cross_reference).await?;
+            numbers.push((target_project_id, number));
+            if let (true, Some(actor_id)) = (closes, actor_id) {
+                self.close_issue(project, &project_id, number, &actor_id, source.actor).await?;
+            }
+        }
+
+        if source.kind != "commit" {
+            self.db.cross_references.delete_removed(source.kind, &source.id, &numbers, &user_ids).await?;
+        }
+        Ok(())
+    }
+
+    async fn close_issue(&self, project: &ProjectInfo, project_id: &Uuid, number: i64, actor_id: &Uuid, actor: &str) -> Result<()> {
+        let issue = match self.db.issues.by_number(project_id, number).await? {
+            Some(issue) if issue.is_open() => issue,
+            _ => return Ok(()),
+        };
+        self.db.issues.close(&issue, actor_id, OffsetDateTime::now_utc()).await?;
+        if let Some(issue) = self.db.issues.by_id(&issue.id).await? {
+            self.events.emit(Event::Issue(IssueEvent {
+                project: project.clone(),
+                action: IssueAction::Closed,
+                issue: (&issue).into(),
+                label: None,
+                assignee: None,
+                sender: actor.to_string(),
+            }));
+        }
+        Ok(())
+    }
+}
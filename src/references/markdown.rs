@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, escape::{escape_href, escape_html}};
+use zorgit_db::Database;
+use zorgit_vcs::{Commit, events::ProjectInfo};
+use super::resolve::{resolve, ResolvedReference, Result};
+
+/// Link targets that would run code in the browser of the reader.
+const UNSAFE_SCHEMES: [&str; 3] = ["javascript:", "vbscript:", "data:"];
+
+/// Turns the references of a markdown text into markdown links.
+pub fn link_references(text: &str, references: &[ResolvedReference]) -> String {
+    let mut linked = String::with_capacity(text.len());
+    let mut last = 0;
+    for reference in references {
+        let found = &reference.found;
+        linked.push_str(&text[last..found.start]);
+        linked.push_str(&format!("[{}]({})", &text[found.start..found.end], reference.target.url()));
+        last = found.end;
+    }
+    linked.push_str(&text[last..]);
+    linked
+}
+
+/// Escapes a plain text, like a commit message, and turns its references into HTML links.
+pub fn link_plain_text(text: &str, references: &[ResolvedReference]) -> String {
+    let mut linked = String::with_capacity(text.len());
+    let mut last = 0;
+    for reference in references {
+        let found = &reference.found;
+        // Writing to a `String` can't fail
+        let _ = escape_html(&mut linked, &text[last..found.start]);
+        linked.push_str("<a href=\"");
+        let _ = escape_href(&mut linked, &reference.target.url());
+        linked.push_str("\">");
+        let _ = escape_html(&mut linked, &text[found.start..found.end]);
+        linked.push_str("</a>");
+        last = found.end;
+    }
+    let _ = escape_html(&mut linked, &text[last..]);
+    linked
+}
+
+fn is_safe(url: &str) -> bool {
+    let url = url.trim_start().to_ascii_lowercase();
+    !UNSAFE_SCHEMES.iter().any(|scheme| url.starts_with(scheme))
+}
+
+/// Renders markdown to HTML. Raw HTML of the text is escaped instead of passed through,
+/// because the text comes from users.
+pub fn to_html(markdown: &str) -> String {
+    let parser = Parser::new_ext(markdown, Options::all()).map(|event| match event {
+        Event::Html(raw) => Event::Text(raw),
+        Event::Start(Tag::Link(kind, url, title)) if !is_safe(&url) => Event::Start(Tag::Link(kind, CowStr::Borrowed("#"), title)),
+        Event::Start(Tag::Image(kind, url, title)) if !is_safe(&url) => Event::Start(Tag::Image(kind, CowStr::Borrowed("#"), title)),
+        event => event,
+    });
+    let mut rendered = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut rendered, parser);
+    rendered
+}
+
+/// Renders a text of the project with all its references linked.
+pub async fn render(db: &Database, project: &ProjectInfo, text: &str) -> Result<String> {
+    let references = resolve(db, project, text).await?;
+    Ok(to_html(&link_references(text, &references)))
+}
+
+/// Renders the messages of the commits with their references linked, by commit id. Commit
+/// messages are no markdown, so they are only escaped.
+pub async fn render_messages<'a, I: IntoIterator<Item = &'a Commit>>(db: &Database, project: &ProjectInfo, commits: I) -> Result<HashMap<String, String>> {
+    let mut messages = HashMap::new();
+    for commit in commits {
+        let message = commit.message();
+        let references = resolve(db, project, &message).await?;
+        messages.insert(commit.id.clone(), link_plain_text(&message, &references));
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+    use zorgit_common::reference::{find_references, Reference};
+    use super::*;
+    use super::super::resolve::Target;
+
+    #[test]
+    fn links_plain_text_and_escapes_it() {
+        let text = "Fix <script> in #3";
+        let references = find_references(text).into_iter()
+            .map(|found| {
+                assert_eq!(found.reference, Reference::Number { project: None, number: 3 });
+                ResolvedReference {
+                    found,
+                    target: Target::Number { project_id: Uuid::nil(), owner: "acme".to_string(), project: "web".to_string(), number: 3, is_pull_request: false },
+                }
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(link_plain_text(text, &references), "Fix &lt;script&gt; in <a href=\"/acme/web/issues/3\">#3</a>");
+    }
+}
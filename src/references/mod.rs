@@ -0,0 +1,8 @@
+mod linker;
+mod markdown;
+mod resolve;
+mod routes;
+
+pub use linker::Linker;
+pub use markdown::{render, render_messages};
+pub use routes::routes;
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::path::Path;
+use uuid::Uuid;
+use zorgit_common::reference::{find_references, FoundReference, Reference};
+use zorgit_db::Database;
+use zorgit_vcs::{RefKind, VersionControl, events::ProjectInfo, git::Repository};
+use crate::Result;
+
+/// What a reference points at, once it was looked up.
+#[derive(Debug, Clone)]
+pub enum Target {
+    /// An issue or pull request of this or another project.
+    Number { project_id: Uuid, owner: String, project: String, number: i64, is_pull_request: bool },
+    Commit { owner: String, project: String, commit_id: String },
+    User { id: Uuid, username: String },
+}
+
+impl Target {
+    pub fn url(&self) -> String {
+        match self {
+            Target::Number { owner, project, number, is_pull_request, .. } => {
+                let kind = if *is_pull_request { "pulls" } else { "issues" };
+                format!("/{}/{}/{}/{}", owner, project, kind, number)
+            }
+            Target::Commit { owner, project, commit_id } => format!("/{}/{}/commit/{}", owner, project, commit_id),
+            Target::User { username, .. } => format!("/{}", username),
+        }
+    }
+}
+
+/// A reference of a text that points at something that exists.
+#[derive(Debug, Clone)]
+pub struct ResolvedReference {
+    pub found: FoundReference,
+    pub target: Target,
+}
+
+/// Full ids of the referenced commits, by the id as it was written.
+fn commit_ids(dir: &Path, found: &[FoundReference]) -> std::result::Result<HashMap<String, String>, String> {
+    let mut ids = HashMap::new();
+    if !found.iter().any(|f| matches!(f.reference, Reference::Commit(_))) {
+        return Ok(ids);
+    }
+    let repo = Repository::open(dir).map_err(|e| e.to_string())?;
+    for f in found {
+        if let Reference::Commit(id) = &f.reference {
+            // A branch that happens to look like a commit id is not a commit
+            match repo.resolve(id).map_err(|e| e.to_string())? {
+                Some(resolved) if resolved.kind == RefKind::Commit => ids.insert(id.clone(), resolved.commit_id),
+                _ => None,
+            };
+        }
+    }
+    Ok(ids)
+}
+
+/// Looks up everything the text references. References to things that don't exist, or to
+/// private projects other than the own one, are dropped.
+pub async fn resolve(db: &Database, project: &ProjectInfo, text: &str) -> Result<Vec<ResolvedReference>> {
+    let found = find_references(text);
+    if found.is_empty() {
+        return Ok(Vec::new());
+    }
+    let project_id = project.id.parse::<Uuid>()?;
+    let commits = commit_ids(&project.dir, &found)?;
+
+    let mut projects = HashMap::<(String, String), Option<Uuid>>::new();
+    let mut resolved = Vec::new();
+    for f in found {
+        let target = match &f.reference {
+            Reference::Number { project: None, number } => {
+                number_target(db, &project_id, &project.owner, &project.name, *number).await?
+            }
+            Reference::Number { project: Some((owner, name)), number } => {
+                let key = (owner.clone(), name.clone());
+                if !projects.contains_key(&key) {
+                    let visible = match db.projects.find(owner, name).await? {
+                        Some(id) if id == project_id => Some(id),
+                        Some(id) => db.projects.summary(&id).await?.filter(|p| !p.is_private).map(|p| p.id),
+                        None => None,
+                    };
+                    projects.insert(key.clone(), visible);
+                }
+                match projects[&key] {
+                    Some(id) => number_target(db, &id, owner, name, *number).await?,
+                    None => None,
+                }
+            }
+            Reference::Commit(id) => commits.get(id).map(|commit_id| Target::Commit {
+                owner: project.owner.clone(),
+                project: project.name.clone(),
+                commit_id: commit_id.clone(),
+            }),
+            Reference::Mention(username) => db.owners.find(username, false).await?
+                .map(|id| Target::User { id, username: username.clone() }),
+        };
+        if let Some(target) = target {
+            resolved.push(ResolvedReference { found: f, target });
+        }
+    }
+
+    Ok(resolved)
+}
+
+async fn number_target(db: &Database, project_id: &Uuid, owner: &str, name: &str, number: i64) -> Result<Option<Target>> {
+    let is_pull_request = match db.issues.by_number(project_id, number).await? {
+        Some(_) => false,
+        None => match db.pull_requests.by_number(project_id, number).await? {
+            Some(_) => true,
+            None => return Ok(None),
+        },
+    };
+    Ok(Some(Target::Number {
+        project_id: *project_id,
+        owner: owner.to_string(),
+        project: name.to_string(),
+        number,
+        is_pull_request,
+    }))
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use rocket::{get, post, routes, Route, http::Status};
+use rocket_contrib::json::Json;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use zorgit_common::{Project, entities::{Owner, User}};
+use zorgit_db::{CrossReference, Database, ProjectSummary};
+use zorgit_vcs::events::ProjectInfo;
+use crate::access::can_read;
+use super::markdown::render;
+
+//##### Routes #####//
+// [post]    /{user|org}/{project}/markdown
+// [get]     /{user|org}/{project}/issues/<number>/references
+// [get]     /{user|org}/{project}/pulls/<number>/references
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        markdown_post,
+        issue_references_get,
+        pull_references_get,
+    ]
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MarkdownText {
+    pub text: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarkdownView {
+    pub html: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CrossReferenceView {
+    /// One of `issue`, `pull_request`, `issue_comment` or `commit`.
+    pub source_kind: String,
+    /// `owner/project` of the source.
+    pub source_project: String,
+    pub source_number: Option<i64>,
+    pub source_id: String,
+    pub url: String,
+    pub actor: Option<String>,
+    pub closes: bool,
+    pub created_at: i64,
+}
+
+fn source_url(project: &ProjectSummary, reference: &CrossReference) -> String {
+    let base = format!("/{}/{}", project.owner, project.name);
+    match (reference.source_kind.as_str(), reference.source_number) {
+        ("issue", Some(number)) => format!("{}/issues/{}", base, number),
+        ("issue_comment", Some(number)) => format!("{}/issues/{}#comment-{}", base, number, reference.source_id),
+        ("pull_request", Some(number)) => format!("{}/pulls/{}", base, number),
+        _ => format!("{}/commit/{}", base, reference.source_id),
+    }
+}
+
+/// Everything that references the issue or pull request with the given number.
+async fn references(db: &Database, project: &Project, number: i64) -> Result<Vec<CrossReferenceView>, Status> {
+    let project_id = project.id.to_uuid().map_err(|_| Status::InternalServerError)?;
+    let references = db.cross_references.for_target(&project_id, number).await.map_err(|_| Status::InternalServerError)?;
+
+    let mut projects = HashMap::<Uuid, Option<ProjectSummary>>::new();
+    for reference in &references {
+        if !projects.contains_key(&reference.source_project_id) {
+            let summary = db.projects.summary(&reference.source_project_id).await.map_err(|_| Status::InternalServerError)?;
+            projects.insert(reference.source_project_id, summary);
+        }
+    }
+    let actor_ids = references.iter().filter_map(|r| r.actor_id).collect::<Vec<_>>();
+    let actors = db.owners.names(&actor_ids).await
+        .map_err(|_| Status::InternalServerError)?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+    // References of deleted projects are gone with their project
+    Ok(references.into_iter()
+        .filter_map(|reference| {
+            let project = projects.get(&reference.source_project_id)?.as_ref()?;
+            Some(CrossReferenceView {
+                source_project: format!("{}/{}", project.owner, project.name),
+                url: source_url(project, &reference),
+                actor: reference.actor_id.and_then(|id| actors.get(&id).cloned()),
+                source_kind: reference.source_kind,
+                source_number: reference.source_number,
+                source_id: reference.source_id,
+                closes: reference.closes,
+                created_at: reference.created_at.unix_timestamp(),
+            })
+        })
+        .collect())
+}
+
+/// Renders markdown like it is rendered for issues and comments of the project, i.e. for previews.
+#[post("/<_owner>/<_project_name>/markdown", data = "<markdown>")]
+pub async fn markdown_post(_owner: Owner, _project_name: &str, project: Project, logged_user: Option<User>, db: Database, markdown: Json<MarkdownText>) -> Result<Json<MarkdownView>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    let html = render(&db, &ProjectInfo::from(&project), &markdown.text).await.map_err(|_| Status::InternalServerError)?;
+    Ok(Json(MarkdownView { html }))
+}
+
+#[get("/<_owner>/<_project_name>/issues/<number>/references")]
+pub async fn issue_references_get(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: Option<User>, db: Database) -> Result<Json<Vec<CrossReferenceView>>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    let project_id = project.id.to_uuid().map_err(|_| Status::InternalServerError)?;
+    db.issues.by_number(&project_id, number).await
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::NotFound)?;
+    Ok(Json(references(&db, &project, number).await?))
+}
+
+#[get("/<_owner>/<_project_name>/pulls/<number>/references")]
+pub async fn pull_references_get(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: Option<User>, db: Database) -> Result<Json<Vec<CrossReferenceView>>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    let project_id = project.id.to_uuid().map_err(|_| Status::InternalServerError)?;
+    db.pull_requests.by_number(&project_id, number).await
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::NotFound)?;
+    Ok(Json(references(&db, &project, number).await?))
+}
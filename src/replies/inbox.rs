@@ -0,0 +1,206 @@
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use rocket::{error, warn, fairing::AdHoc};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use zorgit_common::access::{access_by_id, Access, Scope};
+use zorgit_db::{Database, IssueComment, ReplyToken, Review};
+use zorgit_vcs::events::{Event, EventBus, IssueCommentAction, IssueCommentEvent, ProjectInfo, PullRequestReviewAction, PullRequestReviewEvent};
+use crate::Result;
+use crate::config::{Projects, ZorgitConfig};
+use super::message::{reply_token, strip_quoted, Message};
+
+/// A message that is no valid reply, so reading it again would fail again. Other errors,
+/// like a database that can't be reached, may be gone with the next poll.
+#[derive(Debug)]
+struct InvalidReply(String);
+
+impl fmt::Display for InvalidReply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for InvalidReply {}
+
+fn invalid<S: Into<String>>(reason: S) -> Box<dyn Error + Send + Sync> {
+    Box::new(InvalidReply(reason.into()))
+}
+
+/// Reads the replies to notification emails from a maildir and posts them as comments on
+/// the issue or pull request that the notification was about.
+#[derive(Clone)]
+pub struct Inbox {
+    db: Database,
+    events: EventBus,
+    projects: Projects,
+    reply_to: String,
+    maildir: PathBuf,
+}
+
+impl Inbox {
+    /// Starts reading replies. Needs the `Database`, `EventBus` and `ZorgitConfig` to be
+    /// managed already. Without a `reply_to` address and an incoming maildir, nothing is read.
+    pub fn fairing() -> AdHoc {
+        AdHoc::on_attach("Replies by Email", |rocket| async {
+            let config = match rocket.state::<ZorgitConfig>() {
+                Some(config) => config,
+                None => {
+                    warn!("Replies by email are disabled, because the config is missing.");
+                    return Ok(rocket);
+                }
+            };
+            let (reply_to, maildir) = match (&config.mailer.reply_to, &config.mailer.incoming_maildir) {
+                (Some(reply_to), Some(maildir)) => (reply_to.clone(), maildir.clone()),
+                _ => return Ok(rocket),
+            };
+            let inbox = match (rocket.state::<Database>(), rocket.state::<EventBus>()) {
+                (Some(db), Some(events)) => Inbox {
+                    db: db.clone(),
+                    events: events.clone(),
+                    projects: config.projects.clone(),
+                    reply_to,
+                    maildir,
+                },
+                _ => {
+                    warn!("Replies by email are disabled, because the database or event bus is missing.");
+                    return Ok(rocket);
+                }
+            };
+
+            let interval = Duration::from_secs(config.mailer.incoming_interval.max(1));
+            tokio::spawn(async move { inbox.poll(interval).await });
+            Ok(rocket)
+        })
+    }
+
+    async fn poll(&self, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.read_new().await {
+                error!("Reading replies from {} failed: {}", self.maildir.display(), e);
+            }
+        }
+    }
+
+    /// Handles every message in `new` and moves it to `cur` afterwards, as seen. Messages
+    /// that are no valid reply are moved too, so they are not read again and again. Messages
+    /// that failed for another reason stay in `new` and are tried again with the next poll.
+    async fn read_new(&self) -> Result<()> {
+        let mut entries = tokio::fs::read_dir(self.maildir.join("new")).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if !entry.file_type().await?.is_file() {
+                continue;
+            }
+            match self.read(&path).await {
+                Ok(()) => (),
+                Err(e) if e.is::<InvalidReply>() => warn!("Ignoring the reply {}: {}", path.display(), e),
+                Err(e) => {
+                    warn!("Reading the reply {} failed, trying again later: {}", path.display(), e);
+                    continue;
+                }
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            tokio::fs::rename(&path, self.maildir.join("cur").join(format!("{}:2,S", name))).await?;
+        }
+        Ok(())
+    }
+
+    async fn read(&self, path: &Path) -> Result<()> {
+        let raw = tokio::fs::read(path).await?;
+        let message = Message::parse(&String::from_utf8_lossy(&raw));
+
+        let token = message.recipients.iter()
+            .find_map(|address| reply_token(address, &self.reply_to))
+            .ok_or_else(|| invalid("it is not sent to a reply address"))?;
+        let token = self.db.reply_tokens.by_token(token).await?
+            .ok_or_else(|| invalid("its reply address is unknown"))?;
+        // The token is in the email and could be forwarded, so the sender has to match too
+        let from = message.from.as_deref().ok_or_else(|| invalid("it has no sender"))?;
+        if self.db.emails.owner_of(from).await? != Some(token.user_id) {
+            return Err(invalid(format!("{} is not the address of the notified user", from)));
+        }
+        let body = strip_quoted(&message.text);
+        if body.is_empty() {
+            return Err(invalid("it has no text"));
+        }
+        // The user may have lost their access since the notification was sent
+        let project = self.db.projects.summary(&token.project_id).await?
+            .ok_or_else(|| invalid("its project does not exist anymore"))?;
+        let access = access_by_id(&self.db, &Scope::from(&project), &token.user_id).await?;
+        if !access.can_read() {
+            return Err(invalid("its user can not read the project anymore"));
+        }
+        let username = self.db.owners.names(&[token.user_id]).await?.into_iter().next()
+            .map(|(_, name)| name)
+            .ok_or_else(|| invalid("its user does not exist anymore"))?;
+        let dir = self.projects.dir(&project.owner, &project.name);
+        let project = ProjectInfo::from_summary(&project, dir);
+
+        match token.thread_kind.as_str() {
+            "pull_request" => self.comment_pull_request(&token, &project, username, access, body).await,
+            _ => self.comment_issue(&token, &project, username, body).await,
+        }
+    }
+
+    async fn comment_issue(&self, token: &ReplyToken, project: &ProjectInfo, username: String, body: String) -> Result<()> {
+        let issue = self.db.issues.by_number(&token.project_id, token.thread_number).await?
+            .ok_or_else(|| invalid("its issue does not exist anymore"))?;
+
+        let now = OffsetDateTime::now_utc();
+        let comment = IssueComment {
+            id: Uuid::new_v4(),
+            issue_id: issue.id,
+            author_id: token.user_id,
+            body,
+            created_at: now,
+            updated_at: now,
+        };
+        self.db.issue_comments.create(&comment).await?;
+
+        self.events.emit(Event::IssueComment(IssueCommentEvent {
+            project: project.clone(),
+            action: IssueCommentAction::Created,
+            issue: (&issue).into(),
+            comment: (&comment).into(),
+            sender: username,
+        }));
+        Ok(())
+    }
+
+    /// Pull requests have no plain comments, so the reply becomes a review that only comments.
+    async fn comment_pull_request(&self, token: &ReplyToken, project: &ProjectInfo, username: String, access: Access, body: String) -> Result<()> {
+        let pull = self.db.pull_requests.by_number(&token.project_id, token.thread_number).await?
+            .ok_or_else(|| invalid("its pull request does not exist anymore"))?;
+        if !pull.is_open() {
+            return Err(invalid("its pull request is not open anymore"));
+        }
+
+        let review = Review {
+            id: Uuid::new_v4(),
+            pull_request_id: pull.id,
+            author_id: token.user_id,
+            state: "commented".to_string(),
+            body: Some(body),
+            commit_id: pull.head_commit_id.clone(),
+            is_official: access.can_write(),
+            created_at: OffsetDateTime::now_utc(),
+            dismissed_at: None,
+        };
+        self.db.reviews.create(&review).await?;
+        self.db.review_requests.remove_user(&pull.id, &token.user_id).await?;
+
+        self.events.emit(Event::PullRequestReview(PullRequestReviewEvent {
+            project: project.clone(),
+            action: PullRequestReviewAction::Submitted,
+            pull_request: (&pull).into(),
+            review: (&review).into(),
+            sender: username,
+        }));
+        Ok(())
+    }
+}
@@ -0,0 +1,266 @@
+use data_encoding::BASE64_MIME;
+
+/// Headers that can carry the address an email was delivered to.
+const RECIPIENT_HEADERS: [&str; 5] = ["to", "cc", "delivered-to", "x-original-to", "envelope-to"];
+
+/// The parts of an incoming email that a reply needs.
+#[derive(Debug, Clone)]
+pub struct Message {
+    /// Address of the sender, without the display name.
+    pub from: Option<String>,
+    /// All addresses the email was sent or delivered to.
+    pub recipients: Vec<String>,
+    /// The plain text of the email, decoded.
+    pub text: String,
+}
+
+/// Splits a message or a MIME part into its unfolded headers and its body.
+fn split(raw: &str) -> (Vec<(String, String)>, &str) {
+    let (head, body) = match raw.find("\r\n\r\n").map(|i| (i, 4)).or_else(|| raw.find("\n\n").map(|i| (i, 2))) {
+        Some((i, len)) => (&raw[..i], &raw[i + len..]),
+        None => (raw, ""),
+    };
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in head.lines() {
+        // Long headers continue on lines that start with whitespace
+        if line.starts_with(|c: char| c == ' ' || c == '\t') {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+        if let Some(colon) = line.find(':') {
+            headers.push((line[..colon].trim().to_ascii_lowercase(), line[colon + 1..].trim().to_string()));
+        }
+    }
+    (headers, body)
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers.iter().find(|(n, _)| n == name).map(|(_, value)| value.as_str())
+}
+
+/// A parameter of a header value, like the `boundary` of `multipart/alternative; boundary="abc"`.
+fn parameter(value: &str, name: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|param| {
+        let mut parts = param.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) if key.trim().eq_ignore_ascii_case(name) => Some(value.trim().trim_matches('"').to_string()),
+            _ => None,
+        }
+    })
+}
+
+/// The addresses of a header like `Jane <jane@example.com>, joe@example.com`.
+fn addresses(value: &str) -> Vec<String> {
+    value.split(',')
+        .filter_map(|address| {
+            let address = match (address.rfind('<'), address.rfind('>')) {
+                (Some(start), Some(end)) if start < end => &address[start + 1..end],
+                _ => address,
+            };
+            let address = address.trim();
+            match address.contains('@') {
+                true => Some(address.to_string()),
+                false => None,
+            }
+        })
+        .collect()
+}
+
+fn decode_quoted_printable(text: &str) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(text.len());
+    for line in text.lines() {
+        let (line, soft_break) = match line.trim_end().strip_suffix('=') {
+            Some(line) => (line, true),
+            None => (line.trim_end(), false),
+        };
+        let bytes = line.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            let hex = bytes.get(i + 1..i + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+            match (bytes[i], hex.and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+                (b'=', Some(byte)) => {
+                    decoded.push(byte);
+                    i += 3;
+                }
+                (byte, _) => {
+                    decoded.push(byte);
+                    i += 1;
+                }
+            }
+        }
+        if !soft_break {
+            decoded.push(b'\n');
+        }
+    }
+    decoded
+}
+
+/// Decodes the body of a part by its `Content-Transfer-Encoding`.
+fn decode(headers: &[(String, String)], body: &str) -> String {
+    let encoding = header(headers, "content-transfer-encoding").unwrap_or("7bit").to_ascii_lowercase();
+    let bytes = match encoding.as_str() {
+        "quoted-printable" => decode_quoted_printable(body),
+        "base64" => BASE64_MIME.decode(body.trim().as_bytes()).unwrap_or_default(),
+        _ => body.as_bytes().to_vec(),
+    };
+    String::from_utf8_lossy(&bytes).replace("\r\n", "\n")
+}
+
+/// The text of the first `text/plain` part. Emails without one, i.e. HTML only, have no text.
+fn plain_text(headers: &[(String, String)], body: &str) -> Option<String> {
+    let content_type = header(headers, "content-type").unwrap_or("text/plain");
+    let mime_type = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    if mime_type == "text/plain" {
+        return Some(decode(headers, body));
+    }
+    if !mime_type.starts_with("multipart/") {
+        return None;
+    }
+    let boundary = format!("--{}", parameter(content_type, "boundary")?);
+    body.split(boundary.as_str())
+        // The preamble in front of the first boundary is no part
+        .skip(1)
+        .take_while(|part| !part.starts_with("--"))
+        .find_map(|part| {
+            // The line break after the boundary belongs to the boundary, a part without headers starts with an empty line
+            let part = part.strip_prefix("\r\n").or_else(|| part.strip_prefix('\n')).unwrap_or(part);
+            let (headers, body) = match part.starts_with("\r\n") || part.starts_with('\n') {
+                true => (Vec::new(), part.trim_start_matches(|c| c == '\r' || c == '\n')),
+                false => split(part),
+            };
+            plain_text(&headers, body)
+        })
+}
+
+impl Message {
+    pub fn parse(raw: &str) -> Message {
+        let (headers, body) = split(raw);
+        Message {
+            from: header(&headers, "from").and_then(|from| addresses(from).into_iter().next()),
+            recipients: headers.iter()
+                .filter(|(name, _)| RECIPIENT_HEADERS.contains(&name.as_str()))
+                .flat_map(|(_, value)| addresses(value))
+                .collect(),
+            text: plain_text(&headers, body).unwrap_or_default(),
+        }
+    }
+}
+
+/// The token of a reply address like `reply+<token>@example.com`, if the address belongs to
+/// the configured `reply_to` address.
+pub fn reply_token<'a>(address: &'a str, reply_to: &str) -> Option<&'a str> {
+    let (local, domain) = address.split_at(address.rfind('@')?);
+    let (reply_local, reply_domain) = reply_to.split_at(reply_to.rfind('@')?);
+    if !domain.eq_ignore_ascii_case(reply_domain) {
+        return None;
+    }
+    let (prefix, token) = local.split_at(local.find('+')?);
+    match prefix.eq_ignore_ascii_case(reply_local) {
+        true => Some(&token[1..]).filter(|token| !token.is_empty()),
+        false => None,
+    }
+}
+
+/// Whether the line introduces the quoted email, like `On Mon, Jane wrote:`.
+fn is_attribution(line: &str) -> bool {
+    let line = line.trim_end();
+    line.ends_with("wrote:") || line.ends_with("schrieb:")
+}
+
+/// The new text of a reply, without the quoted email and the signature below it.
+pub fn strip_quoted(text: &str) -> String {
+    let mut kept: Vec<&str> = Vec::new();
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if line == "-- " || trimmed == "--" || trimmed.starts_with("-----Original Message-----") || trimmed.starts_with("________________") {
+            break;
+        }
+        if is_attribution(trimmed) {
+            // Mail clients wrap long attributions, so `On Mon ...` may be on the line before
+            if kept.last().map_or(false, |last| {
+                let last = last.trim_start();
+                last.starts_with("On ") || last.starts_with("Am ")
+            }) {
+                kept.pop();
+            }
+            break;
+        }
+        if trimmed.starts_with('>') {
+            continue;
+        }
+        kept.push(line);
+    }
+    kept.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_quoted_text() {
+        let text = "Sounds good.\n\nOn Mon, 5 Apr 2021, Jane <jane@example.com>\nwrote:\n> Should we merge?\n> Thanks\n";
+        assert_eq!(strip_quoted(text), "Sounds good.");
+        assert_eq!(strip_quoted("Agreed\n> quoted\nbelow the quote\n"), "Agreed\nbelow the quote");
+    }
+
+    #[test]
+    fn strips_signatures_and_forwarded_messages() {
+        assert_eq!(strip_quoted("Done.\n-- \nJane\n"), "Done.");
+        assert_eq!(strip_quoted("Done.\n-----Original Message-----\nFrom: zorgit\n"), "Done.");
+        assert_eq!(strip_quoted("Am Montag schrieb:\n> Hallo\n"), "");
+    }
+
+    #[test]
+    fn parses_plain_messages() {
+        let raw = "From: Jane Doe <jane@example.com>\r\nTo: reply+abc@zorgit.example,\r\n Bob <bob@example.com>\r\nSubject: Re: [alice/zorgit] Crash\r\n\r\nThanks!\r\n";
+        let message = Message::parse(raw);
+        assert_eq!(message.from.as_deref(), Some("jane@example.com"));
+        assert_eq!(message.recipients, vec!["reply+abc@zorgit.example".to_string(), "bob@example.com".to_string()]);
+        assert_eq!(message.text, "Thanks!\n");
+    }
+
+    #[test]
+    fn parses_the_plain_part_of_multipart_messages() {
+        let raw = concat!(
+            "From: jane@example.com\n",
+            "Delivered-To: reply+abc@zorgit.example\n",
+            "Content-Type: multipart/alternative; boundary=\"b1\"\n",
+            "\n",
+            "Preamble\n",
+            "--b1\n",
+            "Content-Type: text/html\n",
+            "\n",
+            "<p>Fixed</p>\n",
+            "--b1\n",
+            "Content-Type: text/plain; charset=utf-8\n",
+            "Content-Transfer-Encoding: quoted-printable\n",
+            "\n",
+            "Fixed in =C3=A4 long=\n",
+            " line\n",
+            "--b1--\n",
+        );
+        let message = Message::parse(raw);
+        assert_eq!(message.recipients, vec!["reply+abc@zorgit.example".to_string()]);
+        assert_eq!(message.text, "Fixed in \u{e4} long line\n");
+    }
+
+    #[test]
+    fn decodes_base64_bodies() {
+        let raw = "From: jane@example.com\nContent-Transfer-Encoding: base64\n\nTG9va3MgZ29vZA==\n";
+        assert_eq!(Message::parse(raw).text, "Looks good");
+    }
+
+    #[test]
+    fn finds_reply_tokens() {
+        assert_eq!(reply_token("reply+abc123@zorgit.example", "reply@zorgit.example"), Some("abc123"));
+        assert_eq!(reply_token("Reply+abc123@Zorgit.Example", "reply@zorgit.example"), Some("abc123"));
+        assert_eq!(reply_token("reply+@zorgit.example", "reply@zorgit.example"), None);
+        assert_eq!(reply_token("reply@zorgit.example", "reply@zorgit.example"), None);
+        assert_eq!(reply_token("other+abc123@zorgit.example", "reply@zorgit.example"), None);
+        assert_eq!(reply_token("reply+abc123@evil.example", "reply@zorgit.example"), None);
+    }
+}
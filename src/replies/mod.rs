@@ -0,0 +1,4 @@
+mod inbox;
+mod message;
+
+pub use inbox::Inbox;
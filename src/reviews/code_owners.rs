@@ -0,0 +1,123 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use time::OffsetDateTime;
+use uuid::Uuid;
+use zorgit_db::{Database, PullRequest, ReviewRequest};
+use zorgit_vcs::{CodeOwner, CodeOwners, CompareMode, DiffFile, VersionControl, git::Repository};
+use crate::Result;
+
+/// Users and teams behind the entries of a `CODEOWNERS` rule.
+#[derive(Debug, Default)]
+struct ResolvedOwners {
+    user_ids: Vec<Uuid>,
+    team_ids: Vec<Uuid>,
+}
+
+/// Every file the pull request changes together with its code owners. The `CODEOWNERS` file
+/// is read from the base, so a pull request can't make its author an owner of what it changes.
+fn changed_file_owners(dir: &Path, pull: &PullRequest) -> std::result::Result<Vec<(String, Vec<CodeOwner>)>, String> {
+    let repo = Repository::open(dir).map_err(|e| e.to_string())?;
+    let code_owners = match CodeOwners::load(&repo, &pull.base_commit_id).map_err(|e| e.to_string())? {
+        Some(code_owners) => code_owners,
+        None => return Ok(Vec::new()),
+    };
+    let comparison = repo.compare(&pull.base_commit_id, &pull.head_commit_id, CompareMode::ThreeDot, 0)
+        .map_err(|e| e.to_string())?;
+
+    let mut paths = BTreeSet::new();
+    for file in comparison.diff.files {
+        match file {
+            DiffFile::Add(f) | DiffFile::Change(f) => paths.extend(f.name),
+            DiffFile::Del(f) => paths.extend(f.old_name),
+            DiffFile::Rename(f) => {
+                paths.extend(f.old_name);
+                paths.extend(f.name);
+            }
+        }
+    }
+
+    Ok(paths.into_iter()
+        .map(|path| {
+            let owners = code_owners.owners_of(&path).to_vec();
+            (path, owners)
+        })
+        .filter(|(_, owners)| !owners.is_empty())
+        .collect())
+}
+
+/// Owners given by email can't be resolved, because emails of users are not unique.
+async fn resolve(db: &Database, owners: &[CodeOwner]) -> Result<ResolvedOwners> {
+    let mut resolved = ResolvedOwners::default();
+    for owner in owners {
+        match owner {
+            CodeOwner::User(name) => resolved.user_ids.extend(db.owners.find(name, false).await?),
+            CodeOwner::Team { organisation, name } => {
+                if let Some(organisation_id) = db.owners.find(organisation, true).await? {
+                    resolved.team_ids.extend(db.teams.find(&organisation_id, name).await?);
+                }
+            }
+            CodeOwner::Email(_) => (),
+        }
+    }
+    Ok(resolved)
+}
+
+/// Requests reviews from the code owners of all changed files. The author of the pull
+/// request, reviewers that are requested already and users that reviewed already are left
+/// out, so a new push doesn't request them again.
+pub async fn request_code_owners(db: &Database, dir: &Path, pull: &PullRequest) -> Result<()> {
+    let mut user_ids = BTreeSet::new();
+    let mut team_ids = BTreeSet::new();
+    for (_, owners) in changed_file_owners(dir, pull)? {
+        let resolved = resolve(db, &owners).await?;
+        user_ids.extend(resolved.user_ids);
+        team_ids.extend(resolved.team_ids);
+    }
+    user_ids.remove(&pull.author_id);
+    for request in db.review_requests.for_pull_request(&pull.id).await? {
+        if let Some(user_id) = request.user_id {
+            user_ids.remove(&user_id);
+        }
+        if let Some(team_id) = request.team_id {
+            team_ids.remove(&team_id);
+        }
+    }
+    for review in db.reviews.for_pull_request(&pull.id).await? {
+        user_ids.remove(&review.author_id);
+    }
+
+    let requests = user_ids.into_iter().map(|id| (Some(id), None))
+        .chain(team_ids.into_iter().map(|id| (None, Some(id))));
+    for (user_id, team_id) in requests {
+        db.review_requests.create(&ReviewRequest {
+            id: Uuid::new_v4(),
+            pull_request_id: pull.id,
+            user_id,
+            team_id,
+            requested_by: None,
+            is_code_owner: true,
+            created_at: OffsetDateTime::now_utc(),
+        }).await?;
+    }
+    Ok(())
+}
+
+/// Changed files that have code owners, but none of them approved the pull request yet.
+/// Approving for a team counts, if the approver is a member of it.
+pub async fn unapproved_files(db: &Database, dir: &Path, pull: &PullRequest, approver_ids: &[Uuid]) -> Result<Vec<String>> {
+    let mut unapproved = Vec::new();
+    for (path, owners) in changed_file_owners(dir, pull)? {
+        let resolved = resolve(db, &owners).await?;
+        let mut is_approved = approver_ids.iter().any(|id| resolved.user_ids.contains(id));
+        for approver_id in approver_ids {
+            if is_approved {
+                break;
+            }
+            is_approved = db.teams.is_member_of_any(approver_id, &resolved.team_ids).await?;
+        }
+        if !is_approved {
+            unapproved.push(path);
+        }
+    }
+    Ok(unapproved)
+}
@@ -0,0 +1,7 @@
+mod code_owners;
+mod routes;
+mod threads;
+
+pub use code_owners::{request_code_owners, unapproved_files};
+pub use routes::routes;
+pub use threads::follow_threads;
@@ -0,0 +1,495 @@
+use std::collections::HashMap;
+use rocket::{delete, get, post, routes, Route, State, http::Status};
+use rocket_contrib::{json::Json, uuid::Uuid as UuidParam};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use zorgit_common::{Project, entities::{Owner, User}};
+use zorgit_db::{Database, PullRequest, Review, ReviewComment, ReviewRequest, ReviewThread};
+use zorgit_vcs::{VersionControl, git::Repository};
+use zorgit_vcs::events::{Event, EventBus, ProjectInfo, PullRequestReviewAction, PullRequestReviewEvent};
+use crate::access::{can_read, can_write};
+use super::threads::anchor_commit;
+
+//##### Routes #####//
+// [get]     /{user|org}/{project}/pulls/<number>/threads
+// [post]    /{user|org}/{project}/pulls/<number>/threads
+// [post]    /{user|org}/{project}/pulls/<number>/threads/<id>/comments
+// [post]    /{user|org}/{project}/pulls/<number>/threads/<id>/resolve
+// [post]    /{user|org}/{project}/pulls/<number>/threads/<id>/unresolve
+// [post]    /{user|org}/{project}/pulls/<number>/comments/<id>
+// [delete]  /{user|org}/{project}/pulls/<number>/comments/<id>
+// [get]     /{user|org}/{project}/pulls/<number>/reviews
+// [post]    /{user|org}/{project}/pulls/<number>/reviews
+// [post]    /{user|org}/{project}/pulls/<number>/reviews/<id>/dismiss
+// [get]     /{user|org}/{project}/pulls/<number>/reviewers
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        threads_get,
+        threads_post,
+        thread_comments_post,
+        thread_resolve_post,
+        thread_unresolve_post,
+        comment_post,
+        comment_delete,
+        reviews_get,
+        reviews_post,
+        review_dismiss_post,
+        reviewers_get,
+    ]
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewThread {
+    pub path: String,
+    /// `old` to comment on lines of the base, `new` for lines of the head.
+    pub side: String,
+    /// First line of the comment. All its lines have to be in the same hunk of the diff.
+    pub start_line: usize,
+    /// Last line of a multi-line comment. Defaults to `start_line`.
+    pub end_line: Option<usize>,
+    /// Commit of the diff the user commented on. If the pull request moved on since, the comment is refused.
+    pub commit_id: Option<String>,
+    pub body: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewComment {
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Approve,
+    RequestChanges,
+    Comment,
+}
+
+impl Verdict {
+    fn state(self) -> &'static str {
+        match self {
+            Verdict::Approve => "approved",
+            Verdict::RequestChanges => "changes_requested",
+            Verdict::Comment => "commented",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewReview {
+    pub verdict: Verdict,
+    pub body: Option<String>,
+    /// Head the user reviewed. If the pull request moved on since, the review is refused.
+    pub commit_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReviewView {
+    pub id: String,
+    pub author_id: String,
+    pub state: String,
+    pub body: Option<String>,
+    pub commit_id: String,
+    pub is_official: bool,
+    pub created_at: i64,
+    pub dismissed_at: Option<i64>,
+}
+
+impl From<Review> for ReviewView {
+    fn from(review: Review) -> Self {
+        ReviewView {
+            id: review.id.to_string(),
+            author_id: review.author_id.to_string(),
+            state: review.state,
+            body: review.body,
+            commit_id: review.commit_id,
+            is_official: review.is_official,
+            created_at: review.created_at.unix_timestamp(),
+            dismissed_at: review.dismissed_at.map(|t| t.unix_timestamp()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReviewRequestView {
+    pub user_id: Option<String>,
+    pub team_id: Option<String>,
+    /// `None` if the reviewer was requested automatically.
+    pub requested_by: Option<String>,
+    pub is_code_owner: bool,
+    pub created_at: i64,
+}
+
+impl From<ReviewRequest> for ReviewRequestView {
+    fn from(request: ReviewRequest) -> Self {
+        ReviewRequestView {
+            user_id: request.user_id.map(|id| id.to_string()),
+            team_id: request.team_id.map(|id| id.to_string()),
+            requested_by: request.requested_by.map(|id| id.to_string()),
+            is_code_owner: request.is_code_owner,
+            created_at: request.created_at.unix_timestamp(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReviewCommentView {
+    pub id: String,
+    pub author_id: String,
+    pub body: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl From<ReviewComment> for ReviewCommentView {
+    fn from(comment: ReviewComment) -> Self {
+        ReviewCommentView {
+            id: comment.id.to_string(),
+            author_id: comment.author_id.to_string(),
+            body: comment.body,
+            created_at: comment.created_at.unix_timestamp(),
+            updated_at: comment.updated_at.unix_timestamp(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReviewThreadView {
+    pub id: String,
+    pub path: String,
+    pub side: String,
+    pub commit_id: String,
+    pub start_line: i32,
+    pub end_line: i32,
+    pub original_commit_id: String,
+    pub original_start_line: i32,
+    pub original_end_line: i32,
+    pub is_outdated: bool,
+    pub is_resolved: bool,
+    pub resolved_by: Option<String>,
+    pub resolved_at: Option<i64>,
+    pub comments: Vec<ReviewCommentView>,
+}
+
+impl ReviewThreadView {
+    fn new(thread: ReviewThread, comments: Vec<ReviewComment>) -> ReviewThreadView {
+        ReviewThreadView {
+            id: thread.id.to_string(),
+            path: thread.path,
+            side: thread.side,
+            commit_id: thread.commit_id,
+            start_line: thread.start_line,
+            end_line: thread.end_line,
+            original_commit_id: thread.original_commit_id,
+            original_start_line: thread.original_start_line,
+            original_end_line: thread.original_end_line,
+            is_outdated: thread.is_outdated,
+            is_resolved: thread.is_resolved,
+            resolved_by: thread.resolved_by.map(|id| id.to_string()),
+            resolved_at: thread.resolved_at.map(|t| t.unix_timestamp()),
+            comments: comments.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+fn user_uuid(user: &User) -> Result<Uuid, Status> {
+    user.id.to_uuid().map_err(|_| Status::InternalServerError)
+}
+
+/// Loads the pull request, if the user may see it.
+async fn load(db: &Database, project: &Project, number: i64, user: Option<&User>) -> Result<PullRequest, Status> {
+    if !can_read(db, project, user).await? {
+        return Err(Status::NotFound);
+    }
+    let project_id = project.id.to_uuid().map_err(|_| Status::InternalServerError)?;
+    db.pull_requests.by_number(&project_id, number).await
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::NotFound)
+}
+
+async fn load_thread(db: &Database, pull: &PullRequest, id: &Uuid) -> Result<ReviewThread, Status> {
+    db.review_threads.by_id(id).await
+        .map_err(|_| Status::InternalServerError)?
+        .filter(|thread| thread.pull_request_id == pull.id)
+        .ok_or(Status::NotFound)
+}
+
+async fn load_comment(db: &Database, pull: &PullRequest, id: &Uuid) -> Result<ReviewComment, Status> {
+    let comment = db.review_comments.by_id(id).await
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::NotFound)?;
+    load_thread(db, pull, &comment.thread_id).await?;
+    Ok(comment)
+}
+
+async fn thread_view(db: &Database, thread: ReviewThread) -> Result<ReviewThreadView, Status> {
+    let comments = db.review_comments.for_threads(&[thread.id]).await
+        .map_err(|_| Status::InternalServerError)?;
+    Ok(ReviewThreadView::new(thread, comments))
+}
+
+fn body(body: &str) -> Result<&str, Status> {
+    match body.trim() {
+        "" => Err(Status::UnprocessableEntity),
+        body => Ok(body),
+    }
+}
+
+/// Threads can be resolved by the author of the pull request, the one who started
+/// the thread and everybody who can write to the project.
+async fn can_resolve(db: &Database, project: &Project, pull: &PullRequest, thread: &ReviewThread, user: &User) -> Result<bool, Status> {
+    if can_write(db, project, user).await? {
+        return Ok(true);
+    }
+    let user_id = user_uuid(user)?;
+    if pull.author_id == user_id {
+        return Ok(true);
+    }
+    let comments = db.review_comments.for_threads(&[thread.id]).await
+        .map_err(|_| Status::InternalServerError)?;
+    Ok(comments.first().map_or(false, |first| first.author_id == user_id))
+}
+
+/// Whether the lines `start..=end` of the file are shown in one hunk of the pull request's diff,
+/// on the given side. Review threads can only be started on these lines.
+fn is_in_diff(project: &Project, pull: &PullRequest, path: &str, side: &str, start: usize, end: usize) -> Result<bool, Status> {
+    let merge_base = match &pull.merge_base_id {
+        Some(merge_base) => merge_base,
+        None => return Ok(false),
+    };
+    let repo = Repository::open(&project.dir).map_err(|_| Status::InternalServerError)?;
+    let hunks = repo.hunk_lines(merge_base, &pull.head_commit_id, path, side == "new")
+        .map_err(|_| Status::InternalServerError)?;
+    Ok(hunks.iter().any(|&(first, last)| first <= start && end <= last))
+}
+
+#[get("/<_owner>/<_project_name>/pulls/<number>/threads")]
+pub async fn threads_get(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: Option<User>, db: Database) -> Result<Json<Vec<ReviewThreadView>>, Status> {
+    let pull = load(&db, &project, number, logged_user.as_ref()).await?;
+    let threads = db.review_threads.for_pull_request(&pull.id).await
+        .map_err(|_| Status::InternalServerError)?;
+    let thread_ids = threads.iter().map(|t| t.id).collect::<Vec<_>>();
+
+    let mut comments = HashMap::<Uuid, Vec<ReviewComment>>::new();
+    for comment in db.review_comments.for_threads(&thread_ids).await.map_err(|_| Status::InternalServerError)? {
+        comments.entry(comment.thread_id).or_default().push(comment);
+    }
+
+    let views = threads.into_iter()
+        .map(|thread| {
+            let comments = comments.remove(&thread.id).unwrap_or_default();
+            ReviewThreadView::new(thread, comments)
+        })
+        .collect();
+    Ok(Json(views))
+}
+
+#[post("/<_owner>/<_project_name>/pulls/<number>/threads", data = "<new>")]
+pub async fn threads_post(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: User, db: Database, new: Json<NewThread>) -> Result<Json<ReviewThreadView>, Status> {
+    let pull = load(&db, &project, number, Some(&logged_user)).await?;
+    let body = body(&new.body)?;
+    if new.side != "old" && new.side != "new" {
+        return Err(Status::UnprocessableEntity);
+    }
+    let commit_id = anchor_commit(&pull, &new.side).ok_or(Status::UnprocessableEntity)?;
+    if new.commit_id.as_ref().map_or(false, |id| id != &commit_id) {
+        return Err(Status::Conflict);
+    }
+
+    let end_line = new.end_line.unwrap_or(new.start_line);
+    if new.start_line == 0 || new.start_line > end_line {
+        return Err(Status::UnprocessableEntity);
+    }
+    if !is_in_diff(&project, &pull, &new.path, &new.side, new.start_line, end_line)? {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    let now = OffsetDateTime::now_utc();
+    let thread = ReviewThread {
+        id: Uuid::new_v4(),
+        pull_request_id: pull.id,
+        path: new.path.clone(),
+        side: new.side.clone(),
+        original_commit_id: commit_id.clone(),
+        original_start_line: new.start_line as i32,
+        original_end_line: end_line as i32,
+        commit_id,
+        start_line: new.start_line as i32,
+        end_line: end_line as i32,
+        is_outdated: false,
+        is_resolved: false,
+        resolved_by: None,
+        resolved_at: None,
+        created_at: now,
+        updated_at: now,
+    };
+    let comment = ReviewComment {
+        id: Uuid::new_v4(),
+        thread_id: thread.id,
+        author_id: user_uuid(&logged_user)?,
+        body: body.to_string(),
+        created_at: now,
+        updated_at: now,
+    };
+    db.review_threads.create(&thread, &comment).await.map_err(|_| Status::InternalServerError)?;
+
+    Ok(Json(ReviewThreadView::new(thread, vec![comment])))
+}
+
+#[post("/<_owner>/<_project_name>/pulls/<number>/threads/<id>/comments", data = "<new>")]
+pub async fn thread_comments_post(_owner: Owner, _project_name: &str, project: Project, number: i64, id: UuidParam, logged_user: User, db: Database, new: Json<NewComment>) -> Result<Json<ReviewThreadView>, Status> {
+    let pull = load(&db, &project, number, Some(&logged_user)).await?;
+    let thread = load_thread(&db, &pull, &id).await?;
+    let now = OffsetDateTime::now_utc();
+    let comment = ReviewComment {
+        id: Uuid::new_v4(),
+        thread_id: thread.id,
+        author_id: user_uuid(&logged_user)?,
+        body: body(&new.body)?.to_string(),
+        created_at: now,
+        updated_at: now,
+    };
+    db.review_comments.create(&comment).await.map_err(|_| Status::InternalServerError)?;
+
+    thread_view(&db, thread).await.map(Json)
+}
+
+#[post("/<_owner>/<_project_name>/pulls/<number>/threads/<id>/resolve")]
+pub async fn thread_resolve_post(_owner: Owner, _project_name: &str, project: Project, number: i64, id: UuidParam, logged_user: User, db: Database) -> Result<Json<ReviewThreadView>, Status> {
+    let pull = load(&db, &project, number, Some(&logged_user)).await?;
+    let thread = load_thread(&db, &pull, &id).await?;
+    if !can_resolve(&db, &project, &pull, &thread, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+
+    let user_id = user_uuid(&logged_user)?;
+    let now = OffsetDateTime::now_utc();
+    db.review_threads.set_resolved(&thread.id, Some(&user_id), now).await.map_err(|_| Status::InternalServerError)?;
+
+    let thread = ReviewThread { is_resolved: true, resolved_by: Some(user_id), resolved_at: Some(now), updated_at: now, ..thread };
+    thread_view(&db, thread).await.map(Json)
+}
+
+#[post("/<_owner>/<_project_name>/pulls/<number>/threads/<id>/unresolve")]
+pub async fn thread_unresolve_post(_owner: Owner, _project_name: &str, project: Project, number: i64, id: UuidParam, logged_user: User, db: Database) -> Result<Json<ReviewThreadView>, Status> {
+    let pull = load(&db, &project, number, Some(&logged_user)).await?;
+    let thread = load_thread(&db, &pull, &id).await?;
+    if !can_resolve(&db, &project, &pull, &thread, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+
+    let now = OffsetDateTime::now_utc();
+    db.review_threads.set_resolved(&thread.id, None, now).await.map_err(|_| Status::InternalServerError)?;
+
+    let thread = ReviewThread { is_resolved: false, resolved_by: None, resolved_at: None, updated_at: now, ..thread };
+    thread_view(&db, thread).await.map(Json)
+}
+
+#[post("/<_owner>/<_project_name>/pulls/<number>/comments/<id>", data = "<edit>")]
+pub async fn comment_post(_owner: Owner, _project_name: &str, project: Project, number: i64, id: UuidParam, logged_user: User, db: Database, edit: Json<NewComment>) -> Result<Json<ReviewCommentView>, Status> {
+    let pull = load(&db, &project, number, Some(&logged_user)).await?;
+    let comment = load_comment(&db, &pull, &id).await?;
+    // Only the author can put words into their own mouth
+    if comment.author_id != user_uuid(&logged_user)? {
+        return Err(Status::Forbidden);
+    }
+
+    let body = body(&edit.body)?;
+    let now = OffsetDateTime::now_utc();
+    db.review_comments.update_body(&comment.id, body, now).await.map_err(|_| Status::InternalServerError)?;
+
+    Ok(Json(ReviewComment { body: body.to_string(), updated_at: now, ..comment }.into()))
+}
+
+#[delete("/<_owner>/<_project_name>/pulls/<number>/comments/<id>")]
+pub async fn comment_delete(_owner: Owner, _project_name: &str, project: Project, number: i64, id: UuidParam, logged_user: User, db: Database) -> Result<Status, Status> {
+    let pull = load(&db, &project, number, Some(&logged_user)).await?;
+    let comment = load_comment(&db, &pull, &id).await?;
+    if comment.author_id != user_uuid(&logged_user)? && !can_write(&db, &project, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+
+    db.review_comments.delete(&comment).await.map_err(|_| Status::InternalServerError)?;
+    Ok(Status::NoContent)
+}
+
+#[get("/<_owner>/<_project_name>/pulls/<number>/reviews")]
+pub async fn reviews_get(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: Option<User>, db: Database) -> Result<Json<Vec<ReviewView>>, Status> {
+    let pull = load(&db, &project, number, logged_user.as_ref()).await?;
+    let reviews = db.reviews.for_pull_request(&pull.id).await
+        .map_err(|_| Status::InternalServerError)?;
+    Ok(Json(reviews.into_iter().map(Into::into).collect()))
+}
+
+#[post("/<_owner>/<_project_name>/pulls/<number>/reviews", data = "<new>")]
+pub async fn reviews_post(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: User, db: Database, events: State<'_, EventBus>, new: Json<NewReview>) -> Result<Json<ReviewView>, Status> {
+    let pull = load(&db, &project, number, Some(&logged_user)).await?;
+    if !pull.is_open() {
+        return Err(Status::Conflict);
+    }
+    if new.commit_id.as_ref().map_or(false, |id| id != &pull.head_commit_id) {
+        return Err(Status::Conflict);
+    }
+    let author_id = user_uuid(&logged_user)?;
+    // Authors can comment on their own pull request, but not judge it
+    if author_id == pull.author_id && new.verdict != Verdict::Comment {
+        return Err(Status::UnprocessableEntity);
+    }
+    let body = new.body.as_deref().map(str::trim).filter(|b| !b.is_empty());
+    if body.is_none() && new.verdict != Verdict::Approve {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    let review = Review {
+        id: Uuid::new_v4(),
+        pull_request_id: pull.id,
+        author_id,
+        state: new.verdict.state().to_string(),
+        body: body.map(ToString::to_string),
+        commit_id: pull.head_commit_id.clone(),
+        is_official: can_write(&db, &project, &logged_user).await?,
+        created_at: OffsetDateTime::now_utc(),
+        dismissed_at: None,
+    };
+    db.reviews.create(&review).await.map_err(|_| Status::InternalServerError)?;
+    db.review_requests.remove_user(&pull.id, &author_id).await.map_err(|_| Status::InternalServerError)?;
+
+    events.emit(Event::PullRequestReview(PullRequestReviewEvent {
+        project: ProjectInfo::from(&project),
+        action: PullRequestReviewAction::Submitted,
+        pull_request: (&pull).into(),
+        review: (&review).into(),
+        sender: logged_user.username.clone(),
+    }));
+    Ok(Json(review.into()))
+}
+
+#[post("/<_owner>/<_project_name>/pulls/<number>/reviews/<id>/dismiss")]
+pub async fn review_dismiss_post(_owner: Owner, _project_name: &str, project: Project, number: i64, id: UuidParam, logged_user: User, db: Database) -> Result<Json<ReviewView>, Status> {
+    let pull = load(&db, &project, number, Some(&logged_user)).await?;
+    if !can_write(&db, &project, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    let review = db.reviews.by_id(&id).await
+        .map_err(|_| Status::InternalServerError)?
+        .filter(|review| review.pull_request_id == pull.id)
+        .ok_or(Status::NotFound)?;
+    if review.state != "approved" && review.state != "changes_requested" {
+        return Err(Status::Conflict);
+    }
+
+    let dismissed_at = OffsetDateTime::now_utc();
+    db.reviews.dismiss(&review.id, dismissed_at).await.map_err(|_| Status::InternalServerError)?;
+
+    Ok(Json(Review { state: "dismissed".to_string(), dismissed_at: Some(dismissed_at), ..review }.into()))
+}
+
+#[get("/<_owner>/<_project_name>/pulls/<number>/reviewers")]
+pub async fn reviewers_get(_owner: Owner, _project_name: &str, project: Project, number: i64, logged_user: Option<User>, db: Database) -> Result<Json<Vec<ReviewRequestView>>, Status> {
+    let pull = load(&db, &project, number, logged_user.as_ref()).await?;
+    let requests = db.review_requests.for_pull_request(&pull.id).await
+        .map_err(|_| Status::InternalServerError)?;
+    Ok(Json(requests.into_iter().map(Into::into).collect()))
+}
@@ -0,0 +1,38 @@
+use std::path::Path;
+use time::OffsetDateTime;
+use zorgit_db::{Database, PullRequest, ReviewThread};
+use zorgit_vcs::{VersionControl, git::Repository};
+
+/// The commit the lines of a thread refer to right now: the head for the `new` side
+/// and the merge base for the `old` side.
+pub fn anchor_commit(pull: &PullRequest, side: &str) -> Option<String> {
+    match side {
+        "old" => pull.merge_base_id.clone(),
+        _ => Some(pull.head_commit_id.clone()),
+    }
+}
+
+fn follow(dir: &Path, thread: &ReviewThread, to: &str) -> Result<Option<(usize, usize)>, String> {
+    let repo = Repository::open(dir).map_err(|e| e.to_string())?;
+    repo.follow_lines(&thread.commit_id, to, &thread.path, thread.start_line as usize, thread.end_line as usize)
+        .map_err(|e| e.to_string())
+}
+
+/// Moves the threads of a pull request along, after its head or merge base changed. Threads
+/// whose lines got changed become outdated and stay at the commit they were last valid on.
+pub async fn follow_threads(db: &Database, dir: &Path, pull: &PullRequest) -> crate::Result<()> {
+    let threads = db.review_threads.for_pull_request(&pull.id).await?;
+    for thread in threads.into_iter().filter(|t| !t.is_outdated) {
+        let target = match anchor_commit(pull, &thread.side) {
+            Some(target) if target != thread.commit_id => target,
+            _ => continue,
+        };
+
+        let now = OffsetDateTime::now_utc();
+        match follow(dir, &thread, &target)? {
+            Some((start, end)) => db.review_threads.update_anchor(&thread.id, &target, start as i32, end as i32, now).await?,
+            None => db.review_threads.mark_outdated(&thread.id, now).await?,
+        }
+    }
+    Ok(())
+}
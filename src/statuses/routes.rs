@@ -0,0 +1,166 @@
+use rocket::{
+    get, post, routes, Route,
+    http::{Status, uri::Segments},
+    request::FromSegments,
+};
+use rocket_contrib::json::Json;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use zorgit_common::{Project, entities::{Owner, User}};
+use zorgit_db::{CommitStatus, Database};
+use zorgit_vcs::{CombinedStatus, CommitState, ContextStatus, VersionControl, git::Repository};
+use crate::access::{can_read, can_write};
+
+//##### Routes #####//
+// [post]    /{user|org}/{project}/statuses/<sha>
+// [get]     /{user|org}/{project}/commits/<rev..>/statuses
+// [get]     /{user|org}/{project}/commits/<rev..>/status
+
+/// Context that is used, if a status is posted without one.
+const DEFAULT_CONTEXT: &str = "default";
+/// Longer descriptions are cut off, they are shown in a single line.
+const MAX_DESCRIPTION_LENGTH: usize = 140;
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        status_post,
+        statuses_get,
+        combined_status_get,
+    ]
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewStatus {
+    pub state: CommitState,
+    pub context: Option<String>,
+    pub target_url: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatusView {
+    pub commit_id: String,
+    #[serde(flatten)]
+    pub status: ContextStatus,
+}
+
+/// The path of the statuses of a revision, `<rev..>/statuses`.
+pub struct StatusesPath(String);
+
+/// The path of the combined status of a revision, `<rev..>/status`.
+pub struct StatusPath(String);
+
+impl FromSegments<'_> for StatusesPath {
+    type Error = ();
+    fn from_segments(segments: Segments<'_>) -> Result<StatusesPath, Self::Error> {
+        rev_before(segments, "statuses").map(StatusesPath)
+    }
+}
+
+impl FromSegments<'_> for StatusPath {
+    type Error = ();
+    fn from_segments(segments: Segments<'_>) -> Result<StatusPath, Self::Error> {
+        rev_before(segments, "status").map(StatusPath)
+    }
+}
+
+/// Revisions with slashes, like `feature/login`, are split over segments, so the revision is
+/// everything before the last segment. Paths that end differently don't parse, which forwards
+/// them to the commit log at `commits/<rev..>`.
+fn rev_before(segments: Segments<'_>, last: &str) -> Result<String, ()> {
+    let path = segments.to_path_buf(false).map_err(|_| ())?;
+    if path.file_name().and_then(|name| name.to_str()) != Some(last) {
+        return Err(());
+    }
+    let rev = path.parent().and_then(|rev| rev.to_str()).ok_or(())?.replace('\\', "/");
+    if rev.is_empty() {
+        return Err(());
+    }
+    Ok(rev)
+}
+
+/// Finds the commit a revision, like a branch, a tag or a short commit id, points to.
+fn resolve_commit(project: &Project, rev: &str) -> Result<String, Status> {
+    let repo = Repository::open(&project.dir).map_err(|_| Status::InternalServerError)?;
+    match repo.resolve(rev) {
+        Ok(Some(resolved)) => Ok(resolved.commit_id),
+        Ok(None) => Err(Status::NotFound),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[post("/<_owner>/<_project_name>/statuses/<sha>", data = "<status>")]
+pub async fn status_post(_owner: Owner, _project_name: &str, project: Project, sha: &str, logged_user: User, db: Database, status: Json<NewStatus>) -> Result<Json<StatusView>, Status> {
+    if !can_write(&db, &project, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    let new = status.into_inner();
+    let context = new.context.filter(|c| !c.trim().is_empty()).unwrap_or_else(|| DEFAULT_CONTEXT.to_string());
+    let target_url = new.target_url.filter(|u| !u.is_empty());
+    if target_url.as_ref().map_or(false, |u| u.parse::<zorgit_common::Url>().is_err()) {
+        return Err(Status::UnprocessableEntity);
+    }
+    let description = new.description
+        .filter(|d| !d.is_empty())
+        .map(|d| d.chars().take(MAX_DESCRIPTION_LENGTH).collect::<String>());
+
+    // Only statuses for commits that exist in the repository are accepted
+    let commit_id = {
+        let repo = Repository::open(&project.dir).map_err(|_| Status::InternalServerError)?;
+        repo.commit_by_id(sha).map_err(|_| Status::NotFound)?.id
+    };
+    let project_id = project.id.to_uuid().map_err(|_| Status::InternalServerError)?;
+    let creator_id = logged_user.id.to_uuid().map_err(|_| Status::InternalServerError)?;
+    let now = OffsetDateTime::now_utc();
+    let stored = CommitStatus {
+        id: Uuid::new_v4(),
+        project_id,
+        commit_id: commit_id.clone(),
+        context: context.clone(),
+        state: new.state.as_str().to_string(),
+        target_url: target_url.clone(),
+        description: description.clone(),
+        creator_id,
+        created_at: now,
+        updated_at: now,
+    };
+    db.commit_statuses.save(&stored).await.map_err(|_| Status::InternalServerError)?;
+
+    Ok(Json(StatusView {
+        commit_id,
+        status: ContextStatus {
+            context,
+            state: new.state,
+            target_url,
+            description,
+            updated_at: now.unix_timestamp(),
+        },
+    }))
+}
+
+#[get("/<_owner>/<_project_name>/commits/<path..>", rank = 0)]
+pub async fn statuses_get(_owner: Owner, _project_name: &str, project: Project, path: StatusesPath, logged_user: Option<User>, db: Database) -> Result<Json<Vec<StatusView>>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    let commit_id = resolve_commit(&project, &path.0)?;
+    let combined = CombinedStatus::load(&db, &project, &commit_id).await
+        .map_err(|_| Status::InternalServerError)?;
+
+    let statuses = combined.map(|c| c.statuses).unwrap_or_default();
+    Ok(Json(statuses.into_iter()
+        .map(|status| StatusView { commit_id: commit_id.clone(), status })
+        .collect()))
+}
+
+#[get("/<_owner>/<_project_name>/commits/<path..>", rank = 1)]
+pub async fn combined_status_get(_owner: Owner, _project_name: &str, project: Project, path: StatusPath, logged_user: Option<User>, db: Database) -> Result<Json<Option<CombinedStatus>>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    let commit_id = resolve_commit(&project, &path.0)?;
+    CombinedStatus::load(&db, &project, &commit_id).await
+        .map(Json)
+        .map_err(|_| Status::InternalServerError)
+}
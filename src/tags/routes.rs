@@ -0,0 +1,132 @@
+use std::path::PathBuf;
+use rocket::{delete, get, post, routes, Route, State, http::Status};
+use rocket_contrib::json::Json;
+use serde::{Deserialize, Serialize};
+use zorgit_common::{Project, entities::{Owner, User}};
+use zorgit_db::Database;
+use zorgit_vcs::{RefUpdate, Signature, Tag, TagSort, VersionControl, git::Repository};
+use zorgit_vcs::events::{Event, EventBus, ProjectInfo, PushEvent};
+use crate::access::{can_read, can_write};
+
+//##### Routes #####//
+// [get]     /{user|org}/{project}/tags?<sort>
+// [post]    /{user|org}/{project}/tags
+// [delete]  /{user|org}/{project}/tags/<name..>
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        tags_get,
+        tags_post,
+        tag_delete,
+    ]
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewTag {
+    pub name: String,
+    /// Branch, tag or commit the new tag points to.
+    pub target: String,
+    /// Tags with a message are created as annotated tags.
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagView {
+    pub name: String,
+    pub id: String,
+    pub target_id: String,
+    pub is_annotated: bool,
+    pub message: Option<String>,
+    pub tagger_name: Option<String>,
+    pub tagger_email: Option<String>,
+    pub time: i64,
+}
+
+impl From<Tag> for TagView {
+    fn from(tag: Tag) -> Self {
+        TagView {
+            is_annotated: tag.is_annotated(),
+            name: tag.name,
+            id: tag.id,
+            target_id: tag.target_id,
+            message: tag.message,
+            tagger_name: tag.tagger.as_ref().map(|t| t.name.clone()),
+            tagger_email: tag.tagger.map(|t| t.email),
+            time: tag.time.unix_timestamp(),
+        }
+    }
+}
+
+fn open(project: &Project) -> Result<Repository, Status> {
+    Repository::open(&project.dir).map_err(|_| Status::InternalServerError)
+}
+
+/// Tags created or deleted on the server go through the same event as pushed tags.
+fn emit_tag_update(events: &EventBus, project: &Project, user: &User, update: RefUpdate) {
+    events.emit(Event::Push(PushEvent {
+        project: ProjectInfo::from(project),
+        pusher: user.username.clone(),
+        pusher_email: user.email.address.clone(),
+        updates: vec![update],
+    }));
+}
+
+#[get("/<_owner>/<_project_name>/tags?<sort>")]
+pub async fn tags_get(_owner: Owner, _project_name: &str, project: Project, sort: Option<&str>, logged_user: Option<User>, db: Database) -> Result<Json<Vec<TagView>>, Status> {
+    if !can_read(&db, &project, logged_user.as_ref()).await? {
+        return Err(Status::NotFound);
+    }
+    let sort = match sort {
+        Some(sort) => sort.parse::<TagSort>().map_err(|_| Status::BadRequest)?,
+        None => TagSort::default(),
+    };
+
+    let tags = open(&project)?.tags(sort).map_err(|_| Status::InternalServerError)?;
+    Ok(Json(tags.into_iter().map(TagView::from).collect()))
+}
+
+#[post("/<_owner>/<_project_name>/tags", data = "<tag>")]
+pub async fn tags_post(_owner: Owner, _project_name: &str, project: Project, logged_user: User, db: Database, events: State<'_, EventBus>, tag: Json<NewTag>) -> Result<Json<TagView>, Status> {
+    if !can_write(&db, &project, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    let new = tag.into_inner();
+    let repo = open(&project)?;
+    if repo.tag_by_name(&new.name).map_err(|_| Status::InternalServerError)?.is_some() {
+        return Err(Status::Conflict);
+    }
+
+    let tagger = Signature {
+        name: logged_user.full_name.clone().unwrap_or_else(|| logged_user.username.clone()),
+        email: logged_user.email.address.clone(),
+    };
+    let tag = repo.create_tag(&new.name, &new.target, new.message.as_deref(), &tagger)
+        .map_err(|_| Status::UnprocessableEntity)?;
+
+    emit_tag_update(&events, &project, &logged_user, RefUpdate {
+        name: format!("refs/tags/{}", tag.name),
+        old_id: None,
+        new_id: Some(tag.id.clone()),
+    });
+    Ok(Json(tag.into()))
+}
+
+#[delete("/<_owner>/<_project_name>/tags/<name..>")]
+pub async fn tag_delete(_owner: Owner, _project_name: &str, project: Project, name: PathBuf, logged_user: User, db: Database, events: State<'_, EventBus>) -> Result<Status, Status> {
+    if !can_write(&db, &project, &logged_user).await? {
+        return Err(Status::Forbidden);
+    }
+    let name = name.to_str().ok_or(Status::NotFound)?.replace('\\', "/");
+    let repo = open(&project)?;
+    let tag = repo.tag_by_name(&name)
+        .map_err(|_| Status::InternalServerError)?
+        .ok_or(Status::NotFound)?;
+    repo.delete_tag(&tag.name).map_err(|_| Status::InternalServerError)?;
+
+    emit_tag_update(&events, &project, &logged_user, RefUpdate {
+        name: format!("refs/tags/{}", tag.name),
+        old_id: Some(tag.id),
+        new_id: None,
+    });
+    Ok(Status::NoContent)
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use serde::Serialize;
+use time::OffsetDateTime;
+use zorgit_vcs::{CombinedStatus, Commit, CommitState, Diff, DiffFile, DiffLine};
+
+/// The first and last second `time` can represent, -9999-01-01 and 9999-12-31.
+const MIN_TIMESTAMP: i64 = -377_705_203_200;
+const MAX_TIMESTAMP: i64 = 253_402_300_799;
+
+/// Converts a unix timestamp of a request. `None` for dates `time` can't represent, for
+/// which it would panic.
+pub fn timestamp(seconds: i64) -> Option<OffsetDateTime> {
+    if (MIN_TIMESTAMP..=MAX_TIMESTAMP).contains(&seconds) {
+        Some(OffsetDateTime::from_unix_timestamp(seconds))
+    }
+    else {
+        None
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommitView {
+    pub id: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// The commit message as HTML, with its references linked.
+    pub message_html: Option<String>,
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
+    pub committer_name: Option<String>,
+    pub committer_email: Option<String>,
+    pub parents_id: Vec<String>,
+    pub time: i64,
+    /// Combined state of all commit statuses, if any were reported.
+    pub status: Option<CommitState>,
+}
+
+impl From<Commit> for CommitView {
+    fn from(commit: Commit) -> Self {
+        CommitView {
+            id: commit.id,
+            title: commit.title,
+            description: commit.description,
+            message_html: None,
+            author_name: commit.author.as_ref().map(|a| a.name.clone()),
+            author_email: commit.author.map(|a| a.email),
+            committer_name: commit.committer.as_ref().map(|c| c.name.clone()),
+            committer_email: commit.committer.map(|c| c.email),
+            parents_id: commit.parents_id.unwrap_or_default(),
+            time: commit.time.unix_timestamp(),
+            status: None,
+        }
+    }
+}
+
+impl CommitView {
+    /// Converts a list of commits and adds their combined states and rendered messages.
+    pub fn list(commits: Vec<Commit>, statuses: &HashMap<String, CombinedStatus>, messages: &mut HashMap<String, String>) -> Vec<CommitView> {
+        commits.into_iter()
+            .map(|commit| {
+                let status = statuses.get(&commit.id).map(|s| s.state);
+                let message_html = messages.remove(&commit.id);
+                CommitView { status, message_html, ..CommitView::from(commit) }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffView {
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub files: Vec<DiffFileView>,
+}
+
+impl From<Diff> for DiffView {
+    fn from(diff: Diff) -> Self {
+        DiffView {
+            files_changed: diff.files_changed,
+            insertions: diff.insertions,
+            deletions: diff.deletions,
+            files: diff.files.into_iter().map(DiffFileView::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffFileView {
+    /// One of `added`, `changed`, `deleted` or `renamed`.
+    pub status: &'static str,
+    pub name: Option<String>,
+    pub old_name: Option<String>,
+    pub additions: usize,
+    pub deletions: usize,
+    pub hunks: Vec<DiffHunkView>,
+}
+
+impl From<DiffFile> for DiffFileView {
+    fn from(file: DiffFile) -> Self {
+        let status = match &file {
+            DiffFile::Add(_) => "added",
+            DiffFile::Change(_) => "changed",
+            DiffFile::Del(_) => "deleted",
+            DiffFile::Rename(_) => "renamed",
+        };
+        DiffFileView {
+            status,
+            name: file.name.clone(),
+            old_name: file.old_name.clone(),
+            additions: file.addition,
+            deletions: file.deletion,
+            hunks: file.hunks.iter()
+                .map(|hunk| DiffHunkView {
+                    header: hunk.header.content_as_string(),
+                    lines: hunk.lines.iter().map(DiffLineView::from).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffHunkView {
+    pub header: String,
+    pub lines: Vec<DiffLineView>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffLineView {
+    /// One of `add`, `del`, `plain` or `binary`.
+    pub kind: &'static str,
+    pub old_num: Option<u32>,
+    pub new_num: Option<u32>,
+    pub content: Option<String>,
+}
+
+impl From<&DiffLine> for DiffLineView {
+    fn from(line: &DiffLine) -> Self {
+        let kind = match line {
+            DiffLine::Add(_) => "add",
+            DiffLine::Del(_) => "del",
+            DiffLine::Binary(_) => "binary",
+            DiffLine::Plain(_) | DiffLine::Hunk(_) => "plain",
+        };
+        DiffLineView {
+            kind,
+            old_num: line.old_num,
+            new_num: line.new_num,
+            content: line.content.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_timestamps_out_of_range() {
+        assert_eq!(timestamp(0).map(OffsetDateTime::unix_timestamp), Some(0));
+        assert_eq!(timestamp(1_618_000_000).map(OffsetDateTime::unix_timestamp), Some(1_618_000_000));
+        assert!(timestamp(i64::MAX).is_none());
+        assert!(timestamp(i64::MIN).is_none());
+    }
+}
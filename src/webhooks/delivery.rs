@@ -0,0 +1,343 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use data_encoding::HEXLOWER;
+use hmac::{Hmac, Mac, NewMac};
+use rocket::{error, warn, fairing::AdHoc};
+use sha2::Sha256;
+use time::OffsetDateTime;
+use tokio::sync::broadcast::error::RecvError;
+use uuid::Uuid;
+use zorgit_common::Url;
+use zorgit_db::{Database, Webhook, WebhookDelivery};
+use zorgit_vcs::events::{Event, EventBus};
+use crate::config::ZorgitConfig;
+use super::payload::{self, Payload};
+
+/// Delay before each attempt. The first attempt is made right away.
+const RETRY_DELAYS: [u64; 5] = [0, 10, 60, 5 * 60, 30 * 60];
+/// Response bodies are cut off after this many bytes, the rest is not even downloaded.
+const MAX_RESPONSE_BODY: usize = 64 * 1024;
+const REQUEST_TIMEOUT: u64 = 10;
+
+/// Listens for events and delivers them to all webhooks that are interested in them.
+#[derive(Clone)]
+pub struct Dispatcher {
+    db: Database,
+    client: reqwest::Client,
+    domain: Url,
+}
+
+impl Dispatcher {
+    /// Creates the dispatcher and starts listening for events. Needs the `Database`,
+    /// `EventBus` and `ZorgitConfig` to be managed already, otherwise webhooks are disabled.
+    pub fn fairing() -> AdHoc {
+        AdHoc::on_attach("Webhooks", |rocket| async {
+            let dispatcher = match (rocket.state::<Database>(), rocket.state::<ZorgitConfig>()) {
+                (Some(db), Some(config)) => Dispatcher::new(db.clone(), config.domain.clone()),
+                _ => {
+                    warn!("Webhooks are disabled, because the database or config is missing.");
+                    return Ok(rocket);
+                }
+            };
+            let events = match rocket.state::<EventBus>() {
+                Some(events) => events.subscribe(),
+                None => {
+                    warn!("Webhooks are disabled, because the event bus is missing.");
+                    return Ok(rocket);
+                }
+            };
+
+            let listener = dispatcher.clone();
+            tokio::spawn(async move { listener.listen(events).await });
+            Ok(rocket.manage(dispatcher))
+        })
+    }
+
+    fn new(db: Database, domain: Url) -> Dispatcher {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT))
+            .user_agent(concat!("Zorgit-Hookshot/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .expect("Building the webhook HTTP client");
+
+        Dispatcher { db, client, domain }
+    }
+
+    async fn listen(&self, mut events: tokio::sync::broadcast::Receiver<Event>) {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    if let Err(e) = self.dispatch(&event).await {
+                        error!("Dispatching event to webhooks failed: {}", e);
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => error!("Webhooks missed {} events.", missed),
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+
+    async fn dispatch(&self, event: &Event) -> crate::Result<()> {
+        let project = event.project();
+        let project_id = project.id.parse::<Uuid>()?;
+        let organisation_id = match project.owner_is_organisation {
+            true => Some(project.owner_id.parse::<Uuid>()?),
+            false => None,
+        };
+
+        let webhooks = self.db.webhooks.active_for(&project_id, organisation_id.as_ref()).await?;
+        if webhooks.is_empty() {
+            return Ok(());
+        }
+        let payloads = payload::payloads(event, &self.domain)?;
+
+        for webhook in webhooks {
+            for payload in payloads.iter().filter(|p| webhook.wants(p.event)) {
+                let delivery = new_delivery(&webhook, payload);
+                self.spawn_delivery(webhook.clone(), delivery);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends the same request of an earlier delivery again, as a new delivery.
+    pub fn redeliver(&self, webhook: Webhook, delivery: &WebhookDelivery) {
+        let mut redelivery = delivery.clone();
+        redelivery.id = Uuid::new_v4();
+        redelivery.response_status = None;
+        redelivery.response_headers = None;
+        redelivery.response_body = None;
+        redelivery.is_success = false;
+        redelivery.attempts = 0;
+        redelivery.delivered_at = OffsetDateTime::now_utc();
+        self.spawn_delivery(webhook, redelivery);
+    }
+
+    fn spawn_delivery(&self, webhook: Webhook, delivery: WebhookDelivery) {
+        let dispatcher = self.clone();
+        tokio::spawn(async move { dispatcher.deliver(webhook, delivery).await });
+    }
+
+    /// Tries to deliver until the receiver answers with a success status or all retries are used up.
+    async fn deliver(&self, webhook: Webhook, mut delivery: WebhookDelivery) {
+        for delay in RETRY_DELAYS.iter() {
+            if *delay > 0 {
+                tokio::time::sleep(Duration::from_secs(*delay)).await;
+            }
+            attempt(&self.client, &webhook.url, &mut delivery).await;
+            if let Err(e) = self.db.webhooks.save_delivery(&delivery).await {
+                error!("Saving webhook delivery {} failed: {}", delivery.id, e);
+            }
+            if delivery.is_success {
+                break;
+            }
+        }
+    }
+}
+
+fn new_delivery(webhook: &Webhook, payload: &Payload) -> WebhookDelivery {
+    let id = Uuid::new_v4();
+    let mut headers = BTreeMap::new();
+    headers.insert("Content-Type".to_string(), "application/json".to_string());
+    for prefix in &["Zorgit", "Gitea", "GitHub"] {
+        headers.insert(format!("X-{}-Event", prefix), payload.event.to_string());
+        headers.insert(format!("X-{}-Delivery", prefix), id.to_string());
+    }
+    if let Some(secret) = &webhook.secret {
+        let signature = sign(secret, &payload.body);
+        headers.insert("X-Zorgit-Signature".to_string(), signature.clone());
+        headers.insert("X-Gitea-Signature".to_string(), signature.clone());
+        headers.insert("X-Hub-Signature-256".to_string(), format!("sha256={}", signature));
+    }
+
+    WebhookDelivery {
+        id,
+        webhook_id: webhook.id,
+        event: payload.event.to_string(),
+        request_headers: serde_json::to_string(&headers).unwrap_or_default(),
+        request_body: payload.body.clone(),
+        response_status: None,
+        response_headers: None,
+        response_body: None,
+        is_success: false,
+        attempts: 0,
+        duration_ms: 0,
+        delivered_at: OffsetDateTime::now_utc(),
+    }
+}
+
+/// Sends the request of the delivery once and records the response, or why there is none.
+async fn attempt(client: &reqwest::Client, url: &str, delivery: &mut WebhookDelivery) {
+    let headers: BTreeMap<String, String> = serde_json::from_str(&delivery.request_headers).unwrap_or_default();
+    let mut request = client.post(url)
+        .body(delivery.request_body.clone());
+    for (name, value) in &headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+
+    let start = Instant::now();
+    delivery.attempts += 1;
+    delivery.delivered_at = OffsetDateTime::now_utc();
+    match request.send().await {
+        Ok(response) => {
+            let status = response.status();
+            let response_headers = response.headers().iter()
+                .map(|(name, value)| (name.to_string(), value.to_str().unwrap_or_default().to_string()))
+                .collect::<BTreeMap<_, _>>();
+            delivery.response_status = Some(status.as_u16() as i32);
+            delivery.response_headers = serde_json::to_string(&response_headers).ok();
+            delivery.response_body = Some(read_body(response, MAX_RESPONSE_BODY).await);
+            delivery.is_success = status.is_success();
+        }
+        Err(e) => {
+            delivery.response_status = None;
+            delivery.response_headers = None;
+            delivery.response_body = Some(e.to_string());
+            delivery.is_success = false;
+        }
+    }
+    delivery.duration_ms = start.elapsed().as_millis() as i64;
+}
+
+/// Reads the body of the response, but never more than `max` bytes of it.
+async fn read_body(mut response: reqwest::Response, max: usize) -> String {
+    let mut body = Vec::new();
+    while body.len() < max {
+        match response.chunk().await {
+            Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+            Ok(None) | Err(_) => break,
+        }
+    }
+    body.truncate(max);
+    truncate(String::from_utf8_lossy(&body).into_owned(), max)
+}
+
+/// Signs the body with HMAC-SHA256 and returns the hex encoded signature.
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_varkey(secret.as_bytes())
+        .expect("HMAC can take a key of any size");
+    mac.update(body.as_bytes());
+    HEXLOWER.encode(&mac.finalize().into_bytes())
+}
+
+fn truncate(mut text: String, max: usize) -> String {
+    if text.len() > max {
+        let mut end = max;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+    use super::*;
+
+    fn webhook(url: String, secret: Option<&str>) -> Webhook {
+        Webhook {
+            id: Uuid::new_v4(),
+            project_id: Some(Uuid::new_v4()),
+            organisation_id: None,
+            url,
+            secret: secret.map(ToString::to_string),
+            events: Vec::new(),
+            is_active: true,
+            created_at: OffsetDateTime::now_utc(),
+        }
+    }
+
+    fn payload() -> Payload {
+        Payload {
+            event: "push",
+            body: r#"{"ref":"refs/heads/main"}"#.to_string(),
+        }
+    }
+
+    /// A local HTTP server, that accepts a single request and answers it with the given body.
+    /// Returns its url and the headers, with lowercase names, and body of the request.
+    fn sink(response_body: Vec<u8>) -> (String, JoinHandle<(HashMap<String, String>, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut headers = HashMap::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some(colon) = line.find(':') {
+                    headers.insert(line[..colon].trim().to_lowercase(), line[colon + 1..].trim().to_string());
+                }
+            }
+            let length = headers.get("content-length").map_or(0, |l| l.parse().unwrap());
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+
+            let mut stream = reader.into_inner();
+            let head = format!("HTTP/1.1 202 Accepted\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", response_body.len());
+            // The client stops reading at the limit, so the rest can't be written
+            let _ = stream.write_all(head.as_bytes()).and_then(|_| stream.write_all(&response_body));
+            (headers, String::from_utf8(body).unwrap())
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn signs_with_hmac_sha256() {
+        assert_eq!(sign("key", "The quick brown fox jumps over the lazy dog"), "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8");
+    }
+
+    #[test]
+    fn signs_only_with_a_secret() {
+        let headers = |secret| {
+            let delivery = new_delivery(&webhook("http://localhost/".to_string(), secret), &payload());
+            serde_json::from_str::<BTreeMap<String, String>>(&delivery.request_headers).unwrap()
+        };
+
+        let signed = headers(Some("secret"));
+        let signature = sign("secret", &payload().body);
+        assert_eq!(signed["X-Zorgit-Signature"], signature);
+        assert_eq!(signed["X-Gitea-Signature"], signature);
+        assert_eq!(signed["X-Hub-Signature-256"], format!("sha256={}", signature));
+        assert_eq!(signed["X-GitHub-Event"], "push");
+
+        let unsigned = headers(None);
+        assert!(!unsigned.contains_key("X-Hub-Signature-256"));
+        assert_eq!(unsigned["X-GitHub-Delivery"], unsigned["X-Zorgit-Delivery"]);
+    }
+
+    #[test]
+    fn delivers_to_a_local_sink() {
+        let (url, received) = sink(vec![b'x'; 2 * MAX_RESPONSE_BODY]);
+        let webhook = webhook(url, Some("secret"));
+        let mut delivery = new_delivery(&webhook, &payload());
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(attempt(&reqwest::Client::new(), &webhook.url, &mut delivery));
+
+        let (headers, body) = received.join().unwrap();
+        assert_eq!(body, payload().body);
+        assert_eq!(headers["x-github-event"], "push");
+        assert_eq!(headers["x-hub-signature-256"], format!("sha256={}", sign("secret", &body)));
+
+        assert_eq!(delivery.attempts, 1);
+        assert_eq!(delivery.response_status, Some(202));
+        assert!(delivery.is_success);
+        assert_eq!(delivery.response_body.map(|body| body.len()), Some(MAX_RESPONSE_BODY));
+    }
+
+    #[test]
+    fn truncates_at_char_boundaries() {
+        assert_eq!(truncate("äöü".to_string(), 3), "ä");
+        assert_eq!(truncate("abc".to_string(), 3), "abc");
+    }
+}
@@ -0,0 +1,7 @@
+mod delivery;
+mod payload;
+mod routes;
+
+pub use delivery::{Dispatcher, sign};
+pub use payload::{Payload, payloads};
+pub use routes::routes;
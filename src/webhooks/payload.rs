@@ -0,0 +1,601 @@
+//! Payloads are modelled after the ones of GitHub and Gitea, so existing integrations
+//! like chat bots and CI systems can consume them without changes.
+use serde::Serialize;
+use time::Format;
+use zorgit_common::Url;
+use zorgit_vcs::{Commit, DiffFile, RefUpdate, VersionControl, events::{Event, IssueCommentEvent, IssueEvent, IssueInfo, ProjectEvent, ProjectInfo, PullRequestEvent, PullRequestInfo, PullRequestReviewEvent, PushEvent}, git};
+
+/// GitHub only sends the newest 20 commits of a push, we do the same.
+const MAX_COMMITS: usize = 20;
+const ZERO_ID: &str = "0000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PayloadUser {
+    pub login: String,
+    pub username: String,
+    pub name: String,
+    pub email: String,
+}
+
+impl PayloadUser {
+    fn new(username: &str, email: &str) -> PayloadUser {
+        PayloadUser {
+            login: username.to_string(),
+            username: username.to_string(),
+            name: username.to_string(),
+            email: email.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PayloadRepository {
+    pub id: String,
+    pub name: String,
+    pub full_name: String,
+    pub description: Option<String>,
+    pub owner: PayloadUser,
+    pub private: bool,
+    pub html_url: String,
+    pub clone_url: String,
+    pub default_branch: Option<String>,
+}
+
+impl PayloadRepository {
+    fn new(project: &ProjectInfo, domain: &Url) -> PayloadRepository {
+        let html_url = format!("{}{}/{}", domain, project.owner, project.name);
+        PayloadRepository {
+            id: project.id.clone(),
+            name: project.name.clone(),
+            full_name: format!("{}/{}", project.owner, project.name),
+            description: project.description.clone(),
+            owner: PayloadUser::new(&project.owner, ""),
+            private: project.is_private,
+            clone_url: format!("{}.git", html_url),
+            html_url,
+            default_branch: project.default_branch.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PayloadSignature {
+    pub name: String,
+    pub email: String,
+    pub username: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PayloadCommit {
+    pub id: String,
+    pub message: String,
+    pub url: String,
+    pub timestamp: String,
+    pub author: Option<PayloadSignature>,
+    pub committer: Option<PayloadSignature>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PushPayload {
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub before: String,
+    pub after: String,
+    pub created: bool,
+    pub deleted: bool,
+    pub forced: bool,
+    pub compare: String,
+    pub compare_url: String,
+    pub commits: Vec<PayloadCommit>,
+    pub total_commits: usize,
+    pub head_commit: Option<PayloadCommit>,
+    pub repository: PayloadRepository,
+    pub pusher: PayloadUser,
+    pub sender: PayloadUser,
+}
+
+/// Sent when a branch or tag gets created or deleted.
+#[derive(Debug, Clone, Serialize)]
+pub struct RefPayload {
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub ref_type: String,
+    pub sha: String,
+    pub repository: PayloadRepository,
+    pub sender: PayloadUser,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectPayload {
+    pub action: String,
+    pub repository: PayloadRepository,
+    pub sender: PayloadUser,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PayloadBranch {
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub sha: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PayloadPullRequest {
+    pub id: String,
+    pub number: i64,
+    pub title: String,
+    pub body: Option<String>,
+    /// `open` or `closed`, a merged pull request is closed with `merged` set.
+    pub state: String,
+    pub draft: bool,
+    pub merged: bool,
+    pub merge_commit_sha: Option<String>,
+    pub html_url: String,
+    pub head: PayloadBranch,
+    pub base: PayloadBranch,
+}
+
+impl PayloadPullRequest {
+    fn new(pull: &PullRequestInfo, repository: &PayloadRepository) -> PayloadPullRequest {
+        let merged = pull.state == "merged";
+        PayloadPullRequest {
+            id: pull.id.clone(),
+            number: pull.number,
+            title: pull.title.clone(),
+            body: pull.description.clone(),
+            state: if merged { "closed".to_string() } else { pull.state.clone() },
+            draft: pull.is_draft,
+            merged,
+            merge_commit_sha: pull.merge_commit_id.clone(),
+            html_url: format!("{}/pulls/{}", repository.html_url, pull.number),
+            head: PayloadBranch { reference: pull.head_branch.clone(), sha: pull.head_commit_id.clone() },
+            base: PayloadBranch { reference: pull.base_branch.clone(), sha: pull.base_commit_id.clone() },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PullRequestPayload {
+    pub action: String,
+    pub number: i64,
+    pub pull_request: PayloadPullRequest,
+    pub repository: PayloadRepository,
+    pub sender: PayloadUser,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PayloadLabel {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PayloadIssue {
+    pub id: String,
+    pub number: i64,
+    pub title: String,
+    pub body: Option<String>,
+    pub state: String,
+    pub html_url: String,
+}
+
+impl PayloadIssue {
+    fn new(issue: &IssueInfo, repository: &PayloadRepository) -> PayloadIssue {
+        PayloadIssue {
+            id: issue.id.clone(),
+            number: issue.number,
+            title: issue.title.clone(),
+            body: issue.description.clone(),
+            state: issue.state.clone(),
+            html_url: format!("{}/issues/{}", repository.html_url, issue.number),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuePayload {
+    pub action: String,
+    pub issue: PayloadIssue,
+    pub label: Option<PayloadLabel>,
+    pub assignee: Option<PayloadUser>,
+    pub repository: PayloadRepository,
+    pub sender: PayloadUser,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PayloadComment {
+    pub id: String,
+    pub body: String,
+    pub html_url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssueCommentPayload {
+    pub action: String,
+    pub issue: PayloadIssue,
+    pub comment: PayloadComment,
+    pub repository: PayloadRepository,
+    pub sender: PayloadUser,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PayloadReview {
+    pub id: String,
+    pub body: Option<String>,
+    pub state: String,
+    pub commit_id: String,
+    pub html_url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PullRequestReviewPayload {
+    pub action: String,
+    pub review: PayloadReview,
+    pub pull_request: PayloadPullRequest,
+    pub repository: PayloadRepository,
+    pub sender: PayloadUser,
+}
+
+/// A payload that is ready to be sent, together with the name of its event.
+#[derive(Debug, Clone)]
+pub struct Payload {
+    /// Name of the event, as in the `X-GitHub-Event` header, i.e. `push`, `create` or `delete`.
+    pub event: &'static str,
+    pub body: String,
+}
+
+/// Builds all payloads for the given event. A push can result in several payloads, because
+/// every updated reference gets its own push payload and creating or deleting a branch or
+/// tag also sends a `create` or `delete` payload.
+pub fn payloads(event: &Event, domain: &Url) -> crate::Result<Vec<Payload>> {
+    match event {
+        Event::Push(push) => push_payloads(push, domain),
+        Event::Project(project) => Ok(vec![project_payload(project, domain)?]),
+        Event::PullRequest(pull) => Ok(vec![pull_request_payload(pull, domain)?]),
+        Event::PullRequestReview(review) => Ok(vec![pull_request_review_payload(review, domain)?]),
+        Event::Issue(issue) => Ok(vec![issue_payload(issue, domain)?]),
+        Event::IssueComment(comment) => Ok(vec![issue_comment_payload(comment, domain)?]),
+    }
+}
+
+fn push_payloads(event: &PushEvent, domain: &Url) -> crate::Result<Vec<Payload>> {
+    let repo = git::Repository::open(&event.project.dir).map_err(|e| e.to_string())?;
+    let repository = PayloadRepository::new(&event.project, domain);
+    let pusher = PayloadUser::new(&event.pusher, &event.pusher_email);
+    let mut payloads = Vec::new();
+
+    for update in &event.updates {
+        let short_name = update.branch().or_else(|| update.tag());
+        let ref_type = if update.tag().is_some() { "tag" } else { "branch" };
+        if let Some(short_name) = short_name {
+            if update.is_create() || update.is_delete() {
+                let payload = RefPayload {
+                    reference: short_name.to_string(),
+                    ref_type: ref_type.to_string(),
+                    sha: update.new_id.clone().or_else(|| update.old_id.clone()).unwrap_or_default(),
+                    repository: repository.clone(),
+                    sender: pusher.clone(),
+                };
+                payloads.push(Payload {
+                    event: if update.is_create() { "create" } else { "delete" },
+                    body: serde_json::to_string(&payload)?,
+                });
+            }
+        }
+
+        let payload = push_payload(&repo, update, &repository, &pusher)?;
+        payloads.push(Payload {
+            event: "push",
+            body: serde_json::to_string(&payload)?,
+        });
+    }
+
+    Ok(payloads)
+}
+
+fn push_payload(repo: &git::Repository, update: &RefUpdate, repository: &PayloadRepository, pusher: &PayloadUser) -> crate::Result<PushPayload> {
+    let before = update.old_id.clone().unwrap_or_else(|| ZERO_ID.to_string());
+    let after = update.new_id.clone().unwrap_or_else(|| ZERO_ID.to_string());
+
+    let mut commits = Vec::new();
+    let mut forced = false;
+    if let Some(new_id) = &update.new_id {
+        for commit in repo.commits_between(update.old_id.as_deref(), new_id, MAX_COMMITS).map_err(|e| e.to_string())? {
+            commits.push(payload_commit(repo, &commit, &repository.html_url)?);
+        }
+        if let Some(old_id) = &update.old_id {
+            forced = !repo.is_ancestor(old_id, new_id).map_err(|e| e.to_string())?;
+        }
+    }
+    // GitHub lists the oldest commit first
+    commits.reverse();
+    let compare = format!("{}/compare/{}...{}", repository.html_url, &before[..12], &after[..12]);
+
+    Ok(PushPayload {
+        reference: update.name.clone(),
+        created: update.is_create(),
+        deleted: update.is_delete(),
+        forced,
+        compare_url: compare.clone(),
+        compare,
+        total_commits: commits.len(),
+        head_commit: commits.last().cloned(),
+        commits,
+        before,
+        after,
+        repository: repository.clone(),
+        pusher: pusher.clone(),
+        sender: pusher.clone(),
+    })
+}
+
+fn payload_commit(repo: &git::Repository, commit: &Commit, html_url: &str) -> crate::Result<PayloadCommit> {
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    let mut modified = Vec::new();
+    for file in repo.diff_to_parent(commit).map_err(|e| e.to_string())?.files {
+        match &file {
+            DiffFile::Add(f) => added.extend(f.name.clone()),
+            DiffFile::Del(f) => removed.extend(f.old_name.clone()),
+            DiffFile::Change(f) => modified.extend(f.name.clone()),
+            DiffFile::Rename(f) => {
+                removed.extend(f.old_name.clone());
+                added.extend(f.name.clone());
+            }
+        }
+    }
+    let signature = |s: &zorgit_vcs::Signature| PayloadSignature {
+        name: s.name.clone(),
+        email: s.email.clone(),
+        username: s.name.clone(),
+    };
+    let message = match &commit.description {
+        Some(description) => format!("{}\n\n{}", commit.title.as_deref().unwrap_or_default(), description),
+        None => commit.title.clone().unwrap_or_default(),
+    };
+
+    Ok(PayloadCommit {
+        id: commit.id.clone(),
+        message,
+        url: format!("{}/commit/{}", html_url, commit.id),
+        timestamp: commit.time.format(Format::Rfc3339),
+        author: commit.author.as_ref().map(signature),
+        committer: commit.committer.as_ref().map(signature),
+        added,
+        removed,
+        modified,
+    })
+}
+
+fn project_payload(event: &ProjectEvent, domain: &Url) -> crate::Result<Payload> {
+    let payload = ProjectPayload {
+        action: serde_json::to_value(event.action)?.as_str().unwrap_or_default().to_string(),
+        repository: PayloadRepository::new(&event.project, domain),
+        sender: PayloadUser::new(&event.sender, ""),
+    };
+
+    Ok(Payload {
+        event: "repository",
+        body: serde_json::to_string(&payload)?,
+    })
+}
+
+fn pull_request_payload(event: &PullRequestEvent, domain: &Url) -> crate::Result<Payload> {
+    let repository = PayloadRepository::new(&event.project, domain);
+    let payload = PullRequestPayload {
+        action: serde_json::to_value(event.action)?.as_str().unwrap_or_default().to_string(),
+        number: event.pull_request.number,
+        pull_request: PayloadPullRequest::new(&event.pull_request, &repository),
+        repository,
+        sender: PayloadUser::new(&event.sender, ""),
+    };
+
+    Ok(Payload {
+        event: "pull_request",
+        body: serde_json::to_string(&payload)?,
+    })
+}
+
+fn pull_request_review_payload(event: &PullRequestReviewEvent, domain: &Url) -> crate::Result<Payload> {
+    let repository = PayloadRepository::new(&event.project, domain);
+    let pull_request = PayloadPullRequest::new(&event.pull_request, &repository);
+    let payload = PullRequestReviewPayload {
+        action: serde_json::to_value(event.action)?.as_str().unwrap_or_default().to_string(),
+        review: PayloadReview {
+            id: event.review.id.clone(),
+            body: event.review.body.clone(),
+            state: event.review.state.clone(),
+            commit_id: event.review.commit_id.clone(),
+            html_url: format!("{}#review-{}", pull_request.html_url, event.review.id),
+        },
+        pull_request,
+        repository,
+        sender: PayloadUser::new(&event.sender, ""),
+    };
+
+    Ok(Payload {
+        event: "pull_request_review",
+        body: serde_json::to_string(&payload)?,
+    })
+}
+
+fn issue_payload(event: &IssueEvent, domain: &Url) -> crate::Result<Payload> {
+    let repository = PayloadRepository::new(&event.project, domain);
+    let payload = IssuePayload {
+        action: serde_json::to_value(event.action)?.as_str().unwrap_or_default().to_string(),
+        issue: PayloadIssue::new(&event.issue, &repository),
+        label: event.label.as_ref().map(|name| PayloadLabel { name: name.clone() }),
+        assignee: event.assignee.as_ref().map(|username| PayloadUser::new(username, "")),
+        repository,
+        sender: PayloadUser::new(&event.sender, ""),
+    };
+
+    Ok(Payload {
+        event: "issues",
+        body: serde_json::to_string(&payload)?,
+    })
+}
+
+fn issue_comment_payload(event: &IssueCommentEvent, domain: &Url) -> crate::Result<Payload> {
+    let repository = PayloadRepository::new(&event.project, domain);
+    let issue = PayloadIssue::new(&event.issue, &repository);
+    let payload = IssueCommentPayload {
+        action: serde_json::to_value(event.action)?.as_str().unwrap_or_default().to_string(),
+        comment: PayloadComment {
+            id: event.comment.id.clone(),
+            body: event.comment.body.clone(),
+            html_url: format!("{}#comment-{}", issue.html_url, event.comment.id),
+        },
+        issue,
+        repository,
+        sender: PayloadUser::new(&event.sender, ""),
+    };
+
+    Ok(Payload {
+        event: "issue_comment",
+        body: serde_json::to_string(&payload)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use serde_json::Value;
+    use zorgit_vcs::events::{IssueAction, IssueCommentAction, IssueCommentInfo, ProjectAction, PullRequestReviewAction, PullRequestReviewInfo};
+    use super::*;
+
+    fn domain() -> Url {
+        "https://zorgit.example".parse().unwrap()
+    }
+
+    fn project() -> ProjectInfo {
+        ProjectInfo {
+            id: "5b0f7a36-6f7e-4c5e-9a57-0d6f3c0c8f10".to_string(),
+            owner_id: "0f4b0c4e-58d5-4a4e-9d38-6f0c1b8b9a11".to_string(),
+            owner: "alice".to_string(),
+            owner_is_organisation: false,
+            name: "zorgit".to_string(),
+            description: Some("A forge".to_string()),
+            is_private: false,
+            default_branch: Some("main".to_string()),
+            dir: PathBuf::new(),
+        }
+    }
+
+    fn issue() -> IssueInfo {
+        IssueInfo {
+            id: "issue-id".to_string(),
+            number: 7,
+            title: "Crash on start".to_string(),
+            description: None,
+            state: "open".to_string(),
+            author_id: "author-id".to_string(),
+            milestone_id: None,
+        }
+    }
+
+    fn single(event: Event) -> (&'static str, Value) {
+        let mut payloads = payloads(&event, &domain()).unwrap();
+        assert_eq!(payloads.len(), 1);
+        let payload = payloads.remove(0);
+        (payload.event, serde_json::from_str(&payload.body).unwrap())
+    }
+
+    #[test]
+    fn project_payload_is_a_repository_event() {
+        let (event, body) = single(Event::Project(ProjectEvent {
+            project: project(),
+            action: ProjectAction::Edited,
+            sender: "bob".to_string(),
+        }));
+
+        assert_eq!(event, "repository");
+        assert_eq!(body["action"], "edited");
+        assert_eq!(body["sender"]["login"], "bob");
+        let repository = &body["repository"];
+        assert_eq!(repository["full_name"], "alice/zorgit");
+        assert_eq!(repository["owner"]["login"], "alice");
+        assert_eq!(repository["html_url"], "https://zorgit.example/alice/zorgit");
+        assert_eq!(repository["clone_url"], "https://zorgit.example/alice/zorgit.git");
+        assert_eq!(repository["default_branch"], "main");
+        assert_eq!(repository["private"], false);
+    }
+
+    #[test]
+    fn issue_payload_links_the_issue() {
+        let (event, body) = single(Event::Issue(IssueEvent {
+            project: project(),
+            action: IssueAction::Labeled,
+            issue: issue(),
+            label: Some("bug".to_string()),
+            assignee: None,
+            sender: "bob".to_string(),
+        }));
+
+        assert_eq!(event, "issues");
+        assert_eq!(body["action"], "labeled");
+        assert_eq!(body["issue"]["number"], 7);
+        assert_eq!(body["issue"]["html_url"], "https://zorgit.example/alice/zorgit/issues/7");
+        assert_eq!(body["label"]["name"], "bug");
+        assert_eq!(body["assignee"], Value::Null);
+    }
+
+    #[test]
+    fn issue_comment_payload_links_the_comment() {
+        let (event, body) = single(Event::IssueComment(IssueCommentEvent {
+            project: project(),
+            action: IssueCommentAction::Created,
+            issue: issue(),
+            comment: IssueCommentInfo {
+                id: "comment-id".to_string(),
+                author_id: "author-id".to_string(),
+                body: "Same here".to_string(),
+            },
+            sender: "bob".to_string(),
+        }));
+
+        assert_eq!(event, "issue_comment");
+        assert_eq!(body["action"], "created");
+        assert_eq!(body["comment"]["body"], "Same here");
+        assert_eq!(body["comment"]["html_url"], "https://zorgit.example/alice/zorgit/issues/7#comment-comment-id");
+    }
+
+    #[test]
+    fn pull_request_review_payload_links_the_review() {
+        let (event, body) = single(Event::PullRequestReview(PullRequestReviewEvent {
+            project: project(),
+            action: PullRequestReviewAction::Submitted,
+            pull_request: PullRequestInfo {
+                id: "pull-id".to_string(),
+                number: 8,
+                title: "Fix crash on start".to_string(),
+                description: None,
+                is_draft: false,
+                state: "open".to_string(),
+                head_project_id: "5b0f7a36-6f7e-4c5e-9a57-0d6f3c0c8f10".to_string(),
+                head_branch: "fix-crash".to_string(),
+                head_commit_id: "a".repeat(40),
+                base_branch: "main".to_string(),
+                base_commit_id: "b".repeat(40),
+                merge_base_id: None,
+                merge_commit_id: None,
+            },
+            review: PullRequestReviewInfo {
+                id: "review-id".to_string(),
+                author_id: "author-id".to_string(),
+                state: "approved".to_string(),
+                body: Some("Looks good".to_string()),
+                commit_id: "a".repeat(40),
+            },
+            sender: "bob".to_string(),
+        }));
+
+        assert_eq!(event, "pull_request_review");
+        assert_eq!(body["action"], "submitted");
+        assert_eq!(body["review"]["state"], "approved");
+        assert_eq!(body["review"]["html_url"], "https://zorgit.example/alice/zorgit/pulls/8#review-review-id");
+        assert_eq!(body["pull_request"]["head"]["ref"], "fix-crash");
+    }
+}
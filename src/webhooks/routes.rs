@@ -0,0 +1,268 @@
+use rocket::{delete, get, post, routes, Route, State, http::Status};
+use rocket_contrib::{json::Json, uuid::Uuid as UuidParam};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+use zorgit_common::{Project, entities::{Owner, User}};
+use zorgit_db::{Database, Webhook, WebhookDelivery};
+use crate::access::can_write;
+use super::Dispatcher;
+
+//##### Routes #####//
+// [get]     /{user|org}/{project}/settings/hooks
+// [post]    /{user|org}/{project}/settings/hooks
+// [delete]  /{user|org}/{project}/settings/hooks/<id>
+// [get]     /{user|org}/{project}/settings/hooks/<id>/deliveries
+// [post]    /{user|org}/{project}/settings/hooks/<id>/deliveries/<delivery_id>/redeliver
+// [get]     /org/{org}/hooks
+// [post]    /org/{org}/hooks
+// [delete]  /org/{org}/hooks/<id>
+// [get]     /org/{org}/hooks/<id>/deliveries
+// [post]    /org/{org}/hooks/<id>/deliveries/<delivery_id>/redeliver
+
+/// How many deliveries are shown in the delivery log of a webhook.
+const DELIVERY_LOG_SIZE: i64 = 50;
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        project_hooks_get,
+        project_hooks_post,
+        project_hook_delete,
+        project_hook_deliveries_get,
+        project_hook_redeliver_post,
+        org_hooks_get,
+        org_hooks_post,
+        org_hook_delete,
+        org_hook_deliveries_get,
+        org_hook_redeliver_post,
+    ]
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewWebhook {
+    pub url: String,
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(default = "default_active")]
+    pub is_active: bool,
+}
+
+fn default_active() -> bool { true }
+
+/// A webhook as it is shown to the user. The secret never leaves the server.
+#[derive(Debug, Serialize)]
+pub struct WebhookView {
+    pub id: String,
+    pub url: String,
+    pub has_secret: bool,
+    pub events: Vec<String>,
+    pub is_active: bool,
+    pub created_at: i64,
+}
+
+impl From<Webhook> for WebhookView {
+    fn from(webhook: Webhook) -> Self {
+        WebhookView {
+            id: webhook.id.to_string(),
+            url: webhook.url,
+            has_secret: webhook.secret.is_some(),
+            events: webhook.events,
+            is_active: webhook.is_active,
+            created_at: webhook.created_at.unix_timestamp(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeliveryView {
+    pub id: String,
+    pub event: String,
+    pub request_headers: serde_json::Value,
+    pub request_body: String,
+    pub response_status: Option<i32>,
+    pub response_headers: Option<serde_json::Value>,
+    pub response_body: Option<String>,
+    pub is_success: bool,
+    pub attempts: i32,
+    pub duration_ms: i64,
+    pub delivered_at: i64,
+}
+
+impl From<WebhookDelivery> for DeliveryView {
+    fn from(delivery: WebhookDelivery) -> Self {
+        DeliveryView {
+            id: delivery.id.to_string(),
+            event: delivery.event,
+            request_headers: serde_json::from_str(&delivery.request_headers).unwrap_or_default(),
+            request_body: delivery.request_body,
+            response_status: delivery.response_status,
+            response_headers: delivery.response_headers.and_then(|h| serde_json::from_str(&h).ok()),
+            response_body: delivery.response_body,
+            is_success: delivery.is_success,
+            attempts: delivery.attempts,
+            duration_ms: delivery.duration_ms,
+            delivered_at: delivery.delivered_at.unix_timestamp(),
+        }
+    }
+}
+
+/// Where a webhook belongs to.
+enum Scope {
+    Project(Uuid),
+    Organisation(Uuid),
+}
+
+impl Scope {
+    async fn for_project(db: &Database, project: &Project, user: &User) -> Result<Scope, Status> {
+        if !can_write(db, project, user).await? {
+            return Err(Status::Forbidden);
+        }
+        project.id.to_uuid()
+            .map(Scope::Project)
+            .map_err(|_| Status::InternalServerError)
+    }
+
+    //TODO: allow organisation owners, once organisations have members
+    fn for_organisation(owner: &Owner, user: &User) -> Result<Scope, Status> {
+        if !owner.is_organisation() {
+            return Err(Status::NotFound);
+        }
+        if !user.is_admin {
+            return Err(Status::Forbidden);
+        }
+        owner.id().to_uuid()
+            .map(Scope::Organisation)
+            .map_err(|_| Status::InternalServerError)
+    }
+
+    fn owns(&self, webhook: &Webhook) -> bool {
+        match self {
+            Scope::Project(id) => webhook.project_id.as_ref() == Some(id),
+            Scope::Organisation(id) => webhook.organisation_id.as_ref() == Some(id),
+        }
+    }
+
+    async fn webhooks(&self, db: &Database) -> Result<Vec<Webhook>, Status> {
+        let webhooks = match self {
+            Scope::Project(id) => db.webhooks.for_project(id).await,
+            Scope::Organisation(id) => db.webhooks.for_organisation(id).await,
+        };
+        webhooks.map_err(|_| Status::InternalServerError)
+    }
+
+    async fn webhook(&self, db: &Database, id: &Uuid) -> Result<Webhook, Status> {
+        match db.webhooks.by_id(id).await {
+            Ok(Some(webhook)) if self.owns(&webhook) => Ok(webhook),
+            Ok(_) => Err(Status::NotFound),
+            Err(_) => Err(Status::InternalServerError),
+        }
+    }
+}
+
+async fn list(db: &Database, scope: Scope) -> Result<Json<Vec<WebhookView>>, Status> {
+    let webhooks = scope.webhooks(db).await?;
+    Ok(Json(webhooks.into_iter().map(WebhookView::from).collect()))
+}
+
+async fn create(db: &Database, scope: Scope, new: NewWebhook) -> Result<Json<WebhookView>, Status> {
+    if new.url.parse::<zorgit_common::Url>().is_err() {
+        return Err(Status::UnprocessableEntity);
+    }
+    let (project_id, organisation_id) = match scope {
+        Scope::Project(id) => (Some(id), None),
+        Scope::Organisation(id) => (None, Some(id)),
+    };
+    let webhook = Webhook {
+        id: Uuid::new_v4(),
+        project_id,
+        organisation_id,
+        url: new.url,
+        secret: new.secret.filter(|s| !s.is_empty()),
+        events: new.events,
+        is_active: new.is_active,
+        created_at: OffsetDateTime::now_utc(),
+    };
+
+    db.webhooks.create(&webhook).await.map_err(|_| Status::InternalServerError)?;
+    Ok(Json(webhook.into()))
+}
+
+async fn remove(db: &Database, scope: Scope, id: &Uuid) -> Result<Status, Status> {
+    let webhook = scope.webhook(db, id).await?;
+    db.webhooks.delete(&webhook.id).await.map_err(|_| Status::InternalServerError)?;
+    Ok(Status::NoContent)
+}
+
+async fn deliveries(db: &Database, scope: Scope, id: &Uuid) -> Result<Json<Vec<DeliveryView>>, Status> {
+    let webhook = scope.webhook(db, id).await?;
+    let deliveries = db.webhooks.deliveries(&webhook.id, DELIVERY_LOG_SIZE).await
+        .map_err(|_| Status::InternalServerError)?;
+    Ok(Json(deliveries.into_iter().map(DeliveryView::from).collect()))
+}
+
+async fn redeliver(db: &Database, dispatcher: &Dispatcher, scope: Scope, id: &Uuid, delivery_id: &Uuid) -> Result<Status, Status> {
+    let webhook = scope.webhook(db, id).await?;
+    let delivery = match db.webhooks.delivery_by_id(delivery_id).await {
+        Ok(Some(delivery)) if delivery.webhook_id == webhook.id => delivery,
+        Ok(_) => return Err(Status::NotFound),
+        Err(_) => return Err(Status::InternalServerError),
+    };
+
+    dispatcher.redeliver(webhook, &delivery);
+    Ok(Status::Accepted)
+}
+
+//################# Project webhooks #################
+#[get("/<_owner>/<_project_name>/settings/hooks")]
+pub async fn project_hooks_get(_owner: Owner, _project_name: &str, project: Project, logged_user: User, db: Database) -> Result<Json<Vec<WebhookView>>, Status> {
+    list(&db, Scope::for_project(&db, &project, &logged_user).await?).await
+}
+
+#[post("/<_owner>/<_project_name>/settings/hooks", data = "<webhook>")]
+pub async fn project_hooks_post(_owner: Owner, _project_name: &str, project: Project, logged_user: User, db: Database, webhook: Json<NewWebhook>) -> Result<Json<WebhookView>, Status> {
+    create(&db, Scope::for_project(&db, &project, &logged_user).await?, webhook.into_inner()).await
+}
+
+#[delete("/<_owner>/<_project_name>/settings/hooks/<id>")]
+pub async fn project_hook_delete(_owner: Owner, _project_name: &str, project: Project, id: UuidParam, logged_user: User, db: Database) -> Result<Status, Status> {
+    remove(&db, Scope::for_project(&db, &project, &logged_user).await?, &id).await
+}
+
+#[get("/<_owner>/<_project_name>/settings/hooks/<id>/deliveries")]
+pub async fn project_hook_deliveries_get(_owner: Owner, _project_name: &str, project: Project, id: UuidParam, logged_user: User, db: Database) -> Result<Json<Vec<DeliveryView>>, Status> {
+    deliveries(&db, Scope::for_project(&db, &project, &logged_user).await?, &id).await
+}
+
+#[post("/<_owner>/<_project_name>/settings/hooks/<id>/deliveries/<delivery_id>/redeliver")]
+pub async fn project_hook_redeliver_post(_owner: Owner, _project_name: &str, project: Project, id: UuidParam, delivery_id: UuidParam, logged_user: User, db: Database, dispatcher: State<'_, Dispatcher>) -> Result<Status, Status> {
+    redeliver(&db, &dispatcher, Scope::for_project(&db, &project, &logged_user).await?, &id, &delivery_id).await
+}
+
+//################# Organisation webhooks #################
+// Not below `settings` like the project webhooks, where `/org/<project>/settings/hooks` would
+// collide with the webhooks of a project of a user called `org`.
+#[get("/org/<owner>/hooks")]
+pub async fn org_hooks_get(owner: Owner, logged_user: User, db: Database) -> Result<Json<Vec<WebhookView>>, Status> {
+    list(&db, Scope::for_organisation(&owner, &logged_user)?).await
+}
+
+#[post("/org/<owner>/hooks", data = "<webhook>")]
+pub async fn org_hooks_post(owner: Owner, logged_user: User, db: Database, webhook: Json<NewWebhook>) -> Result<Json<WebhookView>, Status> {
+    create(&db, Scope::for_organisation(&owner, &logged_user)?, webhook.into_inner()).await
+}
+
+#[delete("/org/<owner>/hooks/<id>")]
+pub async fn org_hook_delete(owner: Owner, id: UuidParam, logged_user: User, db: Database) -> Result<Status, Status> {
+    remove(&db, Scope::for_organisation(&owner, &logged_user)?, &id).await
+}
+
+#[get("/org/<owner>/hooks/<id>/deliveries")]
+pub async fn org_hook_deliveries_get(owner: Owner, id: UuidParam, logged_user: User, db: Database) -> Result<Json<Vec<DeliveryView>>, Status> {
+    deliveries(&db, Scope::for_organisation(&owner, &logged_user)?, &id).await
+}
+
+#[post("/org/<owner>/hooks/<id>/deliveries/<delivery_id>/redeliver")]
+pub async fn org_hook_redeliver_post(owner: Owner, id: UuidParam, delivery_id: UuidParam, logged_user: User, db: Database, dispatcher: State<'_, Dispatcher>) -> Result<Status, Status> {
+    redeliver(&db, &dispatcher, Scope::for_organisation(&owner, &logged_user)?, &id, &delivery_id).await
+}
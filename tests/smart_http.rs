@@ -0,0 +1,367 @@
+//! Clones from and pushes to the smart HTTP transport with a real git client.
+//!
+//! The server of these tests mounts the real git routes. Only the gatekeeper, which looks
+//! up projects and users in the database otherwise, is replaced by one that knows a fixed
+//! set of users and keeps the projects in a directory. Everything git sees, from the
+//! advertisement to the pre-receive hook, is the real thing.
+
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use data_encoding::BASE64;
+use rocket::{Request, http::Status, data::ToByteUnit};
+use zorgit_common::access::Access;
+use zorgit_vcs::{BranchProtection, PushPolicy, RefUpdate, Result, Server as _};
+use zorgit_vcs::events::ProjectInfo;
+use zorgit_vcs::git::{hooks, post_receive::PushCompletion, server::{self, Gatekeeper, Visit}};
+
+/// Users of the test server as `(username, password, can_write)`.
+const USERS: [(&str, &str, bool); 2] = [("alice", "secret", true), ("mallory", "secret", false)];
+/// Projects that only logged in users can see.
+const PRIVATE_PROJECTS: [&str; 1] = ["private"];
+
+/// The references updated by the pushes to the test server, as reported by git.
+#[derive(Clone, Default)]
+struct Pushed(Arc<Mutex<Vec<RefUpdate>>>);
+
+/// Admits requests to the projects in `<dir>/<owner>/<project>`, with the users logging in
+/// with Basic auth.
+struct TestGatekeeper {
+    dir: PathBuf,
+    pushed: Pushed,
+}
+
+#[rocket::async_trait]
+impl Gatekeeper for TestGatekeeper {
+    async fn admit(&self, request: &Request<'_>) -> std::result::Result<Option<Box<dyn Visit>>, Status> {
+        let (owner, name) = match (request.routed_segment(0), request.routed_segment(1)) {
+            (Some(owner), Some(name)) => (owner, name),
+            _ => return Ok(None),
+        };
+        let dir = self.dir.join(owner).join(name);
+        if !dir.is_dir() {
+            return Ok(None);
+        }
+
+        let credentials = request.headers().get_one("Authorization")
+            .and_then(|header| header.strip_prefix("Basic "))
+            .and_then(|encoded| BASE64.decode(encoded.as_bytes()).ok())
+            .and_then(|decoded| String::from_utf8(decoded).ok());
+        let user = credentials.as_deref()
+            .and_then(|credentials| USERS.iter().find(|(username, password, _)| credentials == format!("{}:{}", username, password)));
+        let is_private = PRIVATE_PROJECTS.contains(&name);
+        let access = match user {
+            Some((_, _, true)) => Access::Write,
+            Some(_) => Access::Read,
+            None if is_private => Access::None,
+            None => Access::Read,
+        };
+
+        Ok(Some(Box::new(TestVisit {
+            project: ProjectInfo {
+                id: String::new(),
+                owner_id: String::new(),
+                owner: owner.to_string(),
+                owner_is_organisation: false,
+                name: name.to_string(),
+                description: None,
+                is_private,
+                default_branch: Some("main".to_string()),
+                dir,
+            },
+            user: user.map(|(username, _, _)| username.to_string()),
+            access,
+            pushed: self.pushed.clone(),
+        })))
+    }
+}
+
+struct TestVisit {
+    project: ProjectInfo,
+    user: Option<String>,
+    access: Access,
+    pushed: Pushed,
+}
+
+#[rocket::async_trait]
+impl Visit for TestVisit {
+    fn project(&self) -> &ProjectInfo {
+        &self.project
+    }
+
+    fn is_logged_in(&self) -> bool {
+        self.user.is_some()
+    }
+
+    fn access(&self) -> Access {
+        self.access
+    }
+
+    // `protected` can't be pushed to by anyone, so the pre-receive hook has something to reject
+    async fn push_policy(&self) -> Result<PushPolicy> {
+        Ok(PushPolicy {
+            pusher: self.user.clone().unwrap_or_default(),
+            can_write: self.access.can_write(),
+            protections: vec![BranchProtection {
+                pattern: "protected".to_string(),
+                allow_force_push: false,
+                allow_deletion: false,
+                pusher_allowed: false,
+                require_linear_history: false,
+                required_status_checks: Vec::new(),
+                required_approvals: 0,
+                require_code_owner_review: false,
+                dismiss_stale_approvals: false,
+            }],
+        })
+    }
+
+    async fn finish_push(self: Box<Self>, completion: PushCompletion) -> Result<()> {
+        let updates = completion.wait().await?;
+        self.pushed.0.lock().unwrap().extend(updates);
+        Ok(())
+    }
+}
+
+/// A running test server with a fresh directory for its projects and the clones.
+struct Server {
+    url: String,
+    dir: PathBuf,
+    pushed: Pushed,
+}
+
+impl Server {
+    /// Starts a server with the projects `alice/public` and `alice/private`, which both
+    /// have a single commit on `main`.
+    fn start() -> Server {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!("zorgit-smart-http-{}-{}", std::process::id(), COUNT.fetch_add(1, Ordering::SeqCst)));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::env::set_var(hooks::HOOK_BINARY_ENV, env!("CARGO_BIN_EXE_zorgit-hook"));
+
+        let seed = dir.join("seed");
+        git(&dir, &["init", "-q", "-b", "main", seed.to_str().unwrap()]).success();
+        std::fs::write(seed.join("README.md"), "# Hello\n").unwrap();
+        git(&seed, &["add", "README.md"]).success();
+        git(&seed, &["commit", "-q", "-m", "Initial commit"]).success();
+        for project in &["public", "private"] {
+            let bare = dir.join("projects/alice").join(project);
+            git(&dir, &["clone", "-q", "--bare", seed.to_str().unwrap(), bare.to_str().unwrap()]).success();
+        }
+
+        // The port is free right now and very likely still is, once Rocket binds it
+        let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap().local_addr().unwrap().port();
+        let figment = rocket::Config::figment()
+            .merge(("address", Ipv4Addr::LOCALHOST.to_string()))
+            .merge(("port", port))
+            .merge(("log_level", "off"))
+            .merge(("limits.git_push", 1.mebibytes()));
+        let pushed = Pushed::default();
+        let gatekeeper: Arc<dyn Gatekeeper> = Arc::new(TestGatekeeper { dir: dir.join("projects"), pushed: pushed.clone() });
+        let rocket = rocket::custom(figment)
+            .manage(gatekeeper)
+            .mount("/", server::Server::routes());
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap();
+            let _ = runtime.block_on(rocket.launch());
+        });
+        for _ in 0..100 {
+            if TcpStream::connect((Ipv4Addr::LOCALHOST, port)).is_ok() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+
+        Server {
+            url: format!("127.0.0.1:{}", port),
+            dir,
+            pushed,
+        }
+    }
+
+    /// Url of the project, with the credentials of the user if given.
+    fn project_url(&self, project: &str, user: Option<&str>) -> String {
+        match user {
+            Some(user) => format!("http://{}:secret@{}/alice/{}", user, self.url, project),
+            None => format!("http://{}/alice/{}", self.url, project),
+        }
+    }
+
+    fn clone(&self, project: &str, user: Option<&str>, name: &str, args: &[&str]) -> (PathBuf, Output) {
+        let target = self.dir.join(name);
+        let url = self.project_url(project, user);
+        let mut all_args = vec!["clone", "-q"];
+        all_args.extend_from_slice(args);
+        all_args.extend_from_slice(&[url.as_str(), target.to_str().unwrap()]);
+        let output = git(&self.dir, &all_args).0;
+        (target, output)
+    }
+
+    /// Id of the commit the branch points to on the server.
+    fn branch(&self, project: &str, branch: &str) -> Option<String> {
+        let bare = self.dir.join("projects/alice").join(project);
+        let output = git(&bare, &["rev-parse", "--verify", "-q", &format!("refs/heads/{}", branch)]).0;
+        match output.status.success() {
+            true => Some(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+            false => None,
+        }
+    }
+}
+
+impl Server {
+    /// Waits until the server has processed the given number of updated references.
+    fn pushed(&self, count: usize) -> Vec<RefUpdate> {
+        for _ in 0..100 {
+            let pushed = self.pushed.0.lock().unwrap().clone();
+            if pushed.len() >= count {
+                return pushed;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        self.pushed.0.lock().unwrap().clone()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// The result of a git command, which can be asserted to have succeeded or failed.
+struct Git(Output);
+
+impl Git {
+    fn success(self) -> Output {
+        assert!(self.0.status.success(), "git failed: {}", String::from_utf8_lossy(&self.0.stderr));
+        self.0
+    }
+
+    fn failure(self) -> Output {
+        assert!(!self.0.status.success(), "git should have failed");
+        self.0
+    }
+}
+
+/// Runs git without any user or system config and without asking for credentials.
+fn git(dir: &Path, args: &[&str]) -> Git {
+    let output = Command::new("git")
+        .args(&["-c", "user.name=Zorgit Test", "-c", "user.email=test@example.com", "-c", "init.defaultBranch=main"])
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("HOME", dir)
+        .output()
+        .unwrap();
+    Git(output)
+}
+
+/// Adds a commit with a file of the given size to the clone and returns its id.
+fn commit(clone: &Path, file: &str, size: usize) -> String {
+    // Random bytes don't compress, so the pack is about as large as the file
+    let content = (0..size).map(|i| (i as u64).wrapping_mul(6364136223846793005).rotate_left(17) as u8).collect::<Vec<_>>();
+    std::fs::write(clone.join(file), content).unwrap();
+    git(clone, &["add", file]).success();
+    git(clone, &["commit", "-q", "-m", &format!("Add {}", file)]).success();
+    let output = git(clone, &["rev-parse", "HEAD"]).success();
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+#[test]
+fn clone_public_project_without_login() {
+    let server = Server::start();
+    let (clone, _) = server.clone("public", None, "clone", &[]);
+    assert!(clone.join("README.md").exists());
+}
+
+#[test]
+fn clone_with_protocol_v2() {
+    let server = Server::start();
+    let (clone, output) = server.clone("public", None, "clone", &["-c", "protocol.version=2"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(clone.join("README.md").exists());
+}
+
+#[test]
+fn clone_private_project_needs_login() {
+    let server = Server::start();
+    let (_, output) = server.clone("private", None, "anonymous", &[]);
+    assert!(!output.status.success());
+    let (_, output) = server.clone("private", Some("wrong"), "wrong", &[]);
+    assert!(!output.status.success());
+
+    let (clone, output) = server.clone("private", Some("alice"), "alice", &[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(clone.join("README.md").exists());
+}
+
+#[test]
+fn push_needs_login() {
+    let server = Server::start();
+    let (clone, _) = server.clone("public", None, "clone", &[]);
+    let before = server.branch("public", "main");
+    commit(&clone, "anonymous.txt", 10);
+
+    git(&clone, &["push", "-q", "origin", "main"]).failure();
+    assert_eq!(server.branch("public", "main"), before);
+}
+
+#[test]
+fn push_needs_write_access() {
+    let server = Server::start();
+    let (clone, _) = server.clone("public", Some("mallory"), "clone", &[]);
+    let before = server.branch("public", "main");
+    commit(&clone, "mallory.txt", 10);
+
+    // Refused when git asks for the references, before it uploads anything
+    let output = git(&clone, &["push", "-q", "origin", "main"]).failure();
+    assert!(String::from_utf8_lossy(&output.stderr).contains("403"));
+    assert_eq!(server.branch("public", "main"), before);
+}
+
+#[test]
+fn push_with_login() {
+    let server = Server::start();
+    let (clone, _) = server.clone("public", Some("alice"), "clone", &[]);
+    let before = server.branch("public", "main");
+    let pushed = commit(&clone, "alice.txt", 10);
+
+    git(&clone, &["push", "-q", "origin", "main"]).success();
+    assert_eq!(server.branch("public", "main").as_deref(), Some(pushed.as_str()));
+
+    git(&clone, &["push", "-q", "origin", "main:feature"]).success();
+    assert_eq!(server.branch("public", "feature").as_deref(), Some(pushed.as_str()));
+
+    // Exactly what git updated is reported after each push
+    assert_eq!(server.pushed(2), vec![
+        RefUpdate { name: "refs/heads/main".to_string(), old_id: before, new_id: Some(pushed.clone()) },
+        RefUpdate { name: "refs/heads/feature".to_string(), old_id: None, new_id: Some(pushed) },
+    ]);
+}
+
+#[test]
+fn push_rejected_by_pre_receive_hook() {
+    let server = Server::start();
+    let (clone, _) = server.clone("public", Some("alice"), "clone", &[]);
+    commit(&clone, "protected.txt", 10);
+
+    let output = git(&clone, &["push", "origin", "main:protected"]).failure();
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not allowed to push to the protected branch 'protected'"));
+    assert_eq!(server.branch("public", "protected"), None);
+}
+
+#[test]
+fn push_over_limit() {
+    let server = Server::start();
+    let (clone, _) = server.clone("public", Some("alice"), "clone", &[]);
+    let before = server.branch("public", "main");
+    commit(&clone, "large.bin", 2 * 1024 * 1024);
+
+    git(&clone, &["push", "-q", "origin", "main"]).failure();
+    assert_eq!(server.branch("public", "main"), before);
+}
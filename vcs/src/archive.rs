@@ -0,0 +1,50 @@
+/// Formats in which a snapshot of the repository can be downloaded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    TarGz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::Zip => "zip",
+        }
+    }
+
+    /// Splits a file name like `v1.0.tar.gz` into the revision and the format.
+    pub fn split_file_name(file_name: &str) -> Option<(&str, ArchiveFormat)> {
+        [ArchiveFormat::TarGz, ArchiveFormat::Zip].iter()
+            .find_map(|format| file_name
+                .strip_suffix(format.extension())
+                .and_then(|rev| rev.strip_suffix('.'))
+                .filter(|rev| !rev.is_empty())
+                .map(|rev| (rev, *format)))
+    }
+
+    /// The name of the archive file with the given prefix, i.e. `zorgit-v1.0.tar.gz`.
+    pub fn file_name(&self, prefix: &str) -> String {
+        format!("{}.{}", prefix.trim_end_matches('/'), self.extension())
+    }
+
+    /// The folder all files of the archive are in, i.e. `zorgit-v1.0/`.
+    pub fn prefix(project_name: &str, rev: &str) -> String {
+        format!("{}-{}/", project_name, rev.replace('/', "-"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_archive_files() {
+        let prefix = ArchiveFormat::prefix("zorgit", "feature/login");
+        assert_eq!(prefix, "zorgit-feature-login/");
+        assert_eq!(ArchiveFormat::TarGz.file_name(&prefix), "zorgit-feature-login.tar.gz");
+        assert_eq!(ArchiveFormat::Zip.file_name(&prefix), "zorgit-feature-login.zip");
+        assert_eq!(ArchiveFormat::split_file_name("v1.0.tar.gz"), Some(("v1.0", ArchiveFormat::TarGz)));
+        assert_eq!(ArchiveFormat::split_file_name("v1.0zip"), None);
+    }
+}
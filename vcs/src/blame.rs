@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+use crate::{Commit, SourceLine};
+
+/// Who last changed which lines of a file at a revision.
+#[derive(Debug, Clone)]
+pub struct Blame {
+    pub path: PathBuf,
+    /// Id of the commit the file was blamed at.
+    pub commit_id: String,
+    /// Consecutive lines that were changed by the same commit, in order of the file.
+    pub hunks: Vec<BlameHunk>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BlameHunk {
+    /// The commit that last changed these lines.
+    pub commit: Commit,
+    /// Path of the file in that commit. It differs from the blamed path, if the file got renamed since.
+    pub path: PathBuf,
+    /// Plain lines, where `new_num` is the line in the blamed file and `old_num`
+    /// is the line in the file of `commit`.
+    pub lines: Vec<SourceLine>,
+    /// Where to continue blaming to see the lines before this change.
+    /// `None` if the file was added by `commit`.
+    pub prior: Option<BlamePrior>,
+}
+
+impl BlameHunk {
+    /// Line in the blamed file this hunk starts at.
+    pub fn start_line(&self) -> u32 {
+        self.lines.first().and_then(|l| l.new_num).unwrap_or_default()
+    }
+}
+
+/// A revision and path that show a file as it was right before a change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlamePrior {
+    pub commit_id: String,
+    pub path: PathBuf,
+}
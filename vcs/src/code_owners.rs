@@ -0,0 +1,197 @@
+use crate::{Result, VersionControl};
+
+
+/// Places where a `CODEOWNERS` file is looked for, in this order.
+pub const CODE_OWNERS_PATHS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CodeOwner {
+    /// `@username`
+    User(String),
+    /// `@organisation/team`
+    Team { organisation: String, name: String },
+    /// `user@example.com`
+    Email(String),
+}
+
+impl CodeOwner {
+    fn parse(token: &str) -> Option<CodeOwner> {
+        match token.strip_prefix('@') {
+            Some(name) => {
+                let mut parts = name.splitn(2, '/');
+                match (parts.next(), parts.next()) {
+                    (Some(organisation), Some(team)) if !organisation.is_empty() && !team.is_empty() => Some(CodeOwner::Team {
+                        organisation: organisation.to_string(),
+                        name: team.to_string(),
+                    }),
+                    (Some(user), None) if !user.is_empty() => Some(CodeOwner::User(user.to_string())),
+                    _ => None,
+                }
+            }
+            None if token.contains('@') => Some(CodeOwner::Email(token.to_string())),
+            None => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CodeOwnersRule {
+    pub pattern: String,
+    /// A rule without owners takes away the owners of earlier rules.
+    pub owners: Vec<CodeOwner>,
+}
+
+impl CodeOwnersRule {
+    /// Matches like GitHub does: patterns with a slash are relative to the root, other patterns
+    /// match at any depth. A pattern that matches a folder matches everything inside of it,
+    /// except for patterns like `docs/*`, which only match the files directly in the folder.
+    pub fn matches(&self, path: &str) -> bool {
+        let trimmed = self.pattern.trim_end_matches('/');
+        let is_folder = self.pattern.ends_with('/');
+        let includes_nested = !trimmed.ends_with("/*");
+        let pattern = match trimmed.strip_prefix('/') {
+            Some(anchored) => anchored.to_string(),
+            None if trimmed.contains('/') => trimmed.to_string(),
+            None => format!("**/{}", trimmed),
+        };
+
+        let segments = path.trim_start_matches('/').split('/').collect::<Vec<_>>();
+        (1..=segments.len()).any(|n| {
+            let is_file = n == segments.len();
+            if (is_file && is_folder) || (!is_file && !includes_nested) {
+                return false;
+            }
+            glob(pattern.as_bytes(), segments[..n].join("/").as_bytes())
+        })
+    }
+}
+
+/// The rules of a `CODEOWNERS` file. The last matching rule decides about the owners of a path.
+#[derive(Debug, Clone, Default)]
+pub struct CodeOwners {
+    pub rules: Vec<CodeOwnersRule>,
+}
+
+impl CodeOwners {
+    pub fn parse(content: &str) -> CodeOwners {
+        let rules = content.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .flat_map(|line| {
+                let line = line.split(" #").next().unwrap_or(line);
+                let mut tokens = line.split_whitespace();
+                tokens.next().map(|pattern| CodeOwnersRule {
+                    pattern: pattern.to_string(),
+                    owners: tokens.flat_map(CodeOwner::parse).collect(),
+                })
+            })
+            .collect();
+        CodeOwners { rules }
+    }
+
+    /// Reads the first `CODEOWNERS` file that exists at the given revision.
+    pub fn load<V: VersionControl>(repo: &V, rev: &str) -> Result<Option<CodeOwners>> {
+        for path in CODE_OWNERS_PATHS.iter() {
+            if let Some(content) = repo.raw_branch_entry_by_path(rev, path)? {
+                return Ok(Some(CodeOwners::parse(&String::from_utf8_lossy(&content))));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn owners_of(&self, path: &str) -> &[CodeOwner] {
+        self.rules.iter()
+            .rev()
+            .find(|rule| rule.matches(path))
+            .map(|rule| rule.owners.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+/// `*` and `?` don't match `/`, while `**` matches across folders. `**/` also matches no folder at all.
+fn glob(pattern: &[u8], value: &[u8]) -> bool {
+    // matches[i][j] tells whether `pattern[i..]` matches `value[j..]`. Filling it from the end
+    // keeps patterns with several `**` linear in the length of the pattern times the value.
+    let mut matches = vec![vec![false; value.len() + 1]; pattern.len() + 1];
+    matches[pattern.len()][value.len()] = true;
+    for i in (0..pattern.len()).rev() {
+        for j in (0..=value.len()).rev() {
+            let next = value.get(j);
+            matches[i][j] = match pattern[i] {
+                b'*' if pattern.get(i + 1) == Some(&b'*') => {
+                    (pattern.get(i + 2) == Some(&b'/') && matches[i + 3][j])
+                        || matches[i + 2][j]
+                        || (next.is_some() && matches[i][j + 1])
+                }
+                b'*' => matches[i + 1][j] || (next.map_or(false, |c| *c != b'/') && matches[i][j + 1]),
+                b'?' => next.map_or(false, |c| *c != b'/') && matches[i + 1][j + 1],
+                c => next == Some(&c) && matches[i + 1][j + 1],
+            };
+        }
+    }
+    matches[0][0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(name: &str) -> CodeOwner {
+        CodeOwner::User(name.to_string())
+    }
+
+    #[test]
+    fn parses_rules_and_owners() {
+        let code_owners = CodeOwners::parse("# Owners\n\n*.rs @alice @acme/core # Rust\n/docs/ docs@example.com\nvendor/\n");
+        assert_eq!(code_owners.rules.len(), 3);
+        assert_eq!(code_owners.rules[0].pattern, "*.rs");
+        assert_eq!(code_owners.rules[0].owners, vec![
+            user("alice"),
+            CodeOwner::Team { organisation: "acme".to_string(), name: "core".to_string() },
+        ]);
+        assert_eq!(code_owners.rules[1].owners, vec![CodeOwner::Email("docs@example.com".to_string())]);
+        assert!(code_owners.rules[2].owners.is_empty());
+        assert_eq!(CodeOwner::parse("@"), None);
+        assert_eq!(CodeOwner::parse("@acme/"), None);
+    }
+
+    #[test]
+    fn last_matching_rule_wins() {
+        let code_owners = CodeOwners::parse("* @alice\n*.rs @bob\n/vendor/ \n");
+        assert_eq!(code_owners.owners_of("README.md"), &[user("alice")]);
+        assert_eq!(code_owners.owners_of("src/main.rs"), &[user("bob")]);
+        assert!(code_owners.owners_of("vendor/lib.rs").is_empty());
+    }
+
+    #[test]
+    fn matches_like_github() {
+        let rule = |pattern: &str| CodeOwnersRule { pattern: pattern.to_string(), owners: Vec::new() };
+        assert!(rule("*.js").matches("web/app/index.js"));
+        assert!(rule("docs/").matches("src/docs/guide.md"));
+        assert!(!rule("docs/").matches("docs"));
+        assert!(rule("/build/logs/").matches("build/logs/2021/today.log"));
+        assert!(!rule("/build/logs/").matches("src/build/logs/today.log"));
+        assert!(rule("docs/*").matches("docs/getting-started.md"));
+        assert!(!rule("docs/*").matches("docs/build-app/troubleshooting.md"));
+        assert!(rule("apps/**/tests").matches("apps/web/tests/login.rs"));
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob(b"**/*.rs", b"main.rs"));
+        assert!(glob(b"**/*.rs", b"src/git/mod.rs"));
+        assert!(glob(b"src/**/mod.rs", b"src/mod.rs"));
+        assert!(!glob(b"*.rs", b"src/main.rs"));
+        assert!(glob(b"?.md", b"a.md"));
+        assert!(!glob(b"?", b"/"));
+        assert!(!glob(b"src/*", b"src"));
+    }
+
+    #[test]
+    fn globs_with_many_double_stars_quickly() {
+        let pattern = "**/".repeat(30) + "x";
+        let value = "a/".repeat(200) + "b";
+        assert!(!glob(pattern.as_bytes(), value.as_bytes()));
+        assert!(glob(pattern.as_bytes(), (value + "/x").as_bytes()));
+    }
+}
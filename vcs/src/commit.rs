@@ -17,3 +17,13 @@ pub struct Commit {
     pub committer: Option<Signature>,
     pub parents_id: Option<Vec<String>>,
 }
+
+impl Commit {
+    /// The full commit message, the title and the description separated by an empty line.
+    pub fn message(&self) -> String {
+        match &self.description {
+            Some(description) => format!("{}\n\n{}", self.title.as_deref().unwrap_or_default(), description),
+            None => self.title.clone().unwrap_or_default(),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use zorgit_common::Project;
+use zorgit_db::{CommitStatus as StoredStatus, Database};
+use crate::Result;
+
+
+/// State a context reported for a commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitState {
+    Pending,
+    Success,
+    Failure,
+    Error,
+}
+
+impl CommitState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommitState::Pending => "pending",
+            CommitState::Success => "success",
+            CommitState::Failure => "failure",
+            CommitState::Error => "error",
+        }
+    }
+
+    /// Combines the states of all contexts into one. Any failure or error makes the whole
+    /// commit fail, any pending context keeps it pending, otherwise it is a success.
+    pub fn combine<I: IntoIterator<Item = CommitState>>(states: I) -> CommitState {
+        let mut combined = CommitState::Success;
+        for state in states {
+            match state {
+                CommitState::Failure | CommitState::Error => return CommitState::Failure,
+                CommitState::Pending => combined = CommitState::Pending,
+                CommitState::Success => (),
+            }
+        }
+        combined
+    }
+}
+
+impl FromStr for CommitState {
+    type Err = String;
+
+    fn from_str(state: &str) -> std::result::Result<Self, Self::Err> {
+        match state {
+            "pending" => Ok(CommitState::Pending),
+            "success" => Ok(CommitState::Success),
+            "failure" => Ok(CommitState::Failure),
+            "error" => Ok(CommitState::Error),
+            state => Err(format!("Unknown commit state: {}", state)),
+        }
+    }
+}
+
+impl std::fmt::Display for CommitState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The status of a single context, i.e. `ci/build`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextStatus {
+    pub context: String,
+    pub state: CommitState,
+    pub target_url: Option<String>,
+    pub description: Option<String>,
+    pub updated_at: i64,
+}
+
+impl ContextStatus {
+    fn from_stored(status: &StoredStatus) -> Result<ContextStatus> {
+        Ok(ContextStatus {
+            context: status.context.clone(),
+            state: status.state.parse()?,
+            target_url: status.target_url.clone(),
+            description: status.description.clone(),
+            updated_at: status.updated_at.unix_timestamp(),
+        })
+    }
+}
+
+/// All statuses of a commit, together with the state they add up to.
+#[derive(Debug, Clone, Serialize)]
+pub struct CombinedStatus {
+    pub commit_id: String,
+    pub state: CommitState,
+    pub statuses: Vec<ContextStatus>,
+}
+
+impl CombinedStatus {
+    fn from_stored(commit_id: String, stored: &[StoredStatus]) -> Result<CombinedStatus> {
+        let statuses = stored.iter()
+            .map(ContextStatus::from_stored)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(CombinedStatus {
+            commit_id,
+            state: CommitState::combine(statuses.iter().map(|s| s.state)),
+            statuses,
+        })
+    }
+
+    /// Loads the status of a commit, i.e. the head of a branch or the newer side of a diff.
+    /// Returns `None` if no context reported anything for the commit yet.
+    pub async fn load(db: &Database, project: &Project, commit_id: &str) -> Result<Option<CombinedStatus>> {
+        let project_id = project.id.to_uuid()?;
+        let stored = db.commit_statuses.for_commit(&project_id, commit_id).await?;
+        if stored.is_empty() {
+            return Ok(None);
+        }
+        CombinedStatus::from_stored(commit_id.to_string(), &stored).map(Some)
+    }
+
+    /// Loads the statuses of a list of commits with one query. Commits without any status are
+    /// missing in the returned map.
+    pub async fn load_many(db: &Database, project: &Project, commit_ids: &[String]) -> Result<HashMap<String, CombinedStatus>> {
+        let project_id = project.id.to_uuid()?;
+        let stored = db.commit_statuses.for_commits(&project_id, commit_ids).await?;
+
+        let mut by_commit: HashMap<String, Vec<StoredStatus>> = HashMap::new();
+        for status in stored {
+            by_commit.entry(status.commit_id.clone()).or_default().push(status);
+        }
+        by_commit.into_iter()
+            .map(|(commit_id, stored)| CombinedStatus::from_stored(commit_id.clone(), &stored).map(|s| (commit_id, s)))
+            .collect()
+    }
+}
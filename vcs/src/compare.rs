@@ -0,0 +1,56 @@
+use crate::{Commit, Diff};
+
+/// Which changes a comparison shows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareMode {
+    /// `base...head`: the changes of head since it split from base, like a pull request shows them.
+    ThreeDot,
+    /// `base..head`: the direct difference between both trees.
+    TwoDot,
+}
+
+impl CompareMode {
+    /// Splits a spec like `main...feature` or `main..feature` into base, head and mode.
+    pub fn split_spec(spec: &str) -> Option<(&str, &str, CompareMode)> {
+        let (base, head, mode) = match spec.find("...") {
+            Some(i) => (&spec[..i], &spec[i + 3..], CompareMode::ThreeDot),
+            None => {
+                let i = spec.find("..")?;
+                (&spec[..i], &spec[i + 2..], CompareMode::TwoDot)
+            }
+        };
+        if base.is_empty() || head.is_empty() {
+            return None;
+        }
+        Some((base, head, mode))
+    }
+}
+
+pub struct Comparison {
+    pub base_id: String,
+    pub head_id: String,
+    /// `None` if both sides have no common history.
+    pub merge_base_id: Option<String>,
+    /// Number of commits in head, that aren't in base.
+    pub ahead_by: usize,
+    /// Number of commits in base, that aren't in head.
+    pub behind_by: usize,
+    /// The newest commits ahead of base, at most as many as requested.
+    pub commits: Vec<Commit>,
+    pub diff: Diff,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_specs() {
+        assert_eq!(CompareMode::split_spec("main...feature"), Some(("main", "feature", CompareMode::ThreeDot)));
+        assert_eq!(CompareMode::split_spec("main..feature/login"), Some(("main", "feature/login", CompareMode::TwoDot)));
+        assert_eq!(CompareMode::split_spec("v1.0...v2.0"), Some(("v1.0", "v2.0", CompareMode::ThreeDot)));
+        assert_eq!(CompareMode::split_spec("main"), None);
+        assert_eq!(CompareMode::split_spec("...feature"), None);
+        assert_eq!(CompareMode::split_spec("main.."), None);
+    }
+}
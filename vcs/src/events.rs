@@ -0,0 +1,326 @@
+use std::path::PathBuf;
+use rocket::fairing::AdHoc;
+use serde::Serialize;
+use tokio::sync::broadcast;
+use zorgit_common::Project;
+use zorgit_db::{Issue, IssueComment, ProjectSummary, PullRequest, Review};
+use crate::RefUpdate;
+
+/// How many events a slow subscriber can lag behind, before it starts to miss events.
+const CAPACITY: usize = 1024;
+
+/// Something that happened to a project, which other subsystems might want to react to.
+#[derive(Debug, Clone)]
+pub enum Event {
+    Push(PushEvent),
+    Project(ProjectEvent),
+    PullRequest(PullRequestEvent),
+    PullRequestReview(PullRequestReviewEvent),
+    Issue(IssueEvent),
+    IssueComment(IssueCommentEvent),
+}
+
+impl Event {
+    pub fn project(&self) -> &ProjectInfo {
+        match self {
+            Event::Push(event) => &event.project,
+            Event::Project(event) => &event.project,
+            Event::PullRequest(event) => &event.project,
+            Event::PullRequestReview(event) => &event.project,
+            Event::Issue(event) => &event.project,
+            Event::IssueComment(event) => &event.project,
+        }
+    }
+}
+
+/// The parts of a project that subscribers need, without having to ask the database.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectInfo {
+    pub id: String,
+    pub owner_id: String,
+    pub owner: String,
+    pub owner_is_organisation: bool,
+    pub name: String,
+    pub description: Option<String>,
+    pub is_private: bool,
+    pub default_branch: Option<String>,
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+impl From<&Project> for ProjectInfo {
+    fn from(project: &Project) -> Self {
+        ProjectInfo {
+            id: project.id.to_string(),
+            owner_id: project.owner.id().to_string(),
+            owner: project.owner.name().to_string(),
+            owner_is_organisation: project.owner.is_organisation(),
+            name: project.name.clone(),
+            description: project.description.clone(),
+            is_private: project.is_private,
+            default_branch: project.default_branch.clone(),
+            dir: project.dir.clone(),
+        }
+    }
+}
+
+impl ProjectInfo {
+    /// For projects that are not at hand as `Project`, i.e. the other side of a pull request from a fork.
+    pub fn from_summary(project: &ProjectSummary, dir: PathBuf) -> ProjectInfo {
+        ProjectInfo {
+            id: project.id.to_string(),
+            owner_id: project.owner_id.to_string(),
+            owner: project.owner.clone(),
+            owner_is_organisation: project.owner_is_organisation,
+            name: project.name.clone(),
+            description: project.description.clone(),
+            is_private: project.is_private,
+            default_branch: project.default_branch.clone(),
+            dir,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PushEvent {
+    pub project: ProjectInfo,
+    /// Username of the user who pushed.
+    pub pusher: String,
+    pub pusher_email: String,
+    pub updates: Vec<RefUpdate>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectAction {
+    /// The settings of the project changed, i.e. its default branch.
+    Edited,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectEvent {
+    pub project: ProjectInfo,
+    pub action: ProjectAction,
+    /// Username of the user who triggered the event.
+    pub sender: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PullRequestAction {
+    Opened,
+    Edited,
+    Closed,
+    Reopened,
+    /// The head branch got new commits.
+    Synchronize,
+}
+
+/// The parts of a pull request that subscribers need.
+#[derive(Debug, Clone, Serialize)]
+pub struct PullRequestInfo {
+    pub id: String,
+    pub number: i64,
+    pub title: String,
+    pub description: Option<String>,
+    pub is_draft: bool,
+    pub state: String,
+    pub head_project_id: String,
+    pub head_branch: String,
+    pub head_commit_id: String,
+    pub base_branch: String,
+    pub base_commit_id: String,
+    pub merge_base_id: Option<String>,
+    pub merge_commit_id: Option<String>,
+}
+
+impl From<&PullRequest> for PullRequestInfo {
+    fn from(pull: &PullRequest) -> Self {
+        PullRequestInfo {
+            id: pull.id.to_string(),
+            number: pull.number,
+            title: pull.title.clone(),
+            description: pull.description.clone(),
+            is_draft: pull.is_draft,
+            state: pull.state.clone(),
+            head_project_id: pull.head_project_id.to_string(),
+            head_branch: pull.head_branch.clone(),
+            head_commit_id: pull.head_commit_id.clone(),
+            base_branch: pull.base_branch.clone(),
+            base_commit_id: pull.base_commit_id.clone(),
+            merge_base_id: pull.merge_base_id.clone(),
+            merge_commit_id: pull.merge_commit_id.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PullRequestEvent {
+    /// The project the pull request wants to be merged into.
+    pub project: ProjectInfo,
+    pub action: PullRequestAction,
+    pub pull_request: PullRequestInfo,
+    /// Username of the user who triggered the event.
+    pub sender: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PullRequestReviewAction {
+    Submitted,
+}
+
+/// The parts of a review that subscribers need.
+#[derive(Debug, Clone, Serialize)]
+pub struct PullRequestReviewInfo {
+    pub id: String,
+    pub author_id: String,
+    /// One of `approved`, `changes_requested` or `commented`.
+    pub state: String,
+    pub body: Option<String>,
+    pub commit_id: String,
+}
+
+impl From<&Review> for PullRequestReviewInfo {
+    fn from(review: &Review) -> Self {
+        PullRequestReviewInfo {
+            id: review.id.to_string(),
+            author_id: review.author_id.to_string(),
+            state: review.state.clone(),
+            body: review.body.clone(),
+            commit_id: review.commit_id.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PullRequestReviewEvent {
+    pub project: ProjectInfo,
+    pub action: PullRequestReviewAction,
+    pub pull_request: PullRequestInfo,
+    pub review: PullRequestReviewInfo,
+    /// Username of the user who triggered the event.
+    pub sender: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueAction {
+    Opened,
+    Edited,
+    Closed,
+    Reopened,
+    Labeled,
+    Unlabeled,
+    Assigned,
+    Unassigned,
+}
+
+/// The parts of an issue that subscribers need.
+#[derive(Debug, Clone, Serialize)]
+pub struct IssueInfo {
+    pub id: String,
+    pub number: i64,
+    pub title: String,
+    pub description: Option<String>,
+    pub state: String,
+    pub author_id: String,
+    pub milestone_id: Option<String>,
+}
+
+impl From<&Issue> for IssueInfo {
+    fn from(issue: &Issue) -> Self {
+        IssueInfo {
+            id: issue.id.to_string(),
+            number: issue.number,
+            title: issue.title.clone(),
+            description: issue.description.clone(),
+            state: issue.state.clone(),
+            author_id: issue.author_id.to_string(),
+            milestone_id: issue.milestone_id.map(|id| id.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssueEvent {
+    pub project: ProjectInfo,
+    pub action: IssueAction,
+    pub issue: IssueInfo,
+    /// Name of the label that was added or removed, for `labeled` and `unlabeled`.
+    pub label: Option<String>,
+    /// Username of the user who was assigned or unassigned, for `assigned` and `unassigned`.
+    pub assignee: Option<String>,
+    /// Username of the user who triggered the event.
+    pub sender: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueCommentAction {
+    Created,
+    Edited,
+    Deleted,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssueCommentInfo {
+    pub id: String,
+    pub author_id: String,
+    pub body: String,
+}
+
+impl From<&IssueComment> for IssueCommentInfo {
+    fn from(comment: &IssueComment) -> Self {
+        IssueCommentInfo {
+            id: comment.id.to_string(),
+            author_id: comment.author_id.to_string(),
+            body: comment.body.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssueCommentEvent {
+    pub project: ProjectInfo,
+    pub action: IssueCommentAction,
+    pub issue: IssueInfo,
+    pub comment: IssueCommentInfo,
+    /// Username of the user who triggered the event.
+    pub sender: String,
+}
+
+/// Broadcasts events to every subscriber. It is managed by Rocket, so routes can get it as `State`.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        EventBus { sender }
+    }
+
+    pub fn fairing() -> AdHoc {
+        AdHoc::on_attach("Event Bus", |rocket| async {
+            Ok(rocket.manage(EventBus::new()))
+        })
+    }
+
+    /// Sends the event to all current subscribers. Having no subscribers is not an error.
+    pub fn emit(&self, event: Event) {
+        let _ = self.sender.send(event);
+    }
+
+    /// Every subscriber receives all events that are emitted after it subscribed.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        EventBus::new()
+    }
+}
@@ -0,0 +1,42 @@
+use std::path::Path;
+use std::process::Stdio;
+use tokio::process::Command;
+use crate::{ArchiveFormat, Result};
+use crate::git::PackStream;
+
+
+fn archive_cmd<P: AsRef<Path>>(repo_path: P, commit_id: &str, format: ArchiveFormat, prefix: &str) -> Command {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_path)
+        .arg("archive")
+        .arg(format!("--format={}", format.extension()))
+        .arg(format!("--prefix={}", prefix))
+        .arg(commit_id)
+        .kill_on_drop(true);
+    cmd
+}
+
+/// Starts `git archive` for the commit and returns its output, while git is still writing it.
+pub fn archive<P: AsRef<Path>>(repo_path: P, commit_id: &str, format: ArchiveFormat, prefix: &str) -> Result<PackStream> {
+    let child = archive_cmd(repo_path, commit_id, format, prefix)
+        .stdout(Stdio::piped())
+        .spawn()?;
+    PackStream::new(child, None)
+}
+
+/// Writes the archive of the commit to a file.
+pub async fn write_archive<P: AsRef<Path>, O: AsRef<Path>>(repo_path: P, commit_id: &str, format: ArchiveFormat, prefix: &str, output: O) -> Result<()> {
+    let status = archive_cmd(repo_path, commit_id, format, prefix)
+        .arg("--output")
+        .arg(output.as_ref())
+        .stdout(Stdio::null())
+        .status()
+        .await?;
+
+    if status.success() {
+        Ok(())
+    }
+    else {
+        Err(format!("git archive exited with {}", status))?
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+use anyhow::anyhow;
+use git2::{BlameOptions, Delta, DiffFindOptions, Oid};
+use crate::{Blame, BlameHunk, BlamePrior, Commit, Result, SourceLine};
+
+
+/// Blames the file at the given commit. Returns `None` if the file doesn't exist at that commit.
+pub fn blame(repo: &git2::Repository, commit: &git2::Commit<'_>, path: &Path) -> Result<Option<Blame>> {
+    let entry = match commit.tree()?.get_path(path) {
+        Ok(entry) => entry,
+        Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(None),
+        Err(err) => Err(err)?,
+    };
+    let blob = repo.find_blob(entry.id())?;
+    if blob.is_binary() {
+        return Err(anyhow!("Binary files can't be blamed: {}", path.display()))?;
+    }
+    let content = String::from_utf8_lossy(blob.content());
+    let lines = content.lines().collect::<Vec<_>>();
+
+    let mut opts = BlameOptions::new();
+    opts.newest_commit(commit.id());
+    let blame = repo.blame_file(path, Some(&mut opts))?;
+
+    let mut commits: HashMap<Oid, (Commit, Option<BlamePrior>)> = HashMap::new();
+    let mut hunks = Vec::with_capacity(blame.len());
+    for hunk in blame.iter() {
+        let commit_id = hunk.final_commit_id();
+        let hunk_path = hunk.path().map(Path::to_path_buf).unwrap_or_else(|| path.to_path_buf());
+        if !commits.contains_key(&commit_id) {
+            let origin = repo.find_commit(commit_id)?;
+            let prior = prior(repo, &origin, &hunk_path)?;
+            commits.insert(commit_id, (Commit::try_from(origin)?, prior));
+        }
+        let (hunk_commit, prior) = commits[&commit_id].clone();
+
+        let start = hunk.final_start_line();
+        let orig_start = hunk.orig_start_line();
+        let hunk_lines = (0..hunk.lines_in_hunk())
+            .map(|i| SourceLine::new_plain(
+                Some((orig_start + i) as u32),
+                Some((start + i) as u32),
+                char::default(),
+                lines.get(start + i - 1).copied(),
+            ))
+            .collect();
+
+        hunks.push(BlameHunk {
+            commit: hunk_commit,
+            path: hunk_path,
+            lines: hunk_lines,
+            prior,
+        });
+    }
+
+    Ok(Some(Blame {
+        path: path.to_path_buf(),
+        commit_id: commit.id().to_string(),
+        hunks,
+    }))
+}
+
+/// Finds the first parent of the commit and the path the file had there. The path differs,
+/// if the commit renamed the file. Returns `None` if the commit added the file.
+fn prior(repo: &git2::Repository, commit: &git2::Commit<'_>, path: &Path) -> Result<Option<BlamePrior>> {
+    let parent = match commit.parents().next() {
+        Some(parent) => parent,
+        None => return Ok(None),
+    };
+    let parent_tree = parent.tree()?;
+    if parent_tree.get_path(path).is_ok() {
+        return Ok(Some(BlamePrior {
+            commit_id: parent.id().to_string(),
+            path: path.to_path_buf(),
+        }));
+    }
+
+    let mut diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&commit.tree()?), None)?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true).copies(true)))?;
+    let old_path = diff.deltas()
+        .filter(|delta| matches!(delta.status(), Delta::Renamed | Delta::Copied))
+        .find(|delta| delta.new_file().path() == Some(path))
+        .and_then(|delta| delta.old_file().path().map(PathBuf::from));
+
+    Ok(old_path.map(|old_path| BlamePrior {
+        commit_id: parent.id().to_string(),
+        path: old_path,
+    }))
+}
@@ -62,3 +62,69 @@ pub async fn repo_size<P: AsRef<Path>>(repo_path: P) -> Result<usize> {
 
     Ok(size)
 }
+
+/// Writes the commit-graph file, which makes walking the history a lot faster for git itself.
+pub async fn write_commit_graph<P: AsRef<Path>>(repo_path: P) -> Result<()> {
+    let status = Command::new("git")
+        .current_dir(repo_path)
+        .arg("commit-graph")
+        .arg("write")
+        .arg("--reachable")
+        .status()
+        .await?;
+
+    if status.success() {
+        Ok(())
+    }
+    else {
+        Err(format!("git commit-graph exited with {}", status))?
+    }
+}
+
+/// Fetches `source_ref` of the repository at `source_path` into `target_ref` of this repository.
+/// The target is overwritten, even if the update is not a fast-forward.
+pub async fn fetch_ref<P: AsRef<Path>, S: AsRef<Path>>(repo_path: P, source_path: S, source_ref: &str, target_ref: &str) -> Result<()> {
+    let status = Command::new("git")
+        .current_dir(repo_path)
+        .arg("fetch")
+        .arg("--quiet")
+        .arg("--no-tags")
+        .arg(source_path.as_ref())
+        .arg(format!("+{}:{}", source_ref, target_ref))
+        .status()
+        .await?;
+
+    if status.success() {
+        Ok(())
+    }
+    else {
+        Err(format!("git fetch exited with {}", status))?
+    }
+}
+
+/// Points `target_ref` to the commit `source_ref` points to and removes `source_ref`.
+pub async fn move_ref<P: AsRef<Path>>(repo_path: P, source_ref: &str, target_ref: &str) -> Result<()> {
+    update_ref(repo_path.as_ref(), &[target_ref, source_ref]).await?;
+    update_ref(repo_path.as_ref(), &["-d", source_ref]).await
+}
+
+/// Removes the reference, if it exists.
+pub async fn delete_ref<P: AsRef<Path>>(repo_path: P, name: &str) -> Result<()> {
+    update_ref(repo_path, &["-d", name]).await
+}
+
+async fn update_ref<P: AsRef<Path>>(repo_path: P, args: &[&str]) -> Result<()> {
+    let status = Command::new("git")
+        .current_dir(repo_path)
+        .arg("update-ref")
+        .args(args)
+        .status()
+        .await?;
+
+    if status.success() {
+        Ok(())
+    }
+    else {
+        Err(format!("git update-ref exited with {}", status))?
+    }
+}
@@ -0,0 +1,45 @@
+use std::convert::TryFrom;
+use anyhow::anyhow;
+use git2::Sort;
+use crate::{Commit, CompareMode, Comparison, Result};
+use crate::git::diff;
+
+
+pub fn compare(repo: &git2::Repository, base: &git2::Commit<'_>, head: &git2::Commit<'_>, mode: CompareMode, limit: usize) -> Result<Comparison> {
+    let merge_base = match repo.merge_base(base.id(), head.id()) {
+        Ok(id) => Some(repo.find_commit(id)?),
+        Err(err) if err.code() == git2::ErrorCode::NotFound => None,
+        Err(err) => Err(err)?,
+    };
+    let (ahead_by, behind_by) = repo.graph_ahead_behind(head.id(), base.id())?;
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    revwalk.push(head.id())?;
+    revwalk.hide(base.id())?;
+    let commits = revwalk
+        .flatten()
+        .take(limit)
+        .flat_map(|oid| repo.find_commit(oid))
+        .flat_map(Commit::try_from)
+        .collect::<Vec<_>>();
+
+    // The newer commit goes first into the diff
+    let diff = match mode {
+        CompareMode::ThreeDot => {
+            let merge_base = merge_base.as_ref().ok_or_else(|| anyhow!("{} and {} have no common history", base.id(), head.id()))?;
+            diff::_diff_from_to(repo, Some(head), Some(merge_base))?
+        }
+        CompareMode::TwoDot => diff::_diff_from_to(repo, Some(head), Some(base))?,
+    };
+
+    Ok(Comparison {
+        base_id: base.id().to_string(),
+        head_id: head.id().to_string(),
+        merge_base_id: merge_base.map(|c| c.id().to_string()),
+        ahead_by,
+        behind_by,
+        commits,
+        diff,
+    })
+}
@@ -0,0 +1,95 @@
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+use git2::{Delta, DiffFindOptions, DiffOptions, Oid, Sort};
+use crate::{Commit, PathCommit, PathHistory, Result};
+
+
+/// Walks the history of `start` and collects the commits that changed `path`, like
+/// `git log --follow -- <path>`. Renames are only followed for files, for folders the
+/// history ends where the folder got created. Merges are only listed, if the path
+/// differs from every parent, so changes that came in from a branch aren't listed twice.
+pub fn path_history(repo: &git2::Repository, start: &git2::Commit<'_>, path: &Path, skip: usize, limit: usize) -> Result<PathHistory> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    revwalk.push(start.id())?;
+
+    let mut current_path = path.to_path_buf();
+    let mut matched = 0;
+    let mut commits = Vec::new();
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        let id = match entry_id(&commit, &current_path) {
+            Some(id) => id,
+            None => continue,
+        };
+
+        let mut renamed_from = None;
+        let changed = if commit.parent_count() == 0 {
+            true
+        }
+        else {
+            let mut differs_from_all = true;
+            for parent in commit.parents() {
+                if entry_id(&parent, &current_path) == Some(id) {
+                    differs_from_all = false;
+                    break;
+                }
+            }
+            if differs_from_all && commit.parent_count() == 1 {
+                let parent = commit.parent(0)?;
+                if entry_id(&parent, &current_path).is_none() {
+                    renamed_from = rename_source(repo, &parent, &commit, &current_path)?;
+                }
+            }
+            differs_from_all
+        };
+        if !changed {
+            continue;
+        }
+
+        if matched >= skip {
+            if commits.len() == limit {
+                return Ok(PathHistory { commits, has_more: true });
+            }
+            commits.push(PathCommit {
+                commit: Commit::try_from(commit)?,
+                path: current_path.clone(),
+            });
+        }
+        matched += 1;
+
+        if let Some(renamed_from) = renamed_from {
+            current_path = renamed_from;
+        }
+    }
+
+    Ok(PathHistory { commits, has_more: false })
+}
+
+fn entry_id(commit: &git2::Commit<'_>, path: &Path) -> Option<Oid> {
+    if path.as_os_str().is_empty() {
+        return Some(commit.tree_id());
+    }
+    commit.tree().ok()?
+        .get_path(path).ok()
+        .map(|entry| entry.id())
+}
+
+/// Looks for the file that got renamed to `path` by the commit.
+fn rename_source(repo: &git2::Repository, parent: &git2::Commit<'_>, commit: &git2::Commit<'_>, path: &Path) -> Result<Option<PathBuf>> {
+    let tree = commit.tree()?;
+    if tree.get_path(path)?.kind() != Some(git2::ObjectType::Blob) {
+        return Ok(None);
+    }
+
+    let mut opts = DiffOptions::new();
+    opts.skip_binary_check(true);
+    let mut diff = repo.diff_tree_to_tree(Some(&parent.tree()?), Some(&tree), Some(&mut opts))?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+    let source = diff.deltas()
+        .filter(|delta| delta.status() == Delta::Renamed)
+        .find(|delta| delta.new_file().path() == Some(path))
+        .and_then(|delta| delta.old_file().path().map(PathBuf::from));
+
+    Ok(source)
+}
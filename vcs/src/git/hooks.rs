@@ -0,0 +1,365 @@
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use git2::{Oid, Sort};
+use crate::{PushPolicy, RefUpdate, Result};
+
+/// Environment variable through which the push policy is handed to the hook process.
+pub const POLICY_ENV: &str = "ZORGIT_PUSH_POLICY";
+/// Environment variable with the file the `post-receive` hook writes the updated references to.
+pub const UPDATES_ENV: &str = "ZORGIT_PUSH_UPDATES";
+/// Name of the binary that runs the hooks. It is expected next to the zorgit binary.
+const HOOK_BINARY: &str = "zorgit-hook";
+/// Environment variable with the path of the hook binary, if it is not next to the zorgit binary.
+pub const HOOK_BINARY_ENV: &str = "ZORGIT_HOOK_BINARY";
+
+/// Installs the hooks zorgit needs into the given bare repository. Existing hooks
+/// with the same name are overwritten.
+pub fn install<P: AsRef<Path>>(repo_path: P) -> Result<()> {
+    let hook_binary = hook_binary()?;
+    let hooks_dir = repo_path.as_ref().join("hooks");
+    std::fs::create_dir_all(&hooks_dir)?;
+
+    for hook in &["pre-receive", "post-receive"] {
+        let script = format!("#!/bin/sh\n# Installed by Zorgit, changes will be overwritten.\nexec \"{}\" {}\n", hook_binary.display(), hook);
+        let hook_path = hooks_dir.join(hook);
+        if std::fs::read_to_string(&hook_path).ok().as_deref() == Some(&script) {
+            continue;
+        }
+        std::fs::write(&hook_path, script)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&hook_path, std::fs::Permissions::from_mode(0o755))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn hook_binary() -> Result<PathBuf> {
+    if let Some(path) = std::env::var_os(HOOK_BINARY_ENV) {
+        return Ok(PathBuf::from(path));
+    }
+    let exe = std::env::current_exe()?;
+    let dir = exe.parent().ok_or("Zorgit binary has no parent directory")?;
+    Ok(dir.join(HOOK_BINARY))
+}
+
+/// Entry point of the `pre-receive` hook. Reads the ref updates from stdin and checks them
+/// against the policy in the environment. Everything written to stderr is sent to the
+/// client over the sideband, so `git push` shows it. Returns the exit code for the hook.
+pub fn pre_receive_main() -> i32 {
+    let policy = match std::env::var(POLICY_ENV) {
+        Ok(policy) => match serde_json::from_str::<PushPolicy>(&policy) {
+            Ok(policy) => policy,
+            Err(e) => {
+                eprintln!("Zorgit: invalid push policy: {}", e);
+                return 1;
+            }
+        },
+        // Pushes that don't come through zorgit, like the initial commit, are not restricted
+        Err(_) => return 0,
+    };
+
+    // Opening from the environment makes the quarantined objects of the push visible
+    let repo = match git2::Repository::open_from_env() {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Zorgit: could not open repository: {}", e);
+            return 1;
+        }
+    };
+
+    let updates = io::stdin().lock().lines()
+        .flatten()
+        .flat_map(|line| parse_update_line(&line))
+        .collect::<Vec<_>>();
+
+    match pre_receive(&repo, &policy, &updates) {
+        Ok(rejections) if rejections.is_empty() => 0,
+        Ok(rejections) => {
+            let mut stderr = io::stderr();
+            for rejection in rejections {
+                let _ = writeln!(stderr, "Zorgit: {}", rejection);
+            }
+            1
+        }
+        Err(e) => {
+            eprintln!("Zorgit: could not check push: {}", e);
+            1
+        }
+    }
+}
+
+/// Entry point of the `post-receive` hook. git passes the references it actually updated
+/// on stdin, in the same format as to `pre-receive`. They are written unchanged to the file
+/// in the environment, where the server picks them up once git is done.
+pub fn post_receive_main() -> i32 {
+    let path = match std::env::var_os(UPDATES_ENV) {
+        Some(path) => path,
+        // Nobody is waiting for the updates of pushes that don't come through zorgit
+        None => return 0,
+    };
+
+    let mut updates = String::new();
+    if let Err(e) = io::stdin().lock().read_to_string(&mut updates) {
+        eprintln!("Zorgit: could not read the updated references: {}", e);
+        return 1;
+    }
+    match std::fs::write(path, updates) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Zorgit: could not record the updated references: {}", e);
+            1
+        }
+    }
+}
+
+/// Parses a line like `<old-oid> <new-oid> <ref-name>`, as git passes them to hooks.
+pub fn parse_update_line(line: &str) -> Option<RefUpdate> {
+    let mut parts = line.trim().splitn(3, ' ');
+    let old_id = parts.next()?;
+    let new_id = parts.next()?;
+    let name = parts.next()?;
+    let non_zero = |id: &str| if id.bytes().all(|b| b == b'0') { None } else { Some(id.to_string()) };
+
+    Some(RefUpdate {
+        name: name.to_string(),
+        old_id: non_zero(old_id),
+        new_id: non_zero(new_id),
+    })
+}
+
+/// Checks all updates against the policy and returns a message for every rule that got violated.
+pub fn pre_receive(repo: &git2::Repository, policy: &PushPolicy, updates: &[RefUpdate]) -> Result<Vec<String>> {
+    let mut rejections = Vec::new();
+    if !policy.can_write && !updates.is_empty() {
+        rejections.push(format!("{} is not allowed to push to this project.", policy.pusher));
+        return Ok(rejections);
+    }
+
+    for update in updates {
+        let branch = match update.branch() {
+            Some(branch) => branch,
+            None => continue,
+        };
+        let protection = match policy.protection_for(branch) {
+            Some(protection) => protection,
+            None => continue,
+        };
+
+        if !protection.pusher_allowed {
+            rejections.push(format!("{} is not allowed to push to the protected branch '{}'.", policy.pusher, branch));
+            continue;
+        }
+
+        match (&update.old_id, &update.new_id) {
+            (Some(_), None) => {
+                if !protection.allow_deletion {
+                    rejections.push(format!("The protected branch '{}' can not be deleted.", branch));
+                }
+            }
+            (Some(old), Some(new)) => {
+                let old = Oid::from_str(old)?;
+                let new = Oid::from_str(new)?;
+                if !protection.allow_force_push && old != new && !repo.graph_descendant_of(new, old)? {
+                    rejections.push(format!("Force pushing to the protected branch '{}' is not allowed.", branch));
+                }
+                else if protection.require_linear_history && has_merge_commits(repo, new, Some(old))? {
+                    rejections.push(format!("The protected branch '{}' requires a linear history, merge commits are not allowed.", branch));
+                }
+            }
+            (None, Some(new)) => {
+                let new = Oid::from_str(new)?;
+                if protection.require_linear_history && has_merge_commits(repo, new, None)? {
+                    rejections.push(format!("The protected branch '{}' requires a linear history, merge commits are not allowed.", branch));
+                }
+            }
+            (None, None) => (),
+        }
+    }
+
+    Ok(rejections)
+}
+
+/// Checks the commits that are new with this push for merge commits. For a new branch,
+/// all commits that are not reachable from another branch are new.
+fn has_merge_commits(repo: &git2::Repository, new: Oid, old: Option<Oid>) -> Result<bool> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL)?;
+    revwalk.push(new)?;
+    match old {
+        Some(old) => revwalk.hide(old)?,
+        None => revwalk.hide_glob("refs/heads/*")?,
+    }
+
+    for oid in revwalk {
+        if repo.find_commit(oid?)?.parent_count() > 1 {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Deref;
+    use crate::BranchProtection;
+    use super::*;
+
+    /// A bare repository in the temp dir, which is removed again at the end of the test.
+    struct TempRepo {
+        repo: git2::Repository,
+        dir: PathBuf,
+    }
+
+    impl TempRepo {
+        fn new(name: &str) -> TempRepo {
+            let dir = std::env::temp_dir().join(format!("zorgit-hooks-{}-{}", std::process::id(), name));
+            let _ = std::fs::remove_dir_all(&dir);
+            TempRepo { repo: git2::Repository::init_bare(&dir).unwrap(), dir }
+        }
+
+        /// Creates an empty commit with the given parents, without updating any reference.
+        fn commit(&self, message: &str, parents: &[Oid]) -> Oid {
+            let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+            let tree = self.repo.find_tree(self.repo.treebuilder(None).unwrap().write().unwrap()).unwrap();
+            let parents = parents.iter().map(|id| self.repo.find_commit(*id).unwrap()).collect::<Vec<_>>();
+            let parents = parents.iter().collect::<Vec<_>>();
+            self.repo.commit(None, &signature, &signature, message, &tree, &parents).unwrap()
+        }
+    }
+
+    impl Deref for TempRepo {
+        type Target = git2::Repository;
+
+        fn deref(&self) -> &Self::Target {
+            &self.repo
+        }
+    }
+
+    impl Drop for TempRepo {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn update(name: &str) -> RefUpdate {
+        RefUpdate { name: name.to_string(), old_id: None, new_id: Some("1".repeat(40)) }
+    }
+
+    fn update_main(old: Option<Oid>, new: Option<Oid>) -> RefUpdate {
+        RefUpdate {
+            name: "refs/heads/main".to_string(),
+            old_id: old.map(|id| id.to_string()),
+            new_id: new.map(|id| id.to_string()),
+        }
+    }
+
+    /// Protects the branches matching the pattern, without allowing anything.
+    fn protection(pattern: &str) -> BranchProtection {
+        BranchProtection {
+            pattern: pattern.to_string(),
+            allow_force_push: false,
+            allow_deletion: false,
+            pusher_allowed: true,
+            require_linear_history: false,
+            required_status_checks: Vec::new(),
+            required_approvals: 0,
+            require_code_owner_review: false,
+            dismiss_stale_approvals: false,
+        }
+    }
+
+    fn policy(protection: BranchProtection) -> PushPolicy {
+        PushPolicy { pusher: "alice".to_string(), can_write: true, protections: vec![protection] }
+    }
+
+    #[test]
+    fn parses_update_lines() {
+        let zero = "0".repeat(40);
+        let id = "a".repeat(40);
+        let created = parse_update_line(&format!("{} {} refs/heads/main\n", zero, id)).unwrap();
+        assert_eq!(created, RefUpdate { name: "refs/heads/main".to_string(), old_id: None, new_id: Some(id.clone()) });
+
+        let deleted = parse_update_line(&format!("{} {} refs/tags/v1.0", id, zero)).unwrap();
+        assert!(deleted.is_delete());
+        assert_eq!(parse_update_line("incomplete line"), None);
+    }
+
+    #[test]
+    fn rejects_pushers_without_write_access() {
+        let repo = TempRepo::new("read-only");
+        let policy = PushPolicy { pusher: "mallory".to_string(), can_write: false, protections: Vec::new() };
+        let rejections = pre_receive(&repo, &policy, &[update("refs/heads/main")]).unwrap();
+        assert_eq!(rejections, vec!["mallory is not allowed to push to this project.".to_string()]);
+    }
+
+    #[test]
+    fn accepts_unprotected_branches() {
+        let repo = TempRepo::new("writable");
+        let policy = PushPolicy { pusher: "alice".to_string(), can_write: true, protections: Vec::new() };
+        assert!(pre_receive(&repo, &policy, &[update("refs/heads/main")]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_force_pushes() {
+        let repo = TempRepo::new("force-push");
+        let base = repo.commit("Base", &[]);
+        let ours = repo.commit("Ours", &[base]);
+        let theirs = repo.commit("Theirs", &[base]);
+
+        let rejections = pre_receive(&repo, &policy(protection("main")), &[update_main(Some(ours), Some(theirs))]).unwrap();
+        assert_eq!(rejections, vec!["Force pushing to the protected branch 'main' is not allowed.".to_string()]);
+        assert!(pre_receive(&repo, &policy(protection("main")), &[update_main(Some(base), Some(ours))]).unwrap().is_empty());
+
+        let allowed = BranchProtection { allow_force_push: true, ..protection("main") };
+        assert!(pre_receive(&repo, &policy(allowed), &[update_main(Some(ours), Some(theirs))]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_deletions() {
+        let repo = TempRepo::new("deletion");
+        let base = repo.commit("Base", &[]);
+
+        let rejections = pre_receive(&repo, &policy(protection("main")), &[update_main(Some(base), None)]).unwrap();
+        assert_eq!(rejections, vec!["The protected branch 'main' can not be deleted.".to_string()]);
+
+        let allowed = BranchProtection { allow_deletion: true, ..protection("main") };
+        assert!(pre_receive(&repo, &policy(allowed), &[update_main(Some(base), None)]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_merge_commits_for_linear_history() {
+        let repo = TempRepo::new("linear-history");
+        let base = repo.commit("Base", &[]);
+        let ours = repo.commit("Ours", &[base]);
+        let theirs = repo.commit("Theirs", &[base]);
+        let merge = repo.commit("Merge", &[ours, theirs]);
+        let linear = BranchProtection { require_linear_history: true, ..protection("main") };
+        let rejection = "The protected branch 'main' requires a linear history, merge commits are not allowed.".to_string();
+
+        let rejections = pre_receive(&repo, &policy(linear.clone()), &[update_main(Some(ours), Some(merge))]).unwrap();
+        assert_eq!(rejections, vec![rejection.clone()]);
+        let rejections = pre_receive(&repo, &policy(linear.clone()), &[update_main(None, Some(merge))]).unwrap();
+        assert_eq!(rejections, vec![rejection]);
+
+        assert!(pre_receive(&repo, &policy(linear), &[update_main(Some(base), Some(ours))]).unwrap().is_empty());
+        assert!(pre_receive(&repo, &policy(protection("main")), &[update_main(Some(ours), Some(merge))]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_pushers_not_on_allow_list() {
+        let repo = TempRepo::new("allow-list");
+        let base = repo.commit("Base", &[]);
+        let next = repo.commit("Next", &[base]);
+        let denied = BranchProtection { pusher_allowed: false, ..protection("ma*") };
+
+        let rejections = pre_receive(&repo, &policy(denied.clone()), &[update_main(Some(base), Some(next))]).unwrap();
+        assert_eq!(rejections, vec!["alice is not allowed to push to the protected branch 'main'.".to_string()]);
+
+        let feature = RefUpdate { name: "refs/heads/feature".to_string(), ..update_main(Some(base), Some(next)) };
+        assert!(pre_receive(&repo, &policy(denied), &[feature]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use git2::{Oid, Sort};
+use serde::{Deserialize, Serialize};
+use crate::Result;
+
+/// Folder inside the bare repository, that holds the last commits of already listed folders.
+const CACHE_DIR: &str = "zorgit/last-commits";
+/// How many folders are cached at most. Those written longest ago are removed first.
+const MAX_CACHED_FOLDERS: usize = 10_000;
+
+/// Cached last commits of a folder with the id `tree`, as listed at `commit`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CachedFolder {
+    tree: String,
+    commit: String,
+    /// Entry name -> commit id
+    entries: BTreeMap<String, String>,
+}
+
+/// Finds the newest commit that changed each of the given entries of the folder `root`, as seen
+/// from `commit`. Results are cached per folder path and id, so listing a folder again is a lookup,
+/// as long as no commit since the cached listing changed the folder.
+///
+/// Instead of diffing every commit, only the ids of the folder and its entries are compared
+/// with the parents. Commits that didn't touch the folder are skipped after a single lookup.
+/// An entry was last changed by the commit, that introduced the id the entry has in the listed
+/// folder: the commit has the same id for it, but none of its parents has. Commits of merged
+/// branches that changed the entry to something else never match, just like git only follows
+/// the parents of a merge that are the same as the merge itself.
+pub fn last_commits(repo: &git2::Repository, commit: &git2::Commit<'_>, root: &Path, names: &[String]) -> Result<HashMap<String, Oid>> {
+    let folder = match folder_id(commit, root) {
+        Some(id) => id,
+        None => return Ok(HashMap::new()),
+    };
+    let path = cache_path(repo, root);
+    if let Some(mut cached) = read_cache(&path) {
+        let is_complete = names.iter().all(|name| cached.entries.contains_key(name));
+        if is_complete && cached.tree == folder.to_string() && is_unchanged_since(repo, commit, root, folder, &cached.commit)? {
+            let found = cached.entries.iter()
+                .map(|(name, id)| Ok((name.clone(), Oid::from_str(id)?)))
+                .collect::<Result<HashMap<_, _>>>()?;
+            // Moving the listing forward keeps the walk short for the next listing at a newer commit
+            if cached.commit != commit.id().to_string() {
+                cached.commit = commit.id().to_string();
+                let _ = write_cache(&path, &cached);
+            }
+            return Ok(found);
+        }
+    }
+
+    let found = find_last_commits(repo, commit, root, names)?;
+    let cached = CachedFolder {
+        tree: folder.to_string(),
+        commit: commit.id().to_string(),
+        entries: found.iter().map(|(name, id)| (name.clone(), id.to_string())).collect(),
+    };
+    // The cache only speeds things up, a listing must not fail because it can't be written
+    let _ = write_cache(&path, &cached);
+
+    Ok(found)
+}
+
+/// Whether all commits since `since` have the same id for the folder, so none of them can be
+/// the last commit of one of its entries. Listings of commits that don't descend from `since`,
+/// like older commits or those of another branch, have to be done again.
+fn is_unchanged_since(repo: &git2::Repository, commit: &git2::Commit<'_>, root: &Path, folder: Oid, since: &str) -> Result<bool> {
+    let since = match Oid::from_str(since) {
+        Ok(since) => since,
+        Err(_) => return Ok(false),
+    };
+    if since == commit.id() {
+        return Ok(true);
+    }
+    // The cached commit may be gone after a force push and the garbage collection
+    if !repo.graph_descendant_of(commit.id(), since).unwrap_or(false) {
+        return Ok(false);
+    }
+
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(commit.id())?;
+    revwalk.hide(since)?;
+    for oid in revwalk {
+        if folder_id(&repo.find_commit(oid?)?, root) != Some(folder) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn find_last_commits(repo: &git2::Repository, commit: &git2::Commit<'_>, root: &Path, names: &[String]) -> Result<HashMap<String, Oid>> {
+    let listed = match folder_id(commit, root) {
+        Some(id) => repo.find_tree(id)?,
+        None => return Ok(HashMap::new()),
+    };
+    let mut pending = names.iter()
+        .filter_map(|name| listed.get_name(name).map(|entry| (name.clone(), entry.id())))
+        .collect::<Vec<_>>();
+
+    // The commit-graph makes walking by time cheap, a topological order would need the whole history first
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TIME)?;
+    revwalk.push(commit.id())?;
+
+    let mut found = HashMap::with_capacity(pending.len());
+    for oid in revwalk {
+        if pending.is_empty() {
+            break;
+        }
+        let current = repo.find_commit(oid?)?;
+        let current_folder = match folder_id(&current, root) {
+            Some(id) => id,
+            None => continue,
+        };
+        let parent_folder_ids = current.parents()
+            .map(|parent| folder_id(&parent, root))
+            .collect::<Vec<_>>();
+        if parent_folder_ids.contains(&Some(current_folder)) {
+            continue;
+        }
+
+        let folder = repo.find_tree(current_folder)?;
+        let parent_folders = parent_folder_ids.into_iter()
+            .map(|id| id.and_then(|id| repo.find_tree(id).ok()))
+            .collect::<Vec<_>>();
+        pending.retain(|(name, listed_id)| {
+            if folder.get_name(name).map(|entry| entry.id()) != Some(*listed_id) {
+                return true;
+            }
+            let introduced = parent_folders.iter().all(|parent| {
+                parent.as_ref().and_then(|p| p.get_name(name)).map(|e| e.id()) != Some(*listed_id)
+            });
+            if introduced {
+                found.insert(name.clone(), current.id());
+            }
+            !introduced
+        });
+    }
+
+    Ok(found)
+}
+
+fn folder_id(commit: &git2::Commit<'_>, root: &Path) -> Option<Oid> {
+    if root.as_os_str().is_empty() {
+        return Some(commit.tree_id());
+    }
+    commit.tree().ok()?
+        .get_path(root).ok()
+        .filter(|entry| entry.kind() == Some(git2::ObjectType::Tree))
+        .map(|entry| entry.id())
+}
+
+/// Every folder has its own file, named after the hash of its path.
+fn cache_path(repo: &git2::Repository, root: &Path) -> PathBuf {
+    let key = root.to_string_lossy().replace("\\", "/");
+    let name = Oid::hash_object(git2::ObjectType::Blob, key.as_bytes()).unwrap_or_else(|_| Oid::zero());
+    repo.path().join(CACHE_DIR).join(format!("{}.json", name))
+}
+
+fn read_cache(path: &Path) -> Option<CachedFolder> {
+    std::fs::read(path).ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+}
+
+fn write_cache(path: &Path, cached: &CachedFolder) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+        if !path.exists() {
+            evict(dir, MAX_CACHED_FOLDERS - 1)?;
+        }
+    }
+    // Write to a temporary file first, so a concurrent listing never reads half a file
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, serde_json::to_vec(cached)?)?;
+    std::fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+/// Removes the folders written longest ago, until at most `max` are left.
+fn evict(dir: &Path, max: usize) -> Result<()> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.path().extension().map_or(false, |ext| ext == "json") {
+            files.push((entry.metadata()?.modified()?, entry.path()));
+        }
+    }
+    if files.len() <= max {
+        return Ok(());
+    }
+
+    files.sort();
+    for (_, path) in &files[..files.len() - max] {
+        let _ = std::fs::remove_file(path);
+    }
+    Ok(())
+}
+
+/// Removes all cached last commits. Listings at commits that got unreachable by a force push
+/// or deletion are never used again, so they would only take up space.
+pub fn clear_last_commits_cache<P: AsRef<Path>>(repo_path: P) -> Result<()> {
+    match std::fs::remove_dir_all(repo_path.as_ref().join(CACHE_DIR)) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err)?,
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Deref;
+    use super::*;
+
+    /// A bare repository in the temp dir, which is removed again at the end of the test.
+    struct TempRepo {
+        repo: git2::Repository,
+        dir: PathBuf,
+    }
+
+    impl Deref for TempRepo {
+        type Target = git2::Repository;
+
+        fn deref(&self) -> &Self::Target {
+            &self.repo
+        }
+    }
+
+    impl Drop for TempRepo {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn repository(name: &str) -> TempRepo {
+        let dir = std::env::temp_dir().join(format!("zorgit-last-commit-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        TempRepo { repo: git2::Repository::init_bare(&dir).unwrap(), dir }
+    }
+
+    fn commit(repo: &git2::Repository, files: &[(&str, &str)], parents: &[Oid], time: i64) -> Oid {
+        let mut builder = repo.treebuilder(None).unwrap();
+        for (name, content) in files {
+            builder.insert(name, repo.blob(content.as_bytes()).unwrap(), 0o100644).unwrap();
+        }
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let signature = git2::Signature::new("Zorgit Test", "test@example.com", &git2::Time::new(time, 0)).unwrap();
+        let parents = parents.iter().map(|id| repo.find_commit(*id).unwrap()).collect::<Vec<_>>();
+        repo.commit(None, &signature, &signature, "Commit", &tree, &parents.iter().collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn ignores_changes_the_merge_did_not_take() {
+        let repo = repository("merge");
+        let base = commit(&repo, &[("a", "1"), ("b", "1")], &[], 100);
+        let main = commit(&repo, &[("a", "2"), ("b", "1")], &[base], 200);
+        // The newest commit changes `b`, but the merge keeps `b` from main
+        let side = commit(&repo, &[("a", "1"), ("b", "2")], &[base], 300);
+        let merge = commit(&repo, &[("a", "2"), ("b", "1")], &[main, side], 400);
+
+        let names = vec!["a".to_string(), "b".to_string()];
+        let found = find_last_commits(&repo, &repo.find_commit(merge).unwrap(), Path::new(""), &names).unwrap();
+        assert_eq!(found["a"], main);
+        assert_eq!(found["b"], base);
+    }
+
+    #[test]
+    fn finds_the_commit_that_reverted_an_entry() {
+        let repo = repository("revert");
+        let first = commit(&repo, &[("a", "1")], &[], 100);
+        let second = commit(&repo, &[("a", "2")], &[first], 200);
+        let revert = commit(&repo, &[("a", "1")], &[second], 300);
+
+        let found = find_last_commits(&repo, &repo.find_commit(revert).unwrap(), Path::new(""), &["a".to_string()]).unwrap();
+        assert_eq!(found["a"], revert);
+    }
+
+    #[test]
+    fn reuses_listings_while_the_folder_is_unchanged() {
+        let repo = repository("reuse");
+        let first = commit(&repo, &[("a", "1")], &[], 100);
+        let unchanged = commit(&repo, &[("a", "1")], &[first], 200);
+        let names = vec!["a".to_string()];
+
+        let found = last_commits(&repo, &repo.find_commit(first).unwrap(), Path::new(""), &names).unwrap();
+        assert_eq!(found["a"], first);
+        let found = last_commits(&repo, &repo.find_commit(unchanged).unwrap(), Path::new(""), &names).unwrap();
+        assert_eq!(found["a"], first);
+        let cached = read_cache(&cache_path(&repo, Path::new(""))).unwrap();
+        assert_eq!(cached.commit, unchanged.to_string());
+    }
+
+    #[test]
+    fn lists_again_when_the_folder_changed_in_between() {
+        let repo = repository("changed");
+        let first = commit(&repo, &[("a", "1")], &[], 100);
+        let second = commit(&repo, &[("a", "2")], &[first], 200);
+        // Same folder id as the cached listing, but `a` was changed and reverted since
+        let revert = commit(&repo, &[("a", "1")], &[second], 300);
+        let names = vec!["a".to_string()];
+
+        last_commits(&repo, &repo.find_commit(first).unwrap(), Path::new(""), &names).unwrap();
+        let found = last_commits(&repo, &repo.find_commit(revert).unwrap(), Path::new(""), &names).unwrap();
+        assert_eq!(found["a"], revert);
+        // Listing the older commit doesn't take the newer listing
+        let found = last_commits(&repo, &repo.find_commit(first).unwrap(), Path::new(""), &names).unwrap();
+        assert_eq!(found["a"], first);
+    }
+
+    #[test]
+    fn evicts_the_oldest_folders() {
+        let repo = repository("evict");
+        let dir = repo.path().join(CACHE_DIR);
+        for folder in &["a", "b", "c"] {
+            write_cache(&cache_path(&repo, Path::new(folder)), &CachedFolder::default()).unwrap();
+        }
+
+        evict(&dir, 2).unwrap();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+    }
+}
@@ -0,0 +1,159 @@
+use std::path::Path;
+use git2::{Commit, DiffFindOptions, DiffOptions, Patch, Repository};
+use crate::Result;
+
+/// Finds the lines `start..=end` of a file in `from` again in `to`. Returns `None` if one of
+/// the lines got changed or the file is gone. Otherwise the lines are moved by the number of
+/// lines that were added or removed above them.
+pub fn follow_lines(repo: &Repository, from: &Commit<'_>, to: &Commit<'_>, path: &Path, start: usize, end: usize) -> Result<Option<(usize, usize)>> {
+    let old = match from.tree()?.get_path(path) {
+        Ok(entry) => repo.find_blob(entry.id())?,
+        Err(_) => return Ok(None),
+    };
+    let new = match to.tree()?.get_path(path) {
+        Ok(entry) => repo.find_blob(entry.id())?,
+        Err(_) => return Ok(None),
+    };
+    if old.id() == new.id() {
+        return Ok(Some((start, end)));
+    }
+
+    // Without context lines every hunk only covers lines that really changed
+    let mut opts = DiffOptions::new();
+    opts.context_lines(0);
+    let patch = Patch::from_blobs(&old, Some(path), &new, Some(path), Some(&mut opts))?;
+
+    let mut offset = 0i64;
+    for i in 0..patch.num_hunks() {
+        let (hunk, _) = patch.hunk(i)?;
+        let old_start = hunk.old_start() as usize;
+        let old_lines = hunk.old_lines() as usize;
+        // A hunk without old lines inserts its lines after `old_start`
+        let (is_before, is_after) = match old_lines {
+            0 => (old_start < start, old_start >= end),
+            _ => (old_start + old_lines - 1 < start, old_start > end),
+        };
+
+        if is_before {
+            offset += hunk.new_lines() as i64 - old_lines as i64;
+        }
+        else if is_after {
+            break;
+        }
+        else {
+            return Ok(None);
+        }
+    }
+
+    Ok(Some(((start as i64 + offset) as usize, (end as i64 + offset) as usize)))
+}
+
+/// The lines of a file that the hunks of the diff from `from` to `to` show, as `(first, last)`
+/// per hunk. Counted in `from` for the old side of the diff and in `to` for the new side, where
+/// the file is looked up by its name on that side. Empty if the file didn't change.
+pub fn hunk_lines(repo: &Repository, from: &Commit<'_>, to: &Commit<'_>, path: &Path, new_side: bool) -> Result<Vec<(usize, usize)>> {
+    let mut diff = repo.diff_tree_to_tree(Some(&from.tree()?), Some(&to.tree()?), None)?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+    let index = diff.deltas().position(|delta| {
+        let file = if new_side { delta.new_file() } else { delta.old_file() };
+        file.path() == Some(path)
+    });
+    let patch = match index {
+        Some(index) => Patch::from_diff(&diff, index)?,
+        None => None,
+    };
+    let patch = match patch {
+        Some(patch) => patch,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut lines = Vec::with_capacity(patch.num_hunks());
+    for i in 0..patch.num_hunks() {
+        let (hunk, _) = patch.hunk(i)?;
+        let (start, count) = if new_side {
+            (hunk.new_start() as usize, hunk.new_lines() as usize)
+        }
+        else {
+            (hunk.old_start() as usize, hunk.old_lines() as usize)
+        };
+        // Hunks that only add or only remove lines have none on the other side
+        if count > 0 {
+            lines.push((start, start + count - 1));
+        }
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Oid;
+
+    fn commit(repo: &Repository, content: &str, parent: Option<Oid>) -> Oid {
+        let blob = repo.blob(content.as_bytes()).unwrap();
+        let mut builder = repo.treebuilder(None).unwrap();
+        builder.insert("notes.txt", blob, 0o100644).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let signature = git2::Signature::now("Alice", "alice@example.com").unwrap();
+        let parents = parent.map(|id| repo.find_commit(id).unwrap()).into_iter().collect::<Vec<_>>();
+        repo.commit(None, &signature, &signature, "Update notes", &tree, &parents.iter().collect::<Vec<_>>()).unwrap()
+    }
+
+    fn follow(content: &str, changed: &str, start: usize, end: usize) -> Option<(usize, usize)> {
+        let dir = std::env::temp_dir().join(format!("zorgit-line-map-{}-{}", std::process::id(), Oid::hash_object(git2::ObjectType::Blob, changed.as_bytes()).unwrap()));
+        let repo = Repository::init_bare(dir).unwrap();
+        let from = commit(&repo, content, None);
+        let to = commit(&repo, changed, Some(from));
+        let (from, to) = (repo.find_commit(from).unwrap(), repo.find_commit(to).unwrap());
+        let lines = follow_lines(&repo, &from, &to, Path::new("notes.txt"), start, end).unwrap();
+        std::fs::remove_dir_all(repo.path()).unwrap();
+        lines
+    }
+
+    fn hunks(content: &str, changed: &str, new_side: bool) -> Vec<(usize, usize)> {
+        let dir = std::env::temp_dir().join(format!("zorgit-hunk-lines-{}-{}-{}", std::process::id(), new_side, Oid::hash_object(git2::ObjectType::Blob, changed.as_bytes()).unwrap()));
+        let repo = Repository::init_bare(dir).unwrap();
+        let from = commit(&repo, content, None);
+        let to = commit(&repo, changed, Some(from));
+        let (from, to) = (repo.find_commit(from).unwrap(), repo.find_commit(to).unwrap());
+        let lines = hunk_lines(&repo, &from, &to, Path::new("notes.txt"), new_side).unwrap();
+        std::fs::remove_dir_all(repo.path()).unwrap();
+        lines
+    }
+
+    #[test]
+    fn moves_lines_by_changes_above() {
+        assert_eq!(follow("a\nb\nc\nd\n", "new\na\nb\nc\nd\n", 3, 4), Some((4, 5)));
+        assert_eq!(follow("a\nb\nc\nd\n", "b\nc\nd\n", 3, 4), Some((2, 3)));
+    }
+
+    #[test]
+    fn keeps_lines_with_changes_below() {
+        assert_eq!(follow("a\nb\nc\nd\n", "a\nb\nc\nchanged\n", 1, 2), Some((1, 2)));
+        assert_eq!(follow("a\nb\n", "a\nb\nappended\n", 1, 2), Some((1, 2)));
+    }
+
+    #[test]
+    fn loses_changed_lines() {
+        assert_eq!(follow("a\nb\nc\nd\n", "a\nchanged\nc\nd\n", 2, 3), None);
+        assert_eq!(follow("a\nb\nc\nd\n", "a\nb\ninserted\nc\nd\n", 2, 3), None);
+    }
+
+    #[test]
+    fn lists_the_lines_of_hunks_with_context() {
+        let content = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let changed = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n10\n11\n12\n";
+        assert_eq!(hunks(content, changed, false), vec![(2, 8)]);
+        assert_eq!(hunks(content, changed, true), vec![(2, 8)]);
+    }
+
+    #[test]
+    fn counts_lines_on_the_requested_side() {
+        let content = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        let changed = "new\n1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        assert_eq!(hunks(content, changed, false), vec![(1, 3)]);
+        assert_eq!(hunks(content, changed, true), vec![(1, 4)]);
+        assert_eq!(hunks(content, content, true), vec![]);
+    }
+}
@@ -0,0 +1,153 @@
+use std::convert::TryFrom;
+use git2::{Oid, Sort};
+use crate::{Commit, CommitLog, LogFilter, MergeFilter, Result};
+
+
+/// Walks the history of `start` lazily and collects the commits that match the filter,
+/// until a page is full. The cursor is the id of the last commit of the previous page.
+///
+/// The walk is only sorted by time, because a topological sort makes libgit2 load the
+/// whole history before it returns the first commit. Commit dates don't have to grow along
+/// the history, i.e. for rebased commits or skewed clocks, so commits older than `since` are
+/// left out, but the walk goes on.
+pub fn log(repo: &git2::Repository, start: &git2::Commit<'_>, filter: &LogFilter, after: Option<&str>, limit: usize) -> Result<CommitLog> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TIME)?;
+    revwalk.push(start.id())?;
+
+    let mut revwalk = revwalk.flatten().peekable();
+    if let Some(after) = after {
+        let after = Oid::from_str(after)?;
+        // Skipping only needs the ids, commits are parsed once the page starts
+        if revwalk.by_ref().find(|oid| *oid == after).is_none() {
+            return Ok(CommitLog { commits: Vec::new(), next_cursor: None });
+        }
+    }
+
+    let filter = Matcher::new(filter);
+    let mut commits = Vec::with_capacity(limit);
+    while let Some(oid) = revwalk.next() {
+        let commit = repo.find_commit(oid)?;
+        if !filter.matches(&commit) {
+            continue;
+        }
+
+        commits.push(Commit::try_from(commit)?);
+        if commits.len() == limit {
+            let next_cursor = revwalk.peek()
+                .and(commits.last())
+                .map(|last| last.id.clone());
+            return Ok(CommitLog { commits, next_cursor });
+        }
+    }
+
+    Ok(CommitLog { commits, next_cursor: None })
+}
+
+/// The filter with lowercased text, so it doesn't need to be lowercased for every commit.
+struct Matcher<'a> {
+    filter: &'a LogFilter,
+    author: Option<String>,
+    committer: Option<String>,
+    message: Option<String>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(filter: &'a LogFilter) -> Matcher<'a> {
+        let lowercase = |text: &Option<String>| text.as_ref()
+            .filter(|t| !t.is_empty())
+            .map(|t| t.to_lowercase());
+        Matcher {
+            filter,
+            author: lowercase(&filter.author),
+            committer: lowercase(&filter.committer),
+            message: lowercase(&filter.message),
+        }
+    }
+
+    fn matches(&self, commit: &git2::Commit<'_>) -> bool {
+        let merges_match = match self.filter.merges {
+            MergeFilter::All => true,
+            MergeFilter::OnlyMerges => commit.parent_count() > 1,
+            MergeFilter::NoMerges => commit.parent_count() < 2,
+        };
+        let since_matches = self.filter.since
+            .map_or(true, |since| commit.time().seconds() >= since.unix_timestamp());
+        let until_matches = self.filter.until
+            .map_or(true, |until| commit.time().seconds() <= until.unix_timestamp());
+
+        merges_match
+            && since_matches
+            && until_matches
+            && signature_matches(&self.author, &commit.author())
+            && signature_matches(&self.committer, &commit.committer())
+            && self.message.as_ref().map_or(true, |message| {
+                commit.message().map_or(false, |m| m.to_lowercase().contains(message))
+            })
+    }
+}
+
+fn signature_matches(needle: &Option<String>, signature: &git2::Signature<'_>) -> bool {
+    match needle {
+        Some(needle) => signature.name().map_or(false, |n| n.to_lowercase().contains(needle))
+            || signature.email().map_or(false, |e| e.to_lowercase().contains(needle)),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Deref;
+    use std::path::PathBuf;
+    use time::OffsetDateTime;
+    use super::*;
+
+    /// A bare repository in the temp dir, which is removed again at the end of the test.
+    struct TempRepo {
+        repo: git2::Repository,
+        dir: PathBuf,
+    }
+
+    impl TempRepo {
+        fn new(name: &str) -> TempRepo {
+            let dir = std::env::temp_dir().join(format!("zorgit-log-{}-{}", std::process::id(), name));
+            let _ = std::fs::remove_dir_all(&dir);
+            TempRepo { repo: git2::Repository::init_bare(&dir).unwrap(), dir }
+        }
+
+        fn commit(&self, parents: &[Oid], time: i64) -> Oid {
+            let tree = self.repo.find_tree(self.repo.treebuilder(None).unwrap().write().unwrap()).unwrap();
+            let signature = git2::Signature::new("Zorgit Test", "test@example.com", &git2::Time::new(time, 0)).unwrap();
+            let parents = parents.iter().map(|id| self.repo.find_commit(*id).unwrap()).collect::<Vec<_>>();
+            self.repo.commit(None, &signature, &signature, "Commit", &tree, &parents.iter().collect::<Vec<_>>()).unwrap()
+        }
+    }
+
+    impl Deref for TempRepo {
+        type Target = git2::Repository;
+
+        fn deref(&self) -> &Self::Target {
+            &self.repo
+        }
+    }
+
+    impl Drop for TempRepo {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn skips_old_commits_without_stopping() {
+        let repo = TempRepo::new("since");
+        let first = repo.commit(&[], 300);
+        // Committed with a clock that was behind
+        let skewed = repo.commit(&[first], 100);
+        let last = repo.commit(&[skewed], 400);
+
+        let filter = LogFilter { since: Some(OffsetDateTime::from_unix_timestamp(200)), ..LogFilter::default() };
+        let log = log(&repo, &repo.find_commit(last).unwrap(), &filter, None, 10).unwrap();
+        let ids = log.commits.iter().map(|c| c.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids, vec![last.to_string(), first.to_string()]);
+    }
+}
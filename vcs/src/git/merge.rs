@@ -0,0 +1,253 @@
+use anyhow::anyhow;
+use git2::{Commit, Index, Oid, Repository, Signature, Sort, Tree};
+use crate::{MergeStyle, Result};
+
+/// Paths that conflict when `head` gets merged into `base`. Everything happens in memory,
+/// neither the repository nor a worktree are touched.
+pub fn merge_conflicts(repo: &Repository, base: &Commit<'_>, head: &Commit<'_>) -> Result<Vec<String>> {
+    let index = repo.merge_commits(base, head, None)?;
+    conflict_paths(&index)
+}
+
+fn conflict_paths(index: &Index) -> Result<Vec<String>> {
+    let mut paths = Vec::new();
+    for conflict in index.conflicts()? {
+        let conflict = conflict?;
+        if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+            paths.push(String::from_utf8_lossy(&entry.path).to_string());
+        }
+    }
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+/// Creates the commits that merge `head` into `base` and returns the commit the base branch
+/// should point to afterwards. No reference is changed, that is up to the caller.
+pub fn merge(repo: &Repository, base: &Commit<'_>, head: &Commit<'_>, style: MergeStyle, message: &str, merger: &Signature<'_>) -> Result<Oid> {
+    if base.id() == head.id() || repo.graph_descendant_of(base.id(), head.id())? {
+        return Err(anyhow!("{} is already merged into {}", head.id(), base.id()))?;
+    }
+
+    match style {
+        MergeStyle::Merge => {
+            let tree = merged_tree(repo, base, head)?;
+            Ok(repo.commit(None, merger, merger, message, &tree, &[base, head])?)
+        }
+        MergeStyle::Squash => {
+            // The changes are credited to the author of the newest commit
+            let tree = merged_tree(repo, base, head)?;
+            Ok(repo.commit(None, &head.author(), merger, message, &tree, &[base])?)
+        }
+        MergeStyle::Rebase => rebase(repo, base, head, merger),
+    }
+}
+
+fn merged_tree<'r>(repo: &'r Repository, base: &Commit<'_>, head: &Commit<'_>) -> Result<Tree<'r>> {
+    let mut index = repo.merge_commits(base, head, None)?;
+    if index.has_conflicts() {
+        return Err(anyhow!("Merging {} into {} conflicts in {}", head.id(), base.id(), conflict_paths(&index)?.join(", ")))?;
+    }
+    let tree_id = index.write_tree_to(repo)?;
+    Ok(repo.find_tree(tree_id)?)
+}
+
+/// Replays the commits of head that are not in base, oldest first. Authors are kept and the
+/// merger becomes the committer. If base didn't move since head branched off, head is used as it is.
+fn rebase(repo: &Repository, base: &Commit<'_>, head: &Commit<'_>, merger: &Signature<'_>) -> Result<Oid> {
+    if repo.graph_descendant_of(head.id(), base.id())? {
+        return Ok(head.id());
+    }
+
+    let mut walk = repo.revwalk()?;
+    walk.push(head.id())?;
+    walk.hide(base.id())?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+
+    let mut onto = repo.find_commit(base.id())?;
+    for id in walk {
+        let commit = repo.find_commit(id?)?;
+        if commit.parent_count() > 1 {
+            return Err(anyhow!("{} is a merge commit, which can't be rebased", commit.id()))?;
+        }
+        let mut index = repo.cherrypick_commit(&commit, &onto, 0, None)?;
+        if index.has_conflicts() {
+            return Err(anyhow!("Rebasing {} conflicts in {}", commit.id(), conflict_paths(&index)?.join(", ")))?;
+        }
+        let tree = repo.find_tree(index.write_tree_to(repo)?)?;
+        let message = commit.message_raw().unwrap_or_default();
+        let id = repo.commit(None, &commit.author(), merger, message, &tree, &[&onto])?;
+        onto = repo.find_commit(id)?;
+    }
+
+    Ok(onto.id())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Deref;
+    use std::path::PathBuf;
+    use super::*;
+
+    /// A bare repository in the temp dir, which is removed again at the end of the test.
+    struct TempRepo {
+        repo: Repository,
+        dir: PathBuf,
+    }
+
+    impl TempRepo {
+        fn new(name: &str) -> TempRepo {
+            let dir = std::env::temp_dir().join(format!("zorgit-merge-{}-{}", std::process::id(), name));
+            let _ = std::fs::remove_dir_all(&dir);
+            TempRepo { repo: Repository::init_bare(&dir).unwrap(), dir }
+        }
+
+        /// Creates a commit by Alice with the given files, without updating any reference.
+        fn commit(&self, message: &str, files: &[(&str, &str)], parents: &[Oid]) -> Oid {
+            let mut builder = self.repo.treebuilder(None).unwrap();
+            for (name, content) in files {
+                builder.insert(name, self.repo.blob(content.as_bytes()).unwrap(), 0o100644).unwrap();
+            }
+            let tree = self.repo.find_tree(builder.write().unwrap()).unwrap();
+            let signature = Signature::now("Alice", "alice@example.com").unwrap();
+            let parents = parents.iter().map(|id| self.repo.find_commit(*id).unwrap()).collect::<Vec<_>>();
+            let parents = parents.iter().collect::<Vec<_>>();
+            self.repo.commit(None, &signature, &signature, message, &tree, &parents).unwrap()
+        }
+
+        fn merge(&self, base: Oid, head: Oid, style: MergeStyle) -> Result<Oid> {
+            let merger = Signature::now("Bob", "bob@example.com").unwrap();
+            let (base, head) = (self.repo.find_commit(base).unwrap(), self.repo.find_commit(head).unwrap());
+            merge(&self.repo, &base, &head, style, "Merge feature", &merger)
+        }
+
+        fn conflicts(&self, base: Oid, head: Oid) -> Vec<String> {
+            let (base, head) = (self.repo.find_commit(base).unwrap(), self.repo.find_commit(head).unwrap());
+            merge_conflicts(&self.repo, &base, &head).unwrap()
+        }
+
+        /// Names and contents of the files of the commit.
+        fn files(&self, id: Oid) -> Vec<(String, String)> {
+            let tree = self.repo.find_commit(id).unwrap().tree().unwrap();
+            tree.iter()
+                .map(|entry| {
+                    let blob = self.repo.find_blob(entry.id()).unwrap();
+                    (entry.name().unwrap().to_string(), String::from_utf8_lossy(blob.content()).to_string())
+                })
+                .collect()
+        }
+    }
+
+    impl Deref for TempRepo {
+        type Target = Repository;
+
+        fn deref(&self) -> &Self::Target {
+            &self.repo
+        }
+    }
+
+    impl Drop for TempRepo {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn files(files: &[(&str, &str)]) -> Vec<(String, String)> {
+        files.iter().map(|(name, content)| (name.to_string(), content.to_string())).collect()
+    }
+
+    /// A root commit with `a`, the base branch adding `b` and the head branch adding `c`.
+    fn diverged(repo: &TempRepo) -> (Oid, Oid) {
+        let root = repo.commit("Add a", &[("a", "1\n")], &[]);
+        let base = repo.commit("Add b", &[("a", "1\n"), ("b", "2\n")], &[root]);
+        let head = repo.commit("Add c", &[("a", "1\n"), ("c", "3\n")], &[root]);
+        (base, head)
+    }
+
+    #[test]
+    fn merges_with_a_merge_commit() {
+        let repo = TempRepo::new("merge");
+        let (base, head) = diverged(&repo);
+
+        let merged = repo.find_commit(repo.merge(base, head, MergeStyle::Merge).unwrap()).unwrap();
+        assert_eq!(merged.parent_ids().collect::<Vec<_>>(), vec![base, head]);
+        assert_eq!(merged.message(), Some("Merge feature"));
+        assert_eq!(merged.author().name(), Some("Bob"));
+        assert_eq!(repo.files(merged.id()), files(&[("a", "1\n"), ("b", "2\n"), ("c", "3\n")]));
+    }
+
+    #[test]
+    fn squashes_into_a_single_commit() {
+        let repo = TempRepo::new("squash");
+        let (base, head) = diverged(&repo);
+        let head = repo.commit("Add d", &[("a", "1\n"), ("c", "3\n"), ("d", "4\n")], &[head]);
+
+        let squashed = repo.find_commit(repo.merge(base, head, MergeStyle::Squash).unwrap()).unwrap();
+        assert_eq!(squashed.parent_ids().collect::<Vec<_>>(), vec![base]);
+        assert_eq!(squashed.author().name(), Some("Alice"));
+        assert_eq!(squashed.committer().name(), Some("Bob"));
+        assert_eq!(repo.files(squashed.id()), files(&[("a", "1\n"), ("b", "2\n"), ("c", "3\n"), ("d", "4\n")]));
+    }
+
+    #[test]
+    fn rebases_every_commit_onto_the_base() {
+        let repo = TempRepo::new("rebase");
+        let (base, head) = diverged(&repo);
+        let head = repo.commit("Add d", &[("a", "1\n"), ("c", "3\n"), ("d", "4\n")], &[head]);
+
+        let last = repo.find_commit(repo.merge(base, head, MergeStyle::Rebase).unwrap()).unwrap();
+        let first = last.parent(0).unwrap();
+        assert_eq!(last.parent_count(), 1);
+        assert_eq!(first.parent_ids().collect::<Vec<_>>(), vec![base]);
+        assert_eq!((first.message(), last.message()), (Some("Add c"), Some("Add d")));
+        assert_eq!((last.author().name(), last.committer().name()), (Some("Alice"), Some("Bob")));
+        assert_eq!(repo.files(first.id()), files(&[("a", "1\n"), ("b", "2\n"), ("c", "3\n")]));
+        assert_eq!(repo.files(last.id()), files(&[("a", "1\n"), ("b", "2\n"), ("c", "3\n"), ("d", "4\n")]));
+    }
+
+    #[test]
+    fn rebases_by_fast_forwarding_if_the_base_did_not_move() {
+        let repo = TempRepo::new("fast-forward");
+        let base = repo.commit("Add a", &[("a", "1\n")], &[]);
+        let head = repo.commit("Add b", &[("a", "1\n"), ("b", "2\n")], &[base]);
+
+        assert_eq!(repo.merge(base, head, MergeStyle::Rebase).unwrap(), head);
+    }
+
+    #[test]
+    fn refuses_to_rebase_merge_commits() {
+        let repo = TempRepo::new("rebase-merge-commit");
+        let (base, head) = diverged(&repo);
+        let side = repo.commit("Add d", &[("a", "1\n"), ("d", "4\n")], &[repo.find_commit(head).unwrap().parent_id(0).unwrap()]);
+        let head = repo.commit("Merge d", &[("a", "1\n"), ("c", "3\n"), ("d", "4\n")], &[head, side]);
+
+        let error = repo.merge(base, head, MergeStyle::Rebase).unwrap_err();
+        assert!(error.to_string().contains("is a merge commit"), "{}", error);
+    }
+
+    #[test]
+    fn refuses_heads_that_are_already_merged() {
+        let repo = TempRepo::new("already-merged");
+        let head = repo.commit("Add a", &[("a", "1\n")], &[]);
+        let base = repo.commit("Add b", &[("a", "1\n"), ("b", "2\n")], &[head]);
+
+        assert!(repo.merge(base, head, MergeStyle::Merge).is_err());
+        assert!(repo.merge(base, base, MergeStyle::Squash).is_err());
+    }
+
+    #[test]
+    fn detects_conflicts() {
+        let repo = TempRepo::new("conflicts");
+        let root = repo.commit("Add a", &[("a", "1\n"), ("b", "2\n")], &[]);
+        let base = repo.commit("Change a", &[("a", "base\n"), ("b", "2\n")], &[root]);
+        let head = repo.commit("Change a too", &[("a", "head\n"), ("b", "3\n")], &[root]);
+        let clean = repo.commit("Change b", &[("a", "1\n"), ("b", "3\n")], &[root]);
+
+        assert_eq!(repo.conflicts(base, head), vec!["a".to_string()]);
+        assert_eq!(repo.conflicts(base, clean), Vec::<String>::new());
+        for &style in &[MergeStyle::Merge, MergeStyle::Squash, MergeStyle::Rebase] {
+            let error = repo.merge(base, head, style).unwrap_err();
+            assert!(error.to_string().contains("conflicts in a"), "{}", error);
+        }
+    }
+}
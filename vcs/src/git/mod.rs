@@ -1,11 +1,31 @@
+mod archive;
+mod blame;
 mod cmd;
+mod compare;
 mod commit;
 mod diff;
+mod history;
+pub mod hooks;
+mod last_commit;
+mod line_map;
+mod log;
+mod merge;
+pub mod post_receive;
 mod repo;
-mod server;
+pub mod server;
+mod tag;
 
+pub use self::archive::*;
+pub use self::blame::*;
 pub use self::cmd::*;
+pub use self::compare::*;
 pub use self::commit::*;
 pub use self::diff::*;
+pub use self::history::*;
+pub use self::last_commit::*;
+pub use self::line_map::*;
+pub use self::log::*;
+pub use self::merge::*;
 pub use self::repo::Repository;
-pub use self::server::Server;
+pub use self::server::{PackStream, Server};
+pub use self::tag::*;
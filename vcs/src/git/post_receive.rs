@@ -0,0 +1,144 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use git2::BranchType;
+use rocket::error;
+use time::OffsetDateTime;
+use tokio::process::Child;
+use tokio::sync::oneshot;
+use zorgit_db::Database;
+use crate::{RefUpdate, Result};
+use crate::events::{Event, EventBus, ProjectInfo, PushEvent};
+use crate::git::{self, cmd, hooks};
+
+/// The state of a repository after a push, which needs to be reflected in the project.
+#[derive(Debug, Clone)]
+pub struct PostReceive {
+    pub updates: Vec<RefUpdate>,
+    pub is_empty: bool,
+    pub default_branch: Option<String>,
+    pub disk_size: usize,
+}
+
+/// Resolves once git has finished processing a push. The `post-receive` hook writes the
+/// references git updated to the file at `updates`.
+pub struct PushCompletion {
+    pub(crate) finished: oneshot::Receiver<Child>,
+    pub(crate) updates: PathBuf,
+}
+
+impl PushCompletion {
+    /// Waits for git to exit and returns the references the push updated.
+    pub async fn wait(self) -> Result<Vec<RefUpdate>> {
+        let mut child = self.finished.await?;
+        child.wait().await?;
+        // git only runs post-receive if it updated something
+        let updates = match tokio::fs::read_to_string(&self.updates).await {
+            Ok(updates) => updates,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        tokio::fs::remove_file(&self.updates).await?;
+        Ok(updates.lines().flat_map(hooks::parse_update_line).collect())
+    }
+}
+
+/// A file for the updated references of a push, that no other push uses at the same time.
+pub(crate) fn updates_file() -> PathBuf {
+    static COUNT: AtomicU64 = AtomicU64::new(0);
+    std::env::temp_dir().join(format!("zorgit-push-{}-{}", std::process::id(), COUNT.fetch_add(1, Ordering::Relaxed)))
+}
+
+/// Collects the changes of a push and fixes HEAD, if it points to a branch that doesn't
+/// exist, i.e. because the first push to a new repository created `main` instead of `master`.
+pub async fn post_receive<P: AsRef<Path>>(repo_path: P, updates: Vec<RefUpdate>) -> Result<PostReceive> {
+    let (is_empty, default_branch) = update_head(repo_path.as_ref(), &updates)?;
+    if !updates.is_empty() {
+        if drops_commits(repo_path.as_ref(), &updates)? {
+            git::clear_last_commits_cache(repo_path.as_ref())?;
+        }
+        // Only speeds up walking the history, so the push went through without it just as well
+        if let Err(e) = cmd::write_commit_graph(repo_path.as_ref()).await {
+            error!("Writing the commit-graph of {} failed: {}", repo_path.as_ref().display(), e);
+        }
+    }
+    let disk_size = cmd::repo_size(repo_path.as_ref()).await?;
+
+    Ok(PostReceive {
+        updates,
+        is_empty,
+        default_branch,
+        disk_size,
+    })
+}
+
+fn update_head(repo_path: &Path, updates: &[RefUpdate]) -> Result<(bool, Option<String>)> {
+    let repo = git2::Repository::open_bare(repo_path)?;
+
+    let branches = repo.branches(Some(BranchType::Local))?
+        .flatten()
+        .flat_map(|(branch, _)| branch.name().ok().flatten().map(ToString::to_string))
+        .collect::<Vec<_>>();
+    let head_is_valid = repo.head().is_ok();
+    if !head_is_valid {
+        let new_head = updates.iter()
+            .filter(|u| u.is_create())
+            .find_map(|u| u.branch().map(ToString::to_string))
+            .or_else(|| branches.first().cloned());
+        if let Some(branch) = new_head {
+            repo.set_head(&format!("refs/heads/{}", branch))?;
+        }
+    }
+
+    let default_branch = repo.head().ok()
+        .filter(|head| head.is_branch())
+        .and_then(|head| head.shorthand().map(ToString::to_string));
+
+    Ok((branches.is_empty(), default_branch))
+}
+
+/// Whether a reference got deleted or force pushed, so commits might not be reachable anymore.
+fn drops_commits(repo_path: &Path, updates: &[RefUpdate]) -> Result<bool> {
+    let repo = git2::Repository::open_bare(repo_path)?;
+    for update in updates {
+        match (&update.old_id, &update.new_id) {
+            (Some(_), None) => return Ok(true),
+            (Some(old), Some(new)) => {
+                let old = git2::Oid::from_str(old)?;
+                let new = git2::Oid::from_str(new)?;
+                if !repo.graph_descendant_of(new, old)? {
+                    return Ok(true);
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(false)
+}
+
+/// Runs after git finished a push: refreshes the project in the database and emits a push event.
+pub async fn process_push(db: Database, events: EventBus, mut project: ProjectInfo, pusher: String, pusher_email: String, completion: PushCompletion) -> Result<()> {
+    let updates = completion.wait().await?;
+    if updates.is_empty() {
+        return Ok(());
+    }
+    let post_receive = post_receive(&project.dir, updates).await?;
+
+    let project_uuid = project.id.parse()?;
+    db.projects.update_after_push(
+        &project_uuid,
+        post_receive.is_empty,
+        post_receive.default_branch.as_deref(),
+        post_receive.disk_size as i64,
+        OffsetDateTime::now_utc()
+    ).await?;
+
+    project.default_branch = post_receive.default_branch;
+    events.emit(Event::Push(PushEvent {
+        project,
+        pusher,
+        pusher_email,
+        updates: post_receive.updates,
+    }));
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::io::Write;
 use std::path::{Path, PathBuf};
@@ -6,8 +6,8 @@ use std::str::{self, FromStr};
 use anyhow::anyhow;
 use git2::{self, BranchType, ObjectType, Oid, RepositoryInitOptions, Signature, TreeWalkMode, TreeWalkResult};
 use zorgit_common::IntoOption;
-use crate::{Commit, Result, SourceEntry, SourceLine};
-use crate::git::{self, cmd, diff};
+use crate::{Blame, Commit, CommitLog, CompareMode, Comparison, LogFilter, MergeStyle, PathHistory, PushPolicy, RefUpdate, RefKind, ResolvedRef, Result, SourceEntry, SourceLine, Tag, TagSort};
+use crate::git::{self, cmd, diff, hooks};
 use crate::{Diff, SourceEntries, VersionControl};
 
 pub struct Repository {
@@ -41,6 +41,7 @@ impl VersionControl for Repository {
                     path,
                     repo,
             })?;
+        hooks::install(&repo.path)?;
         Ok(repo)
     }
 
@@ -97,9 +98,8 @@ impl VersionControl for Repository {
         Commit::try_from(commit)
     }
 
-    fn branch_entry_by_path<P: AsRef<Path>>(&self, branch_name: &str, path: P) -> Result<Option<SourceEntries>> {
-        let branch = self.repo.find_branch(branch_name, git2::BranchType::Local)?;
-        let tree = branch.get().peel_to_tree()?;
+    fn branch_entry_by_path<P: AsRef<Path>>(&self, rev: &str, path: P) -> Result<Option<SourceEntries>> {
+        let tree = Repository::_peel_rev(&self.repo, rev)?.tree()?;
         let pth = path.as_ref().to_str().unwrap().replace("\\", "/");
         let entry = match tree.get_path(Path::new(&pth)) {
             Ok(e) => e,
@@ -110,7 +110,7 @@ impl VersionControl for Repository {
         match entry.kind() {
             Some(git2::ObjectType::Tree) => {
                 let folder_tree = self.repo.find_tree(entry.id())?;
-                entries = self.entries_for_tree(&folder_tree, branch_name, path.as_ref().to_str().unwrap())?;
+                entries = self.entries_for_tree(&folder_tree, rev, path.as_ref().to_str().unwrap())?;
             }
             Some(git2::ObjectType::Blob) => {
                 let blob = self.repo.find_blob(entry.id())?;
@@ -145,16 +145,25 @@ impl VersionControl for Repository {
         Ok(entries.into_option())
     }
 
-    fn raw_branch_entry_by_path<P: AsRef<Path>>(&self, branch_name: &str, path: P) -> Result<Option<Vec<u8>>> {
-        let branch = self.repo.find_branch(branch_name, git2::BranchType::Local)?;
-        let tree = branch.get().peel_to_tree()?;
+    fn raw_branch_entry_by_path<P: AsRef<Path>>(&self, rev: &str, path: P) -> Result<Option<Vec<u8>>> {
+        let tree = Repository::_peel_rev(&self.repo, rev)?.tree()?;
         let pth = path.as_ref().to_str().unwrap().replace("\\", "/");
-        let tree_entry = tree.get_path(Path::new(&pth))?;
+        let tree_entry = match tree.get_path(Path::new(&pth)) {
+            Ok(e) => e,
+            Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(None),
+            Err(err) => Err(err)?,
+        };
         let data = self.repo.find_blob(tree_entry.id())
             .map(|blob| Some(blob.content().to_owned()))?;
         Ok(data)
     }
 
+    fn blame<P: AsRef<Path>>(&self, rev: &str, path: P) -> Result<Option<Blame>> {
+        let commit = Repository::_peel_rev(&self.repo, rev)?;
+        let pth = path.as_ref().to_str().unwrap().replace("\\", "/");
+        git::blame(&self.repo, &commit, Path::new(&pth))
+    }
+
     fn default_branch(&self) -> Result<Option<String>> {
         let head = self.repo.head()?;
         let mut default_branch = None;
@@ -172,6 +181,108 @@ impl VersionControl for Repository {
         Ok(branches)
     }
 
+    fn create_branch(&self, name: &str, rev: &str) -> Result<()> {
+        let commit = Repository::_peel_rev(&self.repo, rev)?;
+        self.repo.branch(name, &commit, false)?;
+        Ok(())
+    }
+
+    fn delete_branch(&self, name: &str) -> Result<()> {
+        self.repo.find_branch(name, BranchType::Local)?.delete()?;
+        Ok(())
+    }
+
+    fn rename_branch(&self, name: &str, new_name: &str) -> Result<()> {
+        let was_default = self.default_branch().ok().flatten().as_deref() == Some(name);
+        self.repo.find_branch(name, BranchType::Local)?.rename(new_name, false)?;
+        if was_default {
+            self.set_default_branch(new_name)?;
+        }
+        Ok(())
+    }
+
+    fn set_default_branch(&self, name: &str) -> Result<()> {
+        let branch = self.repo.find_branch(name, BranchType::Local)?;
+        let reference = branch.get().name().ok_or_else(|| anyhow!("Branch name is not valid UTF-8"))?;
+        self.repo.set_head(reference)?;
+        Ok(())
+    }
+
+    fn update_branch(&self, name: &str, old_id: &str, new_id: &str) -> Result<()> {
+        let old_id = Oid::from_str(old_id)?;
+        let new_id = Oid::from_str(new_id)?;
+        self.repo.reference_matching(&format!("refs/heads/{}", name), new_id, true, old_id, "zorgit: update branch")?;
+        Ok(())
+    }
+
+    fn check_updates(&self, policy: &PushPolicy, updates: &[RefUpdate]) -> Result<Vec<String>> {
+        hooks::pre_receive(&self.repo, policy, updates)
+    }
+
+    fn tags(&self, sort: TagSort) -> Result<Vec<Tag>> {
+        let mut tags = Vec::new();
+        for reference in self.repo.references_glob("refs/tags/*")? {
+            if let Some(tag) = git::tag_from_reference(&self.repo, &reference?)? {
+                tags.push(tag);
+            }
+        }
+        sort.sort(&mut tags);
+        Ok(tags)
+    }
+
+    fn tag_by_name(&self, name: &str) -> Result<Option<Tag>> {
+        match self.repo.find_reference(&format!("refs/tags/{}", name)) {
+            Ok(reference) => git::tag_from_reference(&self.repo, &reference),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(err)?,
+        }
+    }
+
+    fn create_tag(&self, name: &str, target: &str, message: Option<&str>, tagger: &crate::Signature) -> Result<Tag> {
+        if !git2::Reference::is_valid_name(&format!("refs/tags/{}", name)) {
+            return Err(anyhow!("Invalid tag name: {}", name))?;
+        }
+        let commit = self.repo.revparse_single(target)?.peel(ObjectType::Commit)?;
+        match message.filter(|m| !m.trim().is_empty()) {
+            Some(message) => {
+                let signature = Signature::now(&tagger.name, &tagger.email)?;
+                self.repo.tag(name, &commit, &signature, message, false)?;
+            }
+            None => {
+                self.repo.tag_lightweight(name, &commit, false)?;
+            }
+        }
+
+        self.tag_by_name(name)?
+            .ok_or_else(|| anyhow!("Tag {} vanished after it was created", name).into())
+    }
+
+    fn delete_tag(&self, name: &str) -> Result<()> {
+        self.repo.tag_delete(name)?;
+        Ok(())
+    }
+
+    fn resolve(&self, rev: &str) -> Result<Option<ResolvedRef>> {
+        if let Ok(branch) = self.repo.find_branch(rev, BranchType::Local) {
+            let commit = branch.get().peel_to_commit()?;
+            return Ok(Some(ResolvedRef::new(rev, RefKind::Branch, commit.id().to_string())));
+        }
+        if let Ok(tag) = self.repo.find_reference(&format!("refs/tags/{}", rev)) {
+            let commit = tag.peel_to_commit()?;
+            return Ok(Some(ResolvedRef::new(rev, RefKind::Tag, commit.id().to_string())));
+        }
+
+        let object = match self.repo.revparse_single(rev) {
+            Ok(object) => object,
+            Err(err) if matches!(err.code(), git2::ErrorCode::NotFound | git2::ErrorCode::InvalidSpec | git2::ErrorCode::Ambiguous) => return Ok(None),
+            Err(err) => Err(err)?,
+        };
+        match object.peel_to_commit() {
+            Ok(commit) => Ok(Some(ResolvedRef::new(rev, RefKind::Commit, commit.id().to_string()))),
+            Err(_) => Ok(None),
+        }
+    }
+
     fn commit_by_id(&self, commit_id: &str) -> Result<Commit> {
         let sha = Oid::from_str(commit_id)?;
         let commit = self.repo.find_commit(sha)?;
@@ -214,6 +325,17 @@ impl VersionControl for Repository {
             )
     }
 
+    fn log(&self, rev: &str, filter: &LogFilter, after: Option<&str>, limit: usize) -> Result<CommitLog> {
+        let commit = Repository::_peel_rev(&self.repo, rev)?;
+        git::log(&self.repo, &commit, filter, after, limit)
+    }
+
+    fn path_history<P: AsRef<Path>>(&self, rev: &str, path: P, skip: usize, limit: usize) -> Result<PathHistory> {
+        let commit = Repository::_peel_rev(&self.repo, rev)?;
+        let pth = path.as_ref().to_str().unwrap().replace("\\", "/");
+        git::path_history(&self.repo, &commit, Path::new(pth.trim_matches('/')), skip, limit)
+    }
+
     fn branch_commits_count(&self, branch_name: &str) -> Result<usize> {
         let commit = self.repo.find_branch(branch_name, git2::BranchType::Local)?
             .get()
@@ -287,13 +409,55 @@ impl VersionControl for Repository {
         //cmd::commit_associated_branches(&self.path, commit_id)
     }
 
+    fn commits_between(&self, from: Option<&str>, to: &str, limit: usize) -> Result<Vec<Commit>> {
+        let to = Oid::from_str(to)?;
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+        revwalk.push(to)?;
+        match from {
+            Some(from) => revwalk.hide(Oid::from_str(from)?)?,
+            None => {
+                for branch in self.repo.branches(Some(BranchType::Local))? {
+                    let target = branch?.0.get().target();
+                    if let Some(target) = target.filter(|t| *t != to) {
+                        revwalk.hide(target)?;
+                    }
+                }
+            }
+        }
+
+        let commits = revwalk
+            .flatten()
+            .take(limit)
+            .flat_map(|oid| self.repo.find_commit(oid))
+            .flat_map(|commit| Commit::try_from(commit))
+            .collect::<Vec<_>>();
+
+        Ok(commits)
+    }
+
+    fn is_ancestor(&self, ancestor_id: &str, commit_id: &str) -> Result<bool> {
+        let ancestor = Oid::from_str(ancestor_id)?;
+        let commit = Oid::from_str(commit_id)?;
+        Ok(ancestor == commit || self.repo.graph_descendant_of(commit, ancestor)?)
+    }
+
+    fn merge_base(&self, a: &str, b: &str) -> Result<Option<String>> {
+        let a = Repository::_peel_rev(&self.repo, a)?.id();
+        let b = Repository::_peel_rev(&self.repo, b)?.id();
+        match self.repo.merge_base(a, b) {
+            Ok(id) => Ok(Some(id.to_string())),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     /// Returns all entries of the given tree. Each tree represents the not recursive content of a folder.
     /// Meaning a folder present in the given tree is not evaluated and its entries need to be retrieved independently.
-    fn branch_entries(&self, branch_name: &str) -> Result<Option<SourceEntries>> {
-        let branch = self.repo.find_branch(branch_name, git2::BranchType::Local)?;
-        let tree = branch.get().peel_to_tree()?;
+    fn branch_entries(&self, rev: &str) -> Result<Option<SourceEntries>> {
+        let tree = Repository::_peel_rev(&self.repo, rev)?.tree()?;
 
-        self.entries_for_tree(&tree, branch_name, "")
+        self.entries_for_tree(&tree, rev, "")
             .map(|v| v.into_option())
     }
 
@@ -313,6 +477,37 @@ impl VersionControl for Repository {
         diff::diff_from_to(&self.repo, Some(from), Some(to))
     }
 
+    fn compare(&self, base: &str, head: &str, mode: CompareMode, limit: usize) -> Result<Comparison> {
+        let base = Repository::_peel_rev(&self.repo, base)?;
+        let head = Repository::_peel_rev(&self.repo, head)?;
+        git::compare(&self.repo, &base, &head, mode, limit)
+    }
+
+    fn follow_lines<P: AsRef<Path>>(&self, from: &str, to: &str, path: P, start: usize, end: usize) -> Result<Option<(usize, usize)>> {
+        let from = Repository::_peel_rev(&self.repo, from)?;
+        let to = Repository::_peel_rev(&self.repo, to)?;
+        git::follow_lines(&self.repo, &from, &to, path.as_ref(), start, end)
+    }
+
+    fn hunk_lines<P: AsRef<Path>>(&self, from: &str, to: &str, path: P, new_side: bool) -> Result<Vec<(usize, usize)>> {
+        let from = Repository::_peel_rev(&self.repo, from)?;
+        let to = Repository::_peel_rev(&self.repo, to)?;
+        git::hunk_lines(&self.repo, &from, &to, path.as_ref(), new_side)
+    }
+
+    fn merge_conflicts(&self, base: &str, head: &str) -> Result<Vec<String>> {
+        let base = Repository::_peel_rev(&self.repo, base)?;
+        let head = Repository::_peel_rev(&self.repo, head)?;
+        git::merge_conflicts(&self.repo, &base, &head)
+    }
+
+    fn merge(&self, base: &str, head: &str, style: MergeStyle, message: &str, merger: &crate::Signature) -> Result<String> {
+        let base = Repository::_peel_rev(&self.repo, base)?;
+        let head = Repository::_peel_rev(&self.repo, head)?;
+        let signature = Signature::now(&merger.name, &merger.email)?;
+        Ok(git::merge(&self.repo, &base, &head, style, message, &signature)?.to_string())
+    }
+
     fn calc_size(&self) -> Result<usize> {
         // let index = self.repo.index();
         // if index.is_err() {
@@ -330,8 +525,16 @@ impl VersionControl for Repository {
 }
 
 impl Repository {
-    // can maybe be made faster by passing a DiffOptions.pathspec to diff_tree_to_tree. See 'libgit2' Slack channel 'git2-rs' message from 'Alexander von Gluck IV'
-    fn entries_for_tree<P: AsRef<Path>>(&self, tree: &git2::Tree<'_>, branch_name: &str, root: P) -> Result<SourceEntries> {
+    /// Finds the commit of a revision. Local branches win over tags and everything
+    /// else git understands, like `v1.0`, `a1b2c3d` or `HEAD~3`.
+    fn _peel_rev<'a>(repo: &'a git2::Repository, rev: &str) -> Result<git2::Commit<'a>> {
+        if let Ok(branch) = repo.find_branch(rev, BranchType::Local) {
+            return Ok(branch.get().peel_to_commit()?);
+        }
+        Ok(repo.revparse_single(rev)?.peel_to_commit()?)
+    }
+
+    fn entries_for_tree<P: AsRef<Path>>(&self, tree: &git2::Tree<'_>, rev: &str, root: P) -> Result<SourceEntries> {
         // Folders and files are separe collections, because this way we get an explorer like sorted list for nearly free
         let mut folders = BTreeMap::new();
         let mut files = BTreeMap::new();
@@ -377,64 +580,30 @@ impl Repository {
             TreeWalkResult::Ok
         })?;
 
-        let commits = Repository::_branch_history(&self.repo, branch_name, false)?;
-        let mut visited = BTreeSet::new();
-        let mut file_commits = BTreeMap::new();
-        let root = root.as_ref().to_path_buf();
-        for commit in commits {
-            let mut parent = None;
-            if commit.parent_ids().len() > 0 {
-                let parent_oid = commit.parent_ids().next().expect("There should be at least one parent.");
-                parent = self.repo.find_commit(parent_oid)?.into();
-            }
-
-            let diff = diff::_diff_from_to(&self.repo, Some(&commit), parent.as_ref())?;
-            for file in diff.files {
-                // if file got deleted, name is empty and we need to use old_name, because
-                // it's technically still a change that occured in this commit
-                let filename = file.name.as_ref().unwrap_or_else(|| file.old_name.as_ref().unwrap()).to_string();
-                let filename = PathBuf::from(filename);
-                if filename.starts_with(&root) {
-                    let root_rel_filename = filename.strip_prefix(&root).expect("Prefix should be there, we checked for it");
-                    let root_rel_filename = root_rel_filename.to_path_buf();
-                    // don't need the entries in folders, just the folders and entries in the trees root level
-                    let key = root_rel_filename.components().next().unwrap().as_os_str();
-                    let key = PathBuf::from(key);
-                    if !visited.contains(&key) && visited.len() < (folders.len() + files.len()) {
-                        if !file_commits.contains_key(&key) && (folders.contains_key(&key) || files.contains_key(&key)) {
-                            // We start from newest entry in commit tree, so at this point we know that
-                            // the commit we found is the newest and we can stop looking for this file
-                            visited.insert(key.clone());
-                            file_commits.insert(key, commit.clone());
-                        }
-                    }
-                }
-            }
-            if visited.len() >= (folders.len() + files.len()) {
-                break;
-            }
-        }
+        let commit = Repository::_peel_rev(&self.repo, rev)?;
+        let names = folders.keys().chain(files.keys())
+            .map(|name| name.to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        let last_commits = git::last_commits(&self.repo, &commit, root.as_ref(), &names)?;
+        let last_commit = |name: &PathBuf| last_commits.get(name.to_string_lossy().as_ref())
+            .and_then(|id| self.repo.find_commit(*id).ok())
+            .and_then(|commit| Commit::try_from(commit).ok());
 
         let mut entries = Vec::new();
-        for folder in folders {
-            let mut entry = folder.1;
-            entry.last_commit = Commit::try_from(file_commits.get(&folder.0).expect("No commit found for folder").to_owned()).ok();
-            entries.push((folder.0, entry));
+        for (name, mut entry) in folders {
+            entry.last_commit = last_commit(&name);
+            entries.push((name, entry));
         }
-        for file in files {
-            let mut entry = file.1;
-            entry.last_commit = Commit::try_from(file_commits.get(&file.0).expect(&format!("No commit found for file: {:?}", &file.0)).to_owned()).ok();
-            entries.push((file.0, entry));
+        for (name, mut entry) in files {
+            entry.last_commit = last_commit(&name);
+            entries.push((name, entry));
         }
 
         Ok(entries)
     }
 
-    fn _branch_history<'a>(repo: &'a git2::Repository, branch_name: &str, with_merge_commits: bool) -> Result<Vec<git2::Commit<'a>>> {
-        let commit = repo.find_branch(branch_name, git2::BranchType::Local)?
-            .get()
-            .resolve()?
-            .peel_to_commit()?;
+    fn _branch_history<'a>(repo: &'a git2::Repository, rev: &str, with_merge_commits: bool) -> Result<Vec<git2::Commit<'a>>> {
+        let commit = Repository::_peel_rev(repo, rev)?;
 
         let mut revwalk = repo.revwalk()?;
         revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
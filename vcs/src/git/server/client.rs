@@ -0,0 +1,74 @@
+use rocket::{
+    Request,
+    data::{ByteUnit, ToByteUnit},
+    request::{FromRequest, Outcome},
+};
+
+
+/// Default limit for the negotiation request of a fetch or clone, if none is configured.
+const DEFAULT_FETCH_LIMIT: u64 = 10;
+/// Default limit for the packfile of a push, if none is configured.
+const DEFAULT_PUSH_LIMIT: u64 = 500;
+
+/// Encoding with which a git client sent the body of its request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContentEncoding {
+    Identity,
+    Gzip,
+}
+
+/// Everything we need to know about the git client that sent a request, which
+/// is not part of the request body.
+#[derive(Debug, Clone)]
+pub struct GitClient {
+    pub content_encoding: ContentEncoding,
+    /// Value of the `Git-Protocol` header, which is passed on to git as `GIT_PROTOCOL`.
+    /// Only contains values that git itself would accept, like `version=2`.
+    pub protocol: Option<String>,
+    /// Maximum size of the request body for `git-upload-pack`. Can be configured with the `git_fetch` limit.
+    pub fetch_limit: ByteUnit,
+    /// Maximum size of the request body for `git-receive-pack`. Can be configured with the `git_push` limit.
+    pub push_limit: ByteUnit,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for GitClient {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let content_encoding = match request.headers().get_one("Content-Encoding") {
+            Some(encoding) if encoding.eq_ignore_ascii_case("gzip") || encoding.eq_ignore_ascii_case("x-gzip") => ContentEncoding::Gzip,
+            _ => ContentEncoding::Identity,
+        };
+        let protocol = request.headers().get_one("Git-Protocol")
+            .filter(|protocol| is_valid_protocol(protocol))
+            .map(ToString::to_string);
+        let fetch_limit = request.limits()
+            .get("git_fetch")
+            .unwrap_or(DEFAULT_FETCH_LIMIT.mebibytes());
+        let push_limit = request.limits()
+            .get("git_push")
+            .unwrap_or(DEFAULT_PUSH_LIMIT.mebibytes());
+
+        Outcome::Success(GitClient {
+            content_encoding,
+            protocol,
+            fetch_limit,
+            push_limit,
+        })
+    }
+}
+
+/// The header is a colon separated list of `key` or `key=value` parameters. Git is
+/// very strict about what it accepts and so are we, because the value ends up in the
+/// environment of the spawned git process.
+fn is_valid_protocol(protocol: &str) -> bool {
+    !protocol.is_empty() && protocol.split(':').all(|param| {
+        let mut param = param.splitn(2, '=');
+        let key = param.next().unwrap_or_default();
+        let value = param.next().unwrap_or_default();
+        !key.is_empty()
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+            && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    })
+}
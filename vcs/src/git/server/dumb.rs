@@ -0,0 +1,94 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use git2::ObjectType;
+
+
+/// Generates the content of `info/refs` like `git update-server-info` would, but
+/// straight from the repository, so it can't be outdated.
+pub fn info_refs<P: AsRef<Path>>(repo_path: P) -> Result<Vec<u8>, Box<dyn Error>> {
+    let repo = git2::Repository::open_bare(repo_path)?;
+    let mut refs = Vec::new();
+    for reference in repo.references()? {
+        let reference = reference?;
+        let name = match reference.name() {
+            Some(name) if name.starts_with("refs/") => name.to_string(),
+            _ => continue,
+        };
+        let target = match reference.resolve()?.target() {
+            Some(target) => target,
+            None => continue,
+        };
+        refs.push((name, target));
+    }
+    refs.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut info = String::new();
+    for (name, target) in refs {
+        info.push_str(&format!("{}\t{}\n", target, name));
+        // Annotated tags are followed by the object they point to
+        let object = repo.find_object(target, None)?;
+        if object.kind() == Some(ObjectType::Tag) {
+            let peeled = object.peel(ObjectType::Any)?;
+            info.push_str(&format!("{}\t{}^{{}}\n", peeled.id(), name));
+        }
+    }
+
+    Ok(info.into_bytes())
+}
+
+/// Generates the content of `objects/info/packs` from the packs that are currently in the repository.
+pub fn info_packs<P: AsRef<Path>>(repo_path: P) -> Result<Vec<u8>, Box<dyn Error>> {
+    let pack_dir = repo_path.as_ref().join("objects").join("pack");
+    let mut packs = Vec::new();
+    if pack_dir.is_dir() {
+        for entry in std::fs::read_dir(pack_dir)? {
+            let name = entry?.file_name().to_string_lossy().to_string();
+            if name.ends_with(".pack") && is_pack_name(&name) {
+                packs.push(name);
+            }
+        }
+    }
+    packs.sort();
+
+    let mut info = String::new();
+    for pack in packs {
+        info.push_str(&format!("P {}\n", pack));
+    }
+    info.push('\n');
+
+    Ok(info.into_bytes())
+}
+
+/// Returns the path to a loose object, if both parts of the path are valid hex values.
+pub fn loose_object_path<P: AsRef<Path>>(repo_path: P, hex_2: &str, hex_38: &str) -> Option<PathBuf> {
+    if hex_2.len() == 2 && hex_38.len() == 38 && is_hex(hex_2) && is_hex(hex_38) {
+        Some(repo_path.as_ref().join("objects").join(hex_2).join(hex_38))
+    }
+    else {
+        None
+    }
+}
+
+/// Returns the path to a `.pack` or `.idx` file, if the name is a valid pack name.
+pub fn pack_path<P: AsRef<Path>>(repo_path: P, name: &str) -> Option<PathBuf> {
+    if is_pack_name(name) {
+        Some(repo_path.as_ref().join("objects").join("pack").join(name))
+    }
+    else {
+        None
+    }
+}
+
+/// Checks for `pack-<sha1>.pack` or `pack-<sha1>.idx`.
+fn is_pack_name(name: &str) -> bool {
+    let sha = name.strip_prefix("pack-")
+        .and_then(|n| n.strip_suffix(".pack").or_else(|| n.strip_suffix(".idx")));
+    match sha {
+        Some(sha) => sha.len() == 40 && is_hex(sha),
+        None => false,
+    }
+}
+
+fn is_hex(value: &str) -> bool {
+    value.bytes().all(|b| b.is_ascii_hexdigit())
+}
@@ -0,0 +1,136 @@
+use std::ops::Deref;
+use std::sync::Arc;
+use rocket::{
+    Request, State,
+    http::Status,
+    request::{FromRequest, Outcome},
+};
+use zorgit_common::{
+    Project,
+    access::{self, Access},
+    entities::User,
+};
+use zorgit_db::Database;
+use crate::{PushPolicy, Result};
+use crate::events::{EventBus, ProjectInfo};
+use crate::git::post_receive::{self, PushCompletion};
+
+/// Decides who may fetch from and push to a project over HTTP. The git routes ask the
+/// `Arc<dyn Gatekeeper>` managed by Rocket, or the `DatabaseGatekeeper` if there is none.
+#[rocket::async_trait]
+pub trait Gatekeeper: Send + Sync {
+    /// Finds the project the request is for and the user who sent it.
+    /// Returns `None` if there is no such project.
+    async fn admit(&self, request: &Request<'_>) -> std::result::Result<Option<Box<dyn Visit>>, Status>;
+}
+
+/// A request to a project, by a logged in or an anonymous user.
+#[rocket::async_trait]
+pub trait Visit: Send + Sync {
+    fn project(&self) -> &ProjectInfo;
+    fn is_logged_in(&self) -> bool;
+    fn access(&self) -> Access;
+    /// The rules a push of the logged in user has to follow.
+    async fn push_policy(&self) -> Result<PushPolicy>;
+    /// Processes the push once git has finished it.
+    async fn finish_push(self: Box<Self>, completion: PushCompletion) -> Result<()>;
+}
+
+/// The visit of a git route, admitted by the gatekeeper of the server.
+pub struct Visitor(Box<dyn Visit>);
+
+impl Visitor {
+    pub fn into_inner(self) -> Box<dyn Visit> {
+        self.0
+    }
+}
+
+impl Deref for Visitor {
+    type Target = dyn Visit;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Visitor {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let visit = match request.guard::<State<'_, Arc<dyn Gatekeeper>>>().await {
+            Outcome::Success(gatekeeper) => gatekeeper.admit(request).await,
+            _ => DatabaseGatekeeper.admit(request).await,
+        };
+        match visit {
+            Ok(Some(visit)) => Outcome::Success(Visitor(visit)),
+            Ok(None) => Outcome::Failure((Status::NotFound, ())),
+            Err(status) => Outcome::Failure((status, ())),
+        }
+    }
+}
+
+/// Looks up projects, users and their access in the database.
+pub struct DatabaseGatekeeper;
+
+#[rocket::async_trait]
+impl Gatekeeper for DatabaseGatekeeper {
+    async fn admit(&self, request: &Request<'_>) -> std::result::Result<Option<Box<dyn Visit>>, Status> {
+        let project = match request.guard::<Project>().await {
+            Outcome::Success(project) => project,
+            Outcome::Forward(_) => return Ok(None),
+            Outcome::Failure((status, _)) => return Err(status),
+        };
+        let user = request.guard::<Option<User>>().await.succeeded().flatten();
+        let db = request.guard::<Database>().await.succeeded().ok_or(Status::InternalServerError)?;
+        let events = request.guard::<State<'_, EventBus>>().await.succeeded().ok_or(Status::InternalServerError)?;
+        let access = access::access_of(&db, &project, user.as_ref()).await
+            .map_err(|_| Status::InternalServerError)?;
+
+        Ok(Some(Box::new(DatabaseVisit {
+            info: ProjectInfo::from(&project),
+            project,
+            user,
+            access,
+            db,
+            events: events.inner().clone(),
+        })))
+    }
+}
+
+struct DatabaseVisit {
+    info: ProjectInfo,
+    project: Project,
+    user: Option<User>,
+    access: Access,
+    db: Database,
+    events: EventBus,
+}
+
+#[rocket::async_trait]
+impl Visit for DatabaseVisit {
+    fn project(&self) -> &ProjectInfo {
+        &self.info
+    }
+
+    fn is_logged_in(&self) -> bool {
+        self.user.is_some()
+    }
+
+    fn access(&self) -> Access {
+        self.access
+    }
+
+    async fn push_policy(&self) -> Result<PushPolicy> {
+        match &self.user {
+            Some(user) => PushPolicy::load(&self.db, &self.project, user).await,
+            None => Err("Only logged in users can push".into()),
+        }
+    }
+
+    async fn finish_push(self: Box<Self>, completion: PushCompletion) -> Result<()> {
+        let visit = *self;
+        let user = visit.user.ok_or("Only logged in users can push")?;
+        post_receive::process_push(visit.db, visit.events, visit.info, user.username, user.email.address, completion).await
+    }
+}
@@ -1,60 +1,199 @@
 use std::error::Error;
+use std::fmt;
 use std::path::Path;
+use std::pin::Pin;
 use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
 use anyhow::anyhow;
-use rocket::data::DataStream;
-use tokio::process::Command;
+use async_compression::tokio::bufread::GzipDecoder;
+use rocket::data::{ByteUnit, Data, ToByteUnit};
+use tokio::io::{self, AsyncRead, BufReader, ReadBuf};
+use tokio::process::{Child, ChildStdout, Command};
+use tokio::sync::oneshot;
+use crate::PushPolicy;
+use crate::git::hooks;
+use crate::git::post_receive::{self, PushCompletion};
 
+mod client;
+pub mod dumb;
+mod gatekeeper;
 mod routes;
 mod service;
 
+pub use client::{ContentEncoding, GitClient};
+pub use gatekeeper::{DatabaseGatekeeper, Gatekeeper, Visit, Visitor};
 pub use routes::Server;
-pub(crate) use service::Service;
+pub use service::Service;
 
-pub async fn info_refs<P: AsRef<Path>>(repo_path: P, service: &service::Service) -> Result<Vec<u8>, Box<dyn Error>> {
-    let output = Command::new("git")
-        .arg(service.as_git_cmd())
+pub async fn info_refs<P: AsRef<Path>>(repo_path: P, service: &service::Service, protocol: Option<&str>) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut cmd = Command::new("git");
+    cmd.arg(service.as_git_cmd())
         .arg("--stateless-rpc")
         .arg("--advertise-refs")
-        .arg(repo_path.as_ref())
-        .output()
-        .await?;
+        .arg(repo_path.as_ref());
+    if let Some(protocol) = protocol {
+        cmd.env("GIT_PROTOCOL", protocol);
+    }
+    let output = cmd.output().await?;
+
+    // With protocol v2 git advertises its capabilities instead of refs and the
+    // client expects them without the service announcement in front.
+    if is_v2_advertisement(&output.stdout) {
+        return Ok(output.stdout);
+    }
 
-    let packet = service.as_str();
-    let length = packet.len() + 11;
-    let payload = std::str::from_utf8(&output.stdout)?;
+    let packet = format!("# service={}\n", service.as_str());
+    let mut advertisement = format!("{:04x}{}0000", packet.len() + 4, packet).into_bytes();
+    advertisement.extend_from_slice(&output.stdout);
 
-    Ok(format!("{:0>4x}# service={}\n{}0000", length, packet, payload).as_bytes().to_vec())
+    Ok(advertisement)
 }
 
-pub async fn upload_pack<P: AsRef<Path>>(repo_path: P, change_set: DataStream) -> Result<Vec<u8>, Box<dyn Error>> {
-    let mut child = Command::new("git")
-        .arg(Service::UploadPack.as_git_cmd())
-        .arg("--stateless-rpc")
-        .arg(repo_path.as_ref())
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()?;
+fn is_v2_advertisement(advertisement: &[u8]) -> bool {
+    advertisement.len() >= 14 && &advertisement[4..14] == b"version 2\n"
+}
 
-    let stdin = child.stdin.as_mut().ok_or(anyhow!("Failed to open stdin"))?;
-    change_set.stream_to(stdin).await?;
+/// Runs `git-upload-pack` for a fetch or clone. With protocol v2 the request body contains
+/// a single command, like `ls-refs` or `fetch`, which git answers on its own.
+pub async fn upload_pack<P: AsRef<Path>>(repo_path: P, change_set: Data, limit: ByteUnit, encoding: ContentEncoding, protocol: Option<&str>) -> Result<PackStream, Box<dyn Error>> {
+    let child = stateless_rpc(repo_path, Service::UploadPack, change_set, limit, encoding, protocol, &[]).await?;
+    PackStream::new(child, None)
+}
 
-    let output = child.wait_with_output().await?;
-    Ok(output.stdout)
+/// Runs `git-receive-pack` for a push. There is no protocol v2 for pushes, so git would
+/// ignore it anyway and we don't pass it on. The policy is checked by the `pre-receive`
+/// hook, which gets it through the environment of git. The returned completion resolves
+/// to the references the `post-receive` hook reported, once git is done.
+pub async fn receive_pack<P: AsRef<Path>>(repo_path: P, change_set: Data, limit: ByteUnit, encoding: ContentEncoding, policy: &PushPolicy) -> Result<(PackStream, PushCompletion), Box<dyn Error>> {
+    hooks::install(&repo_path)?;
+    let updates = post_receive::updates_file();
+    let hook_env = [
+        (hooks::POLICY_ENV, serde_json::to_string(policy)?),
+        (hooks::UPDATES_ENV, updates.to_string_lossy().into_owned()),
+    ];
+    let child = stateless_rpc(repo_path, Service::ReceivePack, change_set, limit, encoding, None, &hook_env).await?;
+
+    let (sender, finished) = oneshot::channel();
+    let stream = PackStream::new(child, Some(sender))?;
+    Ok((stream, PushCompletion { finished, updates }))
+}
+
+/// The request body is larger than the configured limit. Routes answer it with `413 Payload Too Large`.
+#[derive(Debug)]
+pub struct PayloadTooLarge;
+
+impl fmt::Display for PayloadTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The request body is larger than the configured limit")
+    }
 }
 
-pub async fn receive_pack<P: AsRef<Path>>(repo_path: P, change_set: DataStream) -> Result<Vec<u8>, Box<dyn Error>> {
-    let mut child = Command::new("git")
-        .arg(Service::ReceivePack.as_git_cmd())
+impl Error for PayloadTooLarge {}
+
+/// Counts the bytes that were read from the request body. Rocket ends the body at the
+/// limit without telling, so this is how we find out that it was cut.
+struct CountedBody {
+    body: Pin<Box<dyn AsyncRead + Send>>,
+    read: Arc<AtomicU64>,
+}
+
+impl AsyncRead for CountedBody {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        let poll = self.body.as_mut().poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            self.read.fetch_add((buf.filled().len() - filled) as u64, Ordering::Relaxed);
+        }
+        poll
+    }
+}
+
+/// Spawns the git command for the given service and feeds it the whole request body. A body
+/// larger than `limit` is never handed over completely, because git would only complain
+/// about a broken pack. Instead git is killed and `PayloadTooLarge` is returned.
+async fn stateless_rpc<P: AsRef<Path>>(repo_path: P, service: Service, change_set: Data, limit: ByteUnit, encoding: ContentEncoding, protocol: Option<&str>, hook_env: &[(&str, String)]) -> Result<Child, Box<dyn Error>> {
+    let mut cmd = Command::new("git");
+    cmd.arg(service.as_git_cmd())
         .arg("--stateless-rpc")
         .arg(repo_path.as_ref())
         .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()?;
+        .stdout(Stdio::piped());
+    if let Some(protocol) = protocol {
+        cmd.env("GIT_PROTOCOL", protocol);
+    }
+    for (key, value) in hook_env {
+        cmd.env(key, value);
+    }
+    let mut child = cmd.spawn()?;
+
+    let mut stdin = child.stdin.take().ok_or(anyhow!("Failed to open stdin"))?;
+    // One byte more than allowed, so a body of exactly the limit still fits
+    let read = Arc::new(AtomicU64::new(0));
+    let body = CountedBody {
+        body: Box::pin(change_set.open(limit + 1.bytes())),
+        read: read.clone(),
+    };
+    let mut change_set: Pin<Box<dyn AsyncRead + Send>> = match encoding {
+        ContentEncoding::Identity => Box::pin(body),
+        ContentEncoding::Gzip => Box::pin(GzipDecoder::new(BufReader::new(body))),
+    };
+    let copied = io::copy(&mut change_set, &mut stdin).await;
+    if read.load(Ordering::Relaxed) > limit.as_u64() {
+        // git still waits for the rest of the body, so it can't act on the partial one
+        child.kill().await?;
+        return Err(PayloadTooLarge.into());
+    }
+    copied?;
+    // git waits for EOF on stdin before it starts to answer
+    drop(stdin);
+
+    Ok(child)
+}
 
-    let stdin = child.stdin.as_mut().ok_or(anyhow!("Failed to open stdin"))?;
-    change_set.stream_to(stdin).await?;
+/// The stdout of a running git process, so the response can be sent while git is still
+/// producing it. The process is kept alive as long as the stream exists and handed over
+/// to whoever waits for it, once everything was read or the stream is dropped.
+pub struct PackStream {
+    child: Option<Child>,
+    stdout: ChildStdout,
+    finished: Option<oneshot::Sender<Child>>,
+}
+
+impl PackStream {
+    pub(crate) fn new(mut child: Child, finished: Option<oneshot::Sender<Child>>) -> Result<PackStream, Box<dyn Error>> {
+        let stdout = child.stdout.take().ok_or(anyhow!("Failed to open stdout"))?;
+        Ok(PackStream {
+            child: Some(child),
+            stdout,
+            finished,
+        })
+    }
+
+    fn finish(&mut self) {
+        if let (Some(child), Some(finished)) = (self.child.take(), self.finished.take()) {
+            let _ = finished.send(child);
+        }
+    }
+}
+
+impl AsyncRead for PackStream {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        let poll = Pin::new(&mut self.stdout).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            // Nothing was read, so we reached EOF
+            if buf.filled().len() == filled {
+                self.finish();
+            }
+        }
+        poll
+    }
+}
 
-    let output = child.wait_with_output().await?;
-    Ok(output.stdout)
+impl Drop for PackStream {
+    fn drop(&mut self) {
+        self.finish();
+    }
 }
@@ -1,32 +1,30 @@
-use crate::git::{self, server::Service};
+use crate::git::{self, server::{GitClient, PayloadTooLarge, Service, Visitor, dumb}};
 use rocket::{
-    get, post, routes, Response, Route,
-    data::{Data, ToByteUnit},
-    http::Status,
-    response::{status, ResponseBuilder}
+    error, get, post, routes, Response, Route,
+    data::Data,
+    http::{ContentType, Status},
+    response::ResponseBuilder
 };
 use std::io;
-use zorgit_common::{
-    Project, Sha1,
-    entities::{Owner, User}
-};
+use std::path::PathBuf;
+use tokio::fs::File;
 
 //##### Routes #####//
 //#### Smart git protocol ####
 // ✔ [get]     /{user|org}/{project}/info/refs?service=<...>
 // ✔ [post]    /{user|org}/{project}/git-upload-pack
 // ✔ [post]    /{user|org}/{project}/git-receive-pack
-//#### Dumb git protocol ####
-// ⛔ [get]     /{user|org}/{project}/HEAD
-// ⛔ [get]     /{user|org}/{project}/objects/info/alternate
-// ⛔ [get]     /{user|org}/{project}/objects/info/http-alternates
-// ⛔ [get]     /{user|org}/{project}/objects/info/packs
-// ⛔ [get]     /{user|org}/{project}/objects/info/<requested>
-// ⛔ [get]     /{user|org}/{project}/objects/<hex_2>/<hex_38>
-// ⛔ [get]     /{user|org}/{project}/objects/pack/<pack_sha>.pack
-// ⛔ [get]     /{user|org}/{project}/objects/pack/<pack_sha>.idx
-
-const UPLOAD_LIMIT: i32 = 20; //TODO: change to sensible limit
+// Protocol v2 is negotiated with the `Git-Protocol` header and handled by git itself.
+//#### Dumb git protocol (read-only) ####
+// ✔ [get]     /{user|org}/{project}/info/refs
+// ✔ [get]     /{user|org}/{project}/HEAD
+// ✔ [get]     /{user|org}/{project}/objects/info/alternates
+// ✔ [get]     /{user|org}/{project}/objects/info/http-alternates
+// ✔ [get]     /{user|org}/{project}/objects/info/packs
+// ✔ [get]     /{user|org}/{project}/objects/info/<requested>
+// ✔ [get]     /{user|org}/{project}/objects/<hex_2>/<hex_38>
+// ✔ [get]     /{user|org}/{project}/objects/pack/<pack_sha>.pack
+// ✔ [get]     /{user|org}/{project}/objects/pack/<pack_sha>.idx
 
 pub struct Server;
 
@@ -37,6 +35,7 @@ impl crate::vcs::Server for Server {
             info_refs_get,
             upload_pack_post,
             receive_pack_post,
+            info_refs_dumb_get,
             head_get,
             info_alt_get,
             info_http_alt_get,
@@ -51,6 +50,7 @@ impl crate::vcs::Server for Server {
 
 trait GitResponse<'r> {
     fn git_headers(&mut self) -> &mut ResponseBuilder<'r>;
+    fn cache_forever(&mut self) -> &mut ResponseBuilder<'r>;
     fn unauthorized(&mut self) -> &mut ResponseBuilder<'r>;
 }
 
@@ -61,6 +61,12 @@ impl<'r> GitResponse<'r> for ResponseBuilder<'r> {
             .raw_header("Cache-Control", "no-cache, max-age=0, must-revalidate")
     }
 
+    /// Objects and packs never change their content, so clients and proxies can keep them.
+    fn cache_forever(&mut self) -> &mut ResponseBuilder<'r> {
+        self.raw_header("Expires", "Fri, 01 Jan 2100 00:00:00 GMT")
+            .raw_header("Cache-Control", "public, max-age=31536000")
+    }
+
     fn unauthorized(&mut self) -> &mut ResponseBuilder<'r> {
         self.status(Status::Unauthorized)
             .git_headers()
@@ -69,14 +75,46 @@ impl<'r> GitResponse<'r> for ResponseBuilder<'r> {
 }
 
 
+/// The response for users who may not read the project, if they can't. Anonymous users are
+/// asked to log in, everybody else doesn't get to know that the project exists.
+fn deny_read(visitor: &Visitor) -> Option<Response<'static>> {
+    if visitor.access().can_read() {
+        None
+    }
+    else if !visitor.is_logged_in() {
+        Some(Response::build().unauthorized().finalize())
+    }
+    else {
+        Some(Response::build().status(Status::NotFound).finalize())
+    }
+}
+
+/// The response for users who may not push to the project, if they can't. Checked when git
+/// asks for the references to push to, so it doesn't upload a pack just to have it rejected.
+fn deny_write(visitor: &Visitor) -> Option<Response<'static>> {
+    if !visitor.is_logged_in() {
+        Some(Response::build().unauthorized().finalize())
+    }
+    else if !visitor.access().can_write() {
+        Some(Response::build().status(Status::Forbidden).finalize())
+    }
+    else {
+        None
+    }
+}
+
 //################# Smart git protocol #################
 #[get("/<_owner>/<_project_name>/info/refs?<service>")]
-pub async fn info_refs_get(_owner: Owner, _project_name: &str, project: Project, service: Service, logged_user: Option<User>) -> Response<'static> {
-    if project.is_private {
-        return Response::build().unauthorized().finalize();
+pub async fn info_refs_get(_owner: &str, _project_name: &str, service: Service, client: GitClient, visitor: Visitor) -> Response<'static> {
+    let denied = match service {
+        Service::ReceivePack => deny_write(&visitor),
+        _ => deny_read(&visitor),
+    };
+    if let Some(denied) = denied {
+        return denied;
     }
 
-    let data = git::server::info_refs(project.dir, &service).await;
+    let data = git::server::info_refs(&visitor.project().dir, &service, client.protocol.as_deref()).await;
 
     match data {
         Ok(data) => Response::build()
@@ -89,67 +127,154 @@ pub async fn info_refs_get(_owner: Owner, _project_name: &str, project: Project,
 }
 
 #[post("/<_owner>/<_project_name>/git-upload-pack", data = "<data>")]
-pub async fn upload_pack_post(_owner: Owner, _project_name: &str, project: Project, data: Data, _logged_user: User) -> Response<'static> {
-    let data = git::server::upload_pack(project.dir, data.open(UPLOAD_LIMIT.mebibytes())).await;
+pub async fn upload_pack_post(_owner: &str, _project_name: &str, client: GitClient, data: Data, visitor: Visitor) -> Response<'static> {
+    if let Some(denied) = deny_read(&visitor) {
+        return denied;
+    }
+    let stream = git::server::upload_pack(&visitor.project().dir, data, client.fetch_limit, client.content_encoding, client.protocol.as_deref()).await;
 
-    match data {
-        Ok(data) => Response::build()
+    match stream {
+        Ok(stream) => Response::build()
                         .git_headers()
                         .header(Service::UploadPack)
-                        .sized_body(data.len(), io::Cursor::new(data))
+                        .streamed_body(stream)
                         .finalize(),
+        Err(e) if e.is::<PayloadTooLarge>() => Response::build().status(Status::PayloadTooLarge).finalize(),
         Err(_) => Response::build().status(Status::NotFound).finalize(),
     }
 }
 
 #[post("/<_owner>/<_project_name>/git-receive-pack", data = "<data>")]
-pub async fn receive_pack_post(_owner: Owner, _project_name: &str, project: Project, data: Data, _logged_user: User) -> Response<'static> {
-    let data = git::server::receive_pack(project.dir, data.open(UPLOAD_LIMIT.mebibytes())).await;
+pub async fn receive_pack_post(_owner: &str, _project_name: &str, client: GitClient, data: Data, visitor: Visitor) -> Response<'static> {
+    if let Some(denied) = deny_write(&visitor) {
+        return denied;
+    }
+    let policy = match visitor.push_policy().await {
+        Ok(policy) => policy,
+        Err(_) => return Response::build().status(Status::InternalServerError).finalize(),
+    };
+    let push = git::server::receive_pack(&visitor.project().dir, data, client.push_limit, client.content_encoding, &policy).await;
 
+    match push {
+        Ok((stream, completion)) => {
+            let visit = visitor.into_inner();
+            tokio::spawn(async move {
+                if let Err(e) = visit.finish_push(completion).await {
+                    error!("Post-receive processing failed: {}", e);
+                }
+            });
+
+            Response::build()
+                .git_headers()
+                .header(Service::ReceivePack)
+                .streamed_body(stream)
+                .finalize()
+        }
+        Err(e) if e.is::<PayloadTooLarge>() => Response::build().status(Status::PayloadTooLarge).finalize(),
+        Err(_) => Response::build().status(Status::NotFound).finalize(),
+    }
+}
+
+//################# Dumb git protocol #################
+fn text_response(data: std::result::Result<Vec<u8>, Box<dyn std::error::Error>>) -> Response<'static> {
     match data {
         Ok(data) => Response::build()
                         .git_headers()
-                        .header(Service::ReceivePack)
+                        .header(ContentType::Plain)
                         .sized_body(data.len(), io::Cursor::new(data))
                         .finalize(),
         Err(_) => Response::build().status(Status::NotFound).finalize(),
     }
 }
 
-//################# Dumb git protocol #################
-const NO_DUMB_GIT_MSG: &str = "This server does not support the dumb git protocol. Please use the smart git protocol.";
+async fn file_response(path: Option<PathBuf>, content_type: ContentType) -> Response<'static> {
+    let file = match path {
+        Some(path) => File::open(path).await,
+        None => return Response::build().status(Status::NotFound).finalize(),
+    };
+
+    match file {
+        Ok(file) => Response::build()
+                        .cache_forever()
+                        .header(content_type)
+                        .sized_body(None, file)
+                        .finalize(),
+        Err(_) => Response::build().status(Status::NotFound).finalize(),
+    }
+}
+
+#[get("/<_owner>/<_project_name>/info/refs", rank = 2)]
+pub async fn info_refs_dumb_get(_owner: &str, _project_name: &str, visitor: Visitor) -> Response<'static> {
+    if let Some(denied) = deny_read(&visitor) {
+        return denied;
+    }
+
+    text_response(dumb::info_refs(&visitor.project().dir))
+}
 
 #[get("/<_owner>/<_project_name>/HEAD")]
-pub fn head_get(_owner: Owner, _project_name: &str, _project: Project) -> status::Custom<&'static str> {
-    status::Custom(Status::Forbidden, NO_DUMB_GIT_MSG)
+pub async fn head_get(_owner: &str, _project_name: &str, visitor: Visitor) -> Response<'static> {
+    if let Some(denied) = deny_read(&visitor) {
+        return denied;
+    }
+
+    text_response(std::fs::read(&visitor.project().dir.join("HEAD")).map_err(Into::into))
 }
 
 #[get("/<_owner>/<_project_name>/objects/info/alternates")]
-pub fn info_alt_get(_owner: Owner, _project_name: &str, _project: Project) -> status::Custom<&'static str> {
-    status::Custom(Status::Forbidden, NO_DUMB_GIT_MSG)
+pub async fn info_alt_get(_owner: &str, _project_name: &str, visitor: Visitor) -> Response<'static> {
+    if let Some(denied) = deny_read(&visitor) {
+        return denied;
+    }
+
+    text_response(std::fs::read(&visitor.project().dir.join("objects/info/alternates")).map_err(Into::into))
 }
 
 #[get("/<_owner>/<_project_name>/objects/info/http-alternates")]
-pub fn info_http_alt_get(_owner: Owner, _project_name: &str, _project: Project) -> status::Custom<&'static str> {
-    status::Custom(Status::Forbidden, NO_DUMB_GIT_MSG)
+pub async fn info_http_alt_get(_owner: &str, _project_name: &str, visitor: Visitor) -> Response<'static> {
+    if let Some(denied) = deny_read(&visitor) {
+        return denied;
+    }
+
+    text_response(std::fs::read(&visitor.project().dir.join("objects/info/http-alternates")).map_err(Into::into))
 }
 
 #[get("/<_owner>/<_project_name>/objects/info/packs")]
-pub fn info_packs_get(_owner: Owner, _project_name: &str, _project: Project) -> status::Custom<&'static str> {
-    status::Custom(Status::Forbidden, NO_DUMB_GIT_MSG)
+pub async fn info_packs_get(_owner: &str, _project_name: &str, visitor: Visitor) -> Response<'static> {
+    if let Some(denied) = deny_read(&visitor) {
+        return denied;
+    }
+
+    text_response(dumb::info_packs(&visitor.project().dir))
 }
 
 #[get("/<_owner>/<_project_name>/objects/info/<_requested>", rank = 20)]
-pub fn info_all_get(_owner: Owner, _project_name: &str, _project: Project, _requested: String) -> status::Custom<&'static str> {
-    status::Custom(Status::Forbidden, NO_DUMB_GIT_MSG)
+pub fn info_all_get(_owner: &str, _project_name: &str, _requested: String) -> Status {
+    Status::NotFound
 }
 
-#[get("/<_owner>/<_project_name>/<_hex_2>/<_hex_38>", rank = 20)]
-pub fn loose_object(_owner: Owner, _project_name: &str, _project: Project, _hex_2: String, _hex_38: String) -> status::Custom<&'static str> {
-    status::Custom(Status::Forbidden, NO_DUMB_GIT_MSG)
+#[get("/<_owner>/<_project_name>/objects/<hex_2>/<hex_38>", rank = 20)]
+pub async fn loose_object(_owner: &str, _project_name: &str, hex_2: String, hex_38: String, visitor: Visitor) -> Response<'static> {
+    if let Some(denied) = deny_read(&visitor) {
+        return denied;
+    }
+
+    let path = dumb::loose_object_path(&visitor.project().dir, &hex_2, &hex_38);
+    file_response(path, ContentType::new("application", "x-git-loose-object")).await
 }
 
-#[get("/<_owner>/<_project_name>/objects/pack/<_pack_sha>")] // .idx and .pack
-pub fn pack_get(_owner: Owner, _project_name: &str, _project: Project, _pack_sha: Sha1) -> status::Custom<&'static str> {
-    status::Custom(Status::Forbidden, NO_DUMB_GIT_MSG)
+#[get("/<_owner>/<_project_name>/objects/pack/<pack>")] // .idx and .pack
+pub async fn pack_get(_owner: &str, _project_name: &str, pack: String, visitor: Visitor) -> Response<'static> {
+    if let Some(denied) = deny_read(&visitor) {
+        return denied;
+    }
+
+    let content_type = if pack.ends_with(".idx") {
+        ContentType::new("application", "x-git-packed-objects-toc")
+    }
+    else {
+        ContentType::new("application", "x-git-packed-objects")
+    };
+    let path = dumb::pack_path(&visitor.project().dir, &pack);
+    file_response(path, content_type).await
 }
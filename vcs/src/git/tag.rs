@@ -0,0 +1,49 @@
+use git2::{ObjectType, Reference};
+use time::OffsetDateTime;
+use crate::{Result, Signature, Tag};
+
+
+/// Reads the tag a reference in `refs/tags/` points to. Tags that don't point to a
+/// commit, i.e. tags of trees or blobs, are skipped.
+pub fn tag_from_reference(repo: &git2::Repository, reference: &Reference<'_>) -> Result<Option<Tag>> {
+    let name = match reference.name().and_then(|name| name.strip_prefix("refs/tags/")) {
+        Some(name) => name.to_string(),
+        None => return Ok(None),
+    };
+    let target = match reference.target() {
+        Some(target) => target,
+        None => return Ok(None),
+    };
+    let object = repo.find_object(target, None)?;
+    let commit = match object.peel(ObjectType::Commit) {
+        Ok(commit) => commit.peel_to_commit()?,
+        Err(_) => return Ok(None),
+    };
+
+    let tag = match object.as_tag() {
+        Some(annotated) => {
+            let tagger = annotated.tagger();
+            Tag {
+                name,
+                id: annotated.id().to_string(),
+                target_id: commit.id().to_string(),
+                message: annotated.message().map(|m| m.trim_end().to_string()).filter(|m| !m.is_empty()),
+                tagger: tagger.as_ref().map(|t| Signature {
+                    name: t.name().unwrap_or_default().to_string(),
+                    email: t.email().unwrap_or_default().to_string(),
+                }),
+                time: OffsetDateTime::from_unix_timestamp(tagger.map_or(commit.time().seconds(), |t| t.when().seconds())),
+            }
+        }
+        None => Tag {
+            name,
+            id: commit.id().to_string(),
+            target_id: commit.id().to_string(),
+            message: None,
+            tagger: None,
+            time: OffsetDateTime::from_unix_timestamp(commit.time().seconds()),
+        },
+    };
+
+    Ok(Some(tag))
+}
@@ -0,0 +1,17 @@
+use std::path::PathBuf;
+use crate::Commit;
+
+/// A commit that changed a file or folder, together with the path it had in that commit.
+#[derive(Debug, Clone)]
+pub struct PathCommit {
+    pub commit: Commit,
+    pub path: PathBuf,
+}
+
+/// One page of the history of a path, newest commit first.
+#[derive(Debug, Clone)]
+pub struct PathHistory {
+    pub commits: Vec<PathCommit>,
+    /// Whether there are older commits after this page.
+    pub has_more: bool,
+}
@@ -1,14 +1,37 @@
+pub mod events;
 pub mod git;
+mod archive;
+mod blame;
+mod code_owners;
 mod commit;
+mod commit_status;
+mod compare;
 mod diff;
+mod history;
+mod log;
+mod merge;
+mod push;
+mod revision;
 mod source_entry;
 mod source_line;
+mod tag;
 mod vcs;
 
+pub use self::archive::ArchiveFormat;
+pub use self::blame::{Blame, BlameHunk, BlamePrior};
+pub use self::code_owners::{CODE_OWNERS_PATHS, CodeOwner, CodeOwners, CodeOwnersRule};
 pub use self::commit::{Commit, Signature};
+pub use self::commit_status::{CombinedStatus, CommitState, ContextStatus};
+pub use self::compare::{CompareMode, Comparison};
 pub use self::diff::{Diff, DiffFileInner, DiffFile, DiffHunk, DiffLine, DiffLineInner};
+pub use self::history::{PathCommit, PathHistory};
+pub use self::log::{CommitLog, LogFilter, MergeFilter};
+pub use self::merge::MergeStyle;
+pub use self::push::{BranchProtection, PushPolicy, RefUpdate, glob_match};
+pub use self::revision::{RefKind, ResolvedRef};
 pub use self::source_entry::SourceEntry;
 pub use self::source_line::{SourceLine, SourceLineInner};
+pub use self::tag::{Tag, TagSort};
 pub use self::vcs::{SourceEntries, VCS, VersionControl, Server};
 
 use std::error::Error;
@@ -0,0 +1,53 @@
+use time::OffsetDateTime;
+use crate::Commit;
+
+/// Which commits of a log are shown, depending on how many parents they have.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MergeFilter {
+    All,
+    OnlyMerges,
+    NoMerges,
+}
+
+impl Default for MergeFilter {
+    fn default() -> Self {
+        MergeFilter::All
+    }
+}
+
+impl std::str::FromStr for MergeFilter {
+    type Err = String;
+
+    fn from_str(filter: &str) -> Result<Self, Self::Err> {
+        match filter {
+            "all" => Ok(MergeFilter::All),
+            "only" => Ok(MergeFilter::OnlyMerges),
+            "none" => Ok(MergeFilter::NoMerges),
+            filter => Err(format!("Unknown merge filter: {}", filter)),
+        }
+    }
+}
+
+/// Restricts a commit log. Text filters match case insensitive anywhere in the value.
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// Matches the name or email of the author.
+    pub author: Option<String>,
+    /// Matches the name or email of the committer.
+    pub committer: Option<String>,
+    /// Only commits that were committed at or after this time.
+    pub since: Option<OffsetDateTime>,
+    /// Only commits that were committed at or before this time.
+    pub until: Option<OffsetDateTime>,
+    /// Matches the whole commit message.
+    pub message: Option<String>,
+    pub merges: MergeFilter,
+}
+
+/// One page of a commit log, newest commit first.
+#[derive(Debug, Clone)]
+pub struct CommitLog {
+    pub commits: Vec<Commit>,
+    /// Pass this as `after` to get the next page. `None` on the last page.
+    pub next_cursor: Option<String>,
+}
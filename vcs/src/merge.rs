@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+
+/// How the commits of a pull request end up in the base branch.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeStyle {
+    /// Creates a merge commit with the base and the head as parents.
+    Merge,
+    /// Creates a single commit with all changes on top of the base.
+    Squash,
+    /// Replays every commit on top of the base and fast-forwards the base to the result.
+    Rebase,
+}
+
+impl Default for MergeStyle {
+    fn default() -> Self {
+        MergeStyle::Merge
+    }
+}
@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+use zorgit_common::{Project, access, entities::User};
+use zorgit_db::{Database, ProtectedBranch};
+use crate::Result;
+
+
+/// A single reference that is changed by a push.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RefUpdate {
+    /// Full name of the reference, i.e. `refs/heads/main`.
+    pub name: String,
+    /// Id of the commit the reference pointed to before the push. `None` if it was created.
+    pub old_id: Option<String>,
+    /// Id of the commit the reference points to after the push. `None` if it was deleted.
+    pub new_id: Option<String>,
+}
+
+impl RefUpdate {
+    pub fn is_create(&self) -> bool {
+        self.old_id.is_none() && self.new_id.is_some()
+    }
+
+    pub fn is_delete(&self) -> bool {
+        self.old_id.is_some() && self.new_id.is_none()
+    }
+
+    /// The short name of the branch, if the reference is a branch.
+    pub fn branch(&self) -> Option<&str> {
+        self.name.strip_prefix("refs/heads/")
+    }
+
+    /// The short name of the tag, if the reference is a tag.
+    pub fn tag(&self) -> Option<&str> {
+        self.name.strip_prefix("refs/tags/")
+    }
+}
+
+/// Protection rule for all branches whose name matches `pattern`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchProtection {
+    /// Branch name or glob, where `*` matches any number of characters.
+    pub pattern: String,
+    pub allow_force_push: bool,
+    pub allow_deletion: bool,
+    /// Whether the user who pushes is on the allow list of this rule. Resolving users
+    /// and teams needs the database, so this is done before the push is handed to the VCS.
+    pub pusher_allowed: bool,
+    pub require_linear_history: bool,
+    /// Contexts of commit statuses that must be successful, before a pull request can be merged.
+    pub required_status_checks: Vec<String>,
+    /// Approving reviews that are needed, before a pull request can be merged.
+    pub required_approvals: u32,
+    /// Whether every changed file needs the approval of one of its code owners.
+    pub require_code_owner_review: bool,
+    /// Whether approvals of a pull request are dismissed, when its head gets new commits.
+    pub dismiss_stale_approvals: bool,
+}
+
+impl BranchProtection {
+    pub fn matches(&self, branch: &str) -> bool {
+        glob_match(&self.pattern, branch)
+    }
+
+    /// Converts the stored rule and checks if the given user may push to branches protected by it.
+    pub async fn for_user(db: &Database, rule: &ProtectedBranch, user: &User) -> Result<BranchProtection> {
+        let user_id = user.id.to_uuid()?;
+        let pusher_allowed = (rule.push_user_ids.is_empty() && rule.push_team_ids.is_empty())
+            || rule.push_user_ids.contains(&user_id)
+            || db.teams.is_member_of_any(&user_id, &rule.push_team_ids).await?;
+
+        Ok(BranchProtection {
+            pusher_allowed,
+            ..BranchProtection::from(rule)
+        })
+    }
+}
+
+/// Converts the stored rule for places without a pusher, so nobody is allowed to push.
+impl From<&ProtectedBranch> for BranchProtection {
+    fn from(rule: &ProtectedBranch) -> Self {
+        BranchProtection {
+            pattern: rule.pattern.clone(),
+            allow_force_push: rule.allow_force_push,
+            allow_deletion: rule.allow_deletion,
+            pusher_allowed: false,
+            require_linear_history: rule.require_linear_history,
+            required_status_checks: rule.required_status_checks.clone(),
+            required_approvals: rule.required_approvals.max(0) as u32,
+            require_code_owner_review: rule.require_code_owner_review,
+            dismiss_stale_approvals: rule.dismiss_stale_approvals,
+        }
+    }
+}
+
+/// Everything the pre-receive stage needs to decide if a push is accepted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PushPolicy {
+    pub pusher: String,
+    /// Whether the pusher may write to the project at all. Without it every update is rejected.
+    pub can_write: bool,
+    pub protections: Vec<BranchProtection>,
+}
+
+impl PushPolicy {
+    /// Loads all protection rules of the project for a push of the given user.
+    pub async fn load(db: &Database, project: &Project, user: &User) -> Result<PushPolicy> {
+        let project_id = project.id.to_uuid()?;
+        let rules = db.protected_branches.for_project(&project_id).await?;
+        let mut protections = Vec::with_capacity(rules.len());
+        for rule in &rules {
+            protections.push(BranchProtection::for_user(db, rule, user).await?);
+        }
+
+        Ok(PushPolicy {
+            pusher: user.username.clone(),
+            can_write: access::can_write(db, project, user).await?,
+            protections,
+        })
+    }
+
+    /// Returns the rule that protects the given branch. A rule for exactly this
+    /// branch wins over rules with a matching glob.
+    pub fn protection_for(&self, branch: &str) -> Option<&BranchProtection> {
+        self.protections.iter()
+            .find(|p| p.pattern == branch)
+            .or_else(|| self.protections.iter().find(|p| p.matches(branch)))
+    }
+}
+
+/// Simple glob matching, where `*` matches any number of characters, including none.
+pub fn glob_match(pattern: &str, value: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    let value = value.as_bytes();
+    let (mut p, mut v) = (0, 0);
+    let mut backtrack = None;
+
+    while v < value.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            backtrack = Some((p, v));
+            p += 1;
+        }
+        else if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        }
+        else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            v = matched + 1;
+            backtrack = Some((star, matched + 1));
+        }
+        else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_globs() {
+        assert!(glob_match("main", "main"));
+        assert!(glob_match("release/*", "release/1.0"));
+        assert!(glob_match("release/*", "release/"));
+        assert!(glob_match("*-stable", "2021-stable"));
+        assert!(glob_match("v?.*", "v1.2"));
+        assert!(glob_match("*a*b*", "xaybzb"));
+        assert!(!glob_match("main", "maintenance"));
+        assert!(!glob_match("release/*", "hotfix/1.0"));
+        assert!(!glob_match("*-stable", "stable"));
+    }
+}
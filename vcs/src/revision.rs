@@ -0,0 +1,43 @@
+/// What kind of revision a user asked for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RefKind {
+    Branch,
+    Tag,
+    /// A commit id or any other expression, like `HEAD~3`.
+    Commit,
+}
+
+/// A revision together with the commit it points to at the time it was resolved.
+/// Branches and tags can move, the commit id can't, so links built from
+/// `commit_id` always show the same content.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedRef {
+    /// The revision as it was requested.
+    pub rev: String,
+    pub kind: RefKind,
+    pub commit_id: String,
+}
+
+impl ResolvedRef {
+    pub fn new(rev: &str, kind: RefKind, commit_id: String) -> ResolvedRef {
+        ResolvedRef {
+            rev: rev.to_string(),
+            kind,
+            commit_id,
+        }
+    }
+
+    /// Whether the revision is already pinned to the commit, so a permalink wouldn't change anything.
+    pub fn is_pinned(&self) -> bool {
+        self.rev == self.commit_id
+    }
+
+    /// The same revision, pinned to the commit it currently points to.
+    pub fn pinned(&self) -> ResolvedRef {
+        ResolvedRef::new(&self.commit_id, RefKind::Commit, self.commit_id.clone())
+    }
+
+    pub fn short_id(&self) -> &str {
+        &self.commit_id[..self.commit_id.len().min(7)]
+    }
+}
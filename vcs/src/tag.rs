@@ -0,0 +1,153 @@
+use std::cmp::Ordering;
+use time::OffsetDateTime;
+use crate::Signature;
+
+#[derive(Debug, Clone)]
+pub struct Tag {
+    pub name: String,
+    /// Id of the tag object for annotated tags, otherwise the id of the commit.
+    pub id: String,
+    /// Id of the commit the tag points to.
+    pub target_id: String,
+    /// Only annotated tags have a message and a tagger.
+    pub message: Option<String>,
+    pub tagger: Option<Signature>,
+    /// When the tag was created. For lightweight tags this is the time of the commit.
+    pub time: OffsetDateTime,
+}
+
+impl Tag {
+    pub fn is_annotated(&self) -> bool {
+        self.id != self.target_id
+    }
+}
+
+/// Order of a tag list. Both put the newest tag first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TagSort {
+    Date,
+    /// Tags are compared as versions like `v1.2.3` or `1.2.0-beta.1`. Tags that
+    /// aren't versions come after all versions, sorted by name.
+    Semver,
+}
+
+impl TagSort {
+    pub fn sort(&self, tags: &mut [Tag]) {
+        match self {
+            TagSort::Date => tags.sort_by(|a, b| b.time.cmp(&a.time).then_with(|| a.name.cmp(&b.name))),
+            TagSort::Semver => tags.sort_by(|a, b| {
+                match (Version::parse(&a.name), Version::parse(&b.name)) {
+                    (Some(a), Some(b)) => b.cmp(&a),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => a.name.cmp(&b.name),
+                }
+            }),
+        }
+    }
+}
+
+impl std::str::FromStr for TagSort {
+    type Err = String;
+
+    fn from_str(sort: &str) -> Result<Self, Self::Err> {
+        match sort {
+            "date" => Ok(TagSort::Date),
+            "semver" => Ok(TagSort::Semver),
+            sort => Err(format!("Unknown tag sort order: {}", sort)),
+        }
+    }
+}
+
+impl Default for TagSort {
+    fn default() -> Self {
+        TagSort::Date
+    }
+}
+
+/// Just enough of semver to order tags. Build metadata is ignored.
+#[derive(Debug)]
+struct Version<'a> {
+    numbers: Vec<u64>,
+    pre_release: Option<&'a str>,
+}
+
+impl<'a> Version<'a> {
+    fn parse(name: &'a str) -> Option<Version<'a>> {
+        let name = name.strip_prefix('v').unwrap_or(name);
+        let name = name.split('+').next()?;
+        let mut parts = name.splitn(2, '-');
+        let numbers = parts.next()?
+            .split('.')
+            .map(|n| n.parse::<u64>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        if numbers.is_empty() || numbers.len() > 3 {
+            return None;
+        }
+
+        Some(Version {
+            numbers,
+            pre_release: parts.next().filter(|p| !p.is_empty()),
+        })
+    }
+}
+
+impl Ord for Version<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Missing numbers count as zero, so `v1.2` equals `v1.2.0`
+        for i in 0..3 {
+            let a = self.numbers.get(i).unwrap_or(&0);
+            let b = other.numbers.get(i).unwrap_or(&0);
+            match a.cmp(b) {
+                Ordering::Equal => (),
+                ordering => return ordering,
+            }
+        }
+
+        // A pre-release is lower than the release itself
+        match (self.pre_release, other.pre_release) {
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (Some(a), Some(b)) => compare_pre_release(a, b),
+        }
+    }
+}
+
+impl PartialEq for Version<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version<'_> {}
+
+impl PartialOrd for Version<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Compares dot separated identifiers, numeric ones by value and lower than alphanumeric ones.
+fn compare_pre_release(a: &str, b: &str) -> Ordering {
+    let mut a = a.split('.');
+    let mut b = b.split('.');
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => {
+                let ordering = match (x.parse::<u64>(), y.parse::<u64>()) {
+                    (Ok(x), Ok(y)) => x.cmp(&y),
+                    (Ok(_), Err(_)) => Ordering::Less,
+                    (Err(_), Ok(_)) => Ordering::Greater,
+                    (Err(_), Err(_)) => x.cmp(y),
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::path::{Path, PathBuf};
 use rocket::Route;
-use crate::{Commit, Diff, Result, SourceEntry};
+use crate::{Blame, Commit, CommitLog, CompareMode, Comparison, Diff, LogFilter, MergeStyle, PathHistory, PushPolicy, RefUpdate, ResolvedRef, Result, Signature, SourceEntry, Tag, TagSort};
 
 pub type SourceEntries = Vec<(PathBuf,SourceEntry)>;
 
@@ -36,19 +36,68 @@ pub trait VersionControl: Sized {
     fn init<P: AsRef<Path>>(&self, tmp_dir: P, username: &str, email: &str) -> Result<()>;
     fn open<P: AsRef<Path>>(path: P) -> Result<Self::Output>;
     fn last_commit_in_branch(&self, branch_name: &str) -> Result<Commit>;
-    fn branch_entries(&self, branch_name: &str) -> Result<Option<SourceEntries>>;
-    fn branch_entry_by_path<P: AsRef<Path>>(&self, branch_name: &str, path: P) -> Result<Option<SourceEntries>>;
-    fn raw_branch_entry_by_path<P: AsRef<Path>>(&self, branch_name: &str, path: P) -> Result<Option<Vec<u8>>>;
+    /// Finds out what a revision is and which commit it points to. Returns `None` for unknown revisions.
+    fn resolve(&self, rev: &str) -> Result<Option<ResolvedRef>>;
+    /// Entries of the root folder at the given revision. Like for all `*entr*` functions,
+    /// the revision can be a branch, a tag, a full or short commit id, or i.e. `HEAD~3`.
+    fn branch_entries(&self, rev: &str) -> Result<Option<SourceEntries>>;
+    fn branch_entry_by_path<P: AsRef<Path>>(&self, rev: &str, path: P) -> Result<Option<SourceEntries>>;
+    fn raw_branch_entry_by_path<P: AsRef<Path>>(&self, rev: &str, path: P) -> Result<Option<Vec<u8>>>;
+    /// Blames the file at the given revision and follows it through renames. Returns
+    /// `None` if the file doesn't exist at that revision.
+    fn blame<P: AsRef<Path>>(&self, rev: &str, path: P) -> Result<Option<Blame>>;
     fn default_branch(&self) -> Result<Option<String>>;
     fn branches(&self) -> Result<Option<Vec<String>>>;
+    /// Creates a branch at the commit the revision points to.
+    fn create_branch(&self, name: &str, rev: &str) -> Result<()>;
+    fn delete_branch(&self, name: &str) -> Result<()>;
+    /// Renames the branch. If it was the default branch, the renamed branch stays the default.
+    fn rename_branch(&self, name: &str, new_name: &str) -> Result<()>;
+    fn set_default_branch(&self, name: &str) -> Result<()>;
+    /// Moves a branch from `old_id` to `new_id`. Fails if the branch moved in the meantime.
+    fn update_branch(&self, name: &str, old_id: &str, new_id: &str) -> Result<()>;
+    /// Checks reference updates against the protection rules, like the pre-receive hook does
+    /// for a push. Returns a message for every rule that got violated.
+    fn check_updates(&self, policy: &PushPolicy, updates: &[RefUpdate]) -> Result<Vec<String>>;
+    fn tags(&self, sort: TagSort) -> Result<Vec<Tag>>;
+    fn tag_by_name(&self, name: &str) -> Result<Option<Tag>>;
+    /// Creates an annotated tag if there is a message, otherwise a lightweight tag. The
+    /// target can be anything that resolves to a commit, like a branch name or a commit id.
+    fn create_tag(&self, name: &str, target: &str, message: Option<&str>, tagger: &Signature) -> Result<Tag>;
+    fn delete_tag(&self, name: &str) -> Result<()>;
     fn commit_by_id(&self, commit_id: &str) -> Result<Commit>;
     fn branch_last_commit(&self, branch_name: &str) -> Result<Commit>;
     fn branch_history(&self, branch_name: &str, with_merge_commits: bool) -> Result<Vec<Commit>>;
+    /// One page of the commits reachable from the given revision, that match the filter.
+    /// `after` is the cursor of the previous page.
+    fn log(&self, rev: &str, filter: &LogFilter, after: Option<&str>, limit: usize) -> Result<CommitLog>;
+    /// Commits that changed the file or folder at `path`, starting at the given revision.
+    /// Files are followed through renames. An empty path returns the history of the root folder.
+    fn path_history<P: AsRef<Path>>(&self, rev: &str, path: P, skip: usize, limit: usize) -> Result<PathHistory>;
     fn branch_commits_count(&self, branch_name: &str) -> Result<usize>;
     fn commit_ancestor_count(&self, commit_id: &str) -> Result<usize>;
     fn commit_associated_branches(&self, commit_id: &str) -> Result<Vec<String>>;
+    /// Commits reachable from `to` but not from `from`, newest first. Without `from`, all
+    /// commits that are not reachable from any other branch are returned.
+    fn commits_between(&self, from: Option<&str>, to: &str, limit: usize) -> Result<Vec<Commit>>;
+    fn is_ancestor(&self, ancestor_id: &str, commit_id: &str) -> Result<bool>;
+    /// The best common ancestor of both revisions, `None` if they share no history.
+    fn merge_base(&self, a: &str, b: &str) -> Result<Option<String>>;
     fn diff_to_parent(&self, commit: &Commit) -> Result<Diff>;
     fn diff_from_to(&self, from: &Commit, to: &Commit) -> Result<Diff>;
+    /// Compares two revisions. Lists up to `limit` commits that head is ahead of base.
+    fn compare(&self, base: &str, head: &str, mode: CompareMode, limit: usize) -> Result<Comparison>;
+    /// Where the lines `start..=end` of a file in `from` are in `to`. `None` if one of them
+    /// got changed or the file is gone, so comments on these lines are outdated.
+    fn follow_lines<P: AsRef<Path>>(&self, from: &str, to: &str, path: P, start: usize, end: usize) -> Result<Option<(usize, usize)>>;
+    /// The lines of a file that the hunks of the diff from `from` to `to` show, as `(first, last)`
+    /// per hunk, counted on the old or the new side of the diff.
+    fn hunk_lines<P: AsRef<Path>>(&self, from: &str, to: &str, path: P, new_side: bool) -> Result<Vec<(usize, usize)>>;
+    /// Paths that conflict when `head` gets merged into `base`. Empty if the merge is clean.
+    fn merge_conflicts(&self, base: &str, head: &str) -> Result<Vec<String>>;
+    /// Creates the commits that merge `head` into `base` without moving any branch.
+    /// Returns the commit the base branch should point to afterwards.
+    fn merge(&self, base: &str, head: &str, style: MergeStyle, message: &str, merger: &Signature) -> Result<String>;
     fn calc_size(&self) -> Result<usize>;
     fn server(&self) -> Self::Server;
 }